@@ -0,0 +1,47 @@
+//! 预编译条件表达式的求值性能
+//!
+//! 运行: cargo bench --bench predicate
+
+use alert_rs::dsl::{parser::parse_condition_expr, Predicate};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::json;
+
+const CONDITIONS: &[(&str, &str)] = &[
+    ("equal", r#"protocol == "TCP""#),
+    (
+        "in_and_compare",
+        r#"alarm_severity IN (2, 3) AND dst_port >= 1024 AND src_ip != "10.0.0.1""#,
+    ),
+    (
+        "regex_or_contains",
+        r#"alarm_name REGEX "(?i)webshell|sql" OR attack_payload CONTAINS "/etc/passwd""#,
+    ),
+    (
+        "nested_path",
+        r#"data.http.host CONTAINS "evil" AND ALL procedure_technique_id[*] REGEX "^T1""#,
+    ),
+];
+
+fn bench_predicate(c: &mut Criterion) {
+    let alert = json!({
+        "alarm_name": "WebShell 上传",
+        "alarm_severity": 3,
+        "protocol": "TCP",
+        "src_ip": "192.168.1.10",
+        "dst_ip": "10.0.0.8",
+        "dst_port": 8080,
+        "attack_payload": "GET /../../etc/passwd HTTP/1.1",
+        "procedure_technique_id": ["T1059", "T1105"],
+        "data": { "http": { "host": "evil.example", "uri": "/upload.php" } }
+    });
+
+    let mut group = c.benchmark_group("predicate");
+    for (name, source) in CONDITIONS {
+        let predicate = Predicate::compile(&parse_condition_expr(source).unwrap());
+        group.bench_function(*name, |b| b.iter(|| predicate.matches(black_box(&alert))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_predicate);
+criterion_main!(benches);
@@ -36,9 +36,9 @@ export const getAlarmTypes = () => {
   return api.get('/alarm-types')
 }
 
-export const getThreatEvents = (page = 1, pageSize = 20) => {
+export const getThreatEvents = (page = 1, pageSize = 20, filters = {}) => {
   return api.get('/threat-events', {
-    params: { page, page_size: pageSize }
+    params: { ...filters, page, page_size: pageSize }
   })
 }
 
@@ -46,6 +46,15 @@ export const updateThreatEvent = (id, data) => {
   return api.put(`/threat-events/${id}`, data)
 }
 
+// 流转威胁事件处置状态：{ to, assignee, comment, operator }
+export const transitionThreatEvent = (id, data) => {
+  return api.post(`/threat-events/${id}/transitions`, data)
+}
+
+export const getThreatEventHistory = (id) => {
+  return api.get(`/threat-events/${id}/history`)
+}
+
 // 根据收敛告警ID查询原始告警
 export const getRawNetworkAttacksByConvergedId = (convergedId) => {
   return api.get(`/network-attacks/${convergedId}/raw`)
@@ -0,0 +1,467 @@
+//! 告警类型目录
+//!
+//! 告警类型由配置声明：`config.toml` 的 `[alarm_types.<类型>]` 给出编码、名称和子类型，
+//! 字段取自 `alert_fields.toml` 的 `[<类型>_alert]` 分节，分节不存在时取自 `schema/<类型>.json`。
+//! 入库、收敛、查询和 DSL 校验都按目录处理告警，新增告警类型只需修改配置。
+//!
+//! 每个告警类型对应原始告警表 `<类型>_alerts` 和收敛告警表 `converged_<类型>_alerts`，
+//! 表中每个字段一列，列类型由字段声明的类型决定。
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::sync::OnceLock;
+use tracing::warn;
+
+use crate::config::{self, AlarmTypeInfo, AlarmTypesConfig};
+use crate::dsl::validator::FieldType;
+
+/// 告警字段声明文件
+const ALERT_FIELDS_FILE: &str = "alert_fields.toml";
+/// JSON Schema 目录
+const SCHEMA_DIR: &str = "schema";
+
+/// 告警表自带的列，不能用作告警字段名
+const RESERVED_COLUMNS: &[&str] = &[
+    "id",
+    "dedup_key",
+    "created_at",
+    "convergence_count",
+    "convergence_rule_id",
+    "convergence_rule_version",
+    "group_key",
+    "threshold_reached",
+    "threshold_reached_at",
+    "first_seen",
+    "last_seen",
+];
+
+lazy_static! {
+    static ref IDENTIFIER: Regex = Regex::new(r"^[a-z][a-z0-9_]*$").unwrap();
+}
+
+static CATALOG: OnceLock<AlertCatalog> = OnceLock::new();
+
+/// 用服务配置初始化告警类型目录，应在处理任何告警之前调用
+pub fn init(alarm_types: &AlarmTypesConfig) -> &'static AlertCatalog {
+    CATALOG.get_or_init(|| AlertCatalog::build(alarm_types))
+}
+
+/// 告警类型目录；未初始化时读取 config.toml
+pub fn catalog() -> &'static AlertCatalog {
+    CATALOG.get_or_init(|| match config::load_config("config.toml") {
+        Ok(cfg) => AlertCatalog::build(&cfg.alarm_types),
+        Err(e) => {
+            warn!("读取 config.toml 失败，告警类型目录为空: {}", e);
+            AlertCatalog::default()
+        }
+    })
+}
+
+/// 告警字段
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertField {
+    pub name: String,
+    /// 声明的类型，如 String、u16、Vec<String>、Json
+    pub field_type: String,
+    pub optional: bool,
+    pub description: String,
+}
+
+impl AlertField {
+    pub fn value_type(&self) -> FieldType {
+        FieldType::from_decl(&self.field_type)
+    }
+
+    /// 字段在告警表中的列类型
+    pub fn column_type(&self) -> &'static str {
+        match self.field_type.as_str() {
+            "String" => "TEXT",
+            "u8" | "i8" | "i16" => "SMALLINT",
+            "u16" | "i32" => "INTEGER",
+            "u32" | "u64" | "i64" => "BIGINT",
+            "f32" | "f64" => "DOUBLE PRECISION",
+            "bool" => "BOOLEAN",
+            _ => "JSONB",
+        }
+    }
+}
+
+/// 告警类型
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertType {
+    /// 告警类型名，如 network_attack
+    pub name: String,
+    /// alarm_type 编码
+    pub code: i16,
+    pub display_name: String,
+    pub category: String,
+    pub raw_table: String,
+    pub converged_table: String,
+    /// 推送收敛告警时的 modelType
+    pub model_type: String,
+    pub fields: Vec<AlertField>,
+    /// 默认收敛键，依次尝试
+    pub convergence_keys: Vec<Vec<String>>,
+    /// schema/<告警类型>.json，用于校验接收的告警
+    #[serde(skip)]
+    pub schema: Option<Value>,
+}
+
+impl AlertType {
+    /// 告警按列名取值的记录：字段名统一为小写（如 CVE_id 对应 cve_id 列），
+    /// 同时存在大小写两种写法时以小写为准
+    pub fn record(&self, alert: &Value) -> Value {
+        let Some(object) = alert.as_object() else {
+            return alert.clone();
+        };
+        let mut record = object.clone();
+        for (key, value) in object {
+            let lower = key.to_lowercase();
+            if lower != *key && !object.contains_key(&lower) {
+                record.remove(key);
+                record.insert(lower, value.clone());
+            }
+        }
+        Value::Object(record)
+    }
+
+    /// 检查告警是否符合字段声明：必填字段不能缺失，已声明字段的取值类型必须匹配
+    pub fn check(&self, alert: &Value) -> Result<(), AlertShapeError> {
+        let record = self.record(alert);
+        let object = record
+            .as_object()
+            .ok_or_else(|| AlertShapeError(format!("{} 告警必须是 JSON 对象", self.name)))?;
+
+        for field in &self.fields {
+            let value = object.get(&field.name).filter(|v| !v.is_null());
+            let Some(value) = value else {
+                if field.optional {
+                    continue;
+                }
+                return Err(AlertShapeError(format!("缺少必填字段 {}", field.name)));
+            };
+            if !value_matches(field.value_type(), value) {
+                return Err(AlertShapeError(format!(
+                    "字段 {} 的值 {} 不是 {} 类型",
+                    field.name, value, field.field_type
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn value_matches(field_type: FieldType, value: &Value) -> bool {
+    // u64 字段存为 BIGINT，超出 i64 范围的值同样拒绝
+    let is_integer_in =
+        |v: &Value, min: i64, max: i64| v.as_i64().is_some_and(|n| (min..=max).contains(&n));
+    match field_type {
+        FieldType::String => value.is_string(),
+        FieldType::Integer { min, max } => is_integer_in(value, min, max),
+        FieldType::StringList => value
+            .as_array()
+            .is_some_and(|items| items.iter().all(Value::is_string)),
+        FieldType::IntegerList => value
+            .as_array()
+            .is_some_and(|items| items.iter().all(|v| v.is_i64() || v.is_u64())),
+        FieldType::Json | FieldType::Any => true,
+    }
+}
+
+/// 告警内容与字段声明不符
+#[derive(Debug, Clone)]
+pub struct AlertShapeError(pub String);
+
+impl std::fmt::Display for AlertShapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for AlertShapeError {}
+
+/// 所有已配置的告警类型，按编码排序
+#[derive(Debug, Clone, Default)]
+pub struct AlertCatalog {
+    types: Vec<AlertType>,
+}
+
+impl AlertCatalog {
+    pub fn build(alarm_types: &AlarmTypesConfig) -> Self {
+        let declared_fields = fs::read_to_string(ALERT_FIELDS_FILE)
+            .ok()
+            .and_then(|text| text.parse::<toml::Value>().ok());
+
+        let mut types = Vec::new();
+        let mut codes = HashSet::new();
+        for (name, info) in &alarm_types.types {
+            if !IDENTIFIER.is_match(name) {
+                warn!(
+                    "告警类型名 '{}' 只能包含小写字母、数字和下划线，已忽略",
+                    name
+                );
+                continue;
+            }
+            let Ok(code) = info.code.trim().parse::<i16>() else {
+                warn!("告警类型 '{}' 的编码 '{}' 无效，已忽略", name, info.code);
+                continue;
+            };
+            if !codes.insert(code) {
+                warn!("告警类型 '{}' 的编码 {} 与其他类型重复，已忽略", name, code);
+                continue;
+            }
+            types.push(build_type(name, code, info, declared_fields.as_ref()));
+        }
+        types.sort_by_key(|t| t.code);
+
+        Self { types }
+    }
+
+    pub fn types(&self) -> &[AlertType] {
+        &self.types
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AlertType> {
+        self.types.iter().find(|t| t.name == name)
+    }
+
+    pub fn by_code(&self, code: i64) -> Option<&AlertType> {
+        self.types.iter().find(|t| i64::from(t.code) == code)
+    }
+
+    /// 按名称查找告警类型，未配置时返回错误
+    pub fn require(&self, name: &str) -> anyhow::Result<&AlertType> {
+        self.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported alert type: {}", name))
+    }
+}
+
+fn build_type(
+    name: &str,
+    code: i16,
+    info: &AlarmTypeInfo,
+    declared_fields: Option<&toml::Value>,
+) -> AlertType {
+    let section = info
+        .fields
+        .clone()
+        .unwrap_or_else(|| format!("{}_alert", name));
+    let schema = load_schema(name);
+    let mut fields = match (declared_fields.and_then(|v| v.get(&section)), &schema) {
+        (Some(table), _) => fields_from_toml(table),
+        (None, Some(schema)) => fields_from_schema(schema),
+        (None, None) => Vec::new(),
+    };
+    if fields.is_empty() {
+        warn!("告警类型 '{}' 没有声明任何字段", name);
+    }
+
+    let mut seen = HashSet::new();
+    fields.retain(|field| {
+        let valid = IDENTIFIER.is_match(&field.name)
+            && !RESERVED_COLUMNS.contains(&field.name.as_str())
+            && seen.insert(field.name.clone());
+        if !valid {
+            warn!(
+                "告警类型 '{}' 的字段名 '{}' 无效或重复，已忽略",
+                name, field.name
+            );
+        }
+        valid
+    });
+
+    let convergence_keys = info
+        .convergence_keys
+        .iter()
+        .filter(|keys| {
+            let known = !keys.is_empty()
+                && keys
+                    .iter()
+                    .all(|key| fields.iter().any(|field| &field.name == key));
+            if !known {
+                warn!(
+                    "告警类型 '{}' 的默认收敛键 {:?} 包含未声明的字段，已忽略",
+                    name, keys
+                );
+            }
+            known
+        })
+        .cloned()
+        .collect();
+
+    AlertType {
+        name: name.to_string(),
+        code,
+        display_name: info.name.clone(),
+        category: info.category.clone(),
+        raw_table: format!("{}_alerts", name),
+        converged_table: format!("converged_{}_alerts", name),
+        model_type: info
+            .model_type
+            .clone()
+            .unwrap_or_else(|| format!("ALM_{}", name.to_uppercase())),
+        fields,
+        convergence_keys,
+        schema,
+    }
+}
+
+/// 读取 alert_fields.toml 分节：`字段名 = { type = "类型", optional = true, description = "描述" }`
+fn fields_from_toml(section: &toml::Value) -> Vec<AlertField> {
+    let Some(table) = section.as_table() else {
+        return Vec::new();
+    };
+    table
+        .iter()
+        .filter_map(|(name, def)| {
+            let def = def.as_table()?;
+            Some(AlertField {
+                name: name.clone(),
+                field_type: def
+                    .get("type")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("String")
+                    .to_string(),
+                optional: def
+                    .get("optional")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true),
+                description: def
+                    .get("description")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+        })
+        .collect()
+}
+
+/// 读取 schema/<类型>.json，文件不存在时返回 None
+fn load_schema(name: &str) -> Option<Value> {
+    let path = format!("{}/{}.json", SCHEMA_DIR, name);
+    let text = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str::<Value>(&text) {
+        Ok(schema) if schema.is_object() => Some(schema),
+        Ok(_) => {
+            warn!("{} 不是 JSON 对象，已忽略", path);
+            None
+        }
+        Err(e) => {
+            warn!("解析 {} 失败，已忽略: {}", path, e);
+            None
+        }
+    }
+}
+
+/// 从 schema 的 properties 和 required 推导字段
+fn fields_from_schema(schema: &Value) -> Vec<AlertField> {
+    let required: HashSet<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(field, def)| AlertField {
+                    name: field.to_lowercase(),
+                    field_type: schema_field_type(def).to_string(),
+                    optional: !required.contains(field.as_str()),
+                    description: def
+                        .get("description")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// JSON Schema 类型对应的字段声明类型
+fn schema_field_type(def: &Value) -> &'static str {
+    match def.get("type").and_then(Value::as_str) {
+        Some("string") => "String",
+        Some("integer") => "i64",
+        Some("number") => "f64",
+        Some("boolean") => "bool",
+        Some("array") => match def.pointer("/items/type").and_then(Value::as_str) {
+            Some("string") => "Vec<String>",
+            Some("integer") => "Vec<i64>",
+            _ => "Json",
+        },
+        _ => "Json",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_type_from_config() {
+        let alarm_types: AlarmTypesConfig = toml::from_str(
+            r#"
+            [email_phishing]
+            code = "04"
+            name = "邮件钓鱼告警"
+            category = "邮件安全"
+            fields = "host_behavior_alert"
+            convergence_keys = [["host_name"], ["no_such_field"]]
+            subtypes = { 4001 = "钓鱼链接" }
+            "#,
+        )
+        .unwrap();
+        let catalog = AlertCatalog::build(&alarm_types);
+        let email = catalog.by_code(4).unwrap();
+        assert_eq!(email.raw_table, "email_phishing_alerts");
+        assert_eq!(email.model_type, "ALM_EMAIL_PHISHING");
+        assert_eq!(email.convergence_keys, vec![vec!["host_name".to_string()]]);
+
+        // 必填字段缺失或类型不符时拒绝告警
+        assert!(email.check(&serde_json::json!({"alarm_type": 4})).is_err());
+        let alert = serde_json::json!({"alarm_type": 4, "alarm_subtype": 4001, "source": 1});
+        assert!(email.check(&alert).is_ok());
+        let alert = serde_json::json!({"alarm_type": 4, "alarm_subtype": "4001", "source": 1});
+        assert!(email.check(&alert).is_err());
+
+        // u64 字段存为 BIGINT，超出 i64 范围的值在入库前拒绝
+        let sample = AlertCatalog::build(
+            &toml::from_str(
+                r#"
+                [malicious_sample]
+                code = "02"
+                name = "恶意样本告警"
+                category = "恶意样本"
+                subtypes = { 2001 = "计算机病毒" }
+                "#,
+            )
+            .unwrap(),
+        );
+        let sample = sample.by_code(2).unwrap();
+        let alert = |file_size: u64| serde_json::json!({"alarm_type": 2, "alarm_subtype": 2001, "source": 1, "file_size": file_size});
+        assert!(sample.check(&alert(i64::MAX as u64)).is_ok());
+        assert!(sample.check(&alert(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_integer_column_types() {
+        let field = |field_type: &str| AlertField {
+            name: "n".to_string(),
+            field_type: field_type.to_string(),
+            optional: true,
+            description: String::new(),
+        };
+        assert_eq!(field("i16").column_type(), "SMALLINT");
+        assert_eq!(field("i32").column_type(), "INTEGER");
+        assert_eq!(field("u32").column_type(), "BIGINT");
+    }
+}
@@ -0,0 +1,278 @@
+//! 告警 JSON Schema 校验
+//!
+//! 按 `schema/<告警类型>.json` 校验 Kafka 告警，支持常用的 JSON Schema 关键字：
+//! type、enum、const、required、properties、additionalProperties、items、
+//! minimum/maximum、exclusiveMinimum/exclusiveMaximum、minLength/maxLength、pattern、minItems/maxItems。
+//! 生产方对未填写的可选字段常输出 null，因此非必填字段取值为 null 时视为未提供。
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+lazy_static! {
+    /// 已编译的 pattern，编译失败的记为 None
+    static ref PATTERNS: Mutex<HashMap<String, Option<Regex>>> = Mutex::new(HashMap::new());
+}
+
+/// 单个字段的校验错误
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// 字段路径，如 src_port、procedure_technique_id[1]；告警本身为空串
+    pub field: String,
+    /// 违反的关键字，如 required、type、enum
+    pub keyword: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.field.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.field, self.message)
+        }
+    }
+}
+
+/// 校验告警，返回所有字段错误；通过校验时返回空列表
+pub fn validate(schema: &Value, alert: &Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    check_value(schema, alert, "", &mut violations);
+    violations
+}
+
+fn check_value(schema: &Value, value: &Value, path: &str, out: &mut Vec<SchemaViolation>) {
+    // true 或 {} 接受任何值
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+            out.push(violation(
+                path,
+                "type",
+                format!("应为 {} 类型，实际为 {}", types.join("/"), type_name(value)),
+            ));
+            // 类型不符时其余约束没有意义
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            out.push(violation(
+                path,
+                "enum",
+                format!(
+                    "取值 {} 不在允许的范围 {} 内",
+                    value,
+                    Value::from(allowed.clone())
+                ),
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            out.push(violation(path, "const", format!("取值必须为 {}", expected)));
+        }
+    }
+
+    match value {
+        Value::String(s) => check_string(schema, s, path, out),
+        Value::Number(_) => check_number(schema, value, path, out),
+        Value::Array(items) => check_array(schema, items, path, out),
+        Value::Object(object) => check_object(schema, object, path, out),
+        _ => {}
+    }
+}
+
+fn check_string(schema: &Map<String, Value>, s: &str, path: &str, out: &mut Vec<SchemaViolation>) {
+    let len = s.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if len < min {
+            out.push(violation(
+                path,
+                "minLength",
+                format!("长度不能少于 {}", min),
+            ));
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if len > max {
+            out.push(violation(
+                path,
+                "maxLength",
+                format!("长度不能超过 {}", max),
+            ));
+        }
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        let mut patterns = PATTERNS.lock().unwrap();
+        let regex = patterns
+            .entry(pattern.to_string())
+            .or_insert_with(|| Regex::new(pattern).ok());
+        // 无法编译的 pattern 视为 schema 本身的问题，不拒绝告警
+        if regex.as_ref().is_some_and(|re| !re.is_match(s)) {
+            out.push(violation(path, "pattern", format!("不匹配 {}", pattern)));
+        }
+    }
+}
+
+fn check_number(
+    schema: &Map<String, Value>,
+    value: &Value,
+    path: &str,
+    out: &mut Vec<SchemaViolation>,
+) {
+    let Some(n) = value.as_f64() else {
+        return;
+    };
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    if let Some(min) = bound("minimum") {
+        if n < min {
+            out.push(violation(path, "minimum", format!("不能小于 {}", min)));
+        }
+    }
+    if let Some(max) = bound("maximum") {
+        if n > max {
+            out.push(violation(path, "maximum", format!("不能大于 {}", max)));
+        }
+    }
+    if let Some(min) = bound("exclusiveMinimum") {
+        if n <= min {
+            out.push(violation(
+                path,
+                "exclusiveMinimum",
+                format!("必须大于 {}", min),
+            ));
+        }
+    }
+    if let Some(max) = bound("exclusiveMaximum") {
+        if n >= max {
+            out.push(violation(
+                path,
+                "exclusiveMaximum",
+                format!("必须小于 {}", max),
+            ));
+        }
+    }
+}
+
+fn check_array(
+    schema: &Map<String, Value>,
+    items: &[Value],
+    path: &str,
+    out: &mut Vec<SchemaViolation>,
+) {
+    let len = items.len() as u64;
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if len < min {
+            out.push(violation(
+                path,
+                "minItems",
+                format!("元素个数不能少于 {}", min),
+            ));
+        }
+    }
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if len > max {
+            out.push(violation(
+                path,
+                "maxItems",
+                format!("元素个数不能超过 {}", max),
+            ));
+        }
+    }
+    if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter().enumerate() {
+            check_value(item_schema, item, &format!("{}[{}]", path, i), out);
+        }
+    }
+}
+
+fn check_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    out: &mut Vec<SchemaViolation>,
+) {
+    let child_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if object.get(key).is_none_or(Value::is_null) {
+                out.push(violation(
+                    &child_path(key),
+                    "required",
+                    "缺少必填字段".to_string(),
+                ));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (key, value) in object {
+        match properties.and_then(|p| p.get(key)) {
+            // 必填字段为 null 已在 required 中报告
+            Some(_) if value.is_null() => {}
+            Some(property) => check_value(property, value, &child_path(key), out),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => out.push(violation(
+                    &child_path(key),
+                    "additionalProperties",
+                    "未声明的字段".to_string(),
+                )),
+                Some(additional) => check_value(additional, value, &child_path(key), out),
+                None => {}
+            },
+        }
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn violation(path: &str, keyword: &str, message: String) -> SchemaViolation {
+    SchemaViolation {
+        field: path.to_string(),
+        keyword: keyword.to_string(),
+        message,
+    }
+}
@@ -1,38 +1,19 @@
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    response::{IntoResponse, Json},
+    response::{IntoResponse, Json, Response},
 };
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use serde_json::Value;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use super::PageResponse;
+use super::{ErrorResponse, PageResponse};
+use crate::alert_catalog::{self, AlertType};
+use crate::db::list_filter::{ConvergedAlertFilter, InvalidAlertFilter, ThreatEventFilter};
 use crate::db::{self, ThreatEventInput};
 use crate::AppState;
 
-// 为前端响应创建新的结构体，添加 alarm_subtype_name 字段
-#[derive(Serialize)]
-pub struct NetworkAttackResp {
-    #[serde(flatten)]
-    pub inner: db::ConvergedNetworkAttackRecord,
-    pub alarm_subtype_name: String,
-}
-
-#[derive(Serialize)]
-pub struct MaliciousSampleResp {
-    #[serde(flatten)]
-    pub inner: db::ConvergedMaliciousSampleRecord,
-    pub alarm_subtype_name: String,
-}
-
-#[derive(Serialize)]
-pub struct HostBehaviorResp {
-    #[serde(flatten)]
-    pub inner: db::ConvergedHostBehaviorRecord,
-    pub alarm_subtype_name: String,
-}
-
 /// 查询参数
 #[derive(Deserialize)]
 pub struct PageQuery {
@@ -50,27 +31,55 @@ fn default_page_size() -> u64 {
     20
 }
 
-/// 获取网络攻击告警（收敛后）
-pub async fn get_network_attacks(
+/// 查询条件错误
+fn bad_request(message: String) -> Response {
+    let error = ErrorResponse {
+        success: false,
+        message,
+    };
+    (StatusCode::BAD_REQUEST, Json(error)).into_response()
+}
+
+/// 分页查询收敛告警，并补充 alarm_subtype_name 字段
+///
+/// 支持按时间范围、等级、子类型、IP/CIDR、哈希、主机名、标签和收敛次数过滤，以及按字段排序
+pub async fn get_alerts(
     State(state): State<Arc<AppState>>,
+    Path(alert_type): Path<String>,
     Query(params): Query<PageQuery>,
-) -> Json<PageResponse<NetworkAttackResp>> {
-    match db::query_converged_network_attacks(&state.pool, params.page, params.page_size).await {
+    Query(filter): Query<ConvergedAlertFilter>,
+) -> Response {
+    let Some(alert_type_def) = alert_catalog::catalog().get(&alert_type) else {
+        return bad_request(format!("未知的告警类型: {}", alert_type));
+    };
+    let query = match filter.compile(alert_type_def) {
+        Ok(query) => query,
+        Err(message) => return bad_request(message),
+    };
+
+    match db::query_converged_alerts(
+        &state.pool,
+        &alert_type,
+        &query,
+        params.page,
+        params.page_size,
+    )
+    .await
+    {
         Ok((data, total)) => {
-            let data_with_subtype_name: Vec<NetworkAttackResp> = data
+            let subtypes = state.alarm_types.get(&alert_type).map(|t| &t.subtypes);
+            let data_with_subtype_name: Vec<Value> = data
                 .into_iter()
-                .map(|r| {
-                    let subtype_name = state
-                        .alarm_types
-                        .network_attack
-                        .subtypes
-                        .get(&r.alarm_subtype.to_string())
+                .map(|mut r| {
+                    let subtype_name = r
+                        .get("alarm_subtype")
+                        .and_then(|v| subtypes?.get(&v.to_string()))
                         .cloned()
                         .unwrap_or_else(|| "未知".to_string());
-                    NetworkAttackResp {
-                        inner: r,
-                        alarm_subtype_name: subtype_name,
+                    if let Some(object) = r.as_object_mut() {
+                        object.insert("alarm_subtype_name".to_string(), subtype_name.into());
                     }
+                    r
                 })
                 .collect();
 
@@ -80,167 +89,135 @@ pub async fn get_network_attacks(
                 page: params.page,
                 page_size: params.page_size,
             })
+            .into_response()
         }
         Err(e) => {
-            tracing::error!("Query converged network attacks failed: {}", e);
-            Json(PageResponse {
+            tracing::error!("Query converged {} alerts failed: {}", alert_type, e);
+            Json(PageResponse::<Value> {
                 data: vec![],
                 total: 0,
                 page: params.page,
                 page_size: params.page_size,
             })
+            .into_response()
         }
     }
 }
 
+/// 获取网络攻击告警（收敛后）
+pub async fn get_network_attacks(
+    state: State<Arc<AppState>>,
+    params: Query<PageQuery>,
+    filter: Query<ConvergedAlertFilter>,
+) -> Response {
+    get_alerts(state, Path("network_attack".to_string()), params, filter).await
+}
+
 /// 获取恶意样本告警（收敛后）
 pub async fn get_malicious_samples(
-    State(state): State<Arc<AppState>>,
-    Query(params): Query<PageQuery>,
-) -> Json<PageResponse<MaliciousSampleResp>> {
-    match db::query_converged_malicious_samples(&state.pool, params.page, params.page_size).await {
-        Ok((data, total)) => {
-            let data_with_subtype_name: Vec<MaliciousSampleResp> = data
-                .into_iter()
-                .map(|r| {
-                    let subtype_name = state
-                        .alarm_types
-                        .malicious_sample
-                        .subtypes
-                        .get(&r.alarm_subtype.to_string())
-                        .cloned()
-                        .unwrap_or_else(|| "未知".to_string());
-                    MaliciousSampleResp {
-                        inner: r,
-                        alarm_subtype_name: subtype_name,
-                    }
-                })
-                .collect();
-
-            Json(PageResponse {
-                data: data_with_subtype_name,
-                total,
-                page: params.page,
-                page_size: params.page_size,
-            })
-        }
-        Err(e) => {
-            tracing::error!("Query converged malicious samples failed: {}", e);
-            Json(PageResponse {
-                data: vec![],
-                total: 0,
-                page: params.page,
-                page_size: params.page_size,
-            })
-        }
-    }
+    state: State<Arc<AppState>>,
+    params: Query<PageQuery>,
+    filter: Query<ConvergedAlertFilter>,
+) -> Response {
+    get_alerts(state, Path("malicious_sample".to_string()), params, filter).await
 }
 
 /// 获取主机行为告警（收敛后）
 pub async fn get_host_behaviors(
-    State(state): State<Arc<AppState>>,
-    Query(params): Query<PageQuery>,
-) -> Json<PageResponse<HostBehaviorResp>> {
-    match db::query_converged_host_behaviors(&state.pool, params.page, params.page_size).await {
-        Ok((data, total)) => {
-            let data_with_subtype_name: Vec<HostBehaviorResp> = data
-                .into_iter()
-                .map(|r| {
-                    let subtype_name = state
-                        .alarm_types
-                        .host_behavior
-                        .subtypes
-                        .get(&r.alarm_subtype.to_string())
-                        .cloned()
-                        .unwrap_or_else(|| "未知".to_string());
-                    HostBehaviorResp {
-                        inner: r,
-                        alarm_subtype_name: subtype_name,
-                    }
-                })
-                .collect();
-
-            Json(PageResponse {
-                data: data_with_subtype_name,
-                total,
-                page: params.page,
-                page_size: params.page_size,
-            })
-        }
-        Err(e) => {
-            tracing::error!("Query converged host behaviors failed: {}", e);
-            Json(PageResponse {
-                data: vec![],
-                total: 0,
-                page: params.page,
-                page_size: params.page_size,
-            })
-        }
-    }
+    state: State<Arc<AppState>>,
+    params: Query<PageQuery>,
+    filter: Query<ConvergedAlertFilter>,
+) -> Response {
+    get_alerts(state, Path("host_behavior".to_string()), params, filter).await
 }
 
-/// 获取无效告警
+/// 获取无效告警，支持按告警类型、入库时间、错误信息和过滤规则查询
 pub async fn get_invalid_alerts(
     State(state): State<Arc<AppState>>,
     Query(params): Query<PageQuery>,
-) -> Json<PageResponse<db::InvalidAlertRecord>> {
-    match db::query_invalid_alerts(&state.pool, params.page, params.page_size).await {
+    Query(filter): Query<InvalidAlertFilter>,
+) -> Response {
+    let query = match filter.compile() {
+        Ok(query) => query,
+        Err(message) => return bad_request(message),
+    };
+
+    match db::query_invalid_alerts(&state.pool, &query, params.page, params.page_size).await {
         Ok((data, total)) => Json(PageResponse {
             data,
             total,
             page: params.page,
             page_size: params.page_size,
-        }),
+        })
+        .into_response(),
         Err(e) => {
             tracing::error!("Query invalid alerts failed: {}", e);
-            Json(PageResponse {
+            Json(PageResponse::<db::InvalidAlertRecord> {
                 data: vec![],
                 total: 0,
                 page: params.page,
                 page_size: params.page_size,
             })
+            .into_response()
         }
     }
 }
 
-/// 获取威胁事件
+/// 获取威胁事件，支持按监测时间、等级、优先级、分类、处置状态、处置人、IP/CIDR 和关键字查询
 pub async fn get_threat_events(
     State(state): State<Arc<AppState>>,
     Query(params): Query<PageQuery>,
-) -> Json<PageResponse<db::ThreatEventRecord>> {
-    match db::threat_event::query_threat_events(&state.pool, params.page, params.page_size).await {
+    Query(filter): Query<ThreatEventFilter>,
+) -> Response {
+    let query = match filter.compile() {
+        Ok(query) => query,
+        Err(message) => return bad_request(message),
+    };
+
+    match db::threat_event::query_threat_events(&state.pool, &query, params.page, params.page_size)
+        .await
+    {
         Ok((data, total)) => Json(PageResponse {
             data,
             total,
             page: params.page,
             page_size: params.page_size,
-        }),
+        })
+        .into_response(),
         Err(e) => {
             tracing::error!("Query threat events failed: {}", e);
-            Json(PageResponse {
+            Json(PageResponse::<db::ThreatEventRecord> {
                 data: vec![],
                 total: 0,
                 page: params.page,
                 page_size: params.page_size,
             })
+            .into_response()
         }
     }
 }
 
-/// 更新威胁事件
+/// 更新威胁事件内容（处置状态通过 /api/threat-events/:id/transitions 流转）
 pub async fn update_threat_event(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
     Json(event): Json<ThreatEventInput>,
 ) -> impl IntoResponse {
     match db::threat_event::update_threat_event(&state.pool, id, &event).await {
-        Ok(_) => (
+        Ok(true) => (
             StatusCode::OK,
             Json(serde_json::json!({
                 "success": true,
                 "message": "威胁事件更新成功"
             })),
         ),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "威胁事件不存在"
+            })),
+        ),
         Err(e) => {
             tracing::error!("Update threat event failed: {}", e);
             (
@@ -254,44 +231,50 @@ pub async fn update_threat_event(
     }
 }
 
-/// 根据收敛告警ID查询网络攻击原始告警列表
-pub async fn get_raw_network_attacks_by_converged_id(
+/// 根据收敛告警ID查询原始告警列表
+pub async fn get_raw_alerts_by_converged_id(
     State(state): State<Arc<AppState>>,
-    Path(converged_id): Path<Uuid>,
-) -> Json<Vec<db::NetworkAttackRecord>> {
-    match db::query_raw_network_attacks_by_converged_id(&state.pool, converged_id).await {
+    Path((alert_type, converged_id)): Path<(String, Uuid)>,
+) -> Json<Vec<Value>> {
+    match db::query_raw_alerts_of_converged(&state.pool, &alert_type, converged_id).await {
         Ok(alerts) => Json(alerts),
         Err(e) => {
-            tracing::error!("Query raw network attacks by converged id failed: {}", e);
+            tracing::error!(
+                "Query raw {} alerts by converged id failed: {}",
+                alert_type,
+                e
+            );
             Json(vec![])
         }
     }
 }
 
+/// 根据收敛告警ID查询网络攻击原始告警列表
+pub async fn get_raw_network_attacks_by_converged_id(
+    state: State<Arc<AppState>>,
+    Path(converged_id): Path<Uuid>,
+) -> Json<Vec<Value>> {
+    get_raw_alerts_by_converged_id(state, Path(("network_attack".to_string(), converged_id))).await
+}
+
 /// 根据收敛告警ID查询恶意样本原始告警列表
 pub async fn get_raw_malicious_samples_by_converged_id(
-    State(state): State<Arc<AppState>>,
+    state: State<Arc<AppState>>,
     Path(converged_id): Path<Uuid>,
-) -> Json<Vec<db::MaliciousSampleRecord>> {
-    match db::query_raw_malicious_samples_by_converged_id(&state.pool, converged_id).await {
-        Ok(alerts) => Json(alerts),
-        Err(e) => {
-            tracing::error!("Query raw malicious samples by converged id failed: {}", e);
-            Json(vec![])
-        }
-    }
+) -> Json<Vec<Value>> {
+    get_raw_alerts_by_converged_id(state, Path(("malicious_sample".to_string(), converged_id)))
+        .await
 }
 
 /// 根据收敛告警ID查询主机行为原始告警列表
 pub async fn get_raw_host_behaviors_by_converged_id(
-    State(state): State<Arc<AppState>>,
+    state: State<Arc<AppState>>,
     Path(converged_id): Path<Uuid>,
-) -> Json<Vec<db::HostBehaviorRecord>> {
-    match db::query_raw_host_behaviors_by_converged_id(&state.pool, converged_id).await {
-        Ok(alerts) => Json(alerts),
-        Err(e) => {
-            tracing::error!("Query raw host behaviors by converged id failed: {}", e);
-            Json(vec![])
-        }
-    }
+) -> Json<Vec<Value>> {
+    get_raw_alerts_by_converged_id(state, Path(("host_behavior".to_string(), converged_id))).await
+}
+
+/// 获取已配置的告警类型及其字段声明
+pub async fn get_alert_types() -> Json<&'static [AlertType]> {
+    Json(alert_catalog::catalog().types())
 }
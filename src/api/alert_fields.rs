@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use super::{ErrorResponse, SuccessResponse};
+use crate::alert_catalog;
 use crate::AppState;
 
 /// 字段定义
@@ -48,88 +49,30 @@ pub async fn get_alert_fields(State(_state): State<Arc<AppState>>) -> impl IntoR
     }
 }
 
-/// 从 alert_fields.toml 加载字段定义
+/// 从告警类型目录加载字段定义，字段声明来自 alert_fields.toml 或 schema/*.json
 fn load_alert_fields() -> Result<Vec<AlertTypeFields>, Box<dyn std::error::Error>> {
-    let toml_content = std::fs::read_to_string("alert_fields.toml")?;
-    let parsed: toml::Value = toml::from_str(&toml_content)?;
-
-    let mut result = Vec::new();
-
-    // 解析主机行为告警
-    if let Some(host_behavior) = parsed.get("host_behavior_alert") {
-        result.push(parse_alert_type_fields(
-            "host_behavior_alert",
-            "主机行为告警",
-            host_behavior,
-        )?);
-    }
-
-    // 解析恶意样本告警
-    if let Some(malicious_sample) = parsed.get("malicious_sample_alert") {
-        result.push(parse_alert_type_fields(
-            "malicious_sample_alert",
-            "恶意样本告警",
-            malicious_sample,
-        )?);
-    }
-
-    // 解析网络攻击告警
-    if let Some(network_attack) = parsed.get("network_attack_alert") {
-        result.push(parse_alert_type_fields(
-            "network_attack_alert",
-            "网络攻击告警",
-            network_attack,
-        )?);
-    }
+    let result = alert_catalog::catalog()
+        .types()
+        .iter()
+        .map(|alert_type| AlertTypeFields {
+            alert_type: format!("{}_alert", alert_type.name),
+            display_name: alert_type.display_name.clone(),
+            fields: alert_type
+                .fields
+                .iter()
+                .map(|field| FieldDefinition {
+                    name: field.name.clone(),
+                    field_type: field.field_type.clone(),
+                    optional: field.optional,
+                    description: field.description.clone(),
+                })
+                .collect(),
+        })
+        .collect();
 
     Ok(result)
 }
 
-/// 解析单个告警类型的字段定义
-fn parse_alert_type_fields(
-    alert_type: &str,
-    display_name: &str,
-    value: &toml::Value,
-) -> Result<AlertTypeFields, Box<dyn std::error::Error>> {
-    let mut fields = Vec::new();
-
-    if let Some(table) = value.as_table() {
-        for (field_name, field_value) in table {
-            if let Some(field_table) = field_value.as_table() {
-                let field_type = field_table
-                    .get("type")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("String")
-                    .to_string();
-
-                let optional = field_table
-                    .get("optional")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(true);
-
-                let description = field_table
-                    .get("description")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-
-                fields.push(FieldDefinition {
-                    name: field_name.clone(),
-                    field_type,
-                    optional,
-                    description,
-                });
-            }
-        }
-    }
-
-    Ok(AlertTypeFields {
-        alert_type: alert_type.to_string(),
-        display_name: display_name.to_string(),
-        fields,
-    })
-}
-
 /// 字段分组信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FieldGroup {
@@ -0,0 +1,164 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::rules::{error_response, ApiResponse, ApiResult};
+use crate::alert_catalog::{self, AlertType};
+use crate::alert_schema::{self, SchemaViolation};
+use crate::config::ValidationMode;
+use crate::AppState;
+
+/// 告警类型的 schema 概要
+#[derive(Debug, Serialize)]
+pub struct SchemaInfo {
+    pub alert_type: String,
+    pub display_name: String,
+    /// 告警所在的 Kafka 主题
+    pub topic: String,
+    /// 接收告警时的校验模式
+    pub mode: ValidationMode,
+    pub has_schema: bool,
+}
+
+/// 自检结果
+#[derive(Debug, Serialize)]
+pub struct ValidationResult {
+    pub valid: bool,
+    pub mode: ValidationMode,
+    pub violations: Vec<SchemaViolation>,
+}
+
+/// 列出所有告警类型的 schema 及校验模式
+pub async fn get_schemas(State(state): State<Arc<AppState>>) -> Json<ApiResponse<Vec<SchemaInfo>>> {
+    let schemas = alert_catalog::catalog()
+        .types()
+        .iter()
+        .map(|t| SchemaInfo {
+            alert_type: t.name.clone(),
+            display_name: t.display_name.clone(),
+            topic: state.topics.topic_for(&t.name),
+            mode: state.validation.mode_for(&t.name),
+            has_schema: t.schema.is_some(),
+        })
+        .collect();
+
+    Json(ApiResponse {
+        success: true,
+        data: Some(schemas),
+        error: None,
+    })
+}
+
+/// 获取告警类型的 JSON Schema 原文
+pub async fn get_schema(Path(alert_type): Path<String>) -> Response {
+    match find_schema(&alert_type) {
+        Ok((_, schema)) => Json(schema.clone()).into_response(),
+        Err(e) => error_response::<()>(StatusCode::NOT_FOUND, e).into_response(),
+    }
+}
+
+/// 按告警类型的 schema 校验一条告警，供生产方自检
+pub async fn validate_alert(
+    State(state): State<Arc<AppState>>,
+    Path(alert_type): Path<String>,
+    Json(alert): Json<Value>,
+) -> ApiResult<ValidationResult> {
+    let (alert_type, schema) =
+        find_schema(&alert_type).map_err(|e| error_response(StatusCode::NOT_FOUND, e))?;
+    let violations = alert_schema::validate(schema, &alert);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(ValidationResult {
+            valid: violations.is_empty(),
+            mode: state.validation.mode_for(&alert_type.name),
+            violations,
+        }),
+        error: None,
+    }))
+}
+
+fn find_schema(alert_type: &str) -> Result<(&'static AlertType, &'static Value), String> {
+    let alert_type = alert_catalog::catalog()
+        .get(alert_type)
+        .ok_or_else(|| format!("未知的告警类型: {}", alert_type))?;
+    let schema = alert_type
+        .schema
+        .as_ref()
+        .ok_or_else(|| format!("告警类型 {} 没有配置 schema", alert_type.name))?;
+    Ok((alert_type, schema))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_reports_each_field() {
+        let schema = alert_catalog::catalog()
+            .get("network_attack")
+            .and_then(|t| t.schema.clone())
+            .unwrap();
+
+        let alert = json!({
+            "alarm_id": "a-1",
+            "alarm_date": 1700000000,
+            "alarm_severity": 3,
+            "alarm_name": "SQL 注入",
+            "src_ip": "10.0.0.1",
+            "dst_ip": "10.0.0.2",
+            "protocol": "TCP",
+            "src_port": null,
+            "procedure_technique_id": ["T1190"]
+        });
+        assert!(alert_schema::validate(&schema, &alert).is_empty());
+
+        let alert = json!({
+            "alarm_id": "a-1",
+            "alarm_date": "yesterday",
+            "alarm_severity": 3,
+            "alarm_name": null,
+            "src_ip": "10.0.0.1",
+            "dst_ip": "10.0.0.2",
+            "procedure_technique_id": ["T1190", 7]
+        });
+        let violations = alert_schema::validate(&schema, &alert);
+        let fields: Vec<(&str, &str)> = violations
+            .iter()
+            .map(|v| (v.field.as_str(), v.keyword.as_str()))
+            .collect();
+        assert_eq!(violations.len(), 4);
+        assert!(fields.contains(&("alarm_name", "required")));
+        assert!(fields.contains(&("protocol", "required")));
+        assert!(fields.contains(&("alarm_date", "type")));
+        assert!(fields.contains(&("procedure_technique_id[1]", "type")));
+    }
+
+    #[test]
+    fn test_validate_constraints() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "level": { "type": "integer", "minimum": 1, "maximum": 5 },
+                "kind": { "enum": ["a", "b"] },
+                "sender": { "type": "string", "pattern": "^[^@]+@[^@]+$" }
+            },
+            "additionalProperties": false
+        });
+        let alert = json!({ "level": 9, "kind": "c", "sender": "nobody", "extra": 1 });
+        let keywords: Vec<String> = alert_schema::validate(&schema, &alert)
+            .into_iter()
+            .map(|v| v.keyword)
+            .collect();
+        assert_eq!(keywords.len(), 4);
+        for keyword in ["maximum", "enum", "pattern", "additionalProperties"] {
+            assert!(keywords.iter().any(|k| k == keyword), "missing {}", keyword);
+        }
+    }
+}
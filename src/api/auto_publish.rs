@@ -11,16 +11,13 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::alert_catalog::{self, AlertType};
+use crate::api::{ErrorResponse, SuccessResponse};
 use crate::db::auto_push::{
     get_auto_push_config, insert_push_log, list_push_logs, list_push_logs_by_type,
     update_auto_push_config,
 };
-use crate::db::{
-    query_new_converged_host_behaviors, query_new_converged_malicious_samples,
-    query_new_converged_network_attacks,
-};
-// no model structs needed here
-use crate::api::{ErrorResponse, SuccessResponse};
+use crate::db::query_new_converged_alerts;
 use crate::AppState;
 
 #[derive(Deserialize)]
@@ -138,41 +135,31 @@ pub async fn update_push_config(
 
 // (之前的 CRUD APIs 已移除：list_push_configs, create_push_config, get_push_config_by_id, delete_push_config_by_id)
 
-// 定义一个统一的告警载体，用于序列化
-#[derive(Serialize)]
-#[serde(untagged)]
-enum UnifiedAlert {
-    NetworkAttack(OutNetworkAttack),
-    MaliciousSample(OutMaliciousSample),
-    HostBehavior(OutHostBehavior),
-}
+/// 推送时转为毫秒的时间戳字段
+const TIMESTAMP_FIELDS: [&str; 3] = ["alarm_date", "compile_date", "last_analy_date"];
 
 pub(crate) async fn do_publish(state: &AppState, window_minutes: u64) -> Result<usize> {
     let since = Utc::now() - Duration::minutes(window_minutes as i64);
 
-    // 1. 使用新函数高效查询所有未推送的告警
-    let na_records = query_new_converged_network_attacks(&state.pool, since).await?;
-    let ms_records = query_new_converged_malicious_samples(&state.pool, since).await?;
-    let hb_records = query_new_converged_host_behaviors(&state.pool, since).await?;
-
-    // 2. 将所有告警合并到一个 Vec 中，并记录其 ID 和类型用于后续日志
-    let mut unified_alerts: Vec<UnifiedAlert> = Vec::new();
+    // 1. 按告警类型查询所有未推送的收敛告警，并记录其 ID 和类型用于后续日志
+    let mut unified_alerts: Vec<serde_json::Map<String, serde_json::Value>> = Vec::new();
     let mut logs_to_insert: Vec<(i16, Uuid)> = Vec::new();
 
-    for r in na_records {
-        logs_to_insert.push((1, r.id));
-        unified_alerts.push(UnifiedAlert::NetworkAttack(to_payload_na(&r)));
-    }
-    for r in ms_records {
-        logs_to_insert.push((2, r.id));
-        unified_alerts.push(UnifiedAlert::MaliciousSample(to_payload_ms(&r)));
-    }
-    for r in hb_records {
-        logs_to_insert.push((3, r.id));
-        unified_alerts.push(UnifiedAlert::HostBehavior(to_payload_hb(&r)));
+    for alert_type in alert_catalog::catalog().types() {
+        for r in query_new_converged_alerts(&state.pool, &alert_type.name, since).await? {
+            let Some(id) = r
+                .get("id")
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.parse().ok())
+            else {
+                continue;
+            };
+            logs_to_insert.push((alert_type.code, id));
+            unified_alerts.push(to_payload(alert_type, &r));
+        }
     }
 
-    // 3. 如果有新告警，则合并为单条消息进行推送
+    // 2. 如果有新告警，则合并为单条消息进行推送
     if unified_alerts.is_empty() {
         return Ok(0);
     }
@@ -189,7 +176,7 @@ pub(crate) async fn do_publish(state: &AppState, window_minutes: u64) -> Result<
         )
         .await;
 
-    // 4. 推送成功后，批量记录日志
+    // 3. 推送成功后，批量记录日志
     if delivery_status.is_ok() {
         for (alert_type, converged_id) in &logs_to_insert {
             insert_push_log(&state.pool, *alert_type, *converged_id).await?;
@@ -208,343 +195,57 @@ fn ensure_millis(timestamp: Option<i64>) -> Option<i64> {
     timestamp.map(|ts| if ts < 10000000000 { ts * 1000 } else { ts })
 }
 
-// 输出结构体（符合用户给定格式，字段为小驼峰，并补充 modelType 等）
-#[derive(Serialize)]
-#[allow(non_snake_case)]
-pub struct OutNetworkAttack {
-    #[serde(rename = "modelType")]
-    pub model_type: &'static str,
-    #[serde(rename = "alarmId")]
-    pub alarm_id: Option<String>,
-    #[serde(rename = "alarmDate")]
-    pub alarm_date: Option<i64>,
-    #[serde(rename = "alarmSeverity")]
-    pub alarm_severity: Option<i16>,
-    #[serde(rename = "alarmName")]
-    pub alarm_name: Option<String>,
-    #[serde(rename = "alarmDescription")]
-    pub alarm_description: Option<String>,
-    #[serde(rename = "alarmType")]
-    pub alarm_type: i16,
-    #[serde(rename = "alarmSubType")]
-    pub alarm_sub_type: Option<i32>,
-    #[serde(rename = "controlRuleId")]
-    pub control_rule_id: Option<String>,
-    #[serde(rename = "controlTaskId")]
-    pub control_task_id: Option<String>,
-    #[serde(rename = "procedureTechniqueId")]
-    pub procedure_technique_id: Option<serde_json::Value>,
-    #[serde(rename = "sessionId")]
-    pub session_id: Option<String>,
-    #[serde(rename = "ipVersion")]
-    pub ip_version: Option<i16>,
-    #[serde(rename = "srcIp")]
-    pub src_ip: Option<String>,
-    #[serde(rename = "srcPort")]
-    pub src_port: Option<i32>,
-    #[serde(rename = "dstIp")]
-    pub dst_ip: Option<String>,
-    #[serde(rename = "dstPort")]
-    pub dst_port: Option<i32>,
-    pub protocol: Option<String>,
-    #[serde(rename = "terminalId")]
-    pub terminal_id: Option<String>,
-    #[serde(rename = "sourceFilePath")]
-    pub source_file_path: Option<String>,
-    #[serde(rename = "createdAt")]
-    pub created_at: i64,
-    #[serde(rename = "updatedAt")]
-    pub updated_at: i64,
-    #[serde(rename = "signatureId")]
-    pub signature_id: Option<String>,
-    #[serde(rename = "attackPayload")]
-    pub attack_payload: Option<String>,
-    #[serde(rename = "attackStage")]
-    pub attack_stage: Option<String>,
-    #[serde(rename = "attackIp")]
-    pub attack_ip: Option<String>,
-    #[serde(rename = "attackedIp")]
-    pub attacked_ip: Option<String>,
-    #[serde(rename = "aptGroup")]
-    pub apt_group: Option<String>,
-    #[serde(rename = "vulType")]
-    pub vul_type: Option<String>,
-    #[serde(rename = "cveId")]
-    pub cve_id: Option<String>,
-    #[serde(rename = "vulDesc")]
-    pub vul_desc: Option<String>,
-}
-
-fn to_payload_na(r: &crate::db::ConvergedNetworkAttackRecord) -> OutNetworkAttack {
-    OutNetworkAttack {
-        model_type: "ALM_STR_NA",
-        alarm_id: r.alarm_id.clone(),
-        alarm_date: ensure_millis(r.alarm_date),
-        alarm_severity: r.alarm_severity,
-        alarm_name: r.alarm_name.clone(),
-        alarm_description: r.alarm_description.clone(),
-        alarm_type: 1,
-        alarm_sub_type: Some(r.alarm_subtype),
-        control_rule_id: r.control_rule_id.clone(),
-        control_task_id: r.control_task_id.clone(),
-        procedure_technique_id: r.procedure_technique_id.clone(),
-        session_id: r.session_id.clone(),
-        ip_version: r.ip_version,
-        src_ip: r.src_ip.clone(),
-        src_port: r.src_port,
-        dst_ip: r.dst_ip.clone(),
-        dst_port: r.dst_port,
-        protocol: r.protocol.clone(),
-        terminal_id: r.terminal_id.clone(),
-        source_file_path: r.source_file_path.clone(),
-        created_at: r.created_at.timestamp_millis(),
-        updated_at: Utc::now().timestamp_millis(),
-        signature_id: r.signature_id.clone(),
-        attack_payload: r.attack_payload.clone(),
-        attack_stage: r.attack_stage.clone(),
-        attack_ip: r.attack_ip.clone(),
-        attacked_ip: r.attacked_ip.clone(),
-        apt_group: r.apt_group.clone(),
-        vul_type: r.vul_type.clone(),
-        cve_id: r.cve_id.clone(),
-        vul_desc: r.vul_desc.clone(),
-    }
-}
-
-#[derive(Serialize)]
-#[allow(non_snake_case)]
-pub struct OutMaliciousSample {
-    #[serde(rename = "modelType")]
-    pub model_type: &'static str,
-    #[serde(rename = "alarmDate")]
-    pub alarm_date: Option<i64>,
-    #[serde(rename = "alarmDescription")]
-    pub alarm_description: Option<String>,
-    #[serde(rename = "alarmId")]
-    pub alarm_id: Option<String>,
-    #[serde(rename = "alarmName")]
-    pub alarm_name: Option<String>,
-    #[serde(rename = "alarmSeverity")]
-    pub alarm_severity: Option<i16>,
-    #[serde(rename = "alarmType")]
-    pub alarm_type: i16,
-    #[serde(rename = "alarmSubType")]
-    pub alarm_sub_type: Option<i32>,
-    #[serde(rename = "controlRuleId")]
-    pub control_rule_id: Option<String>,
-    #[serde(rename = "controlTaskId")]
-    pub control_task_id: Option<String>,
-    #[serde(rename = "procedureTechniqueId")]
-    pub procedure_technique_id: Option<serde_json::Value>,
-    #[serde(rename = "sessionId")]
-    pub session_id: Option<String>,
-    #[serde(rename = "createdAt")]
-    pub created_at: i64,
-    #[serde(rename = "dstIp")]
-    pub dst_ip: Option<String>,
-    #[serde(rename = "dstPort")]
-    pub dst_port: Option<i32>,
-    #[serde(rename = "fileSize")]
-    pub file_size: Option<i64>,
-    #[serde(rename = "fileType")]
-    pub file_type: Option<String>,
-    #[serde(rename = "ipVersion")]
-    pub ip_version: Option<i16>,
-    pub md5: Option<String>,
-    #[serde(rename = "sampleFamily")]
-    pub sample_family: Option<String>,
-    #[serde(rename = "sampleOriginalName")]
-    pub sample_original_name: Option<String>,
-    #[serde(rename = "sampleSource")]
-    pub sample_source: Option<i16>,
-    pub sha1: Option<String>,
-    pub sha256: Option<String>,
-    #[serde(rename = "srcIp")]
-    pub src_ip: Option<String>,
-    #[serde(rename = "srcPort")]
-    pub src_port: Option<i32>,
-    pub ssdeep: Option<String>,
-    #[serde(rename = "terminalId")]
-    pub terminal_id: Option<String>,
-    #[serde(rename = "aptGroup")]
-    pub apt_group: Option<String>,
-    #[serde(rename = "sampleDescription")]
-    pub sample_description: Option<String>,
-    #[serde(rename = "sampleAlarmEngine")]
-    pub sample_alarm_engine: Option<serde_json::Value>,
-    #[serde(rename = "targetPlatform")]
-    pub target_platform: Option<String>,
-    pub language: Option<String>,
-    pub rule: Option<String>,
-    #[serde(rename = "targetContent")]
-    pub target_content: Option<String>,
-    #[serde(rename = "compileDate")]
-    pub compile_date: Option<i64>,
-    #[serde(rename = "lastAnalyDate")]
-    pub last_analy_date: Option<i64>,
-    #[serde(rename = "sampleAlarmDetail")]
-    pub sample_alarm_detail: Option<String>,
-    #[serde(rename = "updatedAt")]
-    pub updated_at: i64,
-}
-
-fn to_payload_ms(r: &crate::db::ConvergedMaliciousSampleRecord) -> OutMaliciousSample {
-    OutMaliciousSample {
-        model_type: "ALM_STR_MS",
-        alarm_date: ensure_millis(r.alarm_date),
-        alarm_description: r.alarm_description.clone(),
-        alarm_id: r.alarm_id.clone(),
-        alarm_name: r.alarm_name.clone(),
-        alarm_severity: r.alarm_severity,
-        alarm_type: 2,
-        alarm_sub_type: Some(r.alarm_subtype),
-        control_rule_id: r.control_rule_id.clone(),
-        control_task_id: r.control_task_id.clone(),
-        procedure_technique_id: r.procedure_technique_id.clone(),
-        session_id: r.session_id.clone(),
-        created_at: r.created_at.timestamp_millis(),
-        dst_ip: r.dst_ip.clone(),
-        dst_port: r.dst_port,
-        file_size: r.file_size,
-        file_type: r.file_type.clone(),
-        ip_version: r.ip_version,
-        md5: r.md5.clone(),
-        sample_family: r.sample_family.clone(),
-        sample_original_name: r.sample_original_name.clone(),
-        sample_source: r.sample_source,
-        sha1: r.sha1.clone(),
-        sha256: r.sha256.clone(),
-        src_ip: r.src_ip.clone(),
-        src_port: r.src_port,
-        ssdeep: r.ssdeep.clone(),
-        terminal_id: r.terminal_id.clone(),
-        apt_group: r.apt_group.clone(),
-        sample_description: r.sample_description.clone(),
-        sample_alarm_engine: r.sample_alarm_engine.clone(),
-        target_platform: r.target_platform.clone(),
-        language: r.language.clone(),
-        rule: r.rule.clone(),
-        target_content: r.target_content.clone(),
-        compile_date: ensure_millis(r.compile_date),
-        last_analy_date: ensure_millis(r.last_analy_date),
-        sample_alarm_detail: r.sample_alarm_detail.clone(),
-        updated_at: Utc::now().timestamp_millis(),
+/// 声明的字段转为小驼峰输出，并补充 modelType 等
+fn to_payload(
+    alert_type: &AlertType,
+    r: &serde_json::Value,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut out = serde_json::Map::new();
+    out.insert(
+        "modelType".to_string(),
+        alert_type.model_type.clone().into(),
+    );
+    for field in &alert_type.fields {
+        let value = match (field.name.as_str(), r.get(&field.name)) {
+            (name, Some(v)) if TIMESTAMP_FIELDS.contains(&name) => ensure_millis(v.as_i64()).into(),
+            (_, Some(v)) => v.clone(),
+            (_, None) => serde_json::Value::Null,
+        };
+        let key = match field.name.as_str() {
+            // 推送格式约定的字段名
+            "alarm_subtype" => "alarmSubType".to_string(),
+            name => to_camel_case(name),
+        };
+        out.insert(key, value);
     }
+    let created_at = r
+        .get("created_at")
+        .and_then(|v| v.as_str())
+        .and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok())
+        .map(|t| t.timestamp_millis())
+        .unwrap_or_else(|| Utc::now().timestamp_millis());
+    out.insert("createdAt".to_string(), created_at.into());
+    out.insert(
+        "updatedAt".to_string(),
+        Utc::now().timestamp_millis().into(),
+    );
+    out
 }
 
-#[derive(Serialize)]
-#[allow(non_snake_case)]
-pub struct OutHostBehavior {
-    #[serde(rename = "modelType")]
-    pub model_type: &'static str,
-    #[serde(rename = "alarmDate")]
-    pub alarm_date: Option<i64>,
-    #[serde(rename = "alarmDescription")]
-    pub alarm_description: Option<String>,
-    #[serde(rename = "alarmId")]
-    pub alarm_id: Option<String>,
-    #[serde(rename = "alarmName")]
-    pub alarm_name: Option<String>,
-    #[serde(rename = "alarmSeverity")]
-    pub alarm_severity: Option<i16>,
-    #[serde(rename = "alarmType")]
-    pub alarm_type: i16,
-    #[serde(rename = "alarmSubType")]
-    pub alarm_sub_type: Option<i32>,
-    #[serde(rename = "controlRuleId")]
-    pub control_rule_id: Option<String>,
-    #[serde(rename = "controlTaskId")]
-    pub control_task_id: Option<String>,
-    #[serde(rename = "procedureTechniqueId")]
-    pub procedure_technique_id: Option<serde_json::Value>,
-    #[serde(rename = "sessionId")]
-    pub session_id: Option<String>,
-    #[serde(rename = "createdAt")]
-    pub created_at: i64,
-    #[serde(rename = "dstIp")]
-    pub dst_ip: Option<String>,
-    #[serde(rename = "dstPort")]
-    pub dst_port: Option<i32>,
-    #[serde(rename = "dstProcessMd5")]
-    pub dst_process_md5: Option<String>,
-    #[serde(rename = "fileMd5")]
-    pub file_md5: Option<String>,
-    #[serde(rename = "fileName")]
-    pub file_name: Option<String>,
-    #[serde(rename = "filePath")]
-    pub file_path: Option<String>,
-    #[serde(rename = "hostName")]
-    pub host_name: Option<String>,
-    #[serde(rename = "ipVersion")]
-    pub ip_version: Option<i16>,
-    #[serde(rename = "srcIp")]
-    pub src_ip: Option<String>,
-    #[serde(rename = "srcPort")]
-    pub src_port: Option<i32>,
-    #[serde(rename = "terminalIp")]
-    pub terminal_ip: Option<String>,
-    #[serde(rename = "terminalOs")]
-    pub terminal_os: Option<String>,
-    #[serde(rename = "dstProcessPath")]
-    pub dst_process_path: Option<String>,
-    #[serde(rename = "dstProcessCli")]
-    pub dst_process_cli: Option<String>,
-    #[serde(rename = "srcProcessMd5")]
-    pub src_process_md5: Option<String>,
-    #[serde(rename = "srcProcessPath")]
-    pub src_process_path: Option<String>,
-    #[serde(rename = "srcProcessCli")]
-    pub src_process_cli: Option<String>,
-    #[serde(rename = "registerKeyName")]
-    pub register_key_name: Option<String>,
-    #[serde(rename = "registerKeyValue")]
-    pub register_key_value: Option<String>,
-    #[serde(rename = "registerPath")]
-    pub register_path: Option<String>,
-    #[serde(rename = "updatedAt")]
-    pub updated_at: i64,
-    #[serde(rename = "userAccount")]
-    pub user_account: Option<String>,
-}
-
-fn to_payload_hb(r: &crate::db::ConvergedHostBehaviorRecord) -> OutHostBehavior {
-    OutHostBehavior {
-        model_type: "ALM_CLU_ACT",
-        alarm_date: ensure_millis(r.alarm_date),
-        alarm_description: r.alarm_description.clone(),
-        alarm_id: r.alarm_id.clone(),
-        alarm_name: r.alarm_name.clone(),
-        alarm_severity: r.alarm_severity,
-        alarm_type: 3,
-        alarm_sub_type: Some(r.alarm_subtype),
-        control_rule_id: r.control_rule_id.clone(),
-        control_task_id: r.control_task_id.clone(),
-        procedure_technique_id: r.procedure_technique_id.clone(),
-        session_id: r.session_id.clone(),
-        created_at: r.created_at.timestamp_millis(),
-        dst_ip: r.dst_ip.clone(),
-        dst_port: r.dst_port,
-        dst_process_md5: r.dst_process_md5.clone(),
-        file_md5: r.file_md5.clone(),
-        file_name: r.file_name.clone(),
-        file_path: r.file_path.clone(),
-        host_name: r.host_name.clone(),
-        ip_version: r.ip_version,
-        src_ip: r.src_ip.clone(),
-        src_port: r.src_port,
-        terminal_ip: r.terminal_ip.clone(),
-        terminal_os: r.terminal_os.clone(),
-        dst_process_path: r.dst_process_path.clone(),
-        dst_process_cli: r.dst_process_cli.clone(),
-        src_process_md5: r.src_process_md5.clone(),
-        src_process_path: r.src_process_path.clone(),
-        src_process_cli: r.src_process_cli.clone(),
-        register_key_name: r.register_key_name.clone(),
-        register_key_value: r.register_key_value.clone(),
-        register_path: r.register_path.clone(),
-        updated_at: Utc::now().timestamp_millis(),
-        user_account: r.user_account.clone(),
+fn to_camel_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
     }
+    out
 }
 
 // ===== 推送日志查询 =====
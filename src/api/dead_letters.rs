@@ -0,0 +1,186 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::{ErrorResponse, PageResponse, SuccessResponse};
+use crate::db::dead_letters::{self, DeadLetterRecord};
+use crate::AppState;
+
+/// 查询参数
+#[derive(Deserialize)]
+pub struct PageQuery {
+    #[serde(default = "default_page")]
+    pub page: u64,
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+}
+
+fn default_page() -> u64 {
+    1
+}
+
+fn default_page_size() -> u64 {
+    20
+}
+
+/// 重放请求：payload 为修正后的告警 JSON，为空时重放原始消息
+#[derive(Deserialize)]
+pub struct ReplayRequest {
+    pub payload: Option<Value>,
+}
+
+/// 获取死信消息
+pub async fn get_dead_letters(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PageQuery>,
+) -> Json<PageResponse<DeadLetterRecord>> {
+    match dead_letters::query_dead_letters(&state.pool, params.page, params.page_size).await {
+        Ok((data, total)) => Json(PageResponse {
+            data,
+            total,
+            page: params.page,
+            page_size: params.page_size,
+        }),
+        Err(e) => {
+            tracing::error!("Query dead letters failed: {}", e);
+            Json(PageResponse {
+                data: vec![],
+                total: 0,
+                page: params.page,
+                page_size: params.page_size,
+            })
+        }
+    }
+}
+
+/// 根据ID获取死信消息
+pub async fn get_dead_letter_by_id(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match dead_letters::get_dead_letter_by_id(&state.pool, id).await {
+        Ok(Some(record)) => {
+            let response = SuccessResponse {
+                success: true,
+                message: "查询成功".to_string(),
+                data: Some(record),
+            };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Ok(None) => {
+            let error = ErrorResponse {
+                success: false,
+                message: "死信消息不存在".to_string(),
+            };
+            (StatusCode::NOT_FOUND, Json(error)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Get dead letter failed: {}", e);
+            let error = ErrorResponse {
+                success: false,
+                message: format!("查询死信消息失败: {}", e),
+            };
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+        }
+    }
+}
+
+/// 重放死信消息：将修正后的消息重新投递到原始 topic，由消费者按正常流程处理
+pub async fn replay_dead_letter(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(input): Json<ReplayRequest>,
+) -> impl IntoResponse {
+    let record = match dead_letters::get_dead_letter_by_id(&state.pool, id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => {
+            let error = ErrorResponse {
+                success: false,
+                message: "死信消息不存在".to_string(),
+            };
+            return (StatusCode::NOT_FOUND, Json(error)).into_response();
+        }
+        Err(e) => {
+            tracing::error!("Get dead letter failed: {}", e);
+            let error = ErrorResponse {
+                success: false,
+                message: format!("查询死信消息失败: {}", e),
+            };
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+
+    // 未提供修正内容时，原始消息必须是合法 JSON 才能重放
+    let payload = match input.payload {
+        Some(payload) => payload,
+        None => match serde_json::from_slice::<Value>(&record.payload) {
+            Ok(payload) => payload,
+            Err(e) => {
+                let error = ErrorResponse {
+                    success: false,
+                    message: format!("原始消息不是合法 JSON，请提供修正后的 payload: {}", e),
+                };
+                return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+            }
+        },
+    };
+
+    let bytes = match serde_json::to_vec(&payload) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let error = ErrorResponse {
+                success: false,
+                message: format!("序列化消息失败: {}", e),
+            };
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    };
+
+    let producer: FutureProducer = match state.kafka.producer_config().create() {
+        Ok(producer) => producer,
+        Err(e) => {
+            tracing::error!("Create Kafka producer failed: {}", e);
+            let error = ErrorResponse {
+                success: false,
+                message: format!("创建 Kafka 生产者失败: {}", e),
+            };
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+
+    let delivery_status = producer
+        .send(
+            FutureRecord::<(), _>::to(&record.topic).payload(&bytes),
+            Timeout::After(std::time::Duration::from_secs(3)),
+        )
+        .await;
+
+    if let Err((e, _)) = delivery_status {
+        tracing::error!("Replay dead letter {} failed: {}", id, e);
+        let error = ErrorResponse {
+            success: false,
+            message: format!("重放失败: {}", e),
+        };
+        return (StatusCode::BAD_GATEWAY, Json(error)).into_response();
+    }
+
+    if let Err(e) = dead_letters::mark_dead_letter_replayed(&state.pool, id).await {
+        tracing::error!("Mark dead letter {} replayed failed: {}", id, e);
+    }
+
+    tracing::info!("Dead letter {} replayed to topic {}", id, record.topic);
+    let response = SuccessResponse::<()> {
+        success: true,
+        message: format!("已重放到 {}", record.topic),
+        data: None,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
@@ -1,69 +1,120 @@
-use crate::dsl::types::{CompileRequest, CompileResponse};
-use crate::dsl::{parse_converge_rule, parse_correlate_rule, validate_fields, validator};
+use crate::dsl::types::{CompileRequest, CompileResponse, Diagnostic, DslError};
+use crate::dsl::{parse_converge_rule, parse_correlate_rule, validator};
 use axum::{extract::Json, response::Json as JsonResponse};
+use serde::Serialize;
+
+/// 语法解析失败时的响应
+fn parse_failure(e: &anyhow::Error, input: &str) -> JsonResponse<CompileResponse> {
+    let diagnostic = Diagnostic::from_anyhow(e, input);
+    JsonResponse(CompileResponse::error(
+        format!("语法解析失败: {}", located(&diagnostic)),
+        vec![diagnostic],
+    ))
+}
+
+/// 汇总校验诊断：存在错误时返回失败响应，否则返回诊断列表（仅含警告）
+fn check_diagnostics(
+    errors: &[DslError],
+    input: &str,
+) -> Result<Vec<Diagnostic>, JsonResponse<CompileResponse>> {
+    let diagnostics: Vec<Diagnostic> = errors
+        .iter()
+        .map(|e| Diagnostic::from_error(e, input))
+        .collect();
+
+    match errors.iter().position(DslError::is_error) {
+        Some(index) => Err(JsonResponse(CompileResponse::error(
+            format!("字段验证失败: {}", located(&diagnostics[index])),
+            diagnostics,
+        ))),
+        None => Ok(diagnostics),
+    }
+}
+
+/// 带行列号和修复建议的错误描述
+fn located(diagnostic: &Diagnostic) -> String {
+    let mut text = format!(
+        "第 {} 行第 {} 列: {}",
+        diagnostic.line, diagnostic.column, diagnostic.message
+    );
+    if let Some(suggestion) = &diagnostic.suggestion {
+        text.push_str(&format!("（{}）", suggestion));
+    }
+    text
+}
+
+fn ast_of<T: Serialize>(rule: &T) -> Option<serde_json::Value> {
+    serde_json::to_value(rule).ok()
+}
 
 /// 编译收敛规则
 pub async fn compile_converge_rule(
     Json(payload): Json<CompileRequest>,
 ) -> JsonResponse<CompileResponse> {
-    match parse_converge_rule(&payload.dsl_rule) {
-        Ok(rule) => {
-            // 验证字段
-            match validate_fields(&rule) {
-                Ok(_) => JsonResponse(CompileResponse::success(format!(
-                    "DSL 规则语法正确，可以正常使用。已验证规则结构、字段名称和操作符。\n\
-                            - 条件子句: {} 个\n\
-                            - 分组字段: {} 个\n\
-                            - 时间窗口: {} {:?}\n\
-                            - 收敛阈值: {}",
-                    rule.condition.clauses.len(),
-                    rule.group_by.len(),
-                    rule.window.value,
-                    rule.window.unit,
-                    rule.threshold
-                ))),
-                Err(e) => JsonResponse(CompileResponse::error(format!("字段验证失败: {}", e))),
-            }
-        }
-        Err(e) => JsonResponse(CompileResponse::error(format!("语法解析失败: {}", e))),
-    }
+    let rule = match parse_converge_rule(&payload.dsl_rule) {
+        Ok(rule) => rule,
+        Err(e) => return parse_failure(&e, &payload.dsl_rule),
+    };
+
+    // 验证字段
+    let diagnostics =
+        match check_diagnostics(&validator::check_converge_rule(&rule), &payload.dsl_rule) {
+            Ok(diagnostics) => diagnostics,
+            Err(response) => return response,
+        };
+
+    JsonResponse(CompileResponse::success(
+        format!(
+            "DSL 规则语法正确，可以正常使用。已验证规则结构、字段名称和操作符。\n\
+                - 条件子句: {} 个\n\
+                - 分组字段: {} 个\n\
+                - 时间窗口: {} {:?}\n\
+                - 收敛阈值: {}",
+            rule.condition.clauses().len(),
+            rule.group_by.len(),
+            rule.window.value,
+            rule.window.unit,
+            rule.threshold
+        ),
+        ast_of(&rule),
+        diagnostics,
+    ))
 }
 
 /// 编译关联规则
 pub async fn compile_correlate_rule(
     Json(payload): Json<CompileRequest>,
 ) -> JsonResponse<CompileResponse> {
-    match parse_correlate_rule(&payload.dsl_rule) {
-        Ok(rule) => {
-            // 验证字段
-            match validator::validate_correlate_fields(&rule) {
-                Ok(_) => {
-                    JsonResponse(CompileResponse::success(
-                        format!(
-                            "DSL 规则语法正确，可以正常使用。已验证规则结构、事件定义、关联条件和字段名称。\n\
-                            - 关联事件: {} 个\n\
-                            - 关联条件: {} 个\n\
-                            - 时间窗口: {} {:?}\n\
-                            - 生成威胁等级: {}\n\
-                            - 告警名称: {}",
-                            rule.events.len(),
-                            rule.join_on.clauses.len(),
-                            rule.window.value,
-                            rule.window.unit,
-                            rule.generate.severity,
-                            rule.generate.name
-                        )
-                    ))
-                }
-                Err(e) => {
-                    JsonResponse(CompileResponse::error(
-                        format!("字段验证失败: {}", e)
-                    ))
-                }
-            }
-        }
-        Err(e) => JsonResponse(CompileResponse::error(format!("语法解析失败: {}", e))),
-    }
+    let rule = match parse_correlate_rule(&payload.dsl_rule) {
+        Ok(rule) => rule,
+        Err(e) => return parse_failure(&e, &payload.dsl_rule),
+    };
+
+    // 验证字段
+    let diagnostics =
+        match check_diagnostics(&validator::check_correlate_rule(&rule), &payload.dsl_rule) {
+            Ok(diagnostics) => diagnostics,
+            Err(response) => return response,
+        };
+
+    JsonResponse(CompileResponse::success(
+        format!(
+            "DSL 规则语法正确，可以正常使用。已验证规则结构、事件定义、关联条件和字段名称。\n\
+                - 关联事件: {} 个\n\
+                - 关联条件: {} 个\n\
+                - 时间窗口: {} {:?}\n\
+                - 生成威胁等级: {}\n\
+                - 告警名称: {}",
+            rule.events.len(),
+            rule.join_on.clauses.len(),
+            rule.window.value,
+            rule.window.unit,
+            rule.generate.severity,
+            rule.generate.name
+        ),
+        ast_of(&rule),
+        diagnostics,
+    ))
 }
 
 #[cfg(test)]
@@ -83,6 +134,7 @@ mod tests {
 
         let response = compile_converge_rule(Json(request)).await;
         assert!(response.0.success);
+        assert!(response.0.ast.is_some());
     }
 
     #[tokio::test]
@@ -90,7 +142,7 @@ mod tests {
         let request = CompileRequest {
             dsl_rule: r#"CORRELATE
                 EVENT attack WHERE alarm_type == 1
-                EVENT behavior WHERE alarm_type == 2
+                EVENT behavior WHERE alarm_type == 3
                 JOIN ON attack.dst_ip == behavior.terminal_ip
                 WINDOW 10m
                 GENERATE
@@ -127,6 +179,9 @@ mod tests {
             .as_ref()
             .unwrap()
             .contains("invalid_field_name"));
+        let diagnostic = &response.0.diagnostics[0];
+        assert_eq!(diagnostic.code, "unknown_field");
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 23));
     }
 
     #[tokio::test]
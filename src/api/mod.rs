@@ -1,10 +1,17 @@
 pub mod alert_data;
 pub mod alert_fields;
+pub mod alert_schema;
 pub mod alert_tag;
 pub mod auto_publish;
+pub mod dead_letters;
 pub mod dsl_compile;
+pub mod rule_bundle;
+pub mod rule_simulation;
 pub mod rules;
 pub mod tag_management;
+pub mod threat_event_alerts;
+pub mod threat_event_push;
+pub mod threat_event_workflow;
 
 use serde::Serialize;
 
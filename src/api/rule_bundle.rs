@@ -0,0 +1,151 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::rules::{error_response, reload_rule_set, request_author, ApiResponse, ApiResult};
+use crate::db::rule_bundle::{self, BundleFormat, ConflictMode, ImportOptions, ImportReport};
+use crate::AppState;
+
+/// 规则包导出参数
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: BundleFormat,
+}
+
+/// 规则包导入参数
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub format: BundleFormat,
+    #[serde(default)]
+    pub conflict: ConflictMode,
+    /// 只校验并预演，不写入数据库
+    #[serde(default)]
+    pub validate_only: bool,
+}
+
+/// 导出所有规则和标签为规则包文件
+pub async fn export_rules(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let rendered = match rule_bundle::export_bundle(&state.pool).await {
+        Ok(bundle) => rule_bundle::render_bundle(&bundle, query.format),
+        Err(e) => Err(e),
+    };
+
+    match rendered {
+        Ok(body) => (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    query.format.content_type().to_string(),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!(
+                        "attachment; filename=\"rules.{}\"",
+                        query.format.extension()
+                    ),
+                ),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(e) => error_response::<()>(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("导出规则失败: {}", e),
+        )
+        .into_response(),
+    }
+}
+
+/// 导入规则包；校验失败时不写入任何数据并返回 400 及错误明细
+pub async fn import_rules(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ImportQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> ApiResult<ImportReport> {
+    let bundle = rule_bundle::parse_bundle(&body, query.format)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, format!("规则包格式错误: {}", e)))?;
+
+    let options = ImportOptions {
+        conflict: query.conflict,
+        validate_only: query.validate_only,
+        author: request_author(&headers),
+    };
+    let report = rule_bundle::import_bundle(&state.pool, bundle, &state.alarm_types, &options)
+        .await
+        .map_err(|e| {
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("导入规则失败: {}", e),
+            )
+        })?;
+
+    if !report.errors.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse {
+                success: false,
+                error: Some(format!("规则包校验失败，共 {} 处错误", report.errors.len())),
+                data: Some(report),
+            }),
+        ));
+    }
+
+    if report.applied {
+        reload_rule_set(&state).await;
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(report),
+        error: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::rule_bundle::{RuleBundle, BUNDLE_FORMAT_VERSION};
+    use crate::db::tag_rules::TagRuleInput;
+
+    #[test]
+    fn test_bundle_toml_round_trip() {
+        let bundle = RuleBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            tag_rules: vec![TagRuleInput {
+                name: "外部扫描".to_string(),
+                alert_type: "network_attack".to_string(),
+                alert_subtype: "*".to_string(),
+                condition_field: String::new(),
+                condition_operator: String::new(),
+                condition_value: String::new(),
+                condition: Some("alarm_severity >= 3".to_string()),
+                tags: vec!["扫描".to_string()],
+                description: None,
+                enabled: true,
+            }],
+            ..Default::default()
+        };
+
+        let text = rule_bundle::render_bundle(&bundle, BundleFormat::Toml).unwrap();
+        let parsed = rule_bundle::parse_bundle(&text, BundleFormat::Toml).unwrap();
+        assert_eq!(parsed.tag_rules.len(), 1);
+        assert_eq!(parsed.tag_rules[0].tags, vec!["扫描".to_string()]);
+        assert!(parsed.convergence_rules.is_empty());
+    }
+
+    #[test]
+    fn test_bundle_rejects_newer_format() {
+        let text = format!(r#"{{"format_version": {}}}"#, BUNDLE_FORMAT_VERSION + 1);
+        assert!(rule_bundle::parse_bundle(&text, BundleFormat::Json).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::rules::{error_response, ApiResponse, ApiResult};
+use crate::db::{convergence_rules, filter_rules, raw_alerts, tag_rules};
+use crate::kafka::{
+    ConvergencePlan, FilterPlan, SimulatedRule, Simulation, SimulationReport, TagPlan,
+};
+use crate::AppState;
+
+/// 样例告警和收敛分组的最大返回条数
+const MAX_SAMPLE_SIZE: usize = 100;
+/// 每个告警类型最多扫描的原始告警数
+const MAX_ALERTS_PER_TYPE: i64 = 50_000;
+
+/// 试运行的规则类型
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulatedRuleType {
+    Filter,
+    Tag,
+    Convergence,
+}
+
+/// 规则试运行请求
+#[derive(Debug, Deserialize)]
+pub struct SimulationRequest {
+    pub rule_type: SimulatedRuleType,
+    /// 已保存规则的ID，与 rule 二选一
+    pub rule_id: Option<Uuid>,
+    /// 未保存的规则定义，格式与对应规则的创建接口相同
+    pub rule: Option<serde_json::Value>,
+    /// 按原始告警入库时间筛选，区间为 [start_time, end_time)
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    #[serde(default = "default_sample_size")]
+    pub sample_size: usize,
+    /// 每个告警类型最多扫描的告警数
+    #[serde(default = "default_max_alerts")]
+    pub max_alerts: i64,
+}
+
+fn default_sample_size() -> usize {
+    20
+}
+
+fn default_max_alerts() -> i64 {
+    10_000
+}
+
+/// 对历史原始告警试运行过滤、标签或收敛规则，只返回统计结果，不写入数据
+pub async fn simulate_rule(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SimulationRequest>,
+) -> ApiResult<SimulationReport> {
+    if request.end_time <= request.start_time {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "结束时间必须晚于开始时间".to_string(),
+        ));
+    }
+
+    let rule = load_rule(&state, &request).await?;
+    let sample_size = request.sample_size.min(MAX_SAMPLE_SIZE);
+    let max_alerts = request.max_alerts.clamp(1, MAX_ALERTS_PER_TYPE);
+
+    let mut simulation = Simulation::new(rule, sample_size);
+    for alert_type in simulation.alert_types() {
+        // 多取一条用于判断是否被截断
+        let rows = raw_alerts::query_raw_alerts_in_range(
+            &state.pool,
+            alert_type,
+            request.start_time,
+            request.end_time,
+            max_alerts + 1,
+        )
+        .await
+        .map_err(|e| {
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("查询原始告警失败: {}", e),
+            )
+        })?;
+
+        if rows.len() as i64 > max_alerts {
+            simulation.mark_truncated();
+        }
+        for row in rows.iter().take(max_alerts as usize) {
+            simulation.observe(alert_type, row);
+        }
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(simulation.finish(sample_size)),
+        error: None,
+    }))
+}
+
+/// 加载已保存的规则或解析未保存的规则定义，并编译为可执行计划
+async fn load_rule(
+    state: &AppState,
+    request: &SimulationRequest,
+) -> Result<SimulatedRule, (StatusCode, Json<ApiResponse<SimulationReport>>)> {
+    let invalid = |e: anyhow::Error| bad_request(format!("规则无效: {}", e));
+    let now = Utc::now();
+
+    match request.rule_type {
+        SimulatedRuleType::Filter => {
+            let record = match request.rule_id {
+                Some(id) => filter_rules::get_filter_rule_by_id(&state.pool, id)
+                    .await
+                    .map_err(|_| not_found(id))?,
+                None => {
+                    let input: filter_rules::FilterRuleInput =
+                        draft(request).map_err(bad_request)?;
+                    filter_rules::FilterRuleRecord {
+                        id: Uuid::nil(),
+                        alert_subtype: state
+                            .alarm_types
+                            .validate_subtypes(&input.alert_type, &input.alert_subtype)
+                            .map_err(bad_request)?,
+                        condition: input.resolved_condition(),
+                        name: input.name,
+                        alert_type: input.alert_type,
+                        field: input.field,
+                        operator: input.operator,
+                        value: input.value,
+                        enabled: input.enabled,
+                        version: 0,
+                        hit_count: 0,
+                        last_hit_at: None,
+                        last_hit_alert_id: None,
+                        created_at: now,
+                        updated_at: now,
+                    }
+                }
+            };
+            FilterPlan::compile(&record)
+                .map(SimulatedRule::Filter)
+                .map_err(invalid)
+        }
+        SimulatedRuleType::Tag => {
+            let record = match request.rule_id {
+                Some(id) => tag_rules::get_tag_rule_by_id(&state.pool, id)
+                    .await
+                    .map_err(|_| not_found(id))?,
+                None => {
+                    let input: tag_rules::TagRuleInput = draft(request).map_err(bad_request)?;
+                    tag_rules::TagRuleRecord {
+                        id: Uuid::nil(),
+                        alert_subtype: state
+                            .alarm_types
+                            .validate_subtypes(&input.alert_type, &input.alert_subtype)
+                            .map_err(bad_request)?,
+                        condition: input.resolved_condition(),
+                        name: input.name,
+                        alert_type: input.alert_type,
+                        condition_field: input.condition_field,
+                        condition_operator: input.condition_operator,
+                        condition_value: input.condition_value,
+                        tags: input.tags,
+                        description: input.description,
+                        enabled: input.enabled,
+                        version: 0,
+                        hit_count: 0,
+                        last_hit_at: None,
+                        last_hit_alert_id: None,
+                        created_at: now,
+                        updated_at: now,
+                    }
+                }
+            };
+            TagPlan::compile(&record)
+                .map(SimulatedRule::Tag)
+                .map_err(invalid)
+        }
+        SimulatedRuleType::Convergence => {
+            let record = match request.rule_id {
+                Some(id) => convergence_rules::get_convergence_rule_by_id(&state.pool, id)
+                    .await
+                    .map_err(|_| not_found(id))?,
+                None => {
+                    let input: convergence_rules::ConvergenceRuleInput =
+                        draft(request).map_err(bad_request)?;
+                    convergence_rules::ConvergenceRuleRecord {
+                        id: Uuid::nil(),
+                        name: input.name,
+                        dsl_rule: input.dsl_rule,
+                        description: input.description,
+                        enabled: input.enabled,
+                        version: 0,
+                        created_at: now,
+                        updated_at: now,
+                    }
+                }
+            };
+            ConvergencePlan::compile(&record)
+                .map(SimulatedRule::Convergence)
+                .map_err(invalid)
+        }
+    }
+}
+
+/// 解析请求中未保存的规则定义
+fn draft<T: DeserializeOwned>(request: &SimulationRequest) -> Result<T, String> {
+    let rule = request
+        .rule
+        .clone()
+        .ok_or_else(|| "需要提供 rule_id 或 rule".to_string())?;
+    serde_json::from_value(rule).map_err(|e| format!("规则定义格式错误: {}", e))
+}
+
+fn bad_request(message: String) -> (StatusCode, Json<ApiResponse<SimulationReport>>) {
+    error_response(StatusCode::BAD_REQUEST, message)
+}
+
+fn not_found(id: Uuid) -> (StatusCode, Json<ApiResponse<SimulationReport>>) {
+    error_response(StatusCode::NOT_FOUND, format!("规则不存在: {}", id))
+}
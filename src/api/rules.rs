@@ -1,13 +1,16 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Json,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::db::rule_versions::{self, RuleChange, RuleKind, RuleVersionRecord};
 use crate::db::{convergence_rules, correlation_rules, filter_rules, tag_rules};
+use crate::dsl::validator;
+use crate::kafka::RuleSetSummary;
 use crate::AppState;
 
 /// 分页查询参数
@@ -35,6 +38,24 @@ pub struct ApiResponse<T> {
     pub error: Option<String>,
 }
 
+/// 失败时携带错误信息的响应
+pub(crate) type ApiResult<T> = Result<Json<ApiResponse<T>>, (StatusCode, Json<ApiResponse<T>>)>;
+
+/// 带错误信息的失败响应
+pub(crate) fn error_response<T>(
+    status: StatusCode,
+    message: String,
+) -> (StatusCode, Json<ApiResponse<T>>) {
+    (
+        status,
+        Json(ApiResponse {
+            success: false,
+            data: None,
+            error: Some(message),
+        }),
+    )
+}
+
 /// 分页响应数据
 #[derive(Debug, Serialize)]
 pub struct PageData<T> {
@@ -88,14 +109,24 @@ pub async fn get_convergence_rule_by_id(
 /// 创建收敛规则
 pub async fn create_convergence_rule(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(input): Json<convergence_rules::ConvergenceRuleInput>,
 ) -> Result<Json<ApiResponse<convergence_rules::ConvergenceRuleRecord>>, StatusCode> {
-    match convergence_rules::create_convergence_rule(&state.pool, &input).await {
-        Ok(rule) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(rule),
-            error: None,
-        })),
+    match convergence_rules::create_convergence_rule(
+        &state.pool,
+        &input,
+        &RuleChange::create(request_author(&headers).as_deref()),
+    )
+    .await
+    {
+        Ok(rule) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(rule),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("创建收敛规则失败: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -107,14 +138,25 @@ pub async fn create_convergence_rule(
 pub async fn update_convergence_rule(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
+    headers: HeaderMap,
     Json(input): Json<convergence_rules::ConvergenceRuleInput>,
 ) -> Result<Json<ApiResponse<convergence_rules::ConvergenceRuleRecord>>, StatusCode> {
-    match convergence_rules::update_convergence_rule(&state.pool, id, &input).await {
-        Ok(rule) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(rule),
-            error: None,
-        })),
+    match convergence_rules::update_convergence_rule(
+        &state.pool,
+        id,
+        &input,
+        &RuleChange::update(request_author(&headers).as_deref()),
+    )
+    .await
+    {
+        Ok(rule) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(rule),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("更新收敛规则失败: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -128,11 +170,14 @@ pub async fn delete_convergence_rule(
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<()>>, StatusCode> {
     match convergence_rules::delete_convergence_rule(&state.pool, id).await {
-        Ok(_) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(()),
-            error: None,
-        })),
+        Ok(_) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(()),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("删除收敛规则失败: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -184,14 +229,24 @@ pub async fn get_correlation_rule_by_id(
 /// 创建关联规则
 pub async fn create_correlation_rule(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(input): Json<correlation_rules::CorrelationRuleInput>,
 ) -> Result<Json<ApiResponse<correlation_rules::CorrelationRuleRecord>>, StatusCode> {
-    match correlation_rules::create_correlation_rule(&state.pool, &input).await {
-        Ok(rule) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(rule),
-            error: None,
-        })),
+    match correlation_rules::create_correlation_rule(
+        &state.pool,
+        &input,
+        &RuleChange::create(request_author(&headers).as_deref()),
+    )
+    .await
+    {
+        Ok(rule) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(rule),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("创建关联规则失败: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -203,14 +258,25 @@ pub async fn create_correlation_rule(
 pub async fn update_correlation_rule(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
+    headers: HeaderMap,
     Json(input): Json<correlation_rules::CorrelationRuleInput>,
 ) -> Result<Json<ApiResponse<correlation_rules::CorrelationRuleRecord>>, StatusCode> {
-    match correlation_rules::update_correlation_rule(&state.pool, id, &input).await {
-        Ok(rule) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(rule),
-            error: None,
-        })),
+    match correlation_rules::update_correlation_rule(
+        &state.pool,
+        id,
+        &input,
+        &RuleChange::update(request_author(&headers).as_deref()),
+    )
+    .await
+    {
+        Ok(rule) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(rule),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("更新关联规则失败: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -224,11 +290,14 @@ pub async fn delete_correlation_rule(
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<()>>, StatusCode> {
     match correlation_rules::delete_correlation_rule(&state.pool, id).await {
-        Ok(_) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(()),
-            error: None,
-        })),
+        Ok(_) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(()),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("删除关联规则失败: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -276,20 +345,59 @@ pub async fn get_filter_rule_by_id(
     }
 }
 
+/// 校验过滤规则、标签规则的 DSL 条件，返回规范的条件文本
+fn checked_condition(alert_type: &str, condition: Option<String>) -> Result<String, String> {
+    let condition = condition.ok_or_else(|| "规则缺少有效的条件表达式".to_string())?;
+    validator::compile_condition(&condition, alert_type)
+        .map_err(|e| format!("条件表达式无效: {}", e))?;
+    Ok(condition)
+}
+
+/// 校验过滤规则输入，规范化子类型并写入生效的 DSL 条件
+fn checked_filter_input(
+    state: &AppState,
+    input: &mut filter_rules::FilterRuleInput,
+) -> Result<(), String> {
+    input.alert_subtype = state
+        .alarm_types
+        .validate_subtypes(&input.alert_type, &input.alert_subtype)?;
+    input.condition = Some(checked_condition(
+        &input.alert_type,
+        input.resolved_condition(),
+    )?);
+    Ok(())
+}
+
 /// 创建过滤规则
 pub async fn create_filter_rule(
     State(state): State<Arc<AppState>>,
-    Json(input): Json<filter_rules::FilterRuleInput>,
-) -> Result<Json<ApiResponse<filter_rules::FilterRuleRecord>>, StatusCode> {
-    match filter_rules::create_filter_rule(&state.pool, &input).await {
-        Ok(rule) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(rule),
-            error: None,
-        })),
+    headers: HeaderMap,
+    Json(mut input): Json<filter_rules::FilterRuleInput>,
+) -> ApiResult<filter_rules::FilterRuleRecord> {
+    checked_filter_input(&state, &mut input)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?;
+
+    match filter_rules::create_filter_rule(
+        &state.pool,
+        &input,
+        &RuleChange::create(request_author(&headers).as_deref()),
+    )
+    .await
+    {
+        Ok(rule) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(rule),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("创建过滤规则失败: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("创建过滤规则失败: {}", e),
+            ))
         }
     }
 }
@@ -298,17 +406,34 @@ pub async fn create_filter_rule(
 pub async fn update_filter_rule(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(input): Json<filter_rules::FilterRuleInput>,
-) -> Result<Json<ApiResponse<filter_rules::FilterRuleRecord>>, StatusCode> {
-    match filter_rules::update_filter_rule(&state.pool, id, &input).await {
-        Ok(rule) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(rule),
-            error: None,
-        })),
+    headers: HeaderMap,
+    Json(mut input): Json<filter_rules::FilterRuleInput>,
+) -> ApiResult<filter_rules::FilterRuleRecord> {
+    checked_filter_input(&state, &mut input)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?;
+
+    match filter_rules::update_filter_rule(
+        &state.pool,
+        id,
+        &input,
+        &RuleChange::update(request_author(&headers).as_deref()),
+    )
+    .await
+    {
+        Ok(rule) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(rule),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("更新过滤规则失败: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("更新过滤规则失败: {}", e),
+            ))
         }
     }
 }
@@ -319,11 +444,14 @@ pub async fn delete_filter_rule(
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<()>>, StatusCode> {
     match filter_rules::delete_filter_rule(&state.pool, id).await {
-        Ok(_) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(()),
-            error: None,
-        })),
+        Ok(_) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(()),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("删除过滤规则失败: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -371,20 +499,48 @@ pub async fn get_tag_rule_by_id(
     }
 }
 
+/// 校验标签规则输入，规范化子类型并写入生效的 DSL 条件
+fn checked_tag_input(state: &AppState, input: &mut tag_rules::TagRuleInput) -> Result<(), String> {
+    input.alert_subtype = state
+        .alarm_types
+        .validate_subtypes(&input.alert_type, &input.alert_subtype)?;
+    input.condition = Some(checked_condition(
+        &input.alert_type,
+        input.resolved_condition(),
+    )?);
+    Ok(())
+}
+
 /// 创建标签规则
 pub async fn create_tag_rule(
     State(state): State<Arc<AppState>>,
-    Json(input): Json<tag_rules::TagRuleInput>,
-) -> Result<Json<ApiResponse<tag_rules::TagRuleRecord>>, StatusCode> {
-    match tag_rules::create_tag_rule(&state.pool, &input).await {
-        Ok(rule) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(rule),
-            error: None,
-        })),
+    headers: HeaderMap,
+    Json(mut input): Json<tag_rules::TagRuleInput>,
+) -> ApiResult<tag_rules::TagRuleRecord> {
+    checked_tag_input(&state, &mut input)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?;
+
+    match tag_rules::create_tag_rule(
+        &state.pool,
+        &input,
+        &RuleChange::create(request_author(&headers).as_deref()),
+    )
+    .await
+    {
+        Ok(rule) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(rule),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("创建标签规则失败: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("创建标签规则失败: {}", e),
+            ))
         }
     }
 }
@@ -393,17 +549,34 @@ pub async fn create_tag_rule(
 pub async fn update_tag_rule(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(input): Json<tag_rules::TagRuleInput>,
-) -> Result<Json<ApiResponse<tag_rules::TagRuleRecord>>, StatusCode> {
-    match tag_rules::update_tag_rule(&state.pool, id, &input).await {
-        Ok(rule) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(rule),
-            error: None,
-        })),
+    headers: HeaderMap,
+    Json(mut input): Json<tag_rules::TagRuleInput>,
+) -> ApiResult<tag_rules::TagRuleRecord> {
+    checked_tag_input(&state, &mut input)
+        .map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?;
+
+    match tag_rules::update_tag_rule(
+        &state.pool,
+        id,
+        &input,
+        &RuleChange::update(request_author(&headers).as_deref()),
+    )
+    .await
+    {
+        Ok(rule) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(rule),
+                error: None,
+            }))
+        }
         Err(e) => {
             eprintln!("更新标签规则失败: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("更新标签规则失败: {}", e),
+            ))
         }
     }
 }
@@ -414,13 +587,202 @@ pub async fn delete_tag_rule(
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<()>>, StatusCode> {
     match tag_rules::delete_tag_rule(&state.pool, id).await {
+        Ok(_) => {
+            reload_rule_set(&state).await;
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(()),
+                error: None,
+            }))
+        }
+        Err(e) => {
+            eprintln!("删除标签规则失败: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// ==================== 规则版本 API ====================
+
+/// 从请求头 X-User 中读取变更作者
+pub(crate) fn request_author(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-user")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// 查询规则的版本历史
+pub async fn get_rule_versions(
+    State(state): State<Arc<AppState>>,
+    Path((kind, id)): Path<(RuleKind, Uuid)>,
+) -> ApiResult<Vec<RuleVersionRecord>> {
+    match rule_versions::query_rule_versions(&state.pool, kind, id).await {
+        Ok(versions) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(versions),
+            error: None,
+        })),
+        Err(e) => Err(error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("查询规则版本失败: {}", e),
+        )),
+    }
+}
+
+/// 版本对比查询参数
+#[derive(Debug, Deserialize)]
+pub struct VersionDiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+
+/// 两个版本之间的差异
+#[derive(Debug, Serialize)]
+pub struct RuleVersionDiff {
+    pub from: i32,
+    pub to: i32,
+    /// 发生变化的字段：{"字段": {"old": from 版本的值, "new": to 版本的值}}
+    pub diff: serde_json::Value,
+}
+
+/// 对比规则的两个版本
+pub async fn diff_rule_versions(
+    State(state): State<Arc<AppState>>,
+    Path((kind, id)): Path<(RuleKind, Uuid)>,
+    Query(query): Query<VersionDiffQuery>,
+) -> ApiResult<RuleVersionDiff> {
+    let from = find_rule_version(&state, kind, id, query.from).await?;
+    let to = find_rule_version(&state, kind, id, query.to).await?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(RuleVersionDiff {
+            from: query.from,
+            to: query.to,
+            diff: rule_versions::diff_snapshots(&from.snapshot, &to.snapshot),
+        }),
+        error: None,
+    }))
+}
+
+/// 将规则回滚到指定版本，回滚本身作为一个新版本记录
+pub async fn rollback_rule(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((kind, id, version)): Path<(RuleKind, Uuid, i32)>,
+) -> ApiResult<RuleVersionRecord> {
+    let target = find_rule_version(&state, kind, id, version).await?;
+    let author = request_author(&headers);
+    let change = RuleChange::rollback(author.as_deref(), version);
+
+    let invalid = |e: serde_json::Error| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            format!("版本 {} 的规则定义无法解析: {}", version, e),
+        )
+    };
+    let updated = match kind {
+        RuleKind::Convergence => {
+            let input = serde_json::from_value(target.snapshot).map_err(invalid)?;
+            convergence_rules::update_convergence_rule(&state.pool, id, &input, &change)
+                .await
+                .map(|rule| rule.version)
+        }
+        RuleKind::Correlation => {
+            let input = serde_json::from_value(target.snapshot).map_err(invalid)?;
+            correlation_rules::update_correlation_rule(&state.pool, id, &input, &change)
+                .await
+                .map(|rule| rule.version)
+        }
+        RuleKind::Filter => {
+            let mut input = serde_json::from_value(target.snapshot).map_err(invalid)?;
+            checked_filter_input(&state, &mut input)
+                .map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?;
+            filter_rules::update_filter_rule(&state.pool, id, &input, &change)
+                .await
+                .map(|rule| rule.version)
+        }
+        RuleKind::Tag => {
+            let mut input = serde_json::from_value(target.snapshot).map_err(invalid)?;
+            checked_tag_input(&state, &mut input)
+                .map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?;
+            tag_rules::update_tag_rule(&state.pool, id, &input, &change)
+                .await
+                .map(|rule| rule.version)
+        }
+    };
+
+    let new_version = updated.map_err(|e| {
+        let status = match e.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::RowNotFound) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        error_response(status, format!("回滚规则失败: {}", e))
+    })?;
+    reload_rule_set(&state).await;
+
+    let record = find_rule_version(&state, kind, id, new_version).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(record),
+        error: None,
+    }))
+}
+
+async fn find_rule_version<T>(
+    state: &AppState,
+    kind: RuleKind,
+    id: Uuid,
+    version: i32,
+) -> Result<RuleVersionRecord, (StatusCode, Json<ApiResponse<T>>)> {
+    match rule_versions::get_rule_version(&state.pool, kind, id, version).await {
+        Ok(Some(record)) => Ok(record),
+        Ok(None) => Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("规则版本不存在: {}", version),
+        )),
+        Err(e) => Err(error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("查询规则版本失败: {}", e),
+        )),
+    }
+}
+
+// ==================== 规则热重载 API ====================
+
+/// 规则或标签变更后热重载运行时规则，失败时保留旧规则继续运行
+pub async fn reload_rule_set(state: &AppState) {
+    if let Err(e) = state.rules.reload(&state.pool).await {
+        eprintln!("热重载规则失败: {}", e);
+    }
+}
+
+/// 查询当前生效的规则版本
+pub async fn get_rule_set_version(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<RuleSetSummary>> {
+    Json(ApiResponse {
+        success: true,
+        data: Some(state.rules.summary()),
+        error: None,
+    })
+}
+
+/// 手动触发规则热重载（例如直接修改数据库后）
+pub async fn reload_rules(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<RuleSetSummary>>, StatusCode> {
+    match state.rules.reload(&state.pool).await {
         Ok(_) => Ok(Json(ApiResponse {
             success: true,
-            data: Some(()),
+            data: Some(state.rules.summary()),
             error: None,
         })),
         Err(e) => {
-            eprintln!("删除标签规则失败: {}", e);
+            eprintln!("热重载规则失败: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
@@ -7,6 +7,7 @@ use serde::Deserialize;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use super::rules::reload_rule_set;
 use super::{ErrorResponse, PageResponse, SuccessResponse};
 use crate::db::tag_management::{self, TagInput};
 use crate::AppState;
@@ -157,6 +158,7 @@ pub async fn create_tag(
 
     match tag_management::create_tag(&state.pool, &input).await {
         Ok(tag) => {
+            reload_rule_set(&state).await;
             let response = SuccessResponse {
                 success: true,
                 message: "创建成功".to_string(),
@@ -236,6 +238,7 @@ pub async fn update_tag(
 
     match tag_management::update_tag(&state.pool, id, &input).await {
         Ok(tag) => {
+            reload_rule_set(&state).await;
             let response = SuccessResponse {
                 success: true,
                 message: "更新成功".to_string(),
@@ -275,6 +278,7 @@ pub async fn delete_tag(
 
     match tag_management::delete_tag(&state.pool, id).await {
         Ok(_) => {
+            reload_rule_set(&state).await;
             let response = SuccessResponse::<()> {
                 success: true,
                 message: "删除成功".to_string(),
@@ -0,0 +1,186 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::rules::{error_response, ApiResponse, ApiResult};
+use crate::alert_catalog;
+use crate::db::{self, threat_event, threat_event_alerts, ThreatEventRecord};
+use crate::AppState;
+
+/// 威胁事件详情，包含关联的收敛告警及其原始告警
+#[derive(Debug, Serialize)]
+pub struct ThreatEventDetail {
+    #[serde(flatten)]
+    pub event: ThreatEventRecord,
+    pub alerts: Vec<LinkedAlert>,
+}
+
+/// 威胁事件关联的收敛告警
+#[derive(Debug, Serialize)]
+pub struct LinkedAlert {
+    pub alert_type: String,
+    pub converged_alert_id: Uuid,
+    /// 关联来源：correlation / manual
+    pub source: String,
+    pub linked_at: DateTime<Utc>,
+    /// 收敛告警内容，收敛告警已被删除时为 null
+    pub alert: Option<Value>,
+    pub raw_alerts: Vec<Value>,
+}
+
+/// 关联收敛告警请求
+#[derive(Debug, Deserialize)]
+pub struct AttachAlertReq {
+    pub alert_type: String,
+    pub converged_alert_id: Uuid,
+}
+
+/// 关联结果
+#[derive(Debug, Serialize)]
+pub struct AttachAlertResp {
+    /// 此前未关联、本次新建了关联
+    pub attached: bool,
+}
+
+fn internal_error<T>(e: anyhow::Error) -> (StatusCode, Json<ApiResponse<T>>) {
+    tracing::error!("Threat event alert link failed: {}", e);
+    error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+/// 获取威胁事件详情，展开关联的收敛告警及其原始告警
+pub async fn get_threat_event(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> ApiResult<ThreatEventDetail> {
+    let event = threat_event::get_threat_event_by_id(&state.pool, id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "威胁事件不存在".to_string()))?;
+
+    let links = threat_event_alerts::query_event_alerts(&state.pool, id)
+        .await
+        .map_err(internal_error)?;
+
+    let mut alerts = Vec::with_capacity(links.len());
+    for link in links {
+        // 告警类型已从配置中移除时只返回关联记录本身
+        let Some(alert_type) = alert_catalog::catalog().by_code(link.alert_type as i64) else {
+            alerts.push(LinkedAlert {
+                alert_type: link.alert_type.to_string(),
+                converged_alert_id: link.converged_alert_id,
+                source: link.source,
+                linked_at: link.created_at,
+                alert: None,
+                raw_alerts: Vec::new(),
+            });
+            continue;
+        };
+
+        let alert = db::get_converged_alert(&state.pool, &alert_type.name, link.converged_alert_id)
+            .await
+            .map_err(internal_error)?;
+        let raw_alerts = db::query_raw_alerts_of_converged(
+            &state.pool,
+            &alert_type.name,
+            link.converged_alert_id,
+        )
+        .await
+        .map_err(internal_error)?;
+
+        alerts.push(LinkedAlert {
+            alert_type: alert_type.name.clone(),
+            converged_alert_id: link.converged_alert_id,
+            source: link.source,
+            linked_at: link.created_at,
+            alert,
+            raw_alerts,
+        });
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(ThreatEventDetail { event, alerts }),
+        error: None,
+    }))
+}
+
+/// 手工关联一条收敛告警
+pub async fn attach_alert(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<AttachAlertReq>,
+) -> ApiResult<AttachAlertResp> {
+    if alert_catalog::catalog().get(&req.alert_type).is_none() {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!("未知的告警类型: {}", req.alert_type),
+        ));
+    }
+
+    if threat_event::get_threat_event_by_id(&state.pool, id)
+        .await
+        .map_err(internal_error)?
+        .is_none()
+    {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            "威胁事件不存在".to_string(),
+        ));
+    }
+
+    if db::get_converged_alert(&state.pool, &req.alert_type, req.converged_alert_id)
+        .await
+        .map_err(internal_error)?
+        .is_none()
+    {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            format!("收敛告警 {} 不存在", req.converged_alert_id),
+        ));
+    }
+
+    let attached = threat_event_alerts::link_alert(
+        &state.pool,
+        id,
+        &req.alert_type,
+        req.converged_alert_id,
+        threat_event_alerts::SOURCE_MANUAL,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(AttachAlertResp { attached }),
+        error: None,
+    }))
+}
+
+/// 解除威胁事件与收敛告警的关联
+pub async fn detach_alert(
+    State(state): State<Arc<AppState>>,
+    Path((id, converged_alert_id)): Path<(Uuid, Uuid)>,
+) -> ApiResult<()> {
+    let detached = threat_event_alerts::unlink_alert(&state.pool, id, converged_alert_id)
+        .await
+        .map_err(internal_error)?;
+    if !detached {
+        return Err(error_response(
+            StatusCode::NOT_FOUND,
+            "威胁事件未关联该收敛告警".to_string(),
+        ));
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: None,
+        error: None,
+    }))
+}
@@ -0,0 +1,456 @@
+//! 威胁事件推送
+//!
+//! 按 event.md 的格式把威胁事件发送到 `topics.threat_events`：`id` 为 event_id，`type` 为事件分类，
+//! 时间格式为 `YYYY-MM-DD HH:MM:SS`（UTC），列表字段为 JSON 数组。
+//! 推送任务登记在 threat_event_push_logs 中，由后台推送任务发送并在失败后按退避时间重试。
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::{ErrorResponse, PageResponse, SuccessResponse};
+use crate::db::threat_event::{self, ThreatEventRecord};
+use crate::db::threat_event_push::{
+    self, ThreatEventPushLog, STATUS_FAILED, STATUS_PENDING, STATUS_RETRYING, STATUS_SENDING,
+    STATUS_SENT, TRIGGER_MANUAL,
+};
+use crate::db::threat_event_workflow::DisposeStatus;
+use crate::AppState;
+
+/// 每轮最多领取的推送任务数
+const PUSH_BATCH_SIZE: i64 = 100;
+/// 发送中状态超过该时间（秒）视为中断，重新领取
+const STALE_SENDING_SECONDS: i64 = 300;
+
+/// 推送到下游的威胁事件，字段与 event.md 一致
+#[derive(Debug, Serialize)]
+pub struct ThreatEventPayload {
+    pub id: i64,
+    pub system_code: String,
+    pub name: String,
+    pub description: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub attacker: String,
+    pub victimer: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub found_time: String,
+    pub source: String,
+    pub mitre_technique_id: String,
+    pub attsck_list: String,
+    pub attack_tool: String,
+    pub first_found_time: String,
+    pub priority: String,
+    pub severity: String,
+    pub dispose_status: String,
+    pub app: Option<String>,
+    pub impact_assessment: Option<String>,
+    pub merge_alerts: Value,
+    pub threat_actor: Value,
+    pub org: Value,
+    pub attack_asset_ip: Value,
+    pub victim_asset_ip: Value,
+    pub attack_asset_ip_port: Value,
+    pub victim_asset_ip_port: Value,
+    pub attack_asset_domain: Value,
+    pub victim_asset_domain: Value,
+    pub attack_url: Value,
+    pub victim_url: Value,
+    pub attack_malware: Value,
+    pub attack_malware_sample: Value,
+    pub attack_malware_sample_family: Value,
+    pub attack_email_address: Value,
+    pub victim_email_address: Value,
+    pub attack_email: Value,
+    pub victim_email: Value,
+    pub attack_software: Value,
+    pub victim_software: Value,
+    pub attack_vulnerability: Value,
+    pub attack_certificate: Value,
+    pub victim_certificate: Value,
+}
+
+impl From<&ThreatEventRecord> for ThreatEventPayload {
+    fn from(r: &ThreatEventRecord) -> Self {
+        // 必填的文本字段缺失时输出空串，列表字段缺失时输出空数组
+        let text = |v: &Option<String>| v.clone().unwrap_or_default();
+        let time = |v: &Option<DateTime<Utc>>| {
+            v.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default()
+        };
+        let list = |v: &Option<Value>| v.clone().unwrap_or_else(|| Value::Array(Vec::new()));
+
+        Self {
+            id: r.event_id.unwrap_or_default(),
+            system_code: text(&r.system_code),
+            name: text(&r.name),
+            description: text(&r.description),
+            event_type: text(&r.event_type),
+            attacker: text(&r.attacker),
+            victimer: text(&r.victimer),
+            start_time: time(&r.start_time),
+            end_time: time(&r.end_time),
+            found_time: time(&r.found_time),
+            source: text(&r.source),
+            mitre_technique_id: text(&r.mitre_technique_id),
+            attsck_list: text(&r.attsck_list),
+            attack_tool: text(&r.attack_tool),
+            first_found_time: time(&r.first_found_time),
+            priority: text(&r.priority),
+            severity: text(&r.severity),
+            dispose_status: r
+                .dispose_status
+                .as_deref()
+                .map(|s| DisposeStatus::parse(s).map_or(s, |status| status.label()))
+                .unwrap_or_default()
+                .to_string(),
+            app: r.app.clone(),
+            impact_assessment: r.impact_assessment.clone(),
+            merge_alerts: list(&r.merge_alerts),
+            threat_actor: list(&r.threat_actor),
+            org: list(&r.org),
+            attack_asset_ip: list(&r.attack_asset_ip),
+            victim_asset_ip: list(&r.victim_asset_ip),
+            attack_asset_ip_port: list(&r.attack_asset_ip_port),
+            victim_asset_ip_port: list(&r.victim_asset_ip_port),
+            attack_asset_domain: list(&r.attack_asset_domain),
+            victim_asset_domain: list(&r.victim_asset_domain),
+            attack_url: list(&r.attack_url),
+            victim_url: list(&r.victim_url),
+            attack_malware: list(&r.attack_malware),
+            attack_malware_sample: list(&r.attack_malware_sample),
+            attack_malware_sample_family: list(&r.attack_malware_sample_family),
+            attack_email_address: list(&r.attack_email_address),
+            victim_email_address: list(&r.victim_email_address),
+            attack_email: list(&r.attack_email),
+            victim_email: list(&r.victim_email),
+            attack_software: list(&r.attack_software),
+            victim_software: list(&r.victim_software),
+            attack_vulnerability: list(&r.attack_vulnerability),
+            attack_certificate: list(&r.attack_certificate),
+            victim_certificate: list(&r.victim_certificate),
+        }
+    }
+}
+
+/// 发送一条推送任务对应的威胁事件，返回发送的消息内容
+///
+/// 事件已被删除时返回 Ok(None)，这类任务不再重试
+async fn send_threat_event(
+    state: &AppState,
+    producer: &FutureProducer,
+    threat_event_id: Uuid,
+) -> Result<Option<Value>, String> {
+    let record = threat_event::get_threat_event_by_id(&state.pool, threat_event_id)
+        .await
+        .map_err(|e| format!("查询威胁事件失败: {}", e))?;
+    let Some(record) = record else {
+        return Ok(None);
+    };
+
+    let payload = serde_json::to_value(ThreatEventPayload::from(&record))
+        .map_err(|e| format!("序列化威胁事件失败: {}", e))?;
+    let bytes = serde_json::to_vec(&payload).map_err(|e| format!("序列化威胁事件失败: {}", e))?;
+    // 以事件编号为 key，保证同一事件的消息进入同一分区、按顺序送达
+    let key = record.event_id.unwrap_or_default().to_string();
+
+    producer
+        .send(
+            FutureRecord::to(&state.topics.threat_events)
+                .key(&key)
+                .payload(&bytes),
+            Timeout::After(std::time::Duration::from_secs(3)),
+        )
+        .await
+        .map_err(|(e, _)| format!("发送到 Kafka 失败: {}", e))?;
+
+    Ok(Some(payload))
+}
+
+/// 执行一条已领取的推送任务并记录结果，返回失败原因
+async fn run_push(
+    state: &AppState,
+    producer: &FutureProducer,
+    log: &ThreatEventPushLog,
+) -> anyhow::Result<Option<String>> {
+    let error = match send_threat_event(state, producer, log.threat_event_id).await {
+        Ok(Some(payload)) => {
+            threat_event_push::mark_threat_event_push_sent(&state.pool, log.id, &payload).await?;
+            return Ok(None);
+        }
+        Ok(None) => {
+            let error = "威胁事件不存在".to_string();
+            threat_event_push::mark_threat_event_push_failed(&state.pool, log.id, &error, None)
+                .await?;
+            return Ok(Some(error));
+        }
+        Err(e) => e,
+    };
+
+    let retry_at = state
+        .threat_event_push
+        .retry_delay(log.attempts.max(0) as u32)
+        .map(|delay| Utc::now() + delay);
+    tracing::warn!(
+        target = "threat_event_push",
+        "Push of threat event {} failed (attempt {}): {}",
+        log.threat_event_id,
+        log.attempts,
+        error
+    );
+    threat_event_push::mark_threat_event_push_failed(&state.pool, log.id, &error, retry_at).await?;
+    Ok(Some(error))
+}
+
+/// 领取并发送一批到期的推送任务，返回成功发送的数量
+async fn push_due_events(state: &AppState, producer: &FutureProducer) -> anyhow::Result<usize> {
+    let logs = threat_event_push::claim_due_threat_event_pushes(
+        &state.pool,
+        PUSH_BATCH_SIZE,
+        STALE_SENDING_SECONDS,
+    )
+    .await?;
+
+    let mut sent = 0;
+    for log in &logs {
+        if run_push(state, producer, log).await?.is_none() {
+            sent += 1;
+        }
+    }
+    Ok(sent)
+}
+
+/// 后台威胁事件推送任务
+pub async fn run_threat_event_publisher(state: Arc<AppState>) {
+    let interval = std::time::Duration::from_secs(state.threat_event_push.interval_seconds.max(1));
+    let mut producer: Option<FutureProducer> = None;
+
+    loop {
+        if producer.is_none() {
+            match state.kafka.producer_config().create() {
+                Ok(p) => producer = Some(p),
+                Err(e) => tracing::error!(
+                    target = "threat_event_push",
+                    "Failed to create Kafka producer: {}",
+                    e
+                ),
+            }
+        }
+
+        if let Some(producer) = &producer {
+            match push_due_events(&state, producer).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!(
+                    target = "threat_event_push",
+                    "Pushed {} threat events to {}.",
+                    count,
+                    state.topics.threat_events
+                ),
+                Err(e) => tracing::error!(
+                    target = "threat_event_push",
+                    "Threat event push failed: {}",
+                    e
+                ),
+            }
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// 手工推送结果
+#[derive(Serialize)]
+pub struct PushResult {
+    pub log_id: Uuid,
+    pub sent: bool,
+    pub error: Option<String>,
+}
+
+/// 手工重推威胁事件：立即发送一次，失败时按重试规则由后台继续推送
+pub async fn push_threat_event(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match threat_event::get_threat_event_by_id(&state.pool, id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return error(StatusCode::NOT_FOUND, "威胁事件不存在".to_string()),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+
+    let producer: FutureProducer = match state.kafka.producer_config().create() {
+        Ok(p) => p,
+        Err(e) => {
+            return error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("创建 Kafka producer 失败: {}", e),
+            )
+        }
+    };
+
+    let result =
+        match threat_event_push::start_threat_event_push(&state.pool, id, TRIGGER_MANUAL).await {
+            Ok(log) => run_push(&state, &producer, &log)
+                .await
+                .map(|error| PushResult {
+                    log_id: log.id,
+                    sent: error.is_none(),
+                    error,
+                }),
+            Err(e) => Err(e),
+        };
+
+    match result {
+        Ok(result) => Json(SuccessResponse {
+            success: true,
+            message: if result.sent {
+                "推送成功".to_string()
+            } else {
+                "推送失败，已加入重试".to_string()
+            },
+            data: Some(result),
+        })
+        .into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// 推送日志查询参数
+#[derive(Deserialize)]
+pub struct PushLogQuery {
+    #[serde(default = "default_page")]
+    pub page: u64,
+    #[serde(default = "default_page_size")]
+    pub page_size: u64,
+    /// 按推送状态过滤：pending/sending/sent/retrying/failed
+    pub status: Option<String>,
+}
+
+fn default_page() -> u64 {
+    1
+}
+
+fn default_page_size() -> u64 {
+    20
+}
+
+/// 查询所有威胁事件的推送日志
+pub async fn get_push_logs(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PushLogQuery>,
+) -> Response {
+    list_push_logs(&state, None, params).await
+}
+
+/// 查询单个威胁事件的推送日志
+pub async fn get_event_push_logs(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PushLogQuery>,
+) -> Response {
+    list_push_logs(&state, Some(id), params).await
+}
+
+async fn list_push_logs(
+    state: &AppState,
+    threat_event_id: Option<Uuid>,
+    params: PushLogQuery,
+) -> Response {
+    let statuses = [
+        STATUS_PENDING,
+        STATUS_SENDING,
+        STATUS_SENT,
+        STATUS_RETRYING,
+        STATUS_FAILED,
+    ];
+    let status = params.status.as_deref().filter(|s| !s.is_empty());
+    if let Some(status) = status {
+        if !statuses.contains(&status) {
+            return error(
+                StatusCode::BAD_REQUEST,
+                format!("未知的推送状态: {}", status),
+            );
+        }
+    }
+
+    let (page, page_size) = (params.page, params.page_size);
+    match threat_event_push::query_threat_event_push_logs(
+        &state.pool,
+        threat_event_id,
+        status,
+        page,
+        page_size,
+    )
+    .await
+    {
+        Ok((data, total)) => Json(PageResponse {
+            data,
+            total,
+            page,
+            page_size,
+        })
+        .into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            success: false,
+            message,
+        }),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn test_payload_matches_event_format() {
+        let record: ThreatEventRecord = serde_json::from_value(json!({
+            "id": Uuid::nil(),
+            "event_id": 1000001,
+            "system_code": "SYS-2025-001",
+            "name": "APT攻击事件样例",
+            "event_type": "网络攻击",
+            "start_time": Utc.with_ymd_and_hms(2025, 9, 10, 8, 30, 0).unwrap(),
+            "dispose_status": "in_progress",
+            "attack_asset_ip": ["192.168.10.15", "45.67.89.101"],
+            "created_at": Utc::now()
+        }))
+        .unwrap();
+
+        let payload = serde_json::to_value(ThreatEventPayload::from(&record)).unwrap();
+        assert_eq!(payload["id"], json!(1000001));
+        assert_eq!(payload["type"], json!("网络攻击"));
+        assert!(payload.get("event_type").is_none());
+        assert_eq!(payload["start_time"], json!("2025-09-10 08:30:00"));
+        assert_eq!(payload["dispose_status"], json!("处置中"));
+        assert_eq!(payload["end_time"], json!(""));
+        assert_eq!(payload["description"], json!(""));
+        assert_eq!(payload["app"], Value::Null);
+        assert_eq!(
+            payload["attack_asset_ip"],
+            json!(["192.168.10.15", "45.67.89.101"])
+        );
+        assert_eq!(payload["victim_asset_ip"], json!([]));
+        // event.md 中定义的 43 个字段
+        assert_eq!(payload.as_object().unwrap().len(), 43);
+    }
+}
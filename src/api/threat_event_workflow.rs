@@ -0,0 +1,122 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::rules::{error_response, ApiResponse, ApiResult};
+use crate::db::threat_event_workflow::{
+    self, DisposeStatus, StatusHistoryRecord, TransitionInput, TransitionOutcome,
+};
+use crate::AppState;
+
+/// 处置状态及其允许的流转
+#[derive(Debug, Serialize)]
+pub struct StatusInfo {
+    pub status: DisposeStatus,
+    pub label: &'static str,
+    pub next: &'static [DisposeStatus],
+}
+
+/// 获取处置流程定义
+pub async fn get_workflow() -> Json<ApiResponse<Vec<StatusInfo>>> {
+    let statuses = DisposeStatus::ALL
+        .into_iter()
+        .map(|status| StatusInfo {
+            status,
+            label: status.label(),
+            next: status.next(),
+        })
+        .collect();
+
+    Json(ApiResponse {
+        success: true,
+        data: Some(statuses),
+        error: None,
+    })
+}
+
+/// 流转威胁事件的处置状态
+pub async fn transition_threat_event(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(input): Json<TransitionInput>,
+) -> ApiResult<StatusHistoryRecord> {
+    let outcome = threat_event_workflow::transition_threat_event(&state.pool, id, &input)
+        .await
+        .map_err(|e| {
+            tracing::error!("Transition threat event failed: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })?;
+
+    match outcome {
+        TransitionOutcome::Applied(record) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(record),
+            error: None,
+        })),
+        TransitionOutcome::NotFound => Err(error_response(
+            StatusCode::NOT_FOUND,
+            "威胁事件不存在".to_string(),
+        )),
+        TransitionOutcome::Rejected(from) => Err(error_response(
+            StatusCode::CONFLICT,
+            format!("处置状态不能从 {} 变为 {}", from.label(), input.to.label()),
+        )),
+    }
+}
+
+/// 查询威胁事件的处置历史
+pub async fn get_status_history(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> ApiResult<Vec<StatusHistoryRecord>> {
+    let records = threat_event_workflow::query_status_history(&state.pool, id)
+        .await
+        .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(records),
+        error: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        use DisposeStatus::*;
+        assert!(New.can_transition_to(Triaged));
+        assert!(Triaged.can_transition_to(InProgress));
+        assert!(InProgress.can_transition_to(Resolved));
+        assert!(InProgress.can_transition_to(FalsePositive));
+        assert!(Resolved.can_transition_to(InProgress));
+        assert!(!New.can_transition_to(Resolved));
+        assert!(!Resolved.can_transition_to(FalsePositive));
+        assert!(!Triaged.can_transition_to(Triaged));
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(
+            DisposeStatus::parse("in_progress"),
+            Some(DisposeStatus::InProgress)
+        );
+        assert_eq!(
+            DisposeStatus::parse("处置中"),
+            Some(DisposeStatus::InProgress)
+        );
+        assert_eq!(DisposeStatus::parse("已审核"), Some(DisposeStatus::Triaged));
+        assert_eq!(DisposeStatus::parse("unknown"), None);
+        assert_eq!(
+            serde_json::to_value(DisposeStatus::FalsePositive).unwrap(),
+            serde_json::json!("false_positive")
+        );
+    }
+}
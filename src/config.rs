@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::{fs, path::Path};
 
 #[derive(Debug, Deserialize, Clone)]
@@ -16,6 +16,15 @@ pub struct KafkaConfig {
     /// Kafka offset reset策略: "earliest" (从最早的消息开始) 或 "latest" (只消费新消息)
     #[serde(default = "default_auto_offset_reset")]
     pub auto_offset_reset: String,
+    /// 消息处理 worker 数量，同一分区的消息总由同一个 worker 顺序处理
+    #[serde(default = "default_worker_count")]
+    pub worker_count: usize,
+    /// 每个 worker 的待处理队列长度，队列满时暂停拉取消息
+    #[serde(default = "default_worker_queue_size")]
+    pub worker_queue_size: usize,
+    /// 消息处理失败时的最大重试次数，超过后转入死信表
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
 }
 
 impl KafkaConfig {
@@ -35,6 +44,18 @@ fn default_auto_offset_reset() -> String {
     "earliest".to_string()
 }
 
+fn default_worker_count() -> usize {
+    4
+}
+
+fn default_worker_queue_size() -> usize {
+    64
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct TopicsConfig {
     pub network_attack: String,
@@ -42,6 +63,149 @@ pub struct TopicsConfig {
     pub host_behavior: String,
     /// 聚合后的收敛告警推送主题
     pub converged_alerts: String,
+    /// 上游推送原始威胁事件的主题（格式见 event.md）
+    #[serde(default = "default_raw_threat_events_topic")]
+    pub raw_threat_events: String,
+    /// 向下游推送威胁事件的主题（格式同 event.md）
+    #[serde(default = "default_threat_events_topic")]
+    pub threat_events: String,
+    /// 其他告警类型的主题，键为告警类型名
+    #[serde(flatten)]
+    pub alert_types: HashMap<String, String>,
+}
+
+fn default_raw_threat_events_topic() -> String {
+    "events.raw_threat_events".to_string()
+}
+
+fn default_threat_events_topic() -> String {
+    "events.threat_events".to_string()
+}
+
+impl TopicsConfig {
+    /// 告警类型对应的 Kafka 主题，未配置时为 `alerts.<告警类型>`
+    pub fn topic_for(&self, alert_type: &str) -> String {
+        match alert_type {
+            "network_attack" => self.network_attack.clone(),
+            "malicious_sample" => self.malicious_sample.clone(),
+            "host_behavior" => self.host_behavior.clone(),
+            _ => self
+                .alert_types
+                .get(alert_type)
+                .cloned()
+                .unwrap_or_else(|| format!("alerts.{}", alert_type)),
+        }
+    }
+}
+
+/// 默认收敛的时间窗口配置（分钟）
+///
+/// 同一收敛键的告警只会合并到窗口内的收敛告警，窗口过期后新开一条收敛告警。
+/// 按 `<告警类型>_window_minutes = 分钟数` 配置，未配置的告警类型使用 60 分钟
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ConvergenceConfig {
+    #[serde(flatten)]
+    pub windows: HashMap<String, u32>,
+}
+
+impl ConvergenceConfig {
+    /// 获取告警类型对应的收敛窗口
+    pub fn window_for(&self, alert_type: &str) -> chrono::Duration {
+        let minutes = self
+            .windows
+            .get(&format!("{}_window_minutes", alert_type))
+            .copied()
+            .unwrap_or_else(default_convergence_window_minutes);
+        chrono::Duration::minutes(minutes as i64)
+    }
+}
+
+fn default_convergence_window_minutes() -> u32 {
+    60
+}
+
+/// 告警 JSON Schema 校验配置
+///
+/// strict：不符合 schema 的告警存为无效告警并记录逐字段错误，不再处理；
+/// lenient：只记录警告日志，告警照常处理；off：不校验。
+/// 可按 `[validation.alert_types]` 为单个告警类型覆盖校验模式
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ValidationConfig {
+    #[serde(default)]
+    pub mode: ValidationMode,
+    #[serde(default)]
+    pub alert_types: HashMap<String, ValidationMode>,
+}
+
+impl ValidationConfig {
+    /// 获取告警类型对应的校验模式
+    pub fn mode_for(&self, alert_type: &str) -> ValidationMode {
+        self.alert_types
+            .get(alert_type)
+            .copied()
+            .unwrap_or(self.mode)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationMode {
+    Strict,
+    #[default]
+    Lenient,
+    Off,
+}
+
+/// 威胁事件推送配置
+///
+/// 推送失败后按 retry_backoff_seconds 的指数退避重试，尝试 max_attempts 次后不再自动重试
+#[derive(Debug, Deserialize, Clone)]
+pub struct ThreatEventPushConfig {
+    /// 后台推送任务的轮询间隔（秒）
+    #[serde(default = "default_push_interval_seconds")]
+    pub interval_seconds: u64,
+    #[serde(default = "default_push_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_push_retry_backoff_seconds")]
+    pub retry_backoff_seconds: u64,
+}
+
+impl Default for ThreatEventPushConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: default_push_interval_seconds(),
+            max_attempts: default_push_max_attempts(),
+            retry_backoff_seconds: default_push_retry_backoff_seconds(),
+        }
+    }
+}
+
+impl ThreatEventPushConfig {
+    /// 第 attempts 次尝试失败后的重试等待时间，尝试次数用尽时返回 None
+    pub fn retry_delay(&self, attempts: u32) -> Option<chrono::Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        // 最长等待 1 小时
+        let exponent = attempts.saturating_sub(1).min(16);
+        let seconds = self
+            .retry_backoff_seconds
+            .saturating_mul(1 << exponent)
+            .min(3600);
+        Some(chrono::Duration::seconds(seconds as i64))
+    }
+}
+
+fn default_push_interval_seconds() -> u64 {
+    5
+}
+
+fn default_push_max_attempts() -> u32 {
+    5
+}
+
+fn default_push_retry_backoff_seconds() -> u64 {
+    30
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -55,23 +219,135 @@ pub struct PostgresConfig {
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AlarmTypeInfo {
+    /// alarm_type 编码，如 "01"
     pub code: String,
     pub name: String,
     pub category: String,
     pub subtypes: HashMap<String, String>,
+    /// alert_fields.toml 中的字段分节名，默认为 `<告警类型>_alert`；
+    /// 分节不存在时从 schema/<告警类型>.json 读取字段
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<String>,
+    /// 默认收敛键：依次尝试每组字段，告警中该组字段都有值时按其合并窗口内的收敛告警；
+    /// 未配置时每条告警单独生成收敛告警
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub convergence_keys: Vec<Vec<String>>,
+    /// 推送收敛告警时的 modelType，默认为 `ALM_<告警类型大写>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_type: Option<String>,
 }
 
+/// 告警类型配置，键为告警类型名（同时用于表名、主题名和规则中的 alert_type）
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(transparent)]
 pub struct AlarmTypesConfig {
-    pub network_attack: AlarmTypeInfo,
-    pub malicious_sample: AlarmTypeInfo,
-    pub host_behavior: AlarmTypeInfo,
+    pub types: BTreeMap<String, AlarmTypeInfo>,
+}
+
+impl AlarmTypesConfig {
+    /// 根据告警类型名获取类型配置
+    pub fn get(&self, alert_type: &str) -> Option<&AlarmTypeInfo> {
+        self.types.get(alert_type)
+    }
+
+    /// 校验子类型匹配条件，返回规范化后的写法
+    ///
+    /// 单个编码和区间端点都必须是该告警类型已配置的子类型
+    pub fn validate_subtypes(&self, alert_type: &str, spec: &str) -> Result<String, String> {
+        let info = self
+            .get(alert_type)
+            .ok_or_else(|| format!("未知的告警类型: {}", alert_type))?;
+        let parsed = SubtypeSpec::parse(spec)?;
+
+        for (start, end) in parsed.ranges() {
+            for code in [start, end] {
+                if !info.subtypes.contains_key(&code.to_string()) {
+                    return Err(format!("告警类型 {} 不存在子类型编码 {}", alert_type, code));
+                }
+            }
+        }
+
+        Ok(parsed.to_string())
+    }
+}
+
+/// 告警子类型匹配条件
+///
+/// 写法为逗号分隔的子类型编码或闭区间，如 `1001,1003-1005`；空串表示匹配所有子类型
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtypeSpec {
+    ranges: Vec<(u32, u32)>,
+}
+
+impl SubtypeSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let parse_code = |code: &str| {
+                code.trim()
+                    .parse::<u32>()
+                    .map_err(|_| format!("无效的子类型编码: {}", code.trim()))
+            };
+            let range = match part.split_once('-') {
+                Some((start, end)) => (parse_code(start)?, parse_code(end)?),
+                None => {
+                    let code = parse_code(part)?;
+                    (code, code)
+                }
+            };
+            if range.0 > range.1 {
+                return Err(format!("子类型区间起点大于终点: {}", part));
+            }
+            ranges.push(range);
+        }
+        Ok(Self { ranges })
+    }
+
+    /// 未限定子类型，匹配所有告警
+    pub fn is_any(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn ranges(&self) -> &[(u32, u32)] {
+        &self.ranges
+    }
+
+    pub fn matches(&self, subtype: u32) -> bool {
+        self.is_any()
+            || self
+                .ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&subtype))
+    }
+}
+
+impl std::fmt::Display for SubtypeSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = self
+            .ranges
+            .iter()
+            .map(|(start, end)| {
+                if start == end {
+                    start.to_string()
+                } else {
+                    format!("{}-{}", start, end)
+                }
+            })
+            .collect();
+        f.write_str(&parts.join(","))
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub kafka: KafkaConfig,
     pub topics: TopicsConfig,
+    #[serde(default)]
+    pub convergence: ConvergenceConfig,
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    #[serde(default)]
+    pub threat_event_push: ThreatEventPushConfig,
     pub postgres: PostgresConfig,
     pub alarm_types: AlarmTypesConfig,
 }
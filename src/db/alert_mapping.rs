@@ -5,9 +5,12 @@
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::alert_catalog;
+
 // ============================================================================
 // Record 结构体定义
 // ============================================================================
@@ -18,7 +21,7 @@ pub struct AlertConvergenceMappingRecord {
     pub id: Uuid,
     pub raw_alert_id: Uuid,       // 原始告警的UUID
     pub converged_alert_id: Uuid, // 收敛后告警的UUID
-    pub alert_type: i16,          // 告警类型 (1: 网络攻击, 2: 恶意样本, 3: 主机行为)
+    pub alert_type: i16, // 告警类型编码 (1: 网络攻击, 2: 恶意样本, 3: 主机行为, 其他为配置的类型)
     pub created_at: DateTime<Utc>,
 }
 
@@ -34,13 +37,48 @@ pub async fn create_alert_mapping_table(pool: &PgPool) -> Result<()> {
             raw_alert_id uuid NOT NULL,
             converged_alert_id uuid NOT NULL,
             alert_type SMALLINT NOT NULL,
-            created_at TIMESTAMPTZ DEFAULT now(),
-            CONSTRAINT fk_alert_type CHECK (alert_type IN (1, 2, 3))
+            correlated BOOLEAN NOT NULL DEFAULT false,
+            created_at TIMESTAMPTZ DEFAULT now()
         )",
     )
     .execute(pool)
     .await?;
 
+    // 关联引擎是否已处理该告警：补列时已有的映射视为已处理，新映射在关联完成后置位
+    sqlx::query(
+        "ALTER TABLE alert_convergence_mapping
+            ADD COLUMN IF NOT EXISTS correlated BOOLEAN NOT NULL DEFAULT true",
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query("ALTER TABLE alert_convergence_mapping ALTER COLUMN correlated SET DEFAULT false")
+        .execute(pool)
+        .await?;
+
+    // 告警类型由配置决定：按已配置的类型编码重建类型约束，只校验新写入的映射，
+    // 从配置中移除的类型留下的历史映射不受影响
+    let codes: Vec<String> = alert_catalog::catalog()
+        .types()
+        .iter()
+        .map(|t| t.code.to_string())
+        .collect();
+    if !codes.is_empty() {
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "ALTER TABLE alert_convergence_mapping DROP CONSTRAINT IF EXISTS fk_alert_type",
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!(
+            "ALTER TABLE alert_convergence_mapping
+             ADD CONSTRAINT fk_alert_type CHECK (alert_type IN ({})) NOT VALID",
+            codes.join(", ")
+        ))
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+    }
+
     // 创建索引以提高查询性能
     sqlx::query(
         "CREATE INDEX IF NOT EXISTS idx_raw_alert_id 
@@ -56,6 +94,53 @@ pub async fn create_alert_mapping_table(pool: &PgPool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    // 每条原始告警只映射到一条收敛告警：唯一索引不存在时执行一次迁移
+    let (migrated,): (bool,) = sqlx::query_as(
+        "SELECT to_regclass('uq_alert_convergence_mapping_raw_alert_id') IS NOT NULL",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !migrated {
+        deduplicate_mappings(pool).await?;
+    }
+
+    Ok(())
+}
+
+/// 一次性迁移：清理同一原始告警的重复映射（保留最早的一条）后建立唯一索引，逐条记录删除的映射
+async fn deduplicate_mappings(pool: &PgPool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let removed: Vec<(Uuid, Uuid, Uuid)> = sqlx::query_as(
+        "DELETE FROM alert_convergence_mapping a
+         USING alert_convergence_mapping b
+         WHERE a.raw_alert_id = b.raw_alert_id
+           AND (COALESCE(a.created_at, '-infinity'), a.id)
+             > (COALESCE(b.created_at, '-infinity'), b.id)
+         RETURNING a.id, a.raw_alert_id, a.converged_alert_id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (id, raw_alert_id, converged_alert_id) in &removed {
+        warn!(
+            "删除重复的告警收敛映射 {}: 原始告警 {} -> 收敛告警 {}",
+            id, raw_alert_id, converged_alert_id
+        );
+    }
+
+    sqlx::query(
+        "CREATE UNIQUE INDEX uq_alert_convergence_mapping_raw_alert_id
+         ON alert_convergence_mapping(raw_alert_id)",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    info!(
+        "告警收敛映射去重迁移完成，删除 {} 条重复映射",
+        removed.len()
+    );
     Ok(())
 }
 
@@ -71,21 +156,22 @@ pub async fn drop_alert_mapping_table(pool: &PgPool) -> Result<()> {
 // 插入操作
 // ============================================================================
 
-/// 插入单个映射记录
+/// 插入单个映射记录，原始告警已有映射时忽略
 pub async fn insert_mapping(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     raw_alert_id: Uuid,
     converged_alert_id: Uuid,
     alert_type: i16,
 ) -> Result<()> {
     sqlx::query(
         "INSERT INTO alert_convergence_mapping (raw_alert_id, converged_alert_id, alert_type)
-         VALUES ($1, $2, $3)",
+         VALUES ($1, $2, $3)
+         ON CONFLICT (raw_alert_id) DO NOTHING",
     )
     .bind(raw_alert_id)
     .bind(converged_alert_id)
     .bind(alert_type)
-    .execute(pool)
+    .execute(conn)
     .await?;
 
     Ok(())
@@ -104,7 +190,8 @@ pub async fn insert_mappings_batch(
     for raw_alert_id in raw_alert_ids {
         sqlx::query(
             "INSERT INTO alert_convergence_mapping (raw_alert_id, converged_alert_id, alert_type)
-             VALUES ($1, $2, $3)",
+             VALUES ($1, $2, $3)
+             ON CONFLICT (raw_alert_id) DO NOTHING",
         )
         .bind(raw_alert_id)
         .bind(converged_alert_id)
@@ -139,22 +226,41 @@ pub async fn query_raw_alerts_by_converged_id(
     Ok(records.into_iter().map(|(id,)| id).collect())
 }
 
-/// 根据原始告警ID查询对应的收敛后告警ID
-#[allow(dead_code)]
-pub async fn query_converged_alert_by_raw_id(
+/// 原始告警的处理进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappingState {
+    pub converged_alert_id: Uuid,
+    /// 关联引擎是否已处理完该告警
+    pub correlated: bool,
+}
+
+/// 根据原始告警ID查询其收敛后告警ID和关联处理进度，尚未收敛时返回 None
+pub async fn query_mapping_state(
     pool: &PgPool,
     raw_alert_id: Uuid,
-) -> Result<Option<Uuid>> {
-    let record: Option<(Uuid,)> = sqlx::query_as(
-        "SELECT converged_alert_id FROM alert_convergence_mapping 
-         WHERE raw_alert_id = $1 
-         LIMIT 1",
+) -> Result<Option<MappingState>> {
+    let record: Option<(Uuid, bool)> = sqlx::query_as(
+        "SELECT converged_alert_id, correlated FROM alert_convergence_mapping
+         WHERE raw_alert_id = $1",
     )
     .bind(raw_alert_id)
     .fetch_optional(pool)
     .await?;
 
-    Ok(record.map(|(id,)| id))
+    Ok(record.map(|(converged_alert_id, correlated)| MappingState {
+        converged_alert_id,
+        correlated,
+    }))
+}
+
+/// 标记原始告警已由关联引擎处理完成，重复投递时不再送入关联引擎
+pub async fn mark_mapping_correlated(pool: &PgPool, raw_alert_id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE alert_convergence_mapping SET correlated = true WHERE raw_alert_id = $1")
+        .bind(raw_alert_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
 }
 
 /// 查询收敛后告警包含的原始告警数量
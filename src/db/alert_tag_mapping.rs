@@ -1,7 +1,7 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool};
 use uuid::Uuid;
 
 /// 告警-标签映射记录 - 数据库模型
@@ -62,8 +62,8 @@ pub async fn drop_alert_tag_mapping_table(pool: &PgPool) -> Result<()> {
 }
 
 /// 添加告警-标签映射
-pub async fn add_alert_tag(
-    pool: &PgPool,
+pub async fn add_alert_tag<'e, E: PgExecutor<'e>>(
+    executor: E,
     input: &AlertTagMappingInput,
 ) -> Result<AlertTagMappingRecord> {
     let record = sqlx::query_as::<_, AlertTagMappingRecord>(
@@ -76,7 +76,7 @@ pub async fn add_alert_tag(
     .bind(input.alert_id)
     .bind(&input.alert_type)
     .bind(input.tag_id)
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await?;
 
     Ok(record)
@@ -151,6 +151,19 @@ pub async fn add_alert_tags_batch(
     alert_id: Uuid,
     alert_type: &str,
     tag_ids: &[Uuid],
+) -> Result<Vec<AlertTagMappingRecord>> {
+    let mut tx = pool.begin().await?;
+    let records = add_alert_tags_batch_in_tx(&mut tx, alert_id, alert_type, tag_ids).await?;
+    tx.commit().await?;
+    Ok(records)
+}
+
+/// 在调用方的事务中批量添加告警-标签映射
+pub async fn add_alert_tags_batch_in_tx(
+    conn: &mut PgConnection,
+    alert_id: Uuid,
+    alert_type: &str,
+    tag_ids: &[Uuid],
 ) -> Result<Vec<AlertTagMappingRecord>> {
     let mut records = Vec::new();
 
@@ -160,7 +173,7 @@ pub async fn add_alert_tags_batch(
             alert_type: alert_type.to_string(),
             tag_id: *tag_id,
         };
-        let record = add_alert_tag(pool, &input).await?;
+        let record = add_alert_tag(&mut *conn, &input).await?;
         records.push(record);
     }
 
@@ -1,153 +1,29 @@
 //! 收敛后告警表及相关操作
 //!
-//! 本模块包含三类收敛后告警表的定义和操作：
-//! - 网络攻击收敛告警表 (converged_network_attack_alerts)
-//! - 恶意样本收敛告警表 (converged_malicious_sample_alerts)  
-//! - 主机行为收敛告警表 (converged_host_behavior_alerts)
+//! 本模块包含收敛后告警表的定义和操作：
+//! - 每个配置的告警类型按字段声明建表 (converged_<告警类型>_alerts)
 //!
 //! 提供的功能包括：
 //! - 建表/删表操作
 //! - 插入收敛后告警数据
+//! - 在收敛时间窗口内查找与更新收敛后告警（默认收敛键或收敛规则分组键）
 //! - 分页查询收敛后告警数据
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
-use crate::models::{HostBehaviorAlert, MaliciousSampleAlert, NetworkAttackAlert};
+use super::list_filter::ListQuery;
+use super::raw_alerts::field_columns;
+use crate::alert_catalog::{self, AlertType};
 
-// ============================================================================
-// Record 结构体定义
-// ============================================================================
-
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
-pub struct ConvergedNetworkAttackRecord {
-    pub id: Uuid,
-    pub alarm_id: Option<String>,
-    pub alarm_date: Option<i64>,
-    pub alarm_severity: Option<i16>,
-    pub alarm_name: Option<String>,
-    pub alarm_description: Option<String>,
-    pub alarm_type: i16,
-    pub alarm_subtype: i32,
-    pub source: i16,
-    pub control_rule_id: Option<String>,
-    pub control_task_id: Option<String>,
-    pub procedure_technique_id: Option<serde_json::Value>,
-    pub session_id: Option<String>,
-    pub ip_version: Option<i16>,
-    pub src_ip: Option<String>,
-    pub src_port: Option<i32>,
-    pub dst_ip: Option<String>,
-    pub dst_port: Option<i32>,
-    pub protocol: Option<String>,
-    pub terminal_id: Option<String>,
-    pub source_file_path: Option<String>,
-    pub signature_id: Option<String>,
-    pub attack_payload: Option<String>,
-    pub attack_stage: Option<String>,
-    pub attack_ip: Option<String>,
-    pub attacked_ip: Option<String>,
-    pub apt_group: Option<String>,
-    pub vul_type: Option<String>,
-    pub cve_id: Option<String>,
-    pub vul_desc: Option<String>,
-    pub data: Option<serde_json::Value>,
-    pub convergence_count: i32, // 收敛的原始告警数量
-    pub created_at: DateTime<Utc>,
-}
-
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
-pub struct ConvergedMaliciousSampleRecord {
-    pub id: Uuid,
-    pub alarm_id: Option<String>,
-    pub alarm_date: Option<i64>,
-    pub alarm_severity: Option<i16>,
-    pub alarm_name: Option<String>,
-    pub alarm_description: Option<String>,
-    pub alarm_type: i16,
-    pub alarm_subtype: i32,
-    pub source: i16,
-    pub control_rule_id: Option<String>,
-    pub control_task_id: Option<String>,
-    pub procedure_technique_id: Option<serde_json::Value>,
-    pub session_id: Option<String>,
-    pub ip_version: Option<i16>,
-    pub src_ip: Option<String>,
-    pub src_port: Option<i32>,
-    pub dst_ip: Option<String>,
-    pub dst_port: Option<i32>,
-    pub protocol: Option<String>,
-    pub terminal_id: Option<String>,
-    pub source_file_path: Option<String>,
-    pub sample_source: Option<i16>,
-    pub md5: Option<String>,
-    pub sha1: Option<String>,
-    pub sha256: Option<String>,
-    pub sha512: Option<String>,
-    pub ssdeep: Option<String>,
-    pub sample_original_name: Option<String>,
-    pub sample_description: Option<String>,
-    pub sample_family: Option<String>,
-    pub apt_group: Option<String>,
-    pub sample_alarm_engine: Option<serde_json::Value>,
-    pub target_platform: Option<String>,
-    pub file_type: Option<String>,
-    pub file_size: Option<i64>,
-    pub language: Option<String>,
-    pub rule: Option<String>,
-    pub target_content: Option<String>,
-    pub compile_date: Option<i64>,
-    pub last_analy_date: Option<i64>,
-    pub sample_alarm_detail: Option<String>,
-    pub data: Option<serde_json::Value>,
-    pub convergence_count: i32, // 收敛的原始告警数量
-    pub created_at: DateTime<Utc>,
-}
-
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
-pub struct ConvergedHostBehaviorRecord {
-    pub id: Uuid,
-    pub alarm_id: Option<String>,
-    pub alarm_date: Option<i64>,
-    pub alarm_severity: Option<i16>,
-    pub alarm_name: Option<String>,
-    pub alarm_description: Option<String>,
-    pub alarm_type: i16,
-    pub alarm_subtype: i32,
-    pub source: i16,
-    pub control_rule_id: Option<String>,
-    pub control_task_id: Option<String>,
-    pub procedure_technique_id: Option<serde_json::Value>,
-    pub session_id: Option<String>,
-    pub ip_version: Option<i16>,
-    pub src_ip: Option<String>,
-    pub src_port: Option<i32>,
-    pub dst_ip: Option<String>,
-    pub dst_port: Option<i32>,
-    pub protocol: Option<String>,
-    pub terminal_id: Option<String>,
-    pub source_file_path: Option<String>,
-    pub host_name: Option<String>,
-    pub terminal_ip: Option<String>,
-    pub user_account: Option<String>,
-    pub terminal_os: Option<String>,
-    pub dst_process_md5: Option<String>,
-    pub dst_process_path: Option<String>,
-    pub dst_process_cli: Option<String>,
-    pub src_process_md5: Option<String>,
-    pub src_process_path: Option<String>,
-    pub src_process_cli: Option<String>,
-    pub register_key_name: Option<String>,
-    pub register_key_value: Option<String>,
-    pub register_path: Option<String>,
-    pub file_name: Option<String>,
-    pub file_md5: Option<String>,
-    pub file_path: Option<String>,
-    pub data: Option<serde_json::Value>,
-    pub convergence_count: i32, // 收敛的原始告警数量
-    pub created_at: DateTime<Utc>,
+/// 根据告警类型获取收敛后告警表名
+pub fn converged_table_name(alert_type: &str) -> Option<&'static str> {
+    alert_catalog::catalog()
+        .get(alert_type)
+        .map(|t| t.converged_table.as_str())
 }
 
 // ============================================================================
@@ -156,377 +32,174 @@ pub struct ConvergedHostBehaviorRecord {
 
 /// 创建所有收敛后告警表
 pub async fn create_converged_alerts_tables(pool: &PgPool) -> Result<()> {
-    // 网络攻击收敛告警表
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS converged_network_attack_alerts (
-            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
-            alarm_id TEXT,
-            alarm_date BIGINT,
-            alarm_severity SMALLINT,
-            alarm_name TEXT,
-            alarm_description TEXT,
-            alarm_type SMALLINT NOT NULL,
-            alarm_subtype INTEGER NOT NULL,
-            source SMALLINT NOT NULL,
-            control_rule_id TEXT,
-            control_task_id TEXT,
-            procedure_technique_id JSONB,
-            session_id TEXT,
-            ip_version SMALLINT,
-            src_ip TEXT,
-            src_port INTEGER,
-            dst_ip TEXT,
-            dst_port INTEGER,
-            protocol TEXT,
-            terminal_id TEXT,
-            source_file_path TEXT,
-            signature_id TEXT,
-            attack_payload TEXT,
-            attack_stage TEXT,
-            attack_ip TEXT,
-            attacked_ip TEXT,
-            apt_group TEXT,
-            vul_type TEXT,
-            cve_id TEXT,
-            vul_desc TEXT,
-            data JSONB,
-            convergence_count INTEGER DEFAULT 1,
-            created_at TIMESTAMPTZ DEFAULT now()
-        )",
-    )
-    .execute(pool)
-    .await?;
+    // 按字段声明为所有告警类型建表，并补齐新声明的字段列
+    for alert_type in alert_catalog::catalog().types() {
+        create_converged_alert_table(pool, alert_type).await?;
+    }
 
-    // 恶意样本收敛告警表
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS converged_malicious_sample_alerts (
-            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
-            alarm_id TEXT,
-            alarm_date BIGINT,
-            alarm_severity SMALLINT,
-            alarm_name TEXT,
-            alarm_description TEXT,
-            alarm_type SMALLINT NOT NULL,
-            alarm_subtype INTEGER NOT NULL,
-            source SMALLINT NOT NULL,
-            control_rule_id TEXT,
-            control_task_id TEXT,
-            procedure_technique_id JSONB,
-            session_id TEXT,
-            ip_version SMALLINT,
-            src_ip TEXT,
-            src_port INTEGER,
-            dst_ip TEXT,
-            dst_port INTEGER,
-            protocol TEXT,
-            terminal_id TEXT,
-            source_file_path TEXT,
-            sample_source SMALLINT,
-            md5 TEXT,
-            sha1 TEXT,
-            sha256 TEXT,
-            sha512 TEXT,
-            ssdeep TEXT,
-            sample_original_name TEXT,
-            sample_description TEXT,
-            sample_family TEXT,
-            apt_group TEXT,
-            sample_alarm_engine JSONB,
-            target_platform TEXT,
-            file_type TEXT,
-            file_size BIGINT,
-            language TEXT,
-            rule TEXT,
-            target_content TEXT,
-            compile_date BIGINT,
-            last_analy_date BIGINT,
-            sample_alarm_detail TEXT,
-            data JSONB,
-            convergence_count INTEGER DEFAULT 1,
-            created_at TIMESTAMPTZ DEFAULT now()
-        )",
-    )
-    .execute(pool)
-    .await?;
+    // 兼容旧表结构：补齐收敛规则和收敛窗口相关列，并为分组键查询建立索引
+    for alert_type in alert_catalog::catalog().types() {
+        let table = &alert_type.converged_table;
+        sqlx::query(&format!(
+            "ALTER TABLE {table}
+                ADD COLUMN IF NOT EXISTS convergence_rule_id uuid,
+                ADD COLUMN IF NOT EXISTS convergence_rule_version INT,
+                ADD COLUMN IF NOT EXISTS group_key TEXT,
+                ADD COLUMN IF NOT EXISTS threshold_reached BOOLEAN NOT NULL DEFAULT true,
+                ADD COLUMN IF NOT EXISTS threshold_reached_at TIMESTAMPTZ,
+                ADD COLUMN IF NOT EXISTS first_seen TIMESTAMPTZ,
+                ADD COLUMN IF NOT EXISTS last_seen TIMESTAMPTZ"
+        ))
+        .execute(pool)
+        .await?;
 
-    // 主机行为收敛告警表
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS converged_host_behavior_alerts (
-            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
-            alarm_id TEXT,
-            alarm_date BIGINT,
-            alarm_severity SMALLINT,
-            alarm_name TEXT,
-            alarm_description TEXT,
-            alarm_type SMALLINT NOT NULL,
-            alarm_subtype INTEGER NOT NULL,
-            source SMALLINT NOT NULL,
-            control_rule_id TEXT,
-            control_task_id TEXT,
-            procedure_technique_id JSONB,
-            session_id TEXT,
-            ip_version SMALLINT,
-            src_ip TEXT,
-            src_port INTEGER,
-            dst_ip TEXT,
-            dst_port INTEGER,
-            protocol TEXT,
-            terminal_id TEXT,
-            source_file_path TEXT,
-            host_name TEXT,
-            terminal_ip TEXT,
-            user_account TEXT,
-            terminal_os TEXT,
-            dst_process_md5 TEXT,
-            dst_process_path TEXT,
-            dst_process_cli TEXT,
-            src_process_md5 TEXT,
-            src_process_path TEXT,
-            src_process_cli TEXT,
-            register_key_name TEXT,
-            register_key_value TEXT,
-            register_path TEXT,
-            file_name TEXT,
-            file_md5 TEXT,
-            file_path TEXT,
-            data JSONB,
-            convergence_count INTEGER DEFAULT 1,
-            created_at TIMESTAMPTZ DEFAULT now()
-        )",
-    )
-    .execute(pool)
-    .await?;
+        // 旧数据以创建时间回填收敛窗口
+        sqlx::query(&format!(
+            "UPDATE {table}
+             SET first_seen = COALESCE(first_seen, created_at, now()),
+                 last_seen = COALESCE(last_seen, created_at, now())
+             WHERE first_seen IS NULL OR last_seen IS NULL"
+        ))
+        .execute(pool)
+        .await?;
 
-    Ok(())
-}
+        // 已达到阈值的旧数据以创建时间作为达到阈值的时间
+        sqlx::query(&format!(
+            "UPDATE {table}
+             SET threshold_reached_at = COALESCE(created_at, now())
+             WHERE threshold_reached AND threshold_reached_at IS NULL"
+        ))
+        .execute(pool)
+        .await?;
 
-/// 删除所有收敛后告警表
-pub async fn drop_converged_alerts_tables(pool: &PgPool) -> Result<()> {
-    sqlx::query("DROP TABLE IF EXISTS converged_network_attack_alerts CASCADE")
+        // 补列时已有的收敛告警视为达到阈值，新建的收敛告警由插入语句显式写入
+        sqlx::query(&format!(
+            "ALTER TABLE {table}
+                ALTER COLUMN threshold_reached SET DEFAULT false,
+                ALTER COLUMN first_seen SET DEFAULT now(),
+                ALTER COLUMN first_seen SET NOT NULL,
+                ALTER COLUMN last_seen SET DEFAULT now(),
+                ALTER COLUMN last_seen SET NOT NULL"
+        ))
         .execute(pool)
         .await?;
-    sqlx::query("DROP TABLE IF EXISTS converged_malicious_sample_alerts CASCADE")
+
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS idx_{table}_group_key
+             ON {table}(convergence_rule_id, group_key, first_seen)"
+        ))
         .execute(pool)
         .await?;
-    sqlx::query("DROP TABLE IF EXISTS converged_host_behavior_alerts CASCADE")
+
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS idx_{table}_threshold_reached_at
+             ON {table}(threshold_reached_at)"
+        ))
         .execute(pool)
         .await?;
+    }
+
     Ok(())
 }
 
-// ============================================================================
-// 插入操作
-// ============================================================================
+/// 删除所有收敛后告警表
+pub async fn drop_converged_alerts_tables(pool: &PgPool) -> Result<()> {
+    for alert_type in alert_catalog::catalog().types() {
+        sqlx::query(&format!(
+            "DROP TABLE IF EXISTS {} CASCADE",
+            alert_type.converged_table
+        ))
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
 
-pub async fn insert_converged_network_attack(
-    pool: &PgPool,
-    alert: &NetworkAttackAlert,
-    convergence_count: i32,
-) -> Result<Uuid> {
-    let procedure_technique_id = alert.procedure_technique_id.as_ref().map(|v| {
-        serde_json::Value::Array(v.iter().cloned().map(serde_json::Value::String).collect())
-    });
-
-    let id: (Uuid,) = sqlx::query_as(
-        "INSERT INTO converged_network_attack_alerts (
-            alarm_id, alarm_date, alarm_severity, alarm_name, alarm_description,
-            alarm_type, alarm_subtype, source, control_rule_id, control_task_id,
-            procedure_technique_id, session_id, ip_version, src_ip, src_port,
-            dst_ip, dst_port, protocol, terminal_id, source_file_path,
-            signature_id, attack_payload, attack_stage, attack_ip, attacked_ip,
-            apt_group, vul_type, cve_id, vul_desc, data, convergence_count
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
-            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31
-        ) RETURNING id",
-    )
-    .bind(&alert.alarm_id)
-    .bind(alert.alarm_date)
-    .bind(alert.alarm_severity.map(|v| v as i16))
-    .bind(&alert.alarm_name)
-    .bind(&alert.alarm_description)
-    .bind(alert.alarm_type as i16)
-    .bind(alert.alarm_subtype as i32)
-    .bind(alert.source as i16)
-    .bind(&alert.control_rule_id)
-    .bind(&alert.control_task_id)
-    .bind(&procedure_technique_id)
-    .bind(&alert.session_id)
-    .bind(alert.ip_version.map(|v| v as i16))
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .bind(&alert.terminal_id)
-    .bind(&alert.source_file_path)
-    .bind(&alert.signature_id)
-    .bind(&alert.attack_payload)
-    .bind(&alert.attack_stage)
-    .bind(&alert.attack_ip)
-    .bind(&alert.attacked_ip)
-    .bind(&alert.apt_group)
-    .bind(&alert.vul_type)
-    .bind(&alert.cve_id)
-    .bind(&alert.vul_desc)
-    .bind(&alert.data)
-    .bind(convergence_count)
-    .fetch_one(pool)
+/// 按字段声明创建告警类型的收敛告警表，已存在时补齐缺少的字段列
+async fn create_converged_alert_table(pool: &PgPool, alert_type: &AlertType) -> Result<()> {
+    let table = &alert_type.converged_table;
+    let columns: String = alert_type
+        .fields
+        .iter()
+        .map(|field| {
+            let not_null = if field.optional { "" } else { " NOT NULL" };
+            format!("{} {}{},\n", field.name, field.column_type(), not_null)
+        })
+        .collect();
+
+    sqlx::query(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+            {columns}
+            convergence_count INTEGER DEFAULT 1,
+            convergence_rule_id uuid,
+            convergence_rule_version INT,
+            group_key TEXT,
+            threshold_reached BOOLEAN NOT NULL DEFAULT false,
+            threshold_reached_at TIMESTAMPTZ,
+            first_seen TIMESTAMPTZ NOT NULL DEFAULT now(),
+            last_seen TIMESTAMPTZ NOT NULL DEFAULT now(),
+            created_at TIMESTAMPTZ DEFAULT now()
+        )"
+    ))
+    .execute(pool)
     .await?;
 
-    Ok(id.0)
+    for field in &alert_type.fields {
+        sqlx::query(&format!(
+            "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS {} {}",
+            field.name,
+            field.column_type()
+        ))
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
 }
 
-pub async fn insert_converged_malicious_sample(
-    pool: &PgPool,
-    alert: &MaliciousSampleAlert,
-    convergence_count: i32,
-) -> Result<Uuid> {
-    let procedure_technique_id = alert.procedure_technique_id.as_ref().map(|v| {
-        serde_json::Value::Array(v.iter().cloned().map(serde_json::Value::String).collect())
-    });
-
-    let sample_alarm_engine = alert.sample_alarm_engine.as_ref().map(|v| {
-        serde_json::Value::Array(
-            v.iter()
-                .cloned()
-                .map(|n| serde_json::Value::Number(serde_json::Number::from(n)))
-                .collect(),
-        )
-    });
-
-    let id: (Uuid,) = sqlx::query_as(
-        "INSERT INTO converged_malicious_sample_alerts (
-            alarm_id, alarm_date, alarm_severity, alarm_name, alarm_description,
-            alarm_type, alarm_subtype, source, control_rule_id, control_task_id,
-            procedure_technique_id, session_id, ip_version, src_ip, src_port,
-            dst_ip, dst_port, protocol, terminal_id, source_file_path,
-            sample_source, md5, sha1, sha256, sha512, ssdeep,
-            sample_original_name, sample_description, sample_family, apt_group,
-            sample_alarm_engine, target_platform, file_type, file_size, language,
-            rule, target_content, compile_date, last_analy_date, sample_alarm_detail, data, convergence_count
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
-            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30,
-            $31, $32, $33, $34, $35, $36, $37, $38, $39, $40, $41, $42
-        ) RETURNING id"
-    )
-    .bind(&alert.alarm_id)
-    .bind(alert.alarm_date)
-    .bind(alert.alarm_severity.map(|v| v as i16))
-    .bind(&alert.alarm_name)
-    .bind(&alert.alarm_description)
-    .bind(alert.alarm_type as i16)
-    .bind(alert.alarm_subtype as i32)
-    .bind(alert.source as i16)
-    .bind(&alert.control_rule_id)
-    .bind(&alert.control_task_id)
-    .bind(&procedure_technique_id)
-    .bind(&alert.session_id)
-    .bind(alert.ip_version.map(|v| v as i16))
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .bind(&alert.terminal_id)
-    .bind(&alert.source_file_path)
-    .bind(alert.sample_source.map(|v| v as i16))
-    .bind(&alert.md5)
-    .bind(&alert.sha1)
-    .bind(&alert.sha256)
-    .bind(&alert.sha512)
-    .bind(&alert.ssdeep)
-    .bind(&alert.sample_original_name)
-    .bind(&alert.sample_description)
-    .bind(&alert.sample_family)
-    .bind(&alert.apt_group)
-    .bind(&sample_alarm_engine)
-    .bind(&alert.target_platform)
-    .bind(&alert.file_type)
-    .bind(alert.file_size.map(|v| v as i64))
-    .bind(&alert.language)
-    .bind(&alert.rule)
-    .bind(&alert.target_content)
-    .bind(alert.compile_date)
-    .bind(alert.last_analy_date)
-    .bind(&alert.sample_alarm_detail)
-    .bind(&alert.data)
-    .bind(convergence_count)
-    .fetch_one(pool)
-    .await?;
+// ============================================================================
+// 插入操作
+// ============================================================================
 
-    Ok(id.0)
+/// 收敛规则分组：新建收敛告警时与告警内容一同写入
+pub struct ConvergenceGroup<'a> {
+    pub rule_id: Uuid,
+    pub rule_version: i32,
+    pub group_key: &'a str,
+    pub threshold: i32,
 }
 
-pub async fn insert_converged_host_behavior(
-    pool: &PgPool,
-    alert: &HostBehaviorAlert,
+/// 以告警内容新建一条收敛告警，告警需已通过字段声明检查
+///
+/// 按收敛规则收敛时在同一条 INSERT 中写入分组信息和是否达到阈值，
+/// 避免自动推送读到未达阈值却标记为已达到的分组；默认收敛的告警直接视为达到阈值
+pub async fn insert_converged_alert(
+    conn: &mut PgConnection,
+    alert_type: &str,
+    alert_json: &Value,
     convergence_count: i32,
+    group: Option<&ConvergenceGroup<'_>>,
 ) -> Result<Uuid> {
-    let procedure_technique_id = alert.procedure_technique_id.as_ref().map(|v| {
-        serde_json::Value::Array(v.iter().cloned().map(serde_json::Value::String).collect())
-    });
-
-    let id: (Uuid,) = sqlx::query_as(
-        "INSERT INTO converged_host_behavior_alerts (
-            alarm_id, alarm_date, alarm_severity, alarm_name, alarm_description,
-            alarm_type, alarm_subtype, source, control_rule_id, control_task_id,
-            procedure_technique_id, session_id, ip_version, src_ip, src_port,
-            dst_ip, dst_port, protocol, terminal_id, source_file_path,
-            host_name, terminal_ip, user_account, terminal_os,
-            dst_process_md5, dst_process_path, dst_process_cli,
-            src_process_md5, src_process_path, src_process_cli,
-            register_key_name, register_key_value, register_path,
-            file_name, file_md5, file_path, data, convergence_count
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
-            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30,
-            $31, $32, $33, $34, $35, $36, $37, $38
-        ) RETURNING id",
-    )
-    .bind(&alert.alarm_id)
-    .bind(alert.alarm_date)
-    .bind(alert.alarm_severity.map(|v| v as i16))
-    .bind(&alert.alarm_name)
-    .bind(&alert.alarm_description)
-    .bind(alert.alarm_type as i16)
-    .bind(alert.alarm_subtype as i32)
-    .bind(alert.source as i16)
-    .bind(&alert.control_rule_id)
-    .bind(&alert.control_task_id)
-    .bind(&procedure_technique_id)
-    .bind(&alert.session_id)
-    .bind(alert.ip_version.map(|v| v as i16))
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .bind(&alert.terminal_id)
-    .bind(&alert.source_file_path)
-    .bind(&alert.host_name)
-    .bind(&alert.terminal_ip)
-    .bind(&alert.user_account)
-    .bind(&alert.terminal_os)
-    .bind(&alert.dst_process_md5)
-    .bind(&alert.dst_process_path)
-    .bind(&alert.dst_process_cli)
-    .bind(&alert.src_process_md5)
-    .bind(&alert.src_process_path)
-    .bind(&alert.src_process_cli)
-    .bind(&alert.register_key_name)
-    .bind(&alert.register_key_value)
-    .bind(&alert.register_path)
-    .bind(&alert.file_name)
-    .bind(&alert.file_md5)
-    .bind(&alert.file_path)
-    .bind(&alert.data)
+    let alert_type = alert_catalog::catalog().require(alert_type)?;
+    let table = &alert_type.converged_table;
+    let (columns, values) = field_columns(alert_type);
+
+    let id: (Uuid,) = sqlx::query_as(&format!(
+        "INSERT INTO {table} ({columns}convergence_count,
+            convergence_rule_id, convergence_rule_version, group_key,
+            threshold_reached, threshold_reached_at)
+         SELECT {values}$2, $3, $4, $5, $6, CASE WHEN $6 THEN now() END
+         FROM jsonb_populate_record(NULL::{table}, $1) a
+         RETURNING id"
+    ))
+    .bind(alert_type.record(alert_json))
     .bind(convergence_count)
-    .fetch_one(pool)
+    .bind(group.map(|g| g.rule_id))
+    .bind(group.map(|g| g.rule_version))
+    .bind(group.map(|g| g.group_key))
+    .bind(group.is_none_or(|g| convergence_count >= g.threshold))
+    .fetch_one(&mut *conn)
     .await?;
 
     Ok(id.0)
@@ -536,267 +209,213 @@ pub async fn insert_converged_host_behavior(
 // 收敛逻辑：根据五元组查询已存在的收敛告警
 // ============================================================================
 
-/// 根据五元组查询网络攻击收敛告警
-/// 五元组：src_ip, src_port, dst_ip, dst_port, protocol
-pub async fn find_converged_network_attack_by_five_tuple(
-    pool: &PgPool,
-    alert: &NetworkAttackAlert,
-) -> Result<Option<Uuid>> {
-    let result: Option<(Uuid,)> = sqlx::query_as(
-        "SELECT id FROM converged_network_attack_alerts 
-         WHERE src_ip = $1 
-           AND src_port = $2 
-           AND dst_ip = $3 
-           AND dst_port = $4 
-           AND protocol = $5
-         LIMIT 1",
-    )
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .fetch_optional(pool)
-    .await?;
-
-    Ok(result.map(|(id,)| id))
-}
-
-/// 根据样本哈希查询恶意样本收敛告警
-/// 优先使用 sha256，其次 md5
-pub async fn find_converged_malicious_sample_by_hash(
-    pool: &PgPool,
-    alert: &MaliciousSampleAlert,
+/// 在收敛窗口内按告警类型的默认收敛键查询收敛告警
+///
+/// 依次尝试配置的每组收敛键，只使用告警中该组字段都有值的键；
+/// 只匹配 first_seen 不早于 since 的收敛告警，窗口过期后由调用方新建收敛告警
+pub async fn find_converged_by_default_key(
+    conn: &mut PgConnection,
+    alert_type: &str,
+    alert_json: &Value,
+    since: DateTime<Utc>,
 ) -> Result<Option<Uuid>> {
-    // 优先使用 sha256
-    if let Some(ref sha256) = alert.sha256 {
-        let result: Option<(Uuid,)> = sqlx::query_as(
-            "SELECT id FROM converged_malicious_sample_alerts 
-             WHERE sha256 = $1 
-             LIMIT 1",
-        )
-        .bind(sha256)
-        .fetch_optional(pool)
-        .await?;
-
-        if result.is_some() {
-            return Ok(result.map(|(id,)| id));
+    let alert_type = alert_catalog::catalog().require(alert_type)?;
+    let table = &alert_type.converged_table;
+    let record = alert_type.record(alert_json);
+
+    for keys in &alert_type.convergence_keys {
+        let complete = keys
+            .iter()
+            .all(|key| record.get(key).is_some_and(|v| !v.is_null()));
+        if !complete {
+            continue;
         }
-    }
 
-    // 其次使用 md5
-    if let Some(ref md5) = alert.md5 {
-        let result: Option<(Uuid,)> = sqlx::query_as(
-            "SELECT id FROM converged_malicious_sample_alerts 
-             WHERE md5 = $1 
+        let matches: Vec<String> = keys
+            .iter()
+            .map(|key| format!("c.{key} = a.{key}"))
+            .collect();
+        let result: Option<(Uuid,)> = sqlx::query_as(&format!(
+            "SELECT c.id FROM {table} c, jsonb_populate_record(NULL::{table}, $1) a
+             WHERE {}
+               AND c.first_seen >= $2
+             ORDER BY c.first_seen DESC
              LIMIT 1",
-        )
-        .bind(md5)
-        .fetch_optional(pool)
+            matches.join(" AND ")
+        ))
+        .bind(&record)
+        .bind(since)
+        .fetch_optional(&mut *conn)
         .await?;
 
-        return Ok(result.map(|(id,)| id));
+        if let Some((id,)) = result {
+            return Ok(Some(id));
+        }
     }
 
     Ok(None)
 }
 
-/// 根据主机信息查询主机行为收敛告警
-/// 使用：host_name, terminal_ip, dst_process_path, src_process_path
-pub async fn find_converged_host_behavior_by_host_info(
-    pool: &PgPool,
-    alert: &HostBehaviorAlert,
-) -> Result<Option<Uuid>> {
-    let result: Option<(Uuid,)> = sqlx::query_as(
-        "SELECT id FROM converged_host_behavior_alerts 
-         WHERE host_name = $1 
-           AND terminal_ip = $2 
-           AND dst_process_path = $3 
-           AND src_process_path = $4
-         LIMIT 1",
-    )
-    .bind(&alert.host_name)
-    .bind(&alert.terminal_ip)
-    .bind(&alert.dst_process_path)
-    .bind(&alert.src_process_path)
-    .fetch_optional(pool)
-    .await?;
-
-    Ok(result.map(|(id,)| id))
-}
-
-/// 更新网络攻击收敛告警的收敛计数
-pub async fn increment_convergence_count_network_attack(
-    pool: &PgPool,
+/// 更新收敛告警的收敛计数和最近出现时间
+pub async fn increment_convergence_count(
+    conn: &mut PgConnection,
+    alert_type: &str,
     converged_id: Uuid,
 ) -> Result<()> {
-    sqlx::query(
-        "UPDATE converged_network_attack_alerts 
-         SET convergence_count = convergence_count + 1 
-         WHERE id = $1",
-    )
+    let table = &alert_catalog::catalog()
+        .require(alert_type)?
+        .converged_table;
+
+    sqlx::query(&format!(
+        "UPDATE {table}
+         SET convergence_count = convergence_count + 1,
+             last_seen = now()
+         WHERE id = $1"
+    ))
     .bind(converged_id)
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
     Ok(())
 }
 
-/// 更新恶意样本收敛告警的收敛计数
-pub async fn increment_convergence_count_malicious_sample(
-    pool: &PgPool,
-    converged_id: Uuid,
-) -> Result<()> {
-    sqlx::query(
-        "UPDATE converged_malicious_sample_alerts 
-         SET convergence_count = convergence_count + 1 
-         WHERE id = $1",
-    )
-    .bind(converged_id)
-    .execute(pool)
+// ============================================================================
+// 收敛逻辑：根据收敛规则分组键查询/更新收敛告警
+// ============================================================================
+
+/// 在收敛规则的时间窗口内，按分组键查询已存在的收敛告警
+pub async fn find_converged_by_group_key(
+    conn: &mut PgConnection,
+    alert_type: &str,
+    rule_id: Uuid,
+    group_key: &str,
+    since: DateTime<Utc>,
+) -> Result<Option<Uuid>> {
+    let table = converged_table_name(alert_type)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported alert type: {}", alert_type))?;
+
+    let result: Option<(Uuid,)> = sqlx::query_as(&format!(
+        "SELECT id FROM {table}
+         WHERE convergence_rule_id = $1
+           AND group_key = $2
+           AND first_seen >= $3
+         ORDER BY first_seen DESC
+         LIMIT 1"
+    ))
+    .bind(rule_id)
+    .bind(group_key)
+    .bind(since)
+    .fetch_optional(&mut *conn)
     .await?;
 
-    Ok(())
+    Ok(result.map(|(id,)| id))
 }
 
-/// 更新主机行为收敛告警的收敛计数
-pub async fn increment_convergence_count_host_behavior(
-    pool: &PgPool,
+/// 收敛计数加一，并根据规则阈值更新是否达到阈值
+/// 返回更新后的收敛计数
+pub async fn increment_convergence_count_with_threshold(
+    conn: &mut PgConnection,
+    alert_type: &str,
     converged_id: Uuid,
-) -> Result<()> {
-    sqlx::query(
-        "UPDATE converged_host_behavior_alerts 
-         SET convergence_count = convergence_count + 1 
-         WHERE id = $1",
-    )
+    threshold: i32,
+) -> Result<i32> {
+    let table = converged_table_name(alert_type)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported alert type: {}", alert_type))?;
+
+    let count: (i32,) = sqlx::query_as(&format!(
+        "UPDATE {table}
+         SET convergence_count = convergence_count + 1,
+             threshold_reached = convergence_count + 1 >= $2,
+             threshold_reached_at = CASE
+                 WHEN NOT threshold_reached AND convergence_count + 1 >= $2 THEN now()
+                 ELSE threshold_reached_at
+             END,
+             last_seen = now()
+         WHERE id = $1
+         RETURNING convergence_count"
+    ))
     .bind(converged_id)
-    .execute(pool)
+    .bind(threshold)
+    .fetch_one(&mut *conn)
     .await?;
 
-    Ok(())
+    Ok(count.0)
 }
 
 // ============================================================================
 // 查询操作
 // ============================================================================
 
-pub async fn query_converged_network_attacks(
+/// 按查询条件分页查询收敛告警
+pub async fn query_converged_alerts(
     pool: &PgPool,
+    alert_type: &str,
+    query: &ListQuery,
     page: u64,
     page_size: u64,
-) -> Result<(Vec<ConvergedNetworkAttackRecord>, u64)> {
-    let offset = (page - 1) * page_size;
-
-    let records = sqlx::query_as::<_, ConvergedNetworkAttackRecord>(
-        "SELECT * FROM converged_network_attack_alerts ORDER BY created_at DESC LIMIT $1 OFFSET $2",
-    )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(pool)
-    .await?;
-
-    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM converged_network_attack_alerts")
-        .fetch_one(pool)
-        .await?;
-
-    Ok((records, total.0 as u64))
-}
-
-pub async fn query_converged_malicious_samples(
-    pool: &PgPool,
-    page: u64,
-    page_size: u64,
-) -> Result<(Vec<ConvergedMaliciousSampleRecord>, u64)> {
-    let offset = (page - 1) * page_size;
-
-    let records = sqlx::query_as::<_, ConvergedMaliciousSampleRecord>(
-        "SELECT * FROM converged_malicious_sample_alerts ORDER BY created_at DESC LIMIT $1 OFFSET $2"
-    )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(pool)
-    .await?;
-
-    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM converged_malicious_sample_alerts")
-        .fetch_one(pool)
+) -> Result<(Vec<Value>, u64)> {
+    let table = &alert_catalog::catalog()
+        .require(alert_type)?
+        .converged_table;
+
+    let records: Vec<(Value,)> = query
+        .fetch_page(
+            pool,
+            &format!("SELECT to_jsonb(t) FROM {table} t"),
+            page,
+            page_size,
+        )
         .await?;
+    let total = query.count(pool, &format!("{table} t")).await?;
 
-    Ok((records, total.0 as u64))
+    Ok((records.into_iter().map(|(alert,)| alert).collect(), total))
 }
 
-pub async fn query_converged_host_behaviors(
+/// 根据ID查询单条收敛告警
+pub async fn get_converged_alert(
     pool: &PgPool,
-    page: u64,
-    page_size: u64,
-) -> Result<(Vec<ConvergedHostBehaviorRecord>, u64)> {
-    let offset = (page - 1) * page_size;
-
-    let records = sqlx::query_as::<_, ConvergedHostBehaviorRecord>(
-        "SELECT * FROM converged_host_behavior_alerts ORDER BY created_at DESC LIMIT $1 OFFSET $2",
-    )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(pool)
+    alert_type: &str,
+    id: Uuid,
+) -> Result<Option<Value>> {
+    let table = &alert_catalog::catalog()
+        .require(alert_type)?
+        .converged_table;
+
+    let record: Option<(Value,)> = sqlx::query_as(&format!(
+        "SELECT to_jsonb(t) FROM {table} t WHERE t.id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
     .await?;
 
-    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM converged_host_behavior_alerts")
-        .fetch_one(pool)
-        .await?;
-
-    Ok((records, total.0 as u64))
+    Ok(record.map(|(alert,)| alert))
 }
 
 // ============================================================================
 // 自动推送专用查询
 // ============================================================================
 
-/// 查询在指定时间之后，且尚未被推送过的网络攻击收敛告警
-pub async fn query_new_converged_network_attacks(
-    pool: &PgPool,
-    since: DateTime<Utc>,
-) -> Result<Vec<ConvergedNetworkAttackRecord>> {
-    let records = sqlx::query_as(
-        "SELECT t1.*
-         FROM converged_network_attack_alerts t1
-         LEFT JOIN converged_push_logs t2 ON t1.id = t2.converged_id
-         WHERE t1.created_at >= $1 AND t2.converged_id IS NULL",
-    )
-    .bind(since)
-    .fetch_all(pool)
-    .await?;
-    Ok(records)
-}
-
-/// 查询在指定时间之后，且尚未被推送过的恶意样本收敛告警
-pub async fn query_new_converged_malicious_samples(
+/// 查询在指定时间之后达到收敛阈值、且尚未被推送过的收敛告警（字段名与告警一致）
+///
+/// 按达到阈值的时间筛选，窗口开始前创建、之后才达到阈值的分组同样会被推送
+pub async fn query_new_converged_alerts(
     pool: &PgPool,
+    alert_type: &str,
     since: DateTime<Utc>,
-) -> Result<Vec<ConvergedMaliciousSampleRecord>> {
-    let records = sqlx::query_as(
-        "SELECT t1.*
-         FROM converged_malicious_sample_alerts t1
+) -> Result<Vec<Value>> {
+    let table = &alert_catalog::catalog()
+        .require(alert_type)?
+        .converged_table;
+
+    let records: Vec<(Value,)> = sqlx::query_as(&format!(
+        "SELECT to_jsonb(t1)
+         FROM {table} t1
          LEFT JOIN converged_push_logs t2 ON t1.id = t2.converged_id
-         WHERE t1.created_at >= $1 AND t2.converged_id IS NULL",
-    )
+         WHERE t1.threshold_reached_at >= $1 AND t2.converged_id IS NULL"
+    ))
     .bind(since)
     .fetch_all(pool)
     .await?;
-    Ok(records)
-}
 
-/// 查询在指定时间之后，且尚未被推送过的主机行为收敛告警
-pub async fn query_new_converged_host_behaviors(
-    pool: &PgPool,
-    since: DateTime<Utc>,
-) -> Result<Vec<ConvergedHostBehaviorRecord>> {
-    let records = sqlx::query_as(
-        "SELECT t1.*
-         FROM converged_host_behavior_alerts t1
-         LEFT JOIN converged_push_logs t2 ON t1.id = t2.converged_id
-         WHERE t1.created_at >= $1 AND t2.converged_id IS NULL",
-    )
-    .bind(since)
-    .fetch_all(pool)
-    .await?;
-    Ok(records)
+    Ok(records.into_iter().map(|(alert,)| alert).collect())
 }
@@ -1,9 +1,11 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
+use super::rule_versions::{self, RuleChange, RuleKind};
+
 /// 收敛规则记录 - 数据库模型
 #[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct ConvergenceRuleRecord {
@@ -12,6 +14,8 @@ pub struct ConvergenceRuleRecord {
     pub dsl_rule: String,
     pub description: Option<String>,
     pub enabled: bool,
+    /// 规则版本号，每次修改或回滚加一
+    pub version: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -34,6 +38,7 @@ pub async fn create_convergence_rules_table(pool: &PgPool) -> Result<()> {
             dsl_rule TEXT NOT NULL,
             description TEXT,
             enabled BOOLEAN NOT NULL DEFAULT true,
+            version INT NOT NULL DEFAULT 1,
             created_at TIMESTAMPTZ DEFAULT now(),
             updated_at TIMESTAMPTZ DEFAULT now()
         )",
@@ -41,6 +46,12 @@ pub async fn create_convergence_rules_table(pool: &PgPool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    sqlx::query(
+        "ALTER TABLE convergence_rules ADD COLUMN IF NOT EXISTS version INT NOT NULL DEFAULT 1",
+    )
+    .execute(pool)
+    .await?;
+
     sqlx::query(
         "CREATE INDEX IF NOT EXISTS idx_convergence_rules_enabled ON convergence_rules(enabled)",
     )
@@ -62,6 +73,20 @@ pub async fn drop_convergence_rules_table(pool: &PgPool) -> Result<()> {
 pub async fn create_convergence_rule(
     pool: &PgPool,
     input: &ConvergenceRuleInput,
+    change: &RuleChange<'_>,
+) -> Result<ConvergenceRuleRecord> {
+    let mut tx = pool.begin().await?;
+    let record = create_convergence_rule_in_tx(&mut tx, input, change).await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// 在已有事务中创建收敛规则并记录版本
+pub async fn create_convergence_rule_in_tx(
+    conn: &mut PgConnection,
+    input: &ConvergenceRuleInput,
+    change: &RuleChange<'_>,
 ) -> Result<ConvergenceRuleRecord> {
     let record = sqlx::query_as::<_, ConvergenceRuleRecord>(
         "INSERT INTO convergence_rules (name, dsl_rule, description, enabled)
@@ -72,7 +97,18 @@ pub async fn create_convergence_rule(
     .bind(&input.dsl_rule)
     .bind(&input.description)
     .bind(input.enabled)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    rule_versions::append_version(
+        conn,
+        RuleKind::Convergence,
+        record.id,
+        None,
+        &record,
+        record.version,
+        change,
+    )
     .await?;
 
     Ok(record)
@@ -83,10 +119,33 @@ pub async fn update_convergence_rule(
     pool: &PgPool,
     id: Uuid,
     input: &ConvergenceRuleInput,
+    change: &RuleChange<'_>,
 ) -> Result<ConvergenceRuleRecord> {
+    let mut tx = pool.begin().await?;
+    let record = update_convergence_rule_in_tx(&mut tx, id, input, change).await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// 在已有事务中更新收敛规则并记录版本
+pub async fn update_convergence_rule_in_tx(
+    conn: &mut PgConnection,
+    id: Uuid,
+    input: &ConvergenceRuleInput,
+    change: &RuleChange<'_>,
+) -> Result<ConvergenceRuleRecord> {
+    let previous = sqlx::query_as::<_, ConvergenceRuleRecord>(
+        "SELECT * FROM convergence_rules WHERE id = $1 FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
     let record = sqlx::query_as::<_, ConvergenceRuleRecord>(
         "UPDATE convergence_rules
-         SET name = $2, dsl_rule = $3, description = $4, enabled = $5, updated_at = now()
+         SET name = $2, dsl_rule = $3, description = $4, enabled = $5,
+             version = version + 1, updated_at = now()
          WHERE id = $1
          RETURNING *",
     )
@@ -95,7 +154,18 @@ pub async fn update_convergence_rule(
     .bind(&input.dsl_rule)
     .bind(&input.description)
     .bind(input.enabled)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    rule_versions::append_version(
+        conn,
+        RuleKind::Convergence,
+        id,
+        Some((&previous, previous.version)),
+        &record,
+        record.version,
+        change,
+    )
     .await?;
 
     Ok(record)
@@ -146,7 +216,6 @@ pub async fn query_convergence_rules(
 }
 
 /// 查询所有启用的收敛规则
-#[allow(dead_code)]
 pub async fn get_enabled_convergence_rules(pool: &PgPool) -> Result<Vec<ConvergenceRuleRecord>> {
     let records = sqlx::query_as::<_, ConvergenceRuleRecord>(
         "SELECT * FROM convergence_rules WHERE enabled = true ORDER BY created_at DESC",
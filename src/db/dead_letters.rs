@@ -0,0 +1,154 @@
+//! 死信消息表及相关操作
+//!
+//! 保存消费过程中无法处理的 Kafka 消息（非 UTF-8、非法 JSON、无法反序列化为告警结构、重试后仍处理失败），
+//! 记录消息来源（topic/partition/offset）、原始字节、失败阶段和错误信息，便于排查和重放。
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// 失败阶段：消息体为空或不是合法的 UTF-8
+pub const STAGE_DECODE: &str = "decode";
+/// 失败阶段：消息体不是合法的 JSON
+pub const STAGE_PARSE: &str = "parse";
+/// 失败阶段：JSON 无法反序列化为对应类型的告警结构
+pub const STAGE_DESERIALIZE: &str = "deserialize";
+/// 失败阶段：入库、收敛等处理步骤多次重试后仍然失败
+pub const STAGE_PROCESS: &str = "process";
+
+/// 死信消息数据库记录
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct DeadLetterRecord {
+    pub id: Uuid,
+    pub topic: String,
+    pub partition: i32,
+    pub kafka_offset: i64,
+    pub alert_type: String,
+    #[serde(skip_serializing)]
+    pub payload: Vec<u8>, // 原始消息字节
+    pub payload_text: String, // 原始消息的文本形式（非 UTF-8 字节会被替换）
+    pub stage: String,
+    pub error: String,
+    pub replayed: bool,
+    pub replayed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 死信消息输入
+#[derive(Clone, Debug)]
+pub struct DeadLetterInput<'a> {
+    pub topic: &'a str,
+    pub partition: i32,
+    pub kafka_offset: i64,
+    pub alert_type: &'a str,
+    pub payload: &'a [u8],
+    pub stage: &'a str,
+    pub error: String,
+}
+
+/// 创建死信消息表
+pub async fn create_dead_letter_table(pool: &PgPool) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS dead_letter_messages (
+            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+            topic TEXT NOT NULL,
+            partition INTEGER NOT NULL,
+            kafka_offset BIGINT NOT NULL,
+            alert_type TEXT NOT NULL,
+            payload BYTEA NOT NULL,
+            payload_text TEXT NOT NULL,
+            stage VARCHAR(32) NOT NULL,
+            error TEXT NOT NULL,
+            replayed BOOLEAN NOT NULL DEFAULT false,
+            replayed_at TIMESTAMPTZ,
+            created_at TIMESTAMPTZ DEFAULT now()
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_dead_letter_messages_created_at ON dead_letter_messages(created_at)",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 删除死信消息表
+pub async fn drop_dead_letter_table(pool: &PgPool) -> Result<()> {
+    sqlx::query("DROP TABLE IF EXISTS dead_letter_messages CASCADE")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 保存一条死信消息
+pub async fn insert_dead_letter(pool: &PgPool, input: &DeadLetterInput<'_>) -> Result<Uuid> {
+    let id: (Uuid,) = sqlx::query_as(
+        "INSERT INTO dead_letter_messages
+            (topic, partition, kafka_offset, alert_type, payload, payload_text, stage, error)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+         RETURNING id",
+    )
+    .bind(input.topic)
+    .bind(input.partition)
+    .bind(input.kafka_offset)
+    .bind(input.alert_type)
+    .bind(input.payload)
+    .bind(String::from_utf8_lossy(input.payload).into_owned())
+    .bind(input.stage)
+    .bind(&input.error)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id.0)
+}
+
+/// 分页查询死信消息
+pub async fn query_dead_letters(
+    pool: &PgPool,
+    page: u64,
+    page_size: u64,
+) -> Result<(Vec<DeadLetterRecord>, u64)> {
+    let offset = (page - 1) * page_size;
+
+    let records = sqlx::query_as::<_, DeadLetterRecord>(
+        "SELECT * FROM dead_letter_messages ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+    )
+    .bind(page_size as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await?;
+
+    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM dead_letter_messages")
+        .fetch_one(pool)
+        .await?;
+
+    Ok((records, total.0 as u64))
+}
+
+/// 根据ID查询死信消息
+pub async fn get_dead_letter_by_id(pool: &PgPool, id: Uuid) -> Result<Option<DeadLetterRecord>> {
+    let record =
+        sqlx::query_as::<_, DeadLetterRecord>("SELECT * FROM dead_letter_messages WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(record)
+}
+
+/// 标记死信消息已重放
+pub async fn mark_dead_letter_replayed(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query(
+        "UPDATE dead_letter_messages SET replayed = true, replayed_at = now() WHERE id = $1",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -1,9 +1,13 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
+use tracing::warn;
 use uuid::Uuid;
 
+use super::rule_versions::{self, RuleChange, RuleKind};
+use crate::dsl::legacy::{legacy_condition, legacy_matches_null};
+
 /// 过滤规则记录 - 数据库模型
 #[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct FilterRuleRecord {
@@ -14,7 +18,18 @@ pub struct FilterRuleRecord {
     pub field: String,
     pub operator: String,
     pub value: String,
+    /// DSL 条件表达式（WHERE 子句），为空时由 field/operator/value 生成等价条件
+    pub condition: Option<String>,
     pub enabled: bool,
+    /// 规则版本号，每次修改或回滚加一
+    pub version: i32,
+    /// 命中次数
+    #[serde(default)]
+    pub hit_count: i64,
+    /// 最近一次命中时间
+    pub last_hit_at: Option<DateTime<Utc>>,
+    /// 最近一次命中的告警ID（invalid_alerts 中的记录）
+    pub last_hit_alert_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -25,12 +40,28 @@ pub struct FilterRuleInput {
     pub name: String,
     pub alert_type: String,
     pub alert_subtype: String,
+    #[serde(default)]
     pub field: String,
+    #[serde(default)]
     pub operator: String,
+    #[serde(default)]
     pub value: String,
+    /// DSL 条件表达式，未提供时由 field / operator / value 转换得到
+    #[serde(default)]
+    pub condition: Option<String>,
     pub enabled: bool,
 }
 
+impl FilterRuleInput {
+    /// 规则实际生效的 DSL 条件
+    pub fn resolved_condition(&self) -> Option<String> {
+        match self.condition.as_deref().map(str::trim) {
+            Some(condition) if !condition.is_empty() => Some(condition.to_string()),
+            _ => legacy_condition(&self.alert_type, &self.field, &self.operator, &self.value),
+        }
+    }
+}
+
 /// 创建过滤规则表
 pub async fn create_filter_rules_table(pool: &PgPool) -> Result<()> {
     sqlx::query(
@@ -42,7 +73,12 @@ pub async fn create_filter_rules_table(pool: &PgPool) -> Result<()> {
             field TEXT NOT NULL,
             operator TEXT NOT NULL,
             value TEXT NOT NULL,
+            condition TEXT,
             enabled BOOLEAN NOT NULL DEFAULT true,
+            version INT NOT NULL DEFAULT 1,
+            hit_count BIGINT NOT NULL DEFAULT 0,
+            last_hit_at TIMESTAMPTZ,
+            last_hit_alert_id UUID,
             created_at TIMESTAMPTZ DEFAULT now(),
             updated_at TIMESTAMPTZ DEFAULT now()
         )",
@@ -50,6 +86,23 @@ pub async fn create_filter_rules_table(pool: &PgPool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    sqlx::query("ALTER TABLE filter_rules ADD COLUMN IF NOT EXISTS version INT NOT NULL DEFAULT 1")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("ALTER TABLE filter_rules ADD COLUMN IF NOT EXISTS condition TEXT")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        "ALTER TABLE filter_rules
+         ADD COLUMN IF NOT EXISTS hit_count BIGINT NOT NULL DEFAULT 0,
+         ADD COLUMN IF NOT EXISTS last_hit_at TIMESTAMPTZ,
+         ADD COLUMN IF NOT EXISTS last_hit_alert_id UUID",
+    )
+    .execute(pool)
+    .await?;
+
     sqlx::query(
         "CREATE INDEX IF NOT EXISTS idx_filter_rules_alert_type ON filter_rules(alert_type)",
     )
@@ -60,6 +113,41 @@ pub async fn create_filter_rules_table(pool: &PgPool) -> Result<()> {
         .execute(pool)
         .await?;
 
+    migrate_legacy_conditions(pool).await?;
+
+    Ok(())
+}
+
+/// 将旧版单条件规则转换为 DSL 条件
+async fn migrate_legacy_conditions(pool: &PgPool) -> Result<()> {
+    let rows: Vec<(Uuid, String, String, String, String)> = sqlx::query_as(
+        "SELECT id, alert_type, field, operator, value FROM filter_rules WHERE condition IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (id, alert_type, field, operator, value) in rows {
+        match legacy_condition(&alert_type, &field, &operator, &value) {
+            Some(condition) => {
+                if legacy_matches_null(&operator, &value) {
+                    warn!(
+                        "过滤规则 {} 迁移为 DSL 条件后不再匹配 {} 为 null 的告警: {}",
+                        id, field, condition
+                    );
+                }
+                sqlx::query("UPDATE filter_rules SET condition = $2 WHERE id = $1")
+                    .bind(id)
+                    .bind(condition)
+                    .execute(pool)
+                    .await?;
+            }
+            None => warn!(
+                "过滤规则 {} 的条件无法转换为 DSL: {} {} {}",
+                id, field, operator, value
+            ),
+        }
+    }
+
     Ok(())
 }
 
@@ -75,10 +163,24 @@ pub async fn drop_filter_rules_table(pool: &PgPool) -> Result<()> {
 pub async fn create_filter_rule(
     pool: &PgPool,
     input: &FilterRuleInput,
+    change: &RuleChange<'_>,
+) -> Result<FilterRuleRecord> {
+    let mut tx = pool.begin().await?;
+    let record = create_filter_rule_in_tx(&mut tx, input, change).await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// 在已有事务中创建过滤规则并记录版本
+pub async fn create_filter_rule_in_tx(
+    conn: &mut PgConnection,
+    input: &FilterRuleInput,
+    change: &RuleChange<'_>,
 ) -> Result<FilterRuleRecord> {
     let record = sqlx::query_as::<_, FilterRuleRecord>(
-        "INSERT INTO filter_rules (name, alert_type, alert_subtype, field, operator, value, enabled)
-         VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "INSERT INTO filter_rules (name, alert_type, alert_subtype, field, operator, value, condition, enabled)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
          RETURNING *"
     )
     .bind(&input.name)
@@ -87,8 +189,20 @@ pub async fn create_filter_rule(
     .bind(&input.field)
     .bind(&input.operator)
     .bind(&input.value)
+    .bind(input.resolved_condition())
     .bind(input.enabled)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    rule_versions::append_version(
+        conn,
+        RuleKind::Filter,
+        record.id,
+        None,
+        &record,
+        record.version,
+        change,
+    )
     .await?;
 
     Ok(record)
@@ -99,11 +213,34 @@ pub async fn update_filter_rule(
     pool: &PgPool,
     id: Uuid,
     input: &FilterRuleInput,
+    change: &RuleChange<'_>,
+) -> Result<FilterRuleRecord> {
+    let mut tx = pool.begin().await?;
+    let record = update_filter_rule_in_tx(&mut tx, id, input, change).await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// 在已有事务中更新过滤规则并记录版本
+pub async fn update_filter_rule_in_tx(
+    conn: &mut PgConnection,
+    id: Uuid,
+    input: &FilterRuleInput,
+    change: &RuleChange<'_>,
 ) -> Result<FilterRuleRecord> {
+    let previous = sqlx::query_as::<_, FilterRuleRecord>(
+        "SELECT * FROM filter_rules WHERE id = $1 FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
     let record = sqlx::query_as::<_, FilterRuleRecord>(
         "UPDATE filter_rules
          SET name = $2, alert_type = $3, alert_subtype = $4, field = $5, 
-             operator = $6, value = $7, enabled = $8, updated_at = now()
+             operator = $6, value = $7, condition = $8, enabled = $9,
+             version = version + 1, updated_at = now()
          WHERE id = $1
          RETURNING *",
     )
@@ -114,8 +251,20 @@ pub async fn update_filter_rule(
     .bind(&input.field)
     .bind(&input.operator)
     .bind(&input.value)
+    .bind(input.resolved_condition())
     .bind(input.enabled)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    rule_versions::append_version(
+        conn,
+        RuleKind::Filter,
+        id,
+        Some((&previous, previous.version)),
+        &record,
+        record.version,
+        change,
+    )
     .await?;
 
     Ok(record)
@@ -141,6 +290,21 @@ pub async fn get_filter_rule_by_id(pool: &PgPool, id: Uuid) -> Result<FilterRule
     Ok(record)
 }
 
+/// 记录过滤规则的一次命中
+pub async fn record_filter_rule_hit(pool: &PgPool, id: Uuid, alert_id: Uuid) -> Result<()> {
+    sqlx::query(
+        "UPDATE filter_rules
+         SET hit_count = hit_count + 1, last_hit_at = now(), last_hit_alert_id = $2
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(alert_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 /// 查询过滤规则列表（支持分页）
 pub async fn query_filter_rules(
     pool: &PgPool,
@@ -165,7 +329,6 @@ pub async fn query_filter_rules(
 }
 
 /// 查询所有启用的过滤规则
-#[allow(dead_code)]
 pub async fn get_enabled_filter_rules(pool: &PgPool) -> Result<Vec<FilterRuleRecord>> {
     let records = sqlx::query_as::<_, FilterRuleRecord>(
         "SELECT * FROM filter_rules WHERE enabled = true ORDER BY created_at DESC",
@@ -0,0 +1,747 @@
+//! 列表查询的过滤与排序
+//!
+//! 查询参数先编译为带占位符的 WHERE 条件和白名单内的排序列，执行时由 QueryBuilder 绑定参数，
+//! 用户输入不会拼接进 SQL。编译失败（字段不存在、取值格式错误等）返回错误信息，由接口返回 400。
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserialize;
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use std::net::IpAddr;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::threat_event_workflow::DisposeStatus;
+use crate::alert_catalog::{self, AlertType};
+
+/// 条件 SQL 中的参数占位符
+const ARG: &str = "$_";
+
+/// 哈希字段名，以及以 _md5/_sha1/_sha256 结尾的字段
+const HASH_COLUMNS: [&str; 4] = ["md5", "sha1", "sha256", "sha512"];
+
+/// 收敛告警表的公共列，均可排序
+const CONVERGED_COMMON_COLUMNS: [&str; 4] =
+    ["created_at", "first_seen", "last_seen", "convergence_count"];
+
+const THREAT_EVENT_SORT_COLUMNS: [&str; 11] = [
+    "created_at",
+    "found_time",
+    "start_time",
+    "end_time",
+    "first_found_time",
+    "event_id",
+    "name",
+    "severity",
+    "priority",
+    "dispose_status",
+    "status_updated_at",
+];
+
+const INVALID_ALERT_SORT_COLUMNS: [&str; 2] = ["created_at", "alert_type"];
+
+/// 绑定参数
+enum Arg {
+    Text(String),
+    Texts(Vec<String>),
+    Int(i64),
+    Ints(Vec<i64>),
+    Time(DateTime<Utc>),
+    Uuid(Uuid),
+}
+
+/// 一个 WHERE 条件，sql 中每个占位符按顺序对应 args 中的参数
+struct Condition {
+    sql: String,
+    args: Vec<Arg>,
+}
+
+/// 排序方向
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// 编译后的列表查询，表别名统一为 t
+pub struct ListQuery {
+    conditions: Vec<Condition>,
+    order_by: String,
+}
+
+impl ListQuery {
+    fn new(order_by: String) -> Self {
+        Self {
+            conditions: Vec::new(),
+            order_by,
+        }
+    }
+
+    fn add(&mut self, sql: impl Into<String>, args: Vec<Arg>) {
+        self.conditions.push(Condition {
+            sql: sql.into(),
+            args,
+        });
+    }
+
+    fn push_where(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        for (i, condition) in self.conditions.iter().enumerate() {
+            qb.push(if i == 0 { " WHERE " } else { " AND " });
+            let mut parts = condition.sql.split(ARG);
+            qb.push(parts.next().unwrap_or_default());
+            for (part, arg) in parts.zip(&condition.args) {
+                match arg {
+                    Arg::Text(v) => qb.push_bind(v.clone()),
+                    Arg::Texts(v) => qb.push_bind(v.clone()),
+                    Arg::Int(v) => qb.push_bind(*v),
+                    Arg::Ints(v) => qb.push_bind(v.clone()),
+                    Arg::Time(v) => qb.push_bind(*v),
+                    Arg::Uuid(v) => qb.push_bind(*v),
+                };
+                qb.push(part);
+            }
+        }
+    }
+
+    /// 分页查询，select 为不含 WHERE 的查询语句
+    pub async fn fetch_page<T>(
+        &self,
+        pool: &PgPool,
+        select: &str,
+        page: u64,
+        page_size: u64,
+    ) -> Result<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let mut qb = QueryBuilder::new(select);
+        self.push_where(&mut qb);
+        qb.push(" ORDER BY ");
+        qb.push(&self.order_by);
+        qb.push(" LIMIT ");
+        qb.push_bind(page_size as i64);
+        qb.push(" OFFSET ");
+        qb.push_bind((page.max(1) - 1).saturating_mul(page_size) as i64);
+
+        Ok(qb.build_query_as::<T>().fetch_all(pool).await?)
+    }
+
+    /// 统计满足条件的记录数，from 为表名及别名，如 `threat_events t`
+    pub async fn count(&self, pool: &PgPool, from: &str) -> Result<u64> {
+        let mut qb = QueryBuilder::new(format!("SELECT COUNT(*) FROM {from}"));
+        self.push_where(&mut qb);
+        let (total,): (i64,) = qb.build_query_as().fetch_one(pool).await?;
+        Ok(total as u64)
+    }
+
+    /// 生成的 SQL（参数以 $n 表示）
+    #[cfg(test)]
+    pub fn sql(&self, select: &str) -> String {
+        let mut qb = QueryBuilder::new(select);
+        self.push_where(&mut qb);
+        qb.push(" ORDER BY ");
+        qb.push(&self.order_by);
+        qb.into_sql()
+    }
+}
+
+/// 收敛告警查询条件，告警类型没有对应字段时不支持该过滤条件
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConvergedAlertFilter {
+    /// 时间范围：收敛窗口 [first_seen, last_seen] 与之有交集的告警
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// 告警等级，多个用逗号分隔
+    pub severity: Option<String>,
+    /// 告警子类型编码，多个用逗号分隔
+    pub subtype: Option<String>,
+    /// IP 或 CIDR，匹配告警中任一 IP 字段
+    pub ip: Option<String>,
+    pub src_ip: Option<String>,
+    pub dst_ip: Option<String>,
+    /// 样本、进程或文件的哈希，不区分大小写
+    pub hash: Option<String>,
+    /// 主机名，模糊匹配
+    pub host_name: Option<String>,
+    /// 标签名称或标签ID
+    pub tag: Option<String>,
+    /// 收敛次数范围
+    pub min_count: Option<i64>,
+    pub max_count: Option<i64>,
+    pub sort_by: Option<String>,
+    #[serde(default)]
+    pub sort_order: SortOrder,
+}
+
+impl ConvergedAlertFilter {
+    pub fn compile(&self, alert_type: &AlertType) -> Result<ListQuery, String> {
+        let columns: Vec<String> = alert_type
+            .fields
+            .iter()
+            .map(|f| f.name.to_lowercase())
+            .collect();
+        let require = |column: &str, param: &str| -> Result<(), String> {
+            if columns.iter().any(|c| c == column) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "告警类型 {} 没有 {} 字段，不支持按 {} 过滤",
+                    alert_type.name, column, param
+                ))
+            }
+        };
+
+        let sortable: Vec<&str> = CONVERGED_COMMON_COLUMNS
+            .into_iter()
+            .chain(
+                alert_type
+                    .fields
+                    .iter()
+                    .zip(&columns)
+                    .filter(|(f, _)| f.column_type() != "JSONB")
+                    .map(|(_, c)| c.as_str()),
+            )
+            .collect();
+        let mut query = ListQuery::new(order_by(
+            self.sort_by.as_deref(),
+            self.sort_order,
+            &sortable,
+        )?);
+
+        if let Some(start) = value(&self.start_time) {
+            let start = parse_time("start_time", start)?;
+            query.add("t.last_seen >= $_", vec![Arg::Time(start)]);
+        }
+        if let Some(end) = value(&self.end_time) {
+            let end = parse_time("end_time", end)?;
+            query.add("t.first_seen <= $_", vec![Arg::Time(end)]);
+        }
+        if let Some(severity) = value(&self.severity) {
+            require("alarm_severity", "severity")?;
+            query.add(
+                "t.alarm_severity = ANY($_)",
+                vec![Arg::Ints(parse_list("severity", severity)?)],
+            );
+        }
+        if let Some(subtype) = value(&self.subtype) {
+            require("alarm_subtype", "subtype")?;
+            query.add(
+                "t.alarm_subtype = ANY($_)",
+                vec![Arg::Ints(parse_list("subtype", subtype)?)],
+            );
+        }
+        if let Some(ip) = value(&self.ip) {
+            let ip_columns = ip_columns(alert_type);
+            if ip_columns.is_empty() {
+                return Err(format!("告警类型 {} 没有 IP 字段", alert_type.name));
+            }
+            add_ip_condition(&mut query, &ip_columns, "ip", ip)?;
+        }
+        if let Some(ip) = value(&self.src_ip) {
+            require("src_ip", "src_ip")?;
+            add_ip_condition(&mut query, &["src_ip".to_string()], "src_ip", ip)?;
+        }
+        if let Some(ip) = value(&self.dst_ip) {
+            require("dst_ip", "dst_ip")?;
+            add_ip_condition(&mut query, &["dst_ip".to_string()], "dst_ip", ip)?;
+        }
+        if let Some(hash) = value(&self.hash) {
+            let hash_columns = hash_columns(alert_type);
+            if hash_columns.is_empty() {
+                return Err(format!("告警类型 {} 没有哈希字段", alert_type.name));
+            }
+            let sql = hash_columns
+                .iter()
+                .map(|c| format!("lower(t.{c}) = $_"))
+                .collect::<Vec<_>>()
+                .join(" OR ");
+            let args = hash_columns
+                .iter()
+                .map(|_| Arg::Text(hash.to_lowercase()))
+                .collect();
+            query.add(format!("({sql})"), args);
+        }
+        if let Some(host_name) = value(&self.host_name) {
+            require("host_name", "host_name")?;
+            query.add(
+                "t.host_name ILIKE $_",
+                vec![Arg::Text(contains_pattern(host_name))],
+            );
+        }
+        if let Some(tag) = value(&self.tag) {
+            let (tag_condition, tag_arg) = match Uuid::parse_str(tag) {
+                Ok(id) => ("g.id = $_", Arg::Uuid(id)),
+                Err(_) => ("g.name = $_", Arg::Text(tag.to_string())),
+            };
+            query.add(
+                format!(
+                    "EXISTS (SELECT 1 FROM alert_tag_mapping m JOIN tags g ON g.id = m.tag_id
+                     WHERE m.alert_id = t.id AND m.alert_type = $_ AND {tag_condition})"
+                ),
+                vec![Arg::Text(alert_type.name.clone()), tag_arg],
+            );
+        }
+        if let Some(min) = self.min_count {
+            query.add("t.convergence_count >= $_", vec![Arg::Int(min)]);
+        }
+        if let Some(max) = self.max_count {
+            query.add("t.convergence_count <= $_", vec![Arg::Int(max)]);
+        }
+
+        Ok(query)
+    }
+}
+
+/// 威胁事件查询条件
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ThreatEventFilter {
+    /// 监测时间范围
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// 等级、优先级、分类，多个用逗号分隔
+    pub severity: Option<String>,
+    pub priority: Option<String>,
+    pub event_type: Option<String>,
+    /// 处置状态编码或中文名称，多个用逗号分隔
+    pub dispose_status: Option<String>,
+    pub assignee: Option<String>,
+    /// IP 或 CIDR：ip 匹配攻击方或受害方，attack_ip/victim_ip 只匹配一方
+    pub ip: Option<String>,
+    pub attack_ip: Option<String>,
+    pub victim_ip: Option<String>,
+    /// 关键字，模糊匹配名称、描述、攻击者和受害者
+    pub keyword: Option<String>,
+    pub sort_by: Option<String>,
+    #[serde(default)]
+    pub sort_order: SortOrder,
+}
+
+impl ThreatEventFilter {
+    pub fn compile(&self) -> Result<ListQuery, String> {
+        let mut query = ListQuery::new(order_by(
+            self.sort_by.as_deref(),
+            self.sort_order,
+            &THREAT_EVENT_SORT_COLUMNS,
+        )?);
+
+        if let Some(start) = value(&self.start_time) {
+            let start = parse_time("start_time", start)?;
+            query.add("t.found_time >= $_", vec![Arg::Time(start)]);
+        }
+        if let Some(end) = value(&self.end_time) {
+            let end = parse_time("end_time", end)?;
+            query.add("t.found_time <= $_", vec![Arg::Time(end)]);
+        }
+        for (column, param) in [
+            ("severity", &self.severity),
+            ("priority", &self.priority),
+            ("event_type", &self.event_type),
+        ] {
+            if let Some(values) = value(param) {
+                query.add(
+                    format!("t.{column} = ANY($_)"),
+                    vec![Arg::Texts(parse_list(column, values)?)],
+                );
+            }
+        }
+        if let Some(statuses) = value(&self.dispose_status) {
+            let codes = statuses
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| {
+                    DisposeStatus::parse(s)
+                        .map(|status| status.code().to_string())
+                        .ok_or_else(|| format!("未知的处置状态: {}", s))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            query.add("t.dispose_status = ANY($_)", vec![Arg::Texts(codes)]);
+        }
+        if let Some(assignee) = value(&self.assignee) {
+            query.add("t.assignee = $_", vec![Arg::Text(assignee.to_string())]);
+        }
+        for (columns, param, raw) in [
+            (&["attack_asset_ip", "victim_asset_ip"][..], "ip", &self.ip),
+            (&["attack_asset_ip"][..], "attack_ip", &self.attack_ip),
+            (&["victim_asset_ip"][..], "victim_ip", &self.victim_ip),
+        ] {
+            if let Some(ip) = value(raw) {
+                add_ip_array_condition(&mut query, columns, param, ip)?;
+            }
+        }
+        if let Some(keyword) = value(&self.keyword) {
+            let pattern = contains_pattern(keyword);
+            query.add(
+                "(t.name ILIKE $_ OR t.description ILIKE $_ OR t.attacker ILIKE $_ OR t.victimer ILIKE $_)",
+                (0..4).map(|_| Arg::Text(pattern.clone())).collect(),
+            );
+        }
+
+        Ok(query)
+    }
+}
+
+/// 无效告警查询条件
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InvalidAlertFilter {
+    /// 告警类型，多个用逗号分隔
+    pub alert_type: Option<String>,
+    /// 入库时间范围
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// 错误信息，模糊匹配
+    pub error: Option<String>,
+    /// 丢弃告警的过滤规则
+    pub filter_rule_id: Option<Uuid>,
+    pub sort_by: Option<String>,
+    #[serde(default)]
+    pub sort_order: SortOrder,
+}
+
+impl InvalidAlertFilter {
+    pub fn compile(&self) -> Result<ListQuery, String> {
+        let mut query = ListQuery::new(order_by(
+            self.sort_by.as_deref(),
+            self.sort_order,
+            &INVALID_ALERT_SORT_COLUMNS,
+        )?);
+
+        if let Some(types) = value(&self.alert_type) {
+            query.add(
+                "t.alert_type = ANY($_)",
+                vec![Arg::Texts(parse_list("alert_type", types)?)],
+            );
+        }
+        if let Some(start) = value(&self.start_time) {
+            let start = parse_time("start_time", start)?;
+            query.add("t.created_at >= $_", vec![Arg::Time(start)]);
+        }
+        if let Some(end) = value(&self.end_time) {
+            let end = parse_time("end_time", end)?;
+            query.add("t.created_at <= $_", vec![Arg::Time(end)]);
+        }
+        if let Some(error) = value(&self.error) {
+            query.add("t.error ILIKE $_", vec![Arg::Text(contains_pattern(error))]);
+        }
+        if let Some(rule_id) = self.filter_rule_id {
+            query.add("t.filter_rule_id = $_", vec![Arg::Uuid(rule_id)]);
+        }
+
+        Ok(query)
+    }
+}
+
+/// 创建列表过滤和排序使用的索引，需在各业务表之后调用
+///
+/// IP 字段以文本保存，CIDR 过滤通过 try_inet 转换后走 GiST 索引，无法解析的值转换为 NULL
+pub async fn create_list_indexes(pool: &PgPool) -> Result<()> {
+    sqlx::query(
+        "CREATE OR REPLACE FUNCTION try_inet(value TEXT) RETURNS inet
+         LANGUAGE plpgsql IMMUTABLE STRICT AS $$
+         BEGIN
+             RETURN value::inet;
+         EXCEPTION WHEN others THEN
+             RETURN NULL;
+         END
+         $$",
+    )
+    .execute(pool)
+    .await?;
+
+    for alert_type in alert_catalog::catalog().types() {
+        let table = &alert_type.converged_table;
+        let columns: Vec<String> = alert_type
+            .fields
+            .iter()
+            .map(|f| f.name.to_lowercase())
+            .collect();
+
+        let mut btree_columns: Vec<String> = CONVERGED_COMMON_COLUMNS
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        btree_columns.extend(
+            ["alarm_severity", "alarm_subtype"]
+                .into_iter()
+                .filter(|c| columns.iter().any(|f| f == c))
+                .map(str::to_string),
+        );
+        btree_columns.extend(ip_columns(alert_type));
+        for column in btree_columns {
+            sqlx::query(&format!(
+                "CREATE INDEX IF NOT EXISTS idx_{table}_{column} ON {table}({column})"
+            ))
+            .execute(pool)
+            .await?;
+        }
+
+        for column in ip_columns(alert_type) {
+            sqlx::query(&format!(
+                "CREATE INDEX IF NOT EXISTS idx_{table}_{column}_inet
+                 ON {table} USING gist (try_inet({column}) inet_ops)"
+            ))
+            .execute(pool)
+            .await?;
+        }
+
+        for column in hash_columns(alert_type) {
+            sqlx::query(&format!(
+                "CREATE INDEX IF NOT EXISTS idx_{table}_{column}_lower ON {table}(lower({column}))"
+            ))
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    for sql in [
+        "CREATE INDEX IF NOT EXISTS idx_threat_events_created_at ON threat_events(created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_threat_events_found_time ON threat_events(found_time)",
+        "CREATE INDEX IF NOT EXISTS idx_threat_events_dispose_status ON threat_events(dispose_status)",
+        "CREATE INDEX IF NOT EXISTS idx_threat_events_attack_asset_ip
+         ON threat_events USING gin (attack_asset_ip jsonb_path_ops)",
+        "CREATE INDEX IF NOT EXISTS idx_threat_events_victim_asset_ip
+         ON threat_events USING gin (victim_asset_ip jsonb_path_ops)",
+        "CREATE INDEX IF NOT EXISTS idx_invalid_alerts_created_at ON invalid_alerts(created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_invalid_alerts_alert_type ON invalid_alerts(alert_type)",
+    ] {
+        sqlx::query(sql).execute(pool).await?;
+    }
+
+    Ok(())
+}
+
+/// 告警类型中保存 IP 的文本字段（字段名以 _ip 结尾）
+pub fn ip_columns(alert_type: &AlertType) -> Vec<String> {
+    alert_type
+        .fields
+        .iter()
+        .filter(|f| f.column_type() == "TEXT")
+        .map(|f| f.name.to_lowercase())
+        .filter(|c| c.ends_with("_ip"))
+        .collect()
+}
+
+/// 告警类型中保存哈希的文本字段
+pub fn hash_columns(alert_type: &AlertType) -> Vec<String> {
+    alert_type
+        .fields
+        .iter()
+        .filter(|f| f.column_type() == "TEXT")
+        .map(|f| f.name.to_lowercase())
+        .filter(|c| {
+            HASH_COLUMNS.contains(&c.as_str())
+                || HASH_COLUMNS[..3]
+                    .iter()
+                    .any(|h| c.ends_with(&format!("_{h}")))
+        })
+        .collect()
+}
+
+/// 非空的查询参数
+fn value(param: &Option<String>) -> Option<&str> {
+    param.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// 排序子句：列必须在白名单内，以 id 作为次序保证分页稳定
+fn order_by(sort_by: Option<&str>, order: SortOrder, allowed: &[&str]) -> Result<String, String> {
+    let column = match sort_by.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(column) => *allowed
+            .iter()
+            .find(|c| **c == column)
+            .ok_or_else(|| format!("不支持按 {} 排序，可选: {}", column, allowed.join(", ")))?,
+        None => "created_at",
+    };
+    let direction = match order {
+        SortOrder::Asc => "ASC NULLS FIRST",
+        SortOrder::Desc => "DESC NULLS LAST",
+    };
+    Ok(format!("t.{column} {direction}, t.id {direction}"))
+}
+
+/// 时间参数：`YYYY-MM-DD HH:MM:SS` 按 UTC 处理，也接受 RFC 3339
+fn parse_time(param: &str, value: &str) -> Result<DateTime<Utc>, String> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .map(|dt| dt.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(value).map(|dt| dt.with_timezone(&Utc)))
+        .map_err(|_| {
+            format!(
+                "{} 的时间 '{}' 不是 YYYY-MM-DD HH:MM:SS 或 RFC 3339 格式",
+                param, value
+            )
+        })
+}
+
+/// 逗号分隔的取值列表
+fn parse_list<T: FromStr>(param: &str, value: &str) -> Result<Vec<T>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("{} 的取值 '{}' 格式不正确", param, v))
+        })
+        .collect()
+}
+
+/// IP 过滤值：单个地址精确匹配，CIDR 按网段匹配
+enum IpMatch {
+    Addr(String),
+    Net(String),
+}
+
+fn parse_ip(param: &str, value: &str) -> Result<IpMatch, String> {
+    let invalid = || format!("{} 的取值 '{}' 不是合法的 IP 或 CIDR", param, value);
+    match value.split_once('/') {
+        Some((addr, prefix)) => {
+            let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+            let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+            let max = if addr.is_ipv4() { 32 } else { 128 };
+            if prefix > max {
+                return Err(invalid());
+            }
+            Ok(IpMatch::Net(format!("{addr}/{prefix}")))
+        }
+        None => {
+            let addr: IpAddr = value.parse().map_err(|_| invalid())?;
+            Ok(IpMatch::Addr(addr.to_string()))
+        }
+    }
+}
+
+/// 文本 IP 字段：任一字段匹配即可，CIDR 借助 try_inet 转换（非法 IP 视为不匹配）
+fn add_ip_condition(
+    query: &mut ListQuery,
+    columns: &[String],
+    param: &str,
+    value: &str,
+) -> Result<(), String> {
+    let (template, arg) = match parse_ip(param, value)? {
+        IpMatch::Addr(addr) => ("t.{} = $_", addr),
+        IpMatch::Net(net) => ("try_inet(t.{}) <<= $_::inet", net),
+    };
+    let sql = columns
+        .iter()
+        .map(|c| template.replace("{}", c))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let args = columns.iter().map(|_| Arg::Text(arg.clone())).collect();
+    query.add(format!("({sql})"), args);
+    Ok(())
+}
+
+/// 威胁事件的 IP 列表字段（JSONB 数组）
+fn add_ip_array_condition(
+    query: &mut ListQuery,
+    columns: &[&str],
+    param: &str,
+    value: &str,
+) -> Result<(), String> {
+    let (template, arg) = match parse_ip(param, value)? {
+        IpMatch::Addr(addr) => ("t.{} @> jsonb_build_array($_::text)", addr),
+        IpMatch::Net(net) => (
+            "EXISTS (SELECT 1 FROM jsonb_array_elements_text(
+                CASE WHEN jsonb_typeof(t.{}) = 'array' THEN t.{} ELSE '[]'::jsonb END
+             ) ip WHERE try_inet(ip) <<= $_::inet)",
+            net,
+        ),
+    };
+    let sql = columns
+        .iter()
+        .map(|c| template.replace("{}", c))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let args = columns.iter().map(|_| Arg::Text(arg.clone())).collect();
+    query.add(format!("({sql})"), args);
+    Ok(())
+}
+
+/// 模糊匹配的 ILIKE 模式，转义通配符
+fn contains_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按查询字符串（`k=v&...`，值不做百分号解码）构造过滤参数
+    fn parse<T: serde::de::DeserializeOwned>(query: &str) -> T {
+        let params: serde_json::Map<String, serde_json::Value> = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.into()))
+            .collect();
+        serde_json::from_value(params.into()).unwrap()
+    }
+
+    fn converged_filter(query: &str) -> ConvergedAlertFilter {
+        parse(query)
+    }
+
+    #[test]
+    fn test_converged_alert_filter() {
+        let network_attack = alert_catalog::catalog().get("network_attack").unwrap();
+        let query = converged_filter(
+            "start_time=2024-01-01 00:00:00&severity=2,3&src_ip=10.0.0.0/8&tag=C2&sort_by=last_seen&sort_order=asc",
+        )
+        .compile(network_attack)
+        .unwrap();
+        let sql = query.sql("SELECT to_jsonb(t) FROM converged_network_attack_alerts t");
+        assert!(sql.contains("t.last_seen >= $1"));
+        assert!(sql.contains("t.alarm_severity = ANY($2)"));
+        assert!(sql.contains("try_inet(t.src_ip) <<= $3::inet"));
+        assert!(sql.contains("m.alert_type = $4 AND g.name = $5"));
+        assert!(sql.ends_with("ORDER BY t.last_seen ASC NULLS FIRST, t.id ASC NULLS FIRST"));
+
+        // 用户输入只作为参数绑定，不进入 SQL
+        let sql = converged_filter("dst_ip=1.1.1.1")
+            .compile(network_attack)
+            .unwrap()
+            .sql("SELECT 1 FROM t");
+        assert!(sql.contains("(t.dst_ip = $1)"));
+        assert!(!sql.contains("1.1.1.1"));
+    }
+
+    #[test]
+    fn test_converged_alert_filter_rejects_invalid_values() {
+        let network_attack = alert_catalog::catalog().get("network_attack").unwrap();
+        for query in [
+            "src_ip=10.0.0.300",
+            "src_ip=10.0.0.0/33",
+            "severity=high",
+            "start_time=yesterday",
+            "sort_by=created_at;DROP TABLE x",
+            "hash=abc",
+        ] {
+            assert!(
+                converged_filter(query).compile(network_attack).is_err(),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_threat_event_filter() {
+        let filter: ThreatEventFilter =
+            parse("dispose_status=in_progress,new&ip=192.168.1.0/24&keyword=50%_off");
+        let sql = filter
+            .compile()
+            .unwrap()
+            .sql("SELECT t.* FROM threat_events t");
+        assert!(sql.contains("t.dispose_status = ANY($1)"));
+        assert!(sql.contains("try_inet(ip) <<= $2::inet"));
+        assert!(sql.contains("t.victimer ILIKE $7"));
+        assert!(sql.ends_with("ORDER BY t.created_at DESC NULLS LAST, t.id DESC NULLS LAST"));
+
+        let filter: ThreatEventFilter = parse("dispose_status=closed");
+        assert!(filter.compile().is_err());
+    }
+}
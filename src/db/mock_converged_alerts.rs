@@ -7,15 +7,13 @@
 
 use anyhow::Result;
 use chrono::Utc;
+use serde::Serialize;
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use super::{
-    alert_mapping::insert_mappings_batch,
-    converged_alerts::{
-        insert_converged_host_behavior, insert_converged_malicious_sample,
-        insert_converged_network_attack,
-    },
+    alert_mapping::insert_mappings_batch, converged_alerts::insert_converged_alert,
+    raw_alerts::store_raw_alert,
 };
 use crate::models::{HostBehaviorAlert, MaliciousSampleAlert, NetworkAttackAlert};
 
@@ -75,7 +73,7 @@ async fn insert_mock_network_attacks(pool: &PgPool) -> Result<usize> {
             data: None,
         };
 
-        let raw_id = insert_network_attack_and_get_id(pool, &alert).await?;
+        let raw_id = insert_raw_alert(pool, "network_attack", &alert).await?;
         group1_raw_ids.push(raw_id);
     }
 
@@ -113,7 +111,7 @@ async fn insert_mock_network_attacks(pool: &PgPool) -> Result<usize> {
         data: None,
     };
 
-    let converged_id_1 = insert_converged_network_attack(pool, &converged_alert_1, 3).await?;
+    let converged_id_1 = insert_converged(pool, "network_attack", &converged_alert_1, 3).await?;
     insert_mappings_batch(pool, &group1_raw_ids, converged_id_1, 1).await?;
 
     // 第二组：2个类似的端口扫描 -> 收敛为1个
@@ -152,7 +150,7 @@ async fn insert_mock_network_attacks(pool: &PgPool) -> Result<usize> {
             data: None,
         };
 
-        let raw_id = insert_network_attack_and_get_id(pool, &alert).await?;
+        let raw_id = insert_raw_alert(pool, "network_attack", &alert).await?;
         group2_raw_ids.push(raw_id);
     }
 
@@ -189,7 +187,7 @@ async fn insert_mock_network_attacks(pool: &PgPool) -> Result<usize> {
         data: None,
     };
 
-    let converged_id_2 = insert_converged_network_attack(pool, &converged_alert_2, 2).await?;
+    let converged_id_2 = insert_converged(pool, "network_attack", &converged_alert_2, 2).await?;
     insert_mappings_batch(pool, &group2_raw_ids, converged_id_2, 1).await?;
 
     Ok(7) // 5个原始告警 + 2个收敛告警
@@ -250,7 +248,7 @@ async fn insert_mock_malicious_samples(pool: &PgPool) -> Result<usize> {
             data: None,
         };
 
-        let raw_id = insert_malicious_sample_and_get_id(pool, &alert).await?;
+        let raw_id = insert_raw_alert(pool, "malicious_sample", &alert).await?;
         group1_raw_ids.push(raw_id);
     }
 
@@ -300,7 +298,7 @@ async fn insert_mock_malicious_samples(pool: &PgPool) -> Result<usize> {
         data: None,
     };
 
-    let converged_id_1 = insert_converged_malicious_sample(pool, &converged_sample_1, 3).await?;
+    let converged_id_1 = insert_converged(pool, "malicious_sample", &converged_sample_1, 3).await?;
     insert_mappings_batch(pool, &group1_raw_ids, converged_id_1, 2).await?;
 
     // 第二组：1个木马样本（不收敛，直接作为收敛告警）
@@ -350,8 +348,8 @@ async fn insert_mock_malicious_samples(pool: &PgPool) -> Result<usize> {
         data: None,
     };
 
-    let raw_id = insert_malicious_sample_and_get_id(pool, &trojan_alert).await?;
-    let converged_id_2 = insert_converged_malicious_sample(pool, &trojan_alert, 1).await?;
+    let raw_id = insert_raw_alert(pool, "malicious_sample", &trojan_alert).await?;
+    let converged_id_2 = insert_converged(pool, "malicious_sample", &trojan_alert, 1).await?;
     insert_mappings_batch(pool, &[raw_id], converged_id_2, 2).await?;
 
     Ok(6) // 4个原始告警 + 2个收敛告警
@@ -407,7 +405,7 @@ async fn insert_mock_host_behaviors(pool: &PgPool) -> Result<usize> {
             data: None,
         };
 
-        let raw_id = insert_host_behavior_and_get_id(pool, &alert).await?;
+        let raw_id = insert_raw_alert(pool, "host_behavior", &alert).await?;
         group1_raw_ids.push(raw_id);
     }
 
@@ -451,7 +449,7 @@ async fn insert_mock_host_behaviors(pool: &PgPool) -> Result<usize> {
         data: None,
     };
 
-    let converged_id_1 = insert_converged_host_behavior(pool, &converged_behavior_1, 4).await?;
+    let converged_id_1 = insert_converged(pool, "host_behavior", &converged_behavior_1, 4).await?;
     insert_mappings_batch(pool, &group1_raw_ids, converged_id_1, 3).await?;
 
     // 第二组：2个进程注入 -> 收敛为1个
@@ -500,7 +498,7 @@ async fn insert_mock_host_behaviors(pool: &PgPool) -> Result<usize> {
             data: None,
         };
 
-        let raw_id = insert_host_behavior_and_get_id(pool, &alert).await?;
+        let raw_id = insert_raw_alert(pool, "host_behavior", &alert).await?;
         group2_raw_ids.push(raw_id);
     }
 
@@ -544,7 +542,7 @@ async fn insert_mock_host_behaviors(pool: &PgPool) -> Result<usize> {
         data: None,
     };
 
-    let converged_id_2 = insert_converged_host_behavior(pool, &converged_behavior_2, 2).await?;
+    let converged_id_2 = insert_converged(pool, "host_behavior", &converged_behavior_2, 2).await?;
     insert_mappings_batch(pool, &group2_raw_ids, converged_id_2, 3).await?;
 
     Ok(8) // 6个原始告警 + 2个收敛告警
@@ -554,199 +552,24 @@ async fn insert_mock_host_behaviors(pool: &PgPool) -> Result<usize> {
 // 辅助函数：插入并返回ID
 // ============================================================================
 
-async fn insert_network_attack_and_get_id(
+/// 按告警内容写入原始告警，返回原始告警ID
+async fn insert_raw_alert<T: Serialize>(
     pool: &PgPool,
-    alert: &NetworkAttackAlert,
+    alert_type: &str,
+    alert: &T,
 ) -> Result<Uuid> {
-    let id: (Uuid,) = sqlx::query_as(
-        "INSERT INTO network_attack_alerts (
-            alarm_id, alarm_date, alarm_severity, alarm_name, alarm_description,
-            alarm_type, alarm_subtype, source, control_rule_id, control_task_id,
-            procedure_technique_id, session_id, ip_version, src_ip, src_port,
-            dst_ip, dst_port, protocol, terminal_id, source_file_path,
-            signature_id, attack_payload, attack_stage, attack_ip, attacked_ip,
-            apt_group, vul_type, cve_id, vul_desc, data
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
-            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30
-        ) RETURNING id",
-    )
-    .bind(&alert.alarm_id)
-    .bind(alert.alarm_date)
-    .bind(alert.alarm_severity.map(|v| v as i16))
-    .bind(&alert.alarm_name)
-    .bind(&alert.alarm_description)
-    .bind(alert.alarm_type as i16)
-    .bind(alert.alarm_subtype as i32)
-    .bind(alert.source as i16)
-    .bind(&alert.control_rule_id)
-    .bind(&alert.control_task_id)
-    .bind(&alert.procedure_technique_id.as_ref().map(|v| {
-        serde_json::Value::Array(v.iter().cloned().map(serde_json::Value::String).collect())
-    }))
-    .bind(&alert.session_id)
-    .bind(alert.ip_version.map(|v| v as i16))
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .bind(&alert.terminal_id)
-    .bind(&alert.source_file_path)
-    .bind(&alert.signature_id)
-    .bind(&alert.attack_payload)
-    .bind(&alert.attack_stage)
-    .bind(&alert.attack_ip)
-    .bind(&alert.attacked_ip)
-    .bind(&alert.apt_group)
-    .bind(&alert.vul_type)
-    .bind(&alert.cve_id)
-    .bind(&alert.vul_desc)
-    .bind(&alert.data)
-    .fetch_one(pool)
-    .await?;
-
-    Ok(id.0)
+    let alert_json = serde_json::to_value(alert)?;
+    Ok(store_raw_alert(pool, &alert_json, alert_type).await?.id)
 }
 
-async fn insert_malicious_sample_and_get_id(
+/// 以告警内容新建一条默认收敛的收敛告警，返回收敛告警ID
+async fn insert_converged<T: Serialize>(
     pool: &PgPool,
-    alert: &MaliciousSampleAlert,
+    alert_type: &str,
+    alert: &T,
+    convergence_count: i32,
 ) -> Result<Uuid> {
-    let id: (Uuid,) = sqlx::query_as(
-        "INSERT INTO malicious_sample_alerts (
-            alarm_id, alarm_date, alarm_severity, alarm_name, alarm_description,
-            alarm_type, alarm_subtype, source, control_rule_id, control_task_id,
-            procedure_technique_id, session_id, ip_version, src_ip, src_port,
-            dst_ip, dst_port, protocol, terminal_id, source_file_path,
-            sample_source, md5, sha1, sha256, sha512, ssdeep,
-            sample_original_name, sample_description, sample_family, apt_group,
-            sample_alarm_engine, target_platform, file_type, file_size, language,
-            rule, target_content, compile_date, last_analy_date, sample_alarm_detail, data
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
-            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30,
-            $31, $32, $33, $34, $35, $36, $37, $38, $39, $40, $41
-        ) RETURNING id",
-    )
-    .bind(&alert.alarm_id)
-    .bind(alert.alarm_date)
-    .bind(alert.alarm_severity.map(|v| v as i16))
-    .bind(&alert.alarm_name)
-    .bind(&alert.alarm_description)
-    .bind(alert.alarm_type as i16)
-    .bind(alert.alarm_subtype as i32)
-    .bind(alert.source as i16)
-    .bind(&alert.control_rule_id)
-    .bind(&alert.control_task_id)
-    .bind(&alert.procedure_technique_id.as_ref().map(|v| {
-        serde_json::Value::Array(v.iter().cloned().map(serde_json::Value::String).collect())
-    }))
-    .bind(&alert.session_id)
-    .bind(alert.ip_version.map(|v| v as i16))
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .bind(&alert.terminal_id)
-    .bind(&alert.source_file_path)
-    .bind(alert.sample_source.map(|v| v as i16))
-    .bind(&alert.md5)
-    .bind(&alert.sha1)
-    .bind(&alert.sha256)
-    .bind(&alert.sha512)
-    .bind(&alert.ssdeep)
-    .bind(&alert.sample_original_name)
-    .bind(&alert.sample_description)
-    .bind(&alert.sample_family)
-    .bind(&alert.apt_group)
-    .bind(&alert.sample_alarm_engine.as_ref().map(|v| {
-        serde_json::Value::Array(
-            v.iter()
-                .cloned()
-                .map(|n| serde_json::Value::Number(serde_json::Number::from(n)))
-                .collect(),
-        )
-    }))
-    .bind(&alert.target_platform)
-    .bind(&alert.file_type)
-    .bind(alert.file_size.map(|v| v as i64))
-    .bind(&alert.language)
-    .bind(&alert.rule)
-    .bind(&alert.target_content)
-    .bind(alert.compile_date)
-    .bind(alert.last_analy_date)
-    .bind(&alert.sample_alarm_detail)
-    .bind(&alert.data)
-    .fetch_one(pool)
-    .await?;
-
-    Ok(id.0)
-}
-
-async fn insert_host_behavior_and_get_id(pool: &PgPool, alert: &HostBehaviorAlert) -> Result<Uuid> {
-    let id: (Uuid,) = sqlx::query_as(
-        "INSERT INTO host_behavior_alerts (
-            alarm_id, alarm_date, alarm_severity, alarm_name, alarm_description,
-            alarm_type, alarm_subtype, source, control_rule_id, control_task_id,
-            procedure_technique_id, session_id, ip_version, src_ip, src_port,
-            dst_ip, dst_port, protocol, terminal_id, source_file_path,
-            host_name, terminal_ip, user_account, terminal_os,
-            dst_process_md5, dst_process_path, dst_process_cli,
-            src_process_md5, src_process_path, src_process_cli,
-            register_key_name, register_key_value, register_path,
-            file_name, file_md5, file_path, data
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
-            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30,
-            $31, $32, $33, $34, $35, $36, $37
-        ) RETURNING id",
-    )
-    .bind(&alert.alarm_id)
-    .bind(alert.alarm_date)
-    .bind(alert.alarm_severity.map(|v| v as i16))
-    .bind(&alert.alarm_name)
-    .bind(&alert.alarm_description)
-    .bind(alert.alarm_type as i16)
-    .bind(alert.alarm_subtype as i32)
-    .bind(alert.source as i16)
-    .bind(&alert.control_rule_id)
-    .bind(&alert.control_task_id)
-    .bind(&alert.procedure_technique_id.as_ref().map(|v| {
-        serde_json::Value::Array(v.iter().cloned().map(serde_json::Value::String).collect())
-    }))
-    .bind(&alert.session_id)
-    .bind(alert.ip_version.map(|v| v as i16))
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .bind(&alert.terminal_id)
-    .bind(&alert.source_file_path)
-    .bind(&alert.host_name)
-    .bind(&alert.terminal_ip)
-    .bind(&alert.user_account)
-    .bind(&alert.terminal_os)
-    .bind(&alert.dst_process_md5)
-    .bind(&alert.dst_process_path)
-    .bind(&alert.dst_process_cli)
-    .bind(&alert.src_process_md5)
-    .bind(&alert.src_process_path)
-    .bind(&alert.src_process_cli)
-    .bind(&alert.register_key_name)
-    .bind(&alert.register_key_value)
-    .bind(&alert.register_path)
-    .bind(&alert.file_name)
-    .bind(&alert.file_md5)
-    .bind(&alert.file_path)
-    .bind(&alert.data)
-    .fetch_one(pool)
-    .await?;
-
-    Ok(id.0)
+    let alert_json = serde_json::to_value(alert)?;
+    let mut conn = pool.acquire().await?;
+    insert_converged_alert(&mut conn, alert_type, &alert_json, convergence_count, None).await
 }
@@ -5,6 +5,7 @@ use super::{
     convergence_rules::{self, ConvergenceRuleInput},
     correlation_rules::{self, CorrelationRuleInput},
     filter_rules::{self, FilterRuleInput},
+    rule_versions::RuleChange,
     tag_rules::{self, TagRuleInput},
 };
 
@@ -19,7 +20,7 @@ pub async fn insert_mock_convergence_rules(pool: &PgPool) -> Result<usize> {
         },
         ConvergenceRuleInput {
             name: "主机行为告警收敛".to_string(),
-            dsl_rule: "CONVERGE\n  WHERE alarm_type == 2\n  GROUP BY host_name, user_account\n  WINDOW 10m\n  THRESHOLD 20".to_string(),
+            dsl_rule: "CONVERGE\n  WHERE alarm_type == 3\n  GROUP BY host_name, user_account\n  WINDOW 10m\n  THRESHOLD 20".to_string(),
             description: Some("对同一主机和用户的行为告警进行收敛".to_string()),
             enabled: true,
         },
@@ -37,7 +38,7 @@ pub async fn insert_mock_convergence_rules(pool: &PgPool) -> Result<usize> {
         },
         ConvergenceRuleInput {
             name: "恶意样本告警收敛".to_string(),
-            dsl_rule: "CONVERGE\n  WHERE alarm_type == 3\n  GROUP BY md5, sample_family\n  WINDOW 20m\n  THRESHOLD 3".to_string(),
+            dsl_rule: "CONVERGE\n  WHERE alarm_type == 2\n  GROUP BY md5, sample_family\n  WINDOW 20m\n  THRESHOLD 3".to_string(),
             description: Some("相同MD5和样本家族的恶意样本告警收敛".to_string()),
             enabled: false,
         },
@@ -45,7 +46,7 @@ pub async fn insert_mock_convergence_rules(pool: &PgPool) -> Result<usize> {
 
     let mut count = 0;
     for rule in rules {
-        convergence_rules::create_convergence_rule(pool, &rule).await?;
+        convergence_rules::create_convergence_rule(pool, &rule, &RuleChange::create(None)).await?;
         count += 1;
     }
 
@@ -57,7 +58,7 @@ pub async fn insert_mock_correlation_rules(pool: &PgPool) -> Result<usize> {
     let rules = vec![
         CorrelationRuleInput {
             name: "攻击链关联检测".to_string(),
-            dsl_rule: "CORRELATE\n  EVENT attack WHERE alarm_type == 1 AND alarm_severity >= 2\n  EVENT behavior WHERE alarm_type == 2 AND dst_process_path CONTAINS \"cmd.exe\"\n  JOIN ON attack.dst_ip == behavior.terminal_ip\n  WINDOW 10m\n  GENERATE\n    SEVERITY 3\n    NAME \"检测到攻击链活动\"\n    DESCRIPTION \"网络攻击后发现可疑主机行为\"".to_string(),
+            dsl_rule: "CORRELATE\n  EVENT attack WHERE alarm_type == 1 AND alarm_severity >= 2\n  EVENT behavior WHERE alarm_type == 3 AND dst_process_path CONTAINS \"cmd.exe\"\n  JOIN ON attack.dst_ip == behavior.terminal_ip\n  WINDOW 10m\n  GENERATE\n    SEVERITY 3\n    NAME \"检测到攻击链活动\"\n    DESCRIPTION \"网络攻击后发现可疑主机行为\"".to_string(),
             description: Some("检测网络攻击后的可疑主机行为，识别攻击链".to_string()),
             enabled: true,
         },
@@ -69,7 +70,7 @@ pub async fn insert_mock_correlation_rules(pool: &PgPool) -> Result<usize> {
         },
         CorrelationRuleInput {
             name: "APT攻击场景关联".to_string(),
-            dsl_rule: "CORRELATE\n  EVENT sample WHERE alarm_type == 3 AND apt_group != \"\"\n  EVENT c2 WHERE alarm_subtype == 1020 AND alarm_name CONTAINS \"C2\"\n  EVENT exfil WHERE alarm_name REGEX \".*数据泄露.*\"\n  JOIN ON sample.terminal_ip == c2.src_ip AND c2.src_ip == exfil.src_ip\n  WINDOW 60m\n  GENERATE\n    SEVERITY 4\n    NAME \"APT攻击活动检测\"\n    DESCRIPTION \"检测到完整的APT攻击链\"".to_string(),
+            dsl_rule: "CORRELATE\n  EVENT sample WHERE alarm_type == 2 AND apt_group != \"\"\n  EVENT c2 WHERE alarm_subtype == 1020 AND alarm_name CONTAINS \"C2\"\n  EVENT exfil WHERE alarm_name REGEX \".*数据泄露.*\"\n  JOIN ON sample.dst_ip == c2.src_ip AND c2.src_ip == exfil.src_ip\n  WINDOW 60m\n  GENERATE\n    SEVERITY 4\n    NAME \"APT攻击活动检测\"\n    DESCRIPTION \"检测到完整的APT攻击链\"".to_string(),
             description: Some("检测APT攻击的完整链条：恶意样本->C2通信->数据泄露".to_string()),
             enabled: true,
         },
@@ -89,7 +90,7 @@ pub async fn insert_mock_correlation_rules(pool: &PgPool) -> Result<usize> {
 
     let mut count = 0;
     for rule in rules {
-        correlation_rules::create_correlation_rule(pool, &rule).await?;
+        correlation_rules::create_correlation_rule(pool, &rule, &RuleChange::create(None)).await?;
         count += 1;
     }
 
@@ -106,6 +107,7 @@ pub async fn insert_mock_filter_rules(pool: &PgPool) -> Result<usize> {
             field: "alarm_severity".to_string(),
             operator: "eq".to_string(),
             value: "1".to_string(),
+            condition: None,
             enabled: true,
         },
         FilterRuleInput {
@@ -115,6 +117,7 @@ pub async fn insert_mock_filter_rules(pool: &PgPool) -> Result<usize> {
             field: "src_ip".to_string(),
             operator: "contains".to_string(),
             value: "192.168.100".to_string(),
+            condition: None,
             enabled: true,
         },
         FilterRuleInput {
@@ -124,6 +127,7 @@ pub async fn insert_mock_filter_rules(pool: &PgPool) -> Result<usize> {
             field: "md5".to_string(),
             operator: "regex".to_string(),
             value: "^(abc123|def456).*".to_string(),
+            condition: None,
             enabled: false,
         },
         FilterRuleInput {
@@ -133,6 +137,7 @@ pub async fn insert_mock_filter_rules(pool: &PgPool) -> Result<usize> {
             field: "src_process_path".to_string(),
             operator: "contains".to_string(),
             value: "System32\\svchost.exe".to_string(),
+            condition: None,
             enabled: true,
         },
         FilterRuleInput {
@@ -142,6 +147,7 @@ pub async fn insert_mock_filter_rules(pool: &PgPool) -> Result<usize> {
             field: "src_ip".to_string(),
             operator: "regex".to_string(),
             value: "^10\\.(0|1|2)\\..+".to_string(),
+            condition: None,
             enabled: false,
         },
         FilterRuleInput {
@@ -151,13 +157,14 @@ pub async fn insert_mock_filter_rules(pool: &PgPool) -> Result<usize> {
             field: "alarm_severity".to_string(),
             operator: "eq".to_string(),
             value: "0".to_string(),
+            condition: None,
             enabled: true,
         },
     ];
 
     let mut count = 0;
     for rule in rules {
-        filter_rules::create_filter_rule(pool, &rule).await?;
+        filter_rules::create_filter_rule(pool, &rule, &RuleChange::create(None)).await?;
         count += 1;
     }
 
@@ -174,6 +181,7 @@ pub async fn insert_mock_tag_rules(pool: &PgPool) -> Result<usize> {
             condition_field: "alarm_severity".to_string(),
             condition_operator: "eq".to_string(),
             condition_value: "3".to_string(),
+            condition: None,
             tags: vec!["高危".to_string(), "需人工审核".to_string()],
             description: Some("为高危网络攻击事件添加标签".to_string()),
             enabled: true,
@@ -185,6 +193,7 @@ pub async fn insert_mock_tag_rules(pool: &PgPool) -> Result<usize> {
             condition_field: "apt_group".to_string(),
             condition_operator: "ne".to_string(),
             condition_value: "".to_string(),
+            condition: None,
             tags: vec!["APT攻击".to_string(), "高优先级".to_string()],
             description: Some("为APT组织相关攻击添加标签".to_string()),
             enabled: true,
@@ -196,6 +205,7 @@ pub async fn insert_mock_tag_rules(pool: &PgPool) -> Result<usize> {
             condition_field: "sample_family".to_string(),
             condition_operator: "regex".to_string(),
             condition_value: ".*(Ransom|Crypto|Locker).*".to_string(),
+            condition: None,
             tags: vec![
                 "勒索软件".to_string(),
                 "严重".to_string(),
@@ -211,6 +221,7 @@ pub async fn insert_mock_tag_rules(pool: &PgPool) -> Result<usize> {
             condition_field: "alarm_name".to_string(),
             condition_operator: "contains".to_string(),
             condition_value: "横向".to_string(),
+            condition: None,
             tags: vec!["横向移动".to_string(), "内网渗透".to_string()],
             description: Some("标记可能的内网横向移动行为".to_string()),
             enabled: true,
@@ -222,6 +233,7 @@ pub async fn insert_mock_tag_rules(pool: &PgPool) -> Result<usize> {
             condition_field: "alarm_name".to_string(),
             condition_operator: "contains".to_string(),
             condition_value: "C2".to_string(),
+            condition: None,
             tags: vec![
                 "C2通信".to_string(),
                 "高优先级".to_string(),
@@ -237,6 +249,7 @@ pub async fn insert_mock_tag_rules(pool: &PgPool) -> Result<usize> {
             condition_field: "alarm_name".to_string(),
             condition_operator: "regex".to_string(),
             condition_value: ".*(泄露|外传|上传).*".to_string(),
+            condition: None,
             tags: vec!["数据泄露".to_string(), "严重".to_string()],
             description: Some("标记可能的数据泄露事件".to_string()),
             enabled: true,
@@ -248,6 +261,7 @@ pub async fn insert_mock_tag_rules(pool: &PgPool) -> Result<usize> {
             condition_field: "source".to_string(),
             condition_operator: "contains".to_string(),
             condition_value: "威胁情报".to_string(),
+            condition: None,
             tags: vec!["威胁情报".to_string(), "已确认".to_string()],
             description: Some("标记来自威胁情报的告警".to_string()),
             enabled: false,
@@ -256,7 +270,7 @@ pub async fn insert_mock_tag_rules(pool: &PgPool) -> Result<usize> {
 
     let mut count = 0;
     for rule in rules {
-        tag_rules::create_tag_rule(pool, &rule).await?;
+        tag_rules::create_tag_rule(pool, &rule, &RuleChange::create(None)).await?;
         count += 1;
     }
 
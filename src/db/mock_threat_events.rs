@@ -2,8 +2,8 @@ use anyhow::Result;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use sqlx::PgPool;
 
-/// 插入威胁事件模拟数据
-/// 返回插入的记录数
+/// 插入威胁事件模拟数据，event_id 已存在的示例会被跳过
+/// 返回处理的示例数
 pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
     let mut count = 0;
 
@@ -27,7 +27,8 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
             $21, $22, $23, $24, $25, $26, $27, $28, $29, $30,
             $31, $32, $33, $34, $35, $36, $37, $38, $39, $40,
             $41, $42, $43
-        )",
+        )
+        ON CONFLICT DO NOTHING",
     )
     .bind(1000001_i64)
     .bind("SYS-2025-001")
@@ -46,7 +47,7 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
     .bind(parse_time("2025-09-10 08:45:00"))
     .bind("高")
     .bind("严重")
-    .bind("未审核")
+    .bind("new")
     .bind("Microsoft Word, Apache Tomcat")
     .bind("可能导致核心业务系统数据泄露")
     .bind(serde_json::json!([
@@ -110,7 +111,8 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
             $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
             $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
             $21, $22, $23
-        )",
+        )
+        ON CONFLICT DO NOTHING",
     )
     .bind(1000002_i64)
     .bind("SYS-2025-002")
@@ -129,7 +131,7 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
     .bind(parse_time("2025-10-01 14:30:00"))
     .bind("高")
     .bind("严重")
-    .bind("已审核")
+    .bind("triaged")
     .bind("Windows Server, SQL Server")
     .bind("重要生产数据被加密，业务中断")
     .bind(serde_json::json!(["203.0.113.45"]))
@@ -150,7 +152,8 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
         ) VALUES (
             $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
             $11, $12, $13, $14, $15, $16, $17, $18, $19, $20
-        )",
+        )
+        ON CONFLICT DO NOTHING",
     )
     .bind(1000003_i64)
     .bind("SYS-2025-003")
@@ -168,7 +171,7 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
     .bind(parse_time("2025-10-05 10:02:00"))
     .bind("中")
     .bind("高危")
-    .bind("未审核")
+    .bind("new")
     .bind("官网服务短暂中断，部分用户无法访问")
     .bind(serde_json::json!(["198.51.100.0/24", "203.0.113.0/24"]))
     .bind(serde_json::json!(["104.28.1.100"]))
@@ -186,7 +189,8 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
         ) VALUES (
             $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
             $11, $12, $13, $14, $15, $16, $17, $18, $19
-        )",
+        )
+        ON CONFLICT DO NOTHING",
     )
     .bind(1000004_i64)
     .bind("SYS-2025-004")
@@ -204,7 +208,7 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
     .bind(parse_time("2025-10-10 16:35:00"))
     .bind("高")
     .bind("严重")
-    .bind("已审核")
+    .bind("triaged")
     .bind("Email系统, 文件服务器")
     .bind("约5000条客户个人信息可能泄露")
     .execute(pool)
@@ -222,7 +226,8 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
         ) VALUES (
             $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
             $11, $12, $13, $14, $15, $16, $17, $18
-        )",
+        )
+        ON CONFLICT DO NOTHING",
     )
     .bind(1000005_i64)
     .bind("SYS-2025-005")
@@ -239,7 +244,7 @@ pub async fn insert_mock_data(pool: &PgPool) -> Result<usize> {
     .bind(parse_time("2025-10-15 09:30:00"))
     .bind("高")
     .bind("严重")
-    .bind("未审核")
+    .bind("new")
     .bind("可能导致多个客户环境被渗透")
     .bind(serde_json::json!(["MonitoringSoftware v3.2.1"]))
     .execute(pool)
@@ -4,15 +4,22 @@ pub mod auto_push;
 pub mod converged_alerts;
 pub mod convergence_rules;
 pub mod correlation_rules;
+pub mod dead_letters;
 pub mod filter_rules;
+pub mod list_filter;
 pub mod mock_converged_alerts;
 pub mod mock_rules;
 pub mod mock_tags;
 pub mod mock_threat_events;
 pub mod raw_alerts;
+pub mod rule_bundle;
+pub mod rule_versions;
 pub mod tag_management;
 pub mod tag_rules;
 pub mod threat_event;
+pub mod threat_event_alerts;
+pub mod threat_event_push;
+pub mod threat_event_workflow;
 
 use anyhow::Result;
 use sqlx::{postgres::PgPoolOptions, PgPool};
@@ -22,58 +29,37 @@ use crate::config::PostgresConfig;
 pub use threat_event::{ThreatEventInput, ThreatEventRecord};
 
 // 原始告警
-pub use raw_alerts::{
-    insert_host_behavior,
-    insert_malicious_sample,
-    insert_network_attack,
-    query_invalid_alerts,
-    query_raw_host_behaviors_by_converged_id,
-    query_raw_malicious_samples_by_converged_id,
-    // 根据收敛告警ID查询原始告警
-    query_raw_network_attacks_by_converged_id,
-    HostBehaviorRecord,
-    InvalidAlertRecord,
-    MaliciousSampleRecord,
-    NetworkAttackRecord,
-};
-
-// 保留以备将来直接查询原始告警表使用
-#[allow(unused_imports)]
-pub use raw_alerts::{query_host_behaviors, query_malicious_samples, query_network_attacks};
+pub use raw_alerts::{query_invalid_alerts, query_raw_alerts_of_converged, InvalidAlertRecord};
 
 // 收敛后告警 - 当前使用的主要查询接口
 pub use converged_alerts::{
-    query_converged_host_behaviors, query_converged_malicious_samples,
-    query_converged_network_attacks, query_new_converged_host_behaviors,
-    query_new_converged_malicious_samples, query_new_converged_network_attacks,
-    ConvergedHostBehaviorRecord, ConvergedMaliciousSampleRecord, ConvergedNetworkAttackRecord,
+    get_converged_alert, query_converged_alerts, query_new_converged_alerts,
 };
 
 // 收敛插入函数
-pub use converged_alerts::{
-    insert_converged_host_behavior, insert_converged_malicious_sample,
-    insert_converged_network_attack,
-};
+pub use converged_alerts::{insert_converged_alert, ConvergenceGroup};
 
 // 收敛查询和更新函数
+pub use converged_alerts::{find_converged_by_default_key, increment_convergence_count};
+
+// 收敛规则分组键查询和更新函数
 pub use converged_alerts::{
-    find_converged_host_behavior_by_host_info, find_converged_malicious_sample_by_hash,
-    find_converged_network_attack_by_five_tuple, increment_convergence_count_host_behavior,
-    increment_convergence_count_malicious_sample, increment_convergence_count_network_attack,
+    find_converged_by_group_key, increment_convergence_count_with_threshold,
 };
 
 // 映射表操作
-pub use alert_mapping::insert_mapping;
+pub use alert_mapping::{
+    insert_mapping, mark_mapping_correlated, query_mapping_state, MappingState,
+};
 
 // 统一导出存储函数
-pub use raw_alerts::{store_invalid_alert, store_raw_alert};
+pub use raw_alerts::{store_invalid_alert, store_raw_alert, store_schema_violation};
 
 // 映射表查询函数 - 保留给未来使用
 #[allow(unused_imports)]
 pub use alert_mapping::{
-    count_raw_alerts_by_converged_id, insert_mappings_batch, query_converged_alert_by_raw_id,
-    query_mappings_by_converged_id, query_raw_alerts_by_converged_id,
-    AlertConvergenceMappingRecord,
+    count_raw_alerts_by_converged_id, insert_mappings_batch, query_mappings_by_converged_id,
+    query_raw_alerts_by_converged_id, AlertConvergenceMappingRecord,
 };
 
 pub async fn init_postgres(pg: &PostgresConfig) -> Result<PgPool> {
@@ -92,9 +78,12 @@ pub async fn init_postgres(pg: &PostgresConfig) -> Result<PgPool> {
         .execute(&pool)
         .await?;
 
-    // 原始告警表（网络攻击/恶意样本/主机行为/无效告警）
+    // 原始告警表（各告警类型/无效告警）
     raw_alerts::create_raw_alerts_tables(&pool).await?;
 
+    // 死信消息表（无法解析或反序列化的 Kafka 消息）
+    dead_letters::create_dead_letter_table(&pool).await?;
+
     // 收敛后告警表
     converged_alerts::create_converged_alerts_tables(&pool).await?;
 
@@ -107,6 +96,15 @@ pub async fn init_postgres(pg: &PostgresConfig) -> Result<PgPool> {
     // 威胁事件表
     threat_event::create_threat_event_table(&pool).await?;
 
+    // 威胁事件处置状态与处置历史表
+    threat_event_workflow::create_threat_event_workflow_tables(&pool).await?;
+
+    // 威胁事件与收敛告警的关联表
+    threat_event_alerts::create_threat_event_alerts_table(&pool).await?;
+
+    // 威胁事件推送日志表
+    threat_event_push::create_threat_event_push_table(&pool).await?;
+
     // 标签管理表
     tag_management::create_tag_table(&pool).await?;
 
@@ -119,6 +117,12 @@ pub async fn init_postgres(pg: &PostgresConfig) -> Result<PgPool> {
     filter_rules::create_filter_rules_table(&pool).await?;
     tag_rules::create_tag_rules_table(&pool).await?;
 
+    // 规则版本历史表
+    rule_versions::create_rule_versions_table(&pool).await?;
+
+    // 列表查询的过滤和排序索引
+    list_filter::create_list_indexes(&pool).await?;
+
     Ok(pool)
 }
 
@@ -128,6 +132,10 @@ pub async fn reset_database(pool: &PgPool) -> Result<()> {
     alert_mapping::drop_alert_mapping_table(pool).await?;
     converged_alerts::drop_converged_alerts_tables(pool).await?;
     raw_alerts::drop_raw_alerts_tables(pool).await?;
+    dead_letters::drop_dead_letter_table(pool).await?;
+    threat_event_push::drop_threat_event_push_table(pool).await?;
+    threat_event_workflow::drop_threat_event_workflow_tables(pool).await?;
+    threat_event_alerts::drop_threat_event_alerts_table(pool).await?;
     threat_event::drop_threat_event_table(pool).await?;
     alert_tag_mapping::drop_alert_tag_mapping_table(pool).await?;
     tag_management::drop_tag_table(pool).await?;
@@ -136,6 +144,7 @@ pub async fn reset_database(pool: &PgPool) -> Result<()> {
     correlation_rules::drop_correlation_rules_table(pool).await?;
     filter_rules::drop_filter_rules_table(pool).await?;
     tag_rules::drop_tag_rules_table(pool).await?;
+    rule_versions::drop_rule_versions_table(pool).await?;
     // 注意：自动推送相关表通常不在 reset 中删除，这里保持不删
     Ok(())
 }
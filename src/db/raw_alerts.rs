@@ -1,9 +1,7 @@
 //! 原始告警表及相关操作
 //!
-//! 本模块包含三类原始告警表的定义和操作：
-//! - 网络攻击告警表 (network_attack_alerts)
-//! - 恶意样本告警表 (malicious_sample_alerts)  
-//! - 主机行为告警表 (host_behavior_alerts)
+//! 本模块包含原始告警表的定义和操作：
+//! - 每个配置的告警类型按字段声明建表 (<告警类型>_alerts)
 //! - 无效告警表 (invalid_alerts)
 //!
 //! 提供的功能包括：
@@ -14,139 +12,32 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::models::{HostBehaviorAlert, MaliciousSampleAlert, NetworkAttackAlert};
+use super::filter_rules::FilterRuleRecord;
+use super::list_filter::ListQuery;
+use crate::alert_catalog::{self, AlertType};
+use crate::alert_schema::SchemaViolation;
+
+/// 根据告警类型获取原始告警表名
+pub fn raw_table_name(alert_type: &str) -> Option<&'static str> {
+    alert_catalog::catalog()
+        .get(alert_type)
+        .map(|t| t.raw_table.as_str())
+}
 
 // ============================================================================
 // Record 结构体定义
 // ============================================================================
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
-pub struct NetworkAttackRecord {
-    pub id: Uuid,
-    pub alarm_id: Option<String>,
-    pub alarm_date: Option<i64>,
-    pub alarm_severity: Option<i16>,
-    pub alarm_name: Option<String>,
-    pub alarm_description: Option<String>,
-    pub alarm_type: i16,
-    pub alarm_subtype: i32,
-    pub source: i16,
-    pub control_rule_id: Option<String>,
-    pub control_task_id: Option<String>,
-    pub procedure_technique_id: Option<serde_json::Value>,
-    pub session_id: Option<String>,
-    pub ip_version: Option<i16>,
-    pub src_ip: Option<String>,
-    pub src_port: Option<i32>,
-    pub dst_ip: Option<String>,
-    pub dst_port: Option<i32>,
-    pub protocol: Option<String>,
-    pub terminal_id: Option<String>,
-    pub source_file_path: Option<String>,
-    pub signature_id: Option<String>,
-    pub attack_payload: Option<String>,
-    pub attack_stage: Option<String>,
-    pub attack_ip: Option<String>,
-    pub attacked_ip: Option<String>,
-    pub apt_group: Option<String>,
-    pub vul_type: Option<String>,
-    pub cve_id: Option<String>,
-    pub vul_desc: Option<String>,
-    pub data: Option<serde_json::Value>,
-    pub created_at: DateTime<Utc>,
-}
-
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
-pub struct MaliciousSampleRecord {
+/// 以告警 JSON 形式读出的原始告警，字段名与 Kafka 消息一致
+#[derive(Clone, Debug, serde::Serialize, sqlx::FromRow)]
+pub struct RawAlertJson {
     pub id: Uuid,
-    pub alarm_id: Option<String>,
-    pub alarm_date: Option<i64>,
-    pub alarm_severity: Option<i16>,
-    pub alarm_name: Option<String>,
-    pub alarm_description: Option<String>,
-    pub alarm_type: i16,
-    pub alarm_subtype: i32,
-    pub source: i16,
-    pub control_rule_id: Option<String>,
-    pub control_task_id: Option<String>,
-    pub procedure_technique_id: Option<serde_json::Value>,
-    pub session_id: Option<String>,
-    pub ip_version: Option<i16>,
-    pub src_ip: Option<String>,
-    pub src_port: Option<i32>,
-    pub dst_ip: Option<String>,
-    pub dst_port: Option<i32>,
-    pub protocol: Option<String>,
-    pub terminal_id: Option<String>,
-    pub source_file_path: Option<String>,
-    pub sample_source: Option<i16>,
-    pub md5: Option<String>,
-    pub sha1: Option<String>,
-    pub sha256: Option<String>,
-    pub sha512: Option<String>,
-    pub ssdeep: Option<String>,
-    pub sample_original_name: Option<String>,
-    pub sample_description: Option<String>,
-    pub sample_family: Option<String>,
-    pub apt_group: Option<String>,
-    pub sample_alarm_engine: Option<serde_json::Value>,
-    pub target_platform: Option<String>,
-    pub file_type: Option<String>,
-    pub file_size: Option<i64>,
-    pub language: Option<String>,
-    pub rule: Option<String>,
-    pub target_content: Option<String>,
-    pub compile_date: Option<i64>,
-    pub last_analy_date: Option<i64>,
-    pub sample_alarm_detail: Option<String>,
-    pub data: Option<serde_json::Value>,
-    pub created_at: DateTime<Utc>,
-}
-
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
-pub struct HostBehaviorRecord {
-    pub id: Uuid,
-    pub alarm_id: Option<String>,
-    pub alarm_date: Option<i64>,
-    pub alarm_severity: Option<i16>,
-    pub alarm_name: Option<String>,
-    pub alarm_description: Option<String>,
-    pub alarm_type: i16,
-    pub alarm_subtype: i32,
-    pub source: i16,
-    pub control_rule_id: Option<String>,
-    pub control_task_id: Option<String>,
-    pub procedure_technique_id: Option<serde_json::Value>,
-    pub session_id: Option<String>,
-    pub ip_version: Option<i16>,
-    pub src_ip: Option<String>,
-    pub src_port: Option<i32>,
-    pub dst_ip: Option<String>,
-    pub dst_port: Option<i32>,
-    pub protocol: Option<String>,
-    pub terminal_id: Option<String>,
-    pub source_file_path: Option<String>,
-    pub host_name: Option<String>,
-    pub terminal_ip: Option<String>,
-    pub user_account: Option<String>,
-    pub terminal_os: Option<String>,
-    pub dst_process_md5: Option<String>,
-    pub dst_process_path: Option<String>,
-    pub dst_process_cli: Option<String>,
-    pub src_process_md5: Option<String>,
-    pub src_process_path: Option<String>,
-    pub src_process_cli: Option<String>,
-    pub register_key_name: Option<String>,
-    pub register_key_value: Option<String>,
-    pub register_path: Option<String>,
-    pub file_name: Option<String>,
-    pub file_md5: Option<String>,
-    pub file_path: Option<String>,
-    pub data: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
+    pub alert: Value,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
@@ -155,6 +46,11 @@ pub struct InvalidAlertRecord {
     pub data: serde_json::Value,
     pub alert_type: String, // 新增字段
     pub error: String,
+    /// 丢弃该告警的过滤规则
+    pub filter_rule_id: Option<Uuid>,
+    pub filter_rule_name: Option<String>,
+    /// 不符合 JSON Schema 时的逐字段错误
+    pub validation_errors: Option<Value>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -164,153 +60,66 @@ pub struct InvalidAlertRecord {
 
 /// 创建所有原始告警表
 pub async fn create_raw_alerts_tables(pool: &PgPool) -> Result<()> {
-    // 网络攻击告警表 - 包含所有字段
+    // 按字段声明为所有告警类型建表，并补齐新声明的字段列
+    for alert_type in alert_catalog::catalog().types() {
+        create_raw_alert_table(pool, alert_type).await?;
+    }
+
+    // 兼容旧表结构：补齐去重键列，并建立唯一索引保证重复投递的告警只入库一次
+    for alert_type in alert_catalog::catalog().types() {
+        let table = &alert_type.raw_table;
+        sqlx::query(&format!(
+            "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS dedup_key TEXT"
+        ))
+        .execute(pool)
+        .await?;
+
+        sqlx::query(&format!(
+            "CREATE UNIQUE INDEX IF NOT EXISTS uq_{table}_dedup_key ON {table}(dedup_key)"
+        ))
+        .execute(pool)
+        .await?;
+    }
+
+    // 无效告警表 - 保存解析失败的原始数据与错误信息
     sqlx::query(
-        "CREATE TABLE IF NOT EXISTS network_attack_alerts (
+        "CREATE TABLE IF NOT EXISTS invalid_alerts (
             id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
-            alarm_id TEXT,
-            alarm_date BIGINT,
-            alarm_severity SMALLINT,
-            alarm_name TEXT,
-            alarm_description TEXT,
-            alarm_type SMALLINT NOT NULL,
-            alarm_subtype INTEGER NOT NULL,
-            source SMALLINT NOT NULL,
-            control_rule_id TEXT,
-            control_task_id TEXT,
-            procedure_technique_id JSONB,
-            session_id TEXT,
-            ip_version SMALLINT,
-            src_ip TEXT,
-            src_port INTEGER,
-            dst_ip TEXT,
-            dst_port INTEGER,
-            protocol TEXT,
-            terminal_id TEXT,
-            source_file_path TEXT,
-            signature_id TEXT,
-            attack_payload TEXT,
-            attack_stage TEXT,
-            attack_ip TEXT,
-            attacked_ip TEXT,
-            apt_group TEXT,
-            vul_type TEXT,
-            cve_id TEXT,
-            vul_desc TEXT,
-            data JSONB,
+            data JSONB NOT NULL,
+            alert_type TEXT NOT NULL,
+            error TEXT NOT NULL,
+            filter_rule_id UUID,
+            filter_rule_name TEXT,
+            validation_errors JSONB,
             created_at TIMESTAMPTZ DEFAULT now()
         )",
     )
     .execute(pool)
     .await?;
 
-    // 恶意样本告警表 - 包含所有字段
     sqlx::query(
-        "CREATE TABLE IF NOT EXISTS malicious_sample_alerts (
-            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
-            alarm_id TEXT,
-            alarm_date BIGINT,
-            alarm_severity SMALLINT,
-            alarm_name TEXT,
-            alarm_description TEXT,
-            alarm_type SMALLINT NOT NULL,
-            alarm_subtype INTEGER NOT NULL,
-            source SMALLINT NOT NULL,
-            control_rule_id TEXT,
-            control_task_id TEXT,
-            procedure_technique_id JSONB,
-            session_id TEXT,
-            ip_version SMALLINT,
-            src_ip TEXT,
-            src_port INTEGER,
-            dst_ip TEXT,
-            dst_port INTEGER,
-            protocol TEXT,
-            terminal_id TEXT,
-            source_file_path TEXT,
-            sample_source SMALLINT,
-            md5 TEXT,
-            sha1 TEXT,
-            sha256 TEXT,
-            sha512 TEXT,
-            ssdeep TEXT,
-            sample_original_name TEXT,
-            sample_description TEXT,
-            sample_family TEXT,
-            apt_group TEXT,
-            sample_alarm_engine JSONB,
-            target_platform TEXT,
-            file_type TEXT,
-            file_size BIGINT,
-            language TEXT,
-            rule TEXT,
-            target_content TEXT,
-            compile_date BIGINT,
-            last_analy_date BIGINT,
-            sample_alarm_detail TEXT,
-            data JSONB,
-            created_at TIMESTAMPTZ DEFAULT now()
-        )",
+        "ALTER TABLE invalid_alerts
+         ADD COLUMN IF NOT EXISTS filter_rule_id UUID,
+         ADD COLUMN IF NOT EXISTS filter_rule_name TEXT,
+         ADD COLUMN IF NOT EXISTS validation_errors JSONB",
     )
     .execute(pool)
     .await?;
 
-    // 主机行为告警表 - 包含所有字段
     sqlx::query(
-        "CREATE TABLE IF NOT EXISTS host_behavior_alerts (
-            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
-            alarm_id TEXT,
-            alarm_date BIGINT,
-            alarm_severity SMALLINT,
-            alarm_name TEXT,
-            alarm_description TEXT,
-            alarm_type SMALLINT NOT NULL,
-            alarm_subtype INTEGER NOT NULL,
-            source SMALLINT NOT NULL,
-            control_rule_id TEXT,
-            control_task_id TEXT,
-            procedure_technique_id JSONB,
-            session_id TEXT,
-            ip_version SMALLINT,
-            src_ip TEXT,
-            src_port INTEGER,
-            dst_ip TEXT,
-            dst_port INTEGER,
-            protocol TEXT,
-            terminal_id TEXT,
-            source_file_path TEXT,
-            host_name TEXT,
-            terminal_ip TEXT,
-            user_account TEXT,
-            terminal_os TEXT,
-            dst_process_md5 TEXT,
-            dst_process_path TEXT,
-            dst_process_cli TEXT,
-            src_process_md5 TEXT,
-            src_process_path TEXT,
-            src_process_cli TEXT,
-            register_key_name TEXT,
-            register_key_value TEXT,
-            register_path TEXT,
-            file_name TEXT,
-            file_md5 TEXT,
-            file_path TEXT,
-            data JSONB,
-            created_at TIMESTAMPTZ DEFAULT now()
-        )",
+        "CREATE INDEX IF NOT EXISTS idx_invalid_alerts_filter_rule_id ON invalid_alerts(filter_rule_id)",
     )
     .execute(pool)
     .await?;
 
-    // 无效告警表 - 保存解析失败的原始数据与错误信息
+    // 被过滤或校验拒绝的告警同样按去重键只记录一次，旧数据的去重键为空不参与去重
+    sqlx::query("ALTER TABLE invalid_alerts ADD COLUMN IF NOT EXISTS dedup_key TEXT")
+        .execute(pool)
+        .await?;
+
     sqlx::query(
-        "CREATE TABLE IF NOT EXISTS invalid_alerts (
-            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
-            data JSONB NOT NULL,
-            alert_type TEXT NOT NULL,
-            error TEXT NOT NULL,
-            created_at TIMESTAMPTZ DEFAULT now()
-        )",
+        "CREATE UNIQUE INDEX IF NOT EXISTS uq_invalid_alerts_dedup_key
+         ON invalid_alerts(alert_type, dedup_key)",
     )
     .execute(pool)
     .await?;
@@ -320,18 +129,53 @@ pub async fn create_raw_alerts_tables(pool: &PgPool) -> Result<()> {
 
 /// 删除所有原始告警表
 pub async fn drop_raw_alerts_tables(pool: &PgPool) -> Result<()> {
-    sqlx::query("DROP TABLE IF EXISTS network_attack_alerts CASCADE")
+    for alert_type in alert_catalog::catalog().types() {
+        sqlx::query(&format!(
+            "DROP TABLE IF EXISTS {} CASCADE",
+            alert_type.raw_table
+        ))
         .execute(pool)
         .await?;
-    sqlx::query("DROP TABLE IF EXISTS malicious_sample_alerts CASCADE")
-        .execute(pool)
-        .await?;
-    sqlx::query("DROP TABLE IF EXISTS host_behavior_alerts CASCADE")
+    }
+    sqlx::query("DROP TABLE IF EXISTS invalid_alerts CASCADE")
         .execute(pool)
         .await?;
-    sqlx::query("DROP TABLE IF EXISTS invalid_alerts CASCADE")
+    Ok(())
+}
+
+/// 按字段声明创建告警类型的原始告警表，已存在时补齐缺少的字段列
+async fn create_raw_alert_table(pool: &PgPool, alert_type: &AlertType) -> Result<()> {
+    let table = &alert_type.raw_table;
+    let columns: String = alert_type
+        .fields
+        .iter()
+        .map(|field| {
+            let not_null = if field.optional { "" } else { " NOT NULL" };
+            format!("{} {}{},\n", field.name, field.column_type(), not_null)
+        })
+        .collect();
+
+    sqlx::query(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+            {columns}
+            dedup_key TEXT,
+            created_at TIMESTAMPTZ DEFAULT now()
+        )"
+    ))
+    .execute(pool)
+    .await?;
+
+    for field in &alert_type.fields {
+        sqlx::query(&format!(
+            "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS {} {}",
+            field.name,
+            field.column_type()
+        ))
         .execute(pool)
         .await?;
+    }
+
     Ok(())
 }
 
@@ -339,441 +183,258 @@ pub async fn drop_raw_alerts_tables(pool: &PgPool) -> Result<()> {
 // 插入操作
 // ============================================================================
 
-/// 统一的原始告警存储函数
-pub async fn store_raw_alert(pool: &PgPool, alert_json: &Value, alert_type: &str) -> Result<Uuid> {
-    let id = match alert_type {
-        "network_attack" => {
-            let alert: NetworkAttackAlert = serde_json::from_value(alert_json.clone())?;
-            insert_network_attack(pool, &alert).await?
-        }
-        "malicious_sample" => {
-            let alert: MaliciousSampleAlert = serde_json::from_value(alert_json.clone())?;
-            insert_malicious_sample(pool, &alert).await?
-        }
-        "host_behavior" => {
-            let alert: HostBehaviorAlert = serde_json::from_value(alert_json.clone())?;
-            insert_host_behavior(pool, &alert).await?
-        }
-        _ => return Err(anyhow::anyhow!("Unsupported alert type: {}", alert_type)),
-    };
-    Ok(id)
+/// 原始告警入库结果
+pub struct StoredRawAlert {
+    pub id: Uuid,
+    /// 相同去重键的告警此前已入库（例如 Kafka 重复投递）
+    pub duplicate: bool,
 }
 
-pub async fn insert_network_attack(pool: &PgPool, alert: &NetworkAttackAlert) -> Result<Uuid> {
-    let procedure_technique_id = alert.procedure_technique_id.as_ref().map(|v| {
-        serde_json::Value::Array(v.iter().cloned().map(serde_json::Value::String).collect())
-    });
-
-    let id: (Uuid,) = sqlx::query_as(
-        "INSERT INTO network_attack_alerts (
-            alarm_id, alarm_date, alarm_severity, alarm_name, alarm_description,
-            alarm_type, alarm_subtype, source, control_rule_id, control_task_id,
-            procedure_technique_id, session_id, ip_version, src_ip, src_port,
-            dst_ip, dst_port, protocol, terminal_id, source_file_path,
-            signature_id, attack_payload, attack_stage, attack_ip, attacked_ip,
-            apt_group, vul_type, cve_id, vul_desc, data
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
-            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30
-        ) RETURNING id",
-    )
-    .bind(&alert.alarm_id)
-    .bind(alert.alarm_date)
-    .bind(alert.alarm_severity.map(|v| v as i16))
-    .bind(&alert.alarm_name)
-    .bind(&alert.alarm_description)
-    .bind(alert.alarm_type as i16)
-    .bind(alert.alarm_subtype as i32)
-    .bind(alert.source as i16)
-    .bind(&alert.control_rule_id)
-    .bind(&alert.control_task_id)
-    .bind(&procedure_technique_id)
-    .bind(&alert.session_id)
-    .bind(alert.ip_version.map(|v| v as i16))
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .bind(&alert.terminal_id)
-    .bind(&alert.source_file_path)
-    .bind(&alert.signature_id)
-    .bind(&alert.attack_payload)
-    .bind(&alert.attack_stage)
-    .bind(&alert.attack_ip)
-    .bind(&alert.attacked_ip)
-    .bind(&alert.apt_group)
-    .bind(&alert.vul_type)
-    .bind(&alert.cve_id)
-    .bind(&alert.vul_desc)
-    .bind(&alert.data)
-    .fetch_one(pool)
-    .await?;
-
-    Ok(id.0)
+/// 告警此前的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupState {
+    /// 未处理过
+    New,
+    /// 已作为原始告警入库
+    Stored,
+    /// 已被过滤或 schema 校验拒绝，存为无效告警
+    Rejected,
 }
 
-pub async fn insert_malicious_sample(pool: &PgPool, alert: &MaliciousSampleAlert) -> Result<Uuid> {
-    let procedure_technique_id = alert.procedure_technique_id.as_ref().map(|v| {
-        serde_json::Value::Array(v.iter().cloned().map(serde_json::Value::String).collect())
-    });
-
-    let sample_alarm_engine = alert.sample_alarm_engine.as_ref().map(|v| {
-        serde_json::Value::Array(
-            v.iter()
-                .cloned()
-                .map(|n| serde_json::Value::Number(serde_json::Number::from(n)))
-                .collect(),
-        )
-    });
-
-    let id: (Uuid,) = sqlx::query_as(
-        "INSERT INTO malicious_sample_alerts (
-            alarm_id, alarm_date, alarm_severity, alarm_name, alarm_description,
-            alarm_type, alarm_subtype, source, control_rule_id, control_task_id,
-            procedure_technique_id, session_id, ip_version, src_ip, src_port,
-            dst_ip, dst_port, protocol, terminal_id, source_file_path,
-            sample_source, md5, sha1, sha256, sha512, ssdeep,
-            sample_original_name, sample_description, sample_family, apt_group,
-            sample_alarm_engine, target_platform, file_type, file_size, language,
-            rule, target_content, compile_date, last_analy_date, sample_alarm_detail, data
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
-            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30,
-            $31, $32, $33, $34, $35, $36, $37, $38, $39, $40, $41
-        ) RETURNING id",
+/// 按去重键查询告警此前的处理结果，重复投递的告警据此跳过过滤和校验
+pub async fn dedup_state(
+    pool: &PgPool,
+    alert_type: &str,
+    alert_json: &Value,
+) -> Result<DedupState> {
+    let key = dedup_key(alert_json);
+
+    let (rejected,): (bool,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM invalid_alerts WHERE alert_type = $1 AND dedup_key = $2)",
     )
-    .bind(&alert.alarm_id)
-    .bind(alert.alarm_date)
-    .bind(alert.alarm_severity.map(|v| v as i16))
-    .bind(&alert.alarm_name)
-    .bind(&alert.alarm_description)
-    .bind(alert.alarm_type as i16)
-    .bind(alert.alarm_subtype as i32)
-    .bind(alert.source as i16)
-    .bind(&alert.control_rule_id)
-    .bind(&alert.control_task_id)
-    .bind(&procedure_technique_id)
-    .bind(&alert.session_id)
-    .bind(alert.ip_version.map(|v| v as i16))
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .bind(&alert.terminal_id)
-    .bind(&alert.source_file_path)
-    .bind(alert.sample_source.map(|v| v as i16))
-    .bind(&alert.md5)
-    .bind(&alert.sha1)
-    .bind(&alert.sha256)
-    .bind(&alert.sha512)
-    .bind(&alert.ssdeep)
-    .bind(&alert.sample_original_name)
-    .bind(&alert.sample_description)
-    .bind(&alert.sample_family)
-    .bind(&alert.apt_group)
-    .bind(&sample_alarm_engine)
-    .bind(&alert.target_platform)
-    .bind(&alert.file_type)
-    .bind(alert.file_size.map(|v| v as i64))
-    .bind(&alert.language)
-    .bind(&alert.rule)
-    .bind(&alert.target_content)
-    .bind(alert.compile_date)
-    .bind(alert.last_analy_date)
-    .bind(&alert.sample_alarm_detail)
-    .bind(&alert.data)
+    .bind(alert_type)
+    .bind(&key)
     .fetch_one(pool)
     .await?;
+    if rejected {
+        return Ok(DedupState::Rejected);
+    }
 
-    Ok(id.0)
-}
-
-pub async fn insert_host_behavior(pool: &PgPool, alert: &HostBehaviorAlert) -> Result<Uuid> {
-    let procedure_technique_id = alert.procedure_technique_id.as_ref().map(|v| {
-        serde_json::Value::Array(v.iter().cloned().map(serde_json::Value::String).collect())
-    });
-
-    let id: (Uuid,) = sqlx::query_as(
-        "INSERT INTO host_behavior_alerts (
-            alarm_id, alarm_date, alarm_severity, alarm_name, alarm_description,
-            alarm_type, alarm_subtype, source, control_rule_id, control_task_id,
-            procedure_technique_id, session_id, ip_version, src_ip, src_port,
-            dst_ip, dst_port, protocol, terminal_id, source_file_path,
-            host_name, terminal_ip, user_account, terminal_os,
-            dst_process_md5, dst_process_path, dst_process_cli,
-            src_process_md5, src_process_path, src_process_cli,
-            register_key_name, register_key_value, register_path,
-            file_name, file_md5, file_path, data
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
-            $21, $22, $23, $24, $25, $26, $27, $28, $29, $30,
-            $31, $32, $33, $34, $35, $36, $37
-        ) RETURNING id",
-    )
-    .bind(&alert.alarm_id)
-    .bind(alert.alarm_date)
-    .bind(alert.alarm_severity.map(|v| v as i16))
-    .bind(&alert.alarm_name)
-    .bind(&alert.alarm_description)
-    .bind(alert.alarm_type as i16)
-    .bind(alert.alarm_subtype as i32)
-    .bind(alert.source as i16)
-    .bind(&alert.control_rule_id)
-    .bind(&alert.control_task_id)
-    .bind(&procedure_technique_id)
-    .bind(&alert.session_id)
-    .bind(alert.ip_version.map(|v| v as i16))
-    .bind(&alert.src_ip)
-    .bind(alert.src_port.map(|v| v as i32))
-    .bind(&alert.dst_ip)
-    .bind(alert.dst_port.map(|v| v as i32))
-    .bind(&alert.protocol)
-    .bind(&alert.terminal_id)
-    .bind(&alert.source_file_path)
-    .bind(&alert.host_name)
-    .bind(&alert.terminal_ip)
-    .bind(&alert.user_account)
-    .bind(&alert.terminal_os)
-    .bind(&alert.dst_process_md5)
-    .bind(&alert.dst_process_path)
-    .bind(&alert.dst_process_cli)
-    .bind(&alert.src_process_md5)
-    .bind(&alert.src_process_path)
-    .bind(&alert.src_process_cli)
-    .bind(&alert.register_key_name)
-    .bind(&alert.register_key_value)
-    .bind(&alert.register_path)
-    .bind(&alert.file_name)
-    .bind(&alert.file_md5)
-    .bind(&alert.file_path)
-    .bind(&alert.data)
+    let Some(alert_type) = alert_catalog::catalog().get(alert_type) else {
+        return Ok(DedupState::New);
+    };
+    let (stored,): (bool,) = sqlx::query_as(&format!(
+        "SELECT EXISTS (SELECT 1 FROM {} WHERE dedup_key = $1)",
+        alert_type.raw_table
+    ))
+    .bind(&key)
     .fetch_one(pool)
     .await?;
 
-    Ok(id.0)
+    Ok(if stored {
+        DedupState::Stored
+    } else {
+        DedupState::New
+    })
 }
 
-pub async fn store_invalid_alert(
-    pool: &PgPool,
-    data: &Value,
-    alert_type: &str,
-    error: String,
-) -> Result<()> {
-    sqlx::query("INSERT INTO invalid_alerts (data, alert_type, error) VALUES ($1, $2, $3)")
-        .bind(data)
-        .bind(alert_type)
-        .bind(&error)
-        .execute(pool)
-        .await?;
-
-    Ok(())
+/// 计算原始告警的去重键：优先使用 alarm_id，缺失时使用告警内容的 SHA-256
+pub fn dedup_key(alert_json: &Value) -> String {
+    match alert_json.get("alarm_id").and_then(Value::as_str) {
+        Some(alarm_id) if !alarm_id.trim().is_empty() => format!("alarm_id:{}", alarm_id.trim()),
+        _ => {
+            // serde_json 的对象按键排序序列化，同一内容得到相同哈希
+            let digest = Sha256::digest(alert_json.to_string().as_bytes());
+            let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("sha256:{}", hex)
+        }
+    }
 }
 
-// ============================================================================
-// 查询操作
-// ============================================================================
-
-#[allow(dead_code)]
-pub async fn query_network_attacks(
+/// 统一的原始告警存储函数
+///
+/// 告警需符合告警类型的字段声明；按去重键幂等写入：重复告警不会再次插入，而是返回已存在的记录ID
+pub async fn store_raw_alert(
     pool: &PgPool,
-    page: u64,
-    page_size: u64,
-) -> Result<(Vec<NetworkAttackRecord>, u64)> {
-    let offset = (page - 1) * page_size;
-
-    let records = sqlx::query_as::<_, NetworkAttackRecord>(
-        "SELECT * FROM network_attack_alerts ORDER BY created_at DESC LIMIT $1 OFFSET $2",
-    )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(pool)
+    alert_json: &Value,
+    alert_type: &str,
+) -> Result<StoredRawAlert> {
+    let alert_type = alert_catalog::catalog().require(alert_type)?;
+    alert_type.check(alert_json)?;
+
+    let key = dedup_key(alert_json);
+    let table = &alert_type.raw_table;
+    let (columns, values) = field_columns(alert_type);
+
+    // 按列名从告警 JSON 填充同结构的记录，由数据库完成类型转换
+    let inserted: Option<(Uuid,)> = sqlx::query_as(&format!(
+        "INSERT INTO {table} ({columns}dedup_key)
+         SELECT {values}$2 FROM jsonb_populate_record(NULL::{table}, $1) a
+         ON CONFLICT (dedup_key) DO NOTHING
+         RETURNING id"
+    ))
+    .bind(alert_type.record(alert_json))
+    .bind(&key)
+    .fetch_optional(pool)
     .await?;
 
-    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM network_attack_alerts")
+    if let Some((id,)) = inserted {
+        return Ok(StoredRawAlert {
+            id,
+            duplicate: false,
+        });
+    }
+
+    let existing: (Uuid,) = sqlx::query_as(&format!("SELECT id FROM {table} WHERE dedup_key = $1"))
+        .bind(&key)
         .fetch_one(pool)
         .await?;
 
-    Ok((records, total.0 as u64))
+    Ok(StoredRawAlert {
+        id: existing.0,
+        duplicate: true,
+    })
 }
 
-#[allow(dead_code)]
-pub async fn query_malicious_samples(
-    pool: &PgPool,
-    page: u64,
-    page_size: u64,
-) -> Result<(Vec<MaliciousSampleRecord>, u64)> {
-    let offset = (page - 1) * page_size;
-
-    let records = sqlx::query_as::<_, MaliciousSampleRecord>(
-        "SELECT * FROM malicious_sample_alerts ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+/// 告警字段的列名列表和从填充记录 a 中取值的列表，均以逗号结尾便于追加其他列
+pub(crate) fn field_columns(alert_type: &AlertType) -> (String, String) {
+    alert_type.fields.iter().fold(
+        (String::new(), String::new()),
+        |(mut columns, mut values), field| {
+            columns.push_str(&format!("{}, ", field.name));
+            values.push_str(&format!("a.{}, ", field.name));
+            (columns, values)
+        },
     )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(pool)
-    .await?;
-
-    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM malicious_sample_alerts")
-        .fetch_one(pool)
-        .await?;
-
-    Ok((records, total.0 as u64))
 }
 
-#[allow(dead_code)]
-pub async fn query_host_behaviors(
+/// 存储无效告警，filter_rule 为丢弃该告警的过滤规则
+///
+/// 按去重键幂等写入，返回新增的无效告警ID，此前已记录过时返回 None
+pub async fn store_invalid_alert(
     pool: &PgPool,
-    page: u64,
-    page_size: u64,
-) -> Result<(Vec<HostBehaviorRecord>, u64)> {
-    let offset = (page - 1) * page_size;
-
-    let records = sqlx::query_as::<_, HostBehaviorRecord>(
-        "SELECT * FROM host_behavior_alerts ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+    data: &Value,
+    alert_type: &str,
+    error: String,
+    filter_rule: Option<&FilterRuleRecord>,
+) -> Result<Option<Uuid>> {
+    let id: Option<(Uuid,)> = sqlx::query_as(
+        "INSERT INTO invalid_alerts (data, alert_type, error, filter_rule_id, filter_rule_name, dedup_key)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (alert_type, dedup_key) DO NOTHING
+         RETURNING id",
     )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(pool)
+    .bind(data)
+    .bind(alert_type)
+    .bind(&error)
+    .bind(filter_rule.map(|rule| rule.id))
+    .bind(filter_rule.map(|rule| rule.name.as_str()))
+    .bind(dedup_key(data))
+    .fetch_optional(pool)
     .await?;
 
-    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM host_behavior_alerts")
-        .fetch_one(pool)
-        .await?;
-
-    Ok((records, total.0 as u64))
+    Ok(id.map(|(id,)| id))
 }
 
-pub async fn query_invalid_alerts(
+/// 存储不符合 JSON Schema 的告警及其逐字段错误
+///
+/// 按去重键幂等写入，返回新增的无效告警ID，此前已记录过时返回 None
+pub async fn store_schema_violation(
     pool: &PgPool,
-    page: u64,
-    page_size: u64,
-) -> Result<(Vec<InvalidAlertRecord>, u64)> {
-    let offset = (page - 1) * page_size;
-
-    let records = sqlx::query_as::<_, InvalidAlertRecord>(
-        "SELECT * FROM invalid_alerts ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+    data: &Value,
+    alert_type: &str,
+    violations: &[SchemaViolation],
+) -> Result<Option<Uuid>> {
+    let summary: Vec<String> = violations.iter().map(ToString::to_string).collect();
+    let id: Option<(Uuid,)> = sqlx::query_as(
+        "INSERT INTO invalid_alerts (data, alert_type, error, validation_errors, dedup_key)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (alert_type, dedup_key) DO NOTHING
+         RETURNING id",
     )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(pool)
+    .bind(data)
+    .bind(alert_type)
+    .bind(format!("schema_violation: {}", summary.join("; ")))
+    .bind(serde_json::to_value(violations)?)
+    .bind(dedup_key(data))
+    .fetch_optional(pool)
     .await?;
 
-    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM invalid_alerts")
-        .fetch_one(pool)
-        .await?;
-
-    Ok((records, total.0 as u64))
+    Ok(id.map(|(id,)| id))
 }
 
 // ============================================================================
-// 根据收敛告警ID查询原始告警
+// 查询操作
 // ============================================================================
 
-/// 根据收敛告警ID查询网络攻击原始告警列表
-pub async fn query_raw_network_attacks_by_converged_id(
+/// 按查询条件分页查询无效告警
+pub async fn query_invalid_alerts(
     pool: &PgPool,
-    converged_id: Uuid,
-) -> Result<Vec<NetworkAttackRecord>> {
-    // 先从映射表获取原始告警ID列表
-    let raw_ids: Vec<(Uuid,)> = sqlx::query_as(
-        "SELECT raw_alert_id FROM alert_convergence_mapping 
-         WHERE converged_alert_id = $1 AND alert_type = 1
-         ORDER BY created_at ASC",
-    )
-    .bind(converged_id)
-    .fetch_all(pool)
-    .await?;
-
-    if raw_ids.is_empty() {
-        return Ok(vec![]);
-    }
-
-    // 提取ID列表
-    let ids: Vec<Uuid> = raw_ids.into_iter().map(|(id,)| id).collect();
-
-    // 查询原始告警详情
-    let records = sqlx::query_as::<_, NetworkAttackRecord>(
-        "SELECT * FROM network_attack_alerts 
-         WHERE id = ANY($1)
-         ORDER BY created_at ASC",
-    )
-    .bind(&ids)
-    .fetch_all(pool)
-    .await?;
+    query: &ListQuery,
+    page: u64,
+    page_size: u64,
+) -> Result<(Vec<InvalidAlertRecord>, u64)> {
+    let records = query
+        .fetch_page(pool, "SELECT t.* FROM invalid_alerts t", page, page_size)
+        .await?;
+    let total = query.count(pool, "invalid_alerts t").await?;
 
-    Ok(records)
+    Ok((records, total))
 }
 
-/// 根据收敛告警ID查询恶意样本原始告警列表
-pub async fn query_raw_malicious_samples_by_converged_id(
+/// 按入库时间顺序读取时间范围 [start, end) 内的原始告警，最多 limit 条
+pub async fn query_raw_alerts_in_range(
     pool: &PgPool,
-    converged_id: Uuid,
-) -> Result<Vec<MaliciousSampleRecord>> {
-    // 先从映射表获取原始告警ID列表
-    let raw_ids: Vec<(Uuid,)> = sqlx::query_as(
-        "SELECT raw_alert_id FROM alert_convergence_mapping 
-         WHERE converged_alert_id = $1 AND alert_type = 2
-         ORDER BY created_at ASC",
-    )
-    .bind(converged_id)
-    .fetch_all(pool)
-    .await?;
-
-    if raw_ids.is_empty() {
-        return Ok(vec![]);
-    }
-
-    // 提取ID列表
-    let ids: Vec<Uuid> = raw_ids.into_iter().map(|(id,)| id).collect();
-
-    // 查询原始告警详情
-    let records = sqlx::query_as::<_, MaliciousSampleRecord>(
-        "SELECT * FROM malicious_sample_alerts 
-         WHERE id = ANY($1)
-         ORDER BY created_at ASC",
-    )
-    .bind(&ids)
+    alert_type: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<RawAlertJson>> {
+    let table = raw_table_name(alert_type)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported alert type: {}", alert_type))?;
+
+    let records = sqlx::query_as::<_, RawAlertJson>(&format!(
+        "SELECT id, created_at, to_jsonb(t) - 'id' - 'dedup_key' - 'created_at' AS alert
+         FROM {} t
+         WHERE created_at >= $1 AND created_at < $2
+         ORDER BY created_at, id
+         LIMIT $3",
+        table
+    ))
+    .bind(start)
+    .bind(end)
+    .bind(limit)
     .fetch_all(pool)
     .await?;
 
     Ok(records)
 }
 
-/// 根据收敛告警ID查询主机行为原始告警列表
-pub async fn query_raw_host_behaviors_by_converged_id(
+// ============================================================================
+// 根据收敛告警ID查询原始告警
+// ============================================================================
+
+/// 根据收敛告警ID查询其收敛的原始告警，按入库时间排序
+pub async fn query_raw_alerts_of_converged(
     pool: &PgPool,
+    alert_type: &str,
     converged_id: Uuid,
-) -> Result<Vec<HostBehaviorRecord>> {
-    // 先从映射表获取原始告警ID列表
-    let raw_ids: Vec<(Uuid,)> = sqlx::query_as(
-        "SELECT raw_alert_id FROM alert_convergence_mapping 
-         WHERE converged_alert_id = $1 AND alert_type = 3
-         ORDER BY created_at ASC",
-    )
+) -> Result<Vec<Value>> {
+    let alert_type = alert_catalog::catalog().require(alert_type)?;
+
+    let records: Vec<(Value,)> = sqlx::query_as(&format!(
+        "SELECT to_jsonb(t) FROM {} t
+         WHERE t.id IN (
+             SELECT raw_alert_id FROM alert_convergence_mapping
+             WHERE converged_alert_id = $1 AND alert_type = $2
+         )
+         ORDER BY t.created_at ASC",
+        alert_type.raw_table
+    ))
     .bind(converged_id)
+    .bind(alert_type.code)
     .fetch_all(pool)
     .await?;
 
-    if raw_ids.is_empty() {
-        return Ok(vec![]);
-    }
-
-    // 提取ID列表
-    let ids: Vec<Uuid> = raw_ids.into_iter().map(|(id,)| id).collect();
-
-    // 查询原始告警详情
-    let records = sqlx::query_as::<_, HostBehaviorRecord>(
-        "SELECT * FROM host_behavior_alerts 
-         WHERE id = ANY($1)
-         ORDER BY created_at ASC",
-    )
-    .bind(&ids)
-    .fetch_all(pool)
-    .await?;
-
-    Ok(records)
+    Ok(records.into_iter().map(|(alert,)| alert).collect())
 }
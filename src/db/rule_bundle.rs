@@ -0,0 +1,608 @@
+//! 规则包导入导出
+//!
+//! 将收敛、关联、过滤、标签规则及标签导出为一个带格式版本号的 JSON / TOML 文件，
+//! 或校验后在单个事务中导入，用于在环境之间迁移规则集（例如从预发布环境推广到生产环境）。
+//! 规则包不包含 ID、版本号和命中统计等运行时字段，导入时按名称处理冲突。
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::convergence_rules::{self, ConvergenceRuleInput, ConvergenceRuleRecord};
+use super::correlation_rules::{self, CorrelationRuleInput, CorrelationRuleRecord};
+use super::filter_rules::{self, FilterRuleInput, FilterRuleRecord};
+use super::rule_versions::{self, RuleChange};
+use super::tag_management::{TagInput, TagRecord};
+use super::tag_rules::{self, TagRuleInput, TagRuleRecord};
+use crate::config::AlarmTypesConfig;
+use crate::dsl;
+
+/// 当前规则包格式版本，格式发生不兼容变化时递增
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// 规则包
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RuleBundle {
+    pub format_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Vec<TagInput>,
+    #[serde(default)]
+    pub convergence_rules: Vec<ConvergenceRuleInput>,
+    #[serde(default)]
+    pub correlation_rules: Vec<CorrelationRuleInput>,
+    #[serde(default)]
+    pub filter_rules: Vec<FilterRuleInput>,
+    #[serde(default)]
+    pub tag_rules: Vec<TagRuleInput>,
+}
+
+/// 规则包文件格式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleFormat {
+    #[default]
+    Json,
+    Toml,
+}
+
+impl BundleFormat {
+    /// 根据文件扩展名判断格式，.toml 以外均按 JSON 处理
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => BundleFormat::Toml,
+            _ => BundleFormat::Json,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            BundleFormat::Json => "application/json",
+            BundleFormat::Toml => "application/toml",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BundleFormat::Json => "json",
+            BundleFormat::Toml => "toml",
+        }
+    }
+}
+
+/// 导入时与已有同名规则或标签冲突的处理方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictMode {
+    /// 保留已有的，跳过规则包中的同名项
+    #[default]
+    Skip,
+    /// 用规则包中的定义覆盖已有的同名项（规则会生成新版本）
+    Overwrite,
+    /// 以新名称创建，例如 "规则名 (2)"
+    Rename,
+}
+
+impl FromStr for ConflictMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(ConflictMode::Skip),
+            "overwrite" => Ok(ConflictMode::Overwrite),
+            "rename" => Ok(ConflictMode::Rename),
+            other => bail!(
+                "未知的冲突处理方式: {}（可选 skip / overwrite / rename）",
+                other
+            ),
+        }
+    }
+}
+
+/// 导入选项
+#[derive(Clone, Debug, Default)]
+pub struct ImportOptions {
+    pub conflict: ConflictMode,
+    /// 只校验并预演导入结果，不写入数据库
+    pub validate_only: bool,
+    /// 记录在规则版本历史中的作者
+    pub author: Option<String>,
+}
+
+/// 规则包中的一处校验错误
+#[derive(Clone, Debug, Serialize)]
+pub struct BundleIssue {
+    pub section: &'static str,
+    pub name: String,
+    pub message: String,
+}
+
+/// 单个规则或标签的导入结果
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportItem {
+    pub section: &'static str,
+    pub name: String,
+    /// created / overwritten / renamed / skipped
+    pub action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_to: Option<String>,
+}
+
+/// 导入结果
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportReport {
+    pub validate_only: bool,
+    pub conflict: ConflictMode,
+    /// 是否已提交到数据库；校验失败或只校验时为 false
+    pub applied: bool,
+    pub items: Vec<ImportItem>,
+    pub errors: Vec<BundleIssue>,
+}
+
+const SECTION_TAGS: &str = "tags";
+const SECTION_CONVERGENCE: &str = "convergence_rules";
+const SECTION_CORRELATION: &str = "correlation_rules";
+const SECTION_FILTER: &str = "filter_rules";
+const SECTION_TAG_RULES: &str = "tag_rules";
+
+/// 解析规则包
+pub fn parse_bundle(text: &str, format: BundleFormat) -> Result<RuleBundle> {
+    let bundle: RuleBundle = match format {
+        BundleFormat::Json => serde_json::from_str(text)?,
+        BundleFormat::Toml => toml::from_str(text)?,
+    };
+    if bundle.format_version == 0 || bundle.format_version > BUNDLE_FORMAT_VERSION {
+        bail!(
+            "不支持的规则包格式版本: {}（当前支持 {}）",
+            bundle.format_version,
+            BUNDLE_FORMAT_VERSION
+        );
+    }
+    Ok(bundle)
+}
+
+/// 将规则包序列化为文本
+pub fn render_bundle(bundle: &RuleBundle, format: BundleFormat) -> Result<String> {
+    Ok(match format {
+        BundleFormat::Json => serde_json::to_string_pretty(bundle)?,
+        BundleFormat::Toml => toml::to_string_pretty(bundle)?,
+    })
+}
+
+/// 导出所有规则和标签
+pub async fn export_bundle(pool: &PgPool) -> Result<RuleBundle> {
+    let tags: Vec<TagRecord> = sqlx::query_as("SELECT * FROM tags ORDER BY category, name")
+        .fetch_all(pool)
+        .await?;
+    let convergence: Vec<ConvergenceRuleRecord> =
+        sqlx::query_as("SELECT * FROM convergence_rules ORDER BY created_at")
+            .fetch_all(pool)
+            .await?;
+    let correlation: Vec<CorrelationRuleRecord> =
+        sqlx::query_as("SELECT * FROM correlation_rules ORDER BY created_at")
+            .fetch_all(pool)
+            .await?;
+    let filter: Vec<FilterRuleRecord> =
+        sqlx::query_as("SELECT * FROM filter_rules ORDER BY created_at")
+            .fetch_all(pool)
+            .await?;
+    let tag_rules: Vec<TagRuleRecord> =
+        sqlx::query_as("SELECT * FROM tag_rules ORDER BY created_at")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(RuleBundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        exported_at: Some(Utc::now()),
+        tags: to_inputs(&tags)?,
+        convergence_rules: to_inputs(&convergence)?,
+        correlation_rules: to_inputs(&correlation)?,
+        filter_rules: to_inputs(&filter)?,
+        tag_rules: to_inputs(&tag_rules)?,
+    })
+}
+
+/// 去掉数据库记录中的运行时字段，得到与创建接口一致的输入
+fn to_inputs<R: Serialize, I: DeserializeOwned>(records: &[R]) -> Result<Vec<I>> {
+    records
+        .iter()
+        .map(|record| Ok(serde_json::from_value(rule_versions::snapshot_of(record)?)?))
+        .collect()
+}
+
+/// 校验规则包，并规范化过滤和标签规则的子类型与条件表达式
+pub fn validate_bundle(
+    bundle: &mut RuleBundle,
+    alarm_types: &AlarmTypesConfig,
+) -> Vec<BundleIssue> {
+    let mut issues = Vec::new();
+    let mut issue = |section: &'static str, name: &str, message: String| {
+        issues.push(BundleIssue {
+            section,
+            name: name.to_string(),
+            message,
+        })
+    };
+
+    for (section, names) in [
+        (SECTION_TAGS, bundle.tags.iter().map(|t| &t.name).collect()),
+        (
+            SECTION_CONVERGENCE,
+            bundle.convergence_rules.iter().map(|r| &r.name).collect(),
+        ),
+        (
+            SECTION_CORRELATION,
+            bundle.correlation_rules.iter().map(|r| &r.name).collect(),
+        ),
+        (
+            SECTION_FILTER,
+            bundle.filter_rules.iter().map(|r| &r.name).collect(),
+        ),
+        (
+            SECTION_TAG_RULES,
+            bundle.tag_rules.iter().map(|r| &r.name).collect::<Vec<_>>(),
+        ),
+    ] {
+        let mut seen = HashSet::new();
+        for name in names {
+            if name.trim().is_empty() {
+                issue(section, name, "名称不能为空".to_string());
+            } else if !seen.insert(name) {
+                issue(section, name, "规则包中存在重复的名称".to_string());
+            }
+        }
+    }
+
+    for rule in &bundle.convergence_rules {
+        if let Err(e) = dsl::parse_converge_rule(&rule.dsl_rule)
+            .and_then(|parsed| dsl::validate_fields(&parsed))
+        {
+            issue(SECTION_CONVERGENCE, &rule.name, format!("DSL 无效: {}", e));
+        }
+    }
+
+    for rule in &bundle.correlation_rules {
+        if let Err(e) = dsl::parse_correlate_rule(&rule.dsl_rule)
+            .and_then(|parsed| dsl::validator::validate_correlate_fields(&parsed))
+        {
+            issue(SECTION_CORRELATION, &rule.name, format!("DSL 无效: {}", e));
+        }
+    }
+
+    for rule in &mut bundle.filter_rules {
+        match checked_rule(
+            alarm_types,
+            &rule.alert_type,
+            &rule.alert_subtype,
+            rule.resolved_condition(),
+        ) {
+            Ok((subtype, condition)) => {
+                rule.alert_subtype = subtype;
+                rule.condition = Some(condition);
+            }
+            Err(e) => issue(SECTION_FILTER, &rule.name, e),
+        }
+    }
+
+    for rule in &mut bundle.tag_rules {
+        match checked_rule(
+            alarm_types,
+            &rule.alert_type,
+            &rule.alert_subtype,
+            rule.resolved_condition(),
+        ) {
+            Ok((subtype, condition)) => {
+                rule.alert_subtype = subtype;
+                rule.condition = Some(condition);
+            }
+            Err(e) => issue(SECTION_TAG_RULES, &rule.name, e),
+        }
+        if rule.tags.is_empty() {
+            issue(
+                SECTION_TAG_RULES,
+                &rule.name,
+                "至少需要一个标签".to_string(),
+            );
+        }
+    }
+
+    issues
+}
+
+/// 校验过滤或标签规则的子类型和条件，返回规范化后的子类型和生效的条件表达式
+fn checked_rule(
+    alarm_types: &AlarmTypesConfig,
+    alert_type: &str,
+    alert_subtype: &str,
+    condition: Option<String>,
+) -> std::result::Result<(String, String), String> {
+    let subtype = alarm_types.validate_subtypes(alert_type, alert_subtype)?;
+    let condition = condition.ok_or_else(|| "规则缺少有效的条件表达式".to_string())?;
+    dsl::validator::compile_condition(&condition, alert_type)
+        .map_err(|e| format!("条件表达式无效: {}", e))?;
+    Ok((subtype, condition))
+}
+
+/// 校验并导入规则包；所有写入在同一事务中完成，任一步失败则整体回滚
+pub async fn import_bundle(
+    pool: &PgPool,
+    mut bundle: RuleBundle,
+    alarm_types: &AlarmTypesConfig,
+    options: &ImportOptions,
+) -> Result<ImportReport> {
+    let mut report = ImportReport {
+        validate_only: options.validate_only,
+        conflict: options.conflict,
+        applied: false,
+        items: Vec::new(),
+        errors: validate_bundle(&mut bundle, alarm_types),
+    };
+    if !report.errors.is_empty() {
+        return Ok(report);
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut importer = Importer {
+        conn: &mut tx,
+        conflict: options.conflict,
+        author: options.author.as_deref(),
+        items: Vec::new(),
+    };
+    importer.apply(bundle).await?;
+    report.items = importer.items;
+
+    if options.validate_only {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+        report.applied = true;
+    }
+    Ok(report)
+}
+
+/// 同名冲突的处理结果
+enum Resolution {
+    Create(String),
+    Overwrite(Uuid),
+    Skip,
+}
+
+struct Importer<'c, 'a> {
+    conn: &'c mut PgConnection,
+    conflict: ConflictMode,
+    author: Option<&'a str>,
+    items: Vec<ImportItem>,
+}
+
+impl Importer<'_, '_> {
+    async fn apply(&mut self, bundle: RuleBundle) -> Result<()> {
+        // 标签重命名后，标签规则中引用的标签名随之替换
+        let mut renamed_tags = HashMap::new();
+        for tag in bundle.tags {
+            match self.resolve(SECTION_TAGS, "tags", &tag.name).await? {
+                Resolution::Create(name) => {
+                    sqlx::query(
+                        "INSERT INTO tags (name, category, color, description) VALUES ($1, $2, $3, $4)",
+                    )
+                    .bind(&name)
+                    .bind(&tag.category)
+                    .bind(&tag.color)
+                    .bind(&tag.description)
+                    .execute(&mut *self.conn)
+                    .await?;
+                    if name != tag.name {
+                        renamed_tags.insert(tag.name, name);
+                    }
+                }
+                Resolution::Overwrite(id) => {
+                    sqlx::query(
+                        "UPDATE tags SET category = $2, color = $3, description = $4, updated_at = now()
+                         WHERE id = $1",
+                    )
+                    .bind(id)
+                    .bind(&tag.category)
+                    .bind(&tag.color)
+                    .bind(&tag.description)
+                    .execute(&mut *self.conn)
+                    .await?;
+                }
+                Resolution::Skip => {}
+            }
+        }
+
+        for mut rule in bundle.convergence_rules {
+            match self
+                .resolve(SECTION_CONVERGENCE, "convergence_rules", &rule.name)
+                .await?
+            {
+                Resolution::Create(name) => {
+                    rule.name = name;
+                    convergence_rules::create_convergence_rule_in_tx(
+                        self.conn,
+                        &rule,
+                        &RuleChange::create(self.author),
+                    )
+                    .await?;
+                }
+                Resolution::Overwrite(id) => {
+                    convergence_rules::update_convergence_rule_in_tx(
+                        self.conn,
+                        id,
+                        &rule,
+                        &RuleChange::update(self.author),
+                    )
+                    .await?;
+                }
+                Resolution::Skip => {}
+            }
+        }
+
+        for mut rule in bundle.correlation_rules {
+            match self
+                .resolve(SECTION_CORRELATION, "correlation_rules", &rule.name)
+                .await?
+            {
+                Resolution::Create(name) => {
+                    rule.name = name;
+                    correlation_rules::create_correlation_rule_in_tx(
+                        self.conn,
+                        &rule,
+                        &RuleChange::create(self.author),
+                    )
+                    .await?;
+                }
+                Resolution::Overwrite(id) => {
+                    correlation_rules::update_correlation_rule_in_tx(
+                        self.conn,
+                        id,
+                        &rule,
+                        &RuleChange::update(self.author),
+                    )
+                    .await?;
+                }
+                Resolution::Skip => {}
+            }
+        }
+
+        for mut rule in bundle.filter_rules {
+            match self
+                .resolve(SECTION_FILTER, "filter_rules", &rule.name)
+                .await?
+            {
+                Resolution::Create(name) => {
+                    rule.name = name;
+                    filter_rules::create_filter_rule_in_tx(
+                        self.conn,
+                        &rule,
+                        &RuleChange::create(self.author),
+                    )
+                    .await?;
+                }
+                Resolution::Overwrite(id) => {
+                    filter_rules::update_filter_rule_in_tx(
+                        self.conn,
+                        id,
+                        &rule,
+                        &RuleChange::update(self.author),
+                    )
+                    .await?;
+                }
+                Resolution::Skip => {}
+            }
+        }
+
+        for mut rule in bundle.tag_rules {
+            for tag in &mut rule.tags {
+                if let Some(renamed) = renamed_tags.get(tag) {
+                    *tag = renamed.clone();
+                }
+            }
+            let resolution = self
+                .resolve(SECTION_TAG_RULES, "tag_rules", &rule.name)
+                .await?;
+            if !matches!(resolution, Resolution::Skip) {
+                self.ensure_tags(&rule.tags).await?;
+            }
+            match resolution {
+                Resolution::Create(name) => {
+                    rule.name = name;
+                    tag_rules::create_tag_rule_in_tx(
+                        self.conn,
+                        &rule,
+                        &RuleChange::create(self.author),
+                    )
+                    .await?;
+                }
+                Resolution::Overwrite(id) => {
+                    tag_rules::update_tag_rule_in_tx(
+                        self.conn,
+                        id,
+                        &rule,
+                        &RuleChange::update(self.author),
+                    )
+                    .await?;
+                }
+                Resolution::Skip => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 按名称查找同名项并根据冲突处理方式决定如何导入，同时记录导入结果
+    async fn resolve(
+        &mut self,
+        section: &'static str,
+        table: &str,
+        name: &str,
+    ) -> Result<Resolution> {
+        let existing = self.find_by_name(table, name).await?;
+        let (resolution, action, renamed_to) = match (existing, self.conflict) {
+            (None, _) => (Resolution::Create(name.to_string()), "created", None),
+            (Some(_), ConflictMode::Skip) => (Resolution::Skip, "skipped", None),
+            (Some(id), ConflictMode::Overwrite) => (Resolution::Overwrite(id), "overwritten", None),
+            (Some(_), ConflictMode::Rename) => {
+                let renamed = self.free_name(table, name).await?;
+                (
+                    Resolution::Create(renamed.clone()),
+                    "renamed",
+                    Some(renamed),
+                )
+            }
+        };
+
+        self.items.push(ImportItem {
+            section,
+            name: name.to_string(),
+            action,
+            renamed_to,
+        });
+        Ok(resolution)
+    }
+
+    async fn find_by_name(&mut self, table: &str, name: &str) -> Result<Option<Uuid>> {
+        let id: Option<(Uuid,)> = sqlx::query_as(&format!(
+            "SELECT id FROM {table} WHERE name = $1 ORDER BY created_at LIMIT 1"
+        ))
+        .bind(name)
+        .fetch_optional(&mut *self.conn)
+        .await?;
+        Ok(id.map(|(id,)| id))
+    }
+
+    /// 生成未被占用的名称："名称 (2)"、"名称 (3)" ...
+    async fn free_name(&mut self, table: &str, name: &str) -> Result<String> {
+        for n in 2.. {
+            let candidate = format!("{} ({})", name, n);
+            if self.find_by_name(table, &candidate).await?.is_none() {
+                return Ok(candidate);
+            }
+        }
+        Err(anyhow!("无法为 '{}' 生成新名称", name))
+    }
+
+    /// 标签规则引用的标签不存在时自动创建
+    async fn ensure_tags(&mut self, names: &[String]) -> Result<()> {
+        for name in names {
+            sqlx::query(
+                "INSERT INTO tags (name, category, color, description)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (name) DO NOTHING",
+            )
+            .bind(name)
+            .bind("其他")
+            .bind("#409EFF")
+            .bind(format!("自动创建的标签: {}", name))
+            .execute(&mut *self.conn)
+            .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+//! 规则版本历史
+//!
+//! 收敛、关联、过滤和标签规则每次创建、修改或回滚都会在同一事务中追加一条不可变的版本记录，
+//! 保存变更后的规则快照、与上一版本的字段差异、作者和时间，用于查看历史、对比和回滚。
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// 变更类型：创建规则
+pub const CHANGE_CREATE: &str = "create";
+/// 变更类型：修改规则
+pub const CHANGE_UPDATE: &str = "update";
+/// 变更类型：回滚到历史版本
+pub const CHANGE_ROLLBACK: &str = "rollback";
+/// 变更类型：启用版本管理前已存在的规则，首次修改时补录的原始版本
+pub const CHANGE_BASELINE: &str = "baseline";
+
+/// 不属于规则定义、不参与快照和对比的字段
+const UNVERSIONED_FIELDS: &[&str] = &[
+    "id",
+    "version",
+    "hit_count",
+    "last_hit_at",
+    "last_hit_alert_id",
+    "created_at",
+    "updated_at",
+];
+
+/// 受版本管理的规则类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    Convergence,
+    Correlation,
+    Filter,
+    Tag,
+}
+
+impl RuleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleKind::Convergence => "convergence",
+            RuleKind::Correlation => "correlation",
+            RuleKind::Filter => "filter",
+            RuleKind::Tag => "tag",
+        }
+    }
+}
+
+/// 一次规则变更的类型和作者
+#[derive(Clone, Copy, Debug)]
+pub struct RuleChange<'a> {
+    pub change: &'a str,
+    pub author: Option<&'a str>,
+    /// 回滚时恢复的历史版本号
+    pub rollback_from: Option<i32>,
+}
+
+impl<'a> RuleChange<'a> {
+    pub fn create(author: Option<&'a str>) -> Self {
+        Self {
+            change: CHANGE_CREATE,
+            author,
+            rollback_from: None,
+        }
+    }
+
+    pub fn update(author: Option<&'a str>) -> Self {
+        Self {
+            change: CHANGE_UPDATE,
+            author,
+            rollback_from: None,
+        }
+    }
+
+    pub fn rollback(author: Option<&'a str>, version: i32) -> Self {
+        Self {
+            change: CHANGE_ROLLBACK,
+            author,
+            rollback_from: Some(version),
+        }
+    }
+}
+
+/// 规则版本记录
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RuleVersionRecord {
+    pub id: Uuid,
+    pub rule_kind: String,
+    pub rule_id: Uuid,
+    pub version: i32,
+    /// 该版本的规则定义，格式与规则的创建接口输入一致
+    pub snapshot: Value,
+    /// 相对上一版本变化的字段：{"字段": {"old": 旧值, "new": 新值}}
+    pub diff: Value,
+    pub change: String,
+    pub author: Option<String>,
+    /// 回滚变更所恢复的历史版本号
+    pub rollback_from: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 创建规则版本表
+pub async fn create_rule_versions_table(pool: &PgPool) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS rule_versions (
+            id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+            rule_kind TEXT NOT NULL,
+            rule_id UUID NOT NULL,
+            version INT NOT NULL,
+            snapshot JSONB NOT NULL,
+            diff JSONB NOT NULL DEFAULT '{}',
+            change TEXT NOT NULL,
+            author TEXT,
+            rollback_from INT,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            UNIQUE (rule_kind, rule_id, version)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 删除规则版本表
+pub async fn drop_rule_versions_table(pool: &PgPool) -> Result<()> {
+    sqlx::query("DROP TABLE IF EXISTS rule_versions CASCADE")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 提取规则记录中属于规则定义的字段
+pub fn snapshot_of<R: Serialize>(record: &R) -> Result<Value> {
+    let mut value = serde_json::to_value(record)?;
+    if let Some(fields) = value.as_object_mut() {
+        for field in UNVERSIONED_FIELDS {
+            fields.remove(*field);
+        }
+    }
+    Ok(value)
+}
+
+/// 对比两个快照，返回发生变化的字段及其新旧值
+pub fn diff_snapshots(old: &Value, new: &Value) -> Value {
+    let empty = Map::new();
+    let old = old.as_object().unwrap_or(&empty);
+    let new = new.as_object().unwrap_or(&empty);
+
+    let mut diff = Map::new();
+    for key in old.keys().chain(new.keys()) {
+        let (before, after) = (
+            old.get(key).unwrap_or(&Value::Null),
+            new.get(key).unwrap_or(&Value::Null),
+        );
+        if before != after && !diff.contains_key(key) {
+            diff.insert(key.clone(), json!({ "old": before, "new": after }));
+        }
+    }
+    Value::Object(diff)
+}
+
+/// 在规则变更所在的事务中追加一条版本记录
+///
+/// previous 为变更前的规则记录及其版本号；若该规则还没有任何版本记录（启用版本管理前创建的规则），
+/// 先补录一条原始版本，保证首次修改前的定义不会丢失。
+pub async fn append_version<R: Serialize>(
+    conn: &mut PgConnection,
+    kind: RuleKind,
+    rule_id: Uuid,
+    previous: Option<(&R, i32)>,
+    current: &R,
+    version: i32,
+    change: &RuleChange<'_>,
+) -> Result<()> {
+    let latest: Option<(Value,)> = sqlx::query_as(
+        "SELECT snapshot FROM rule_versions
+         WHERE rule_kind = $1 AND rule_id = $2
+         ORDER BY version DESC
+         LIMIT 1",
+    )
+    .bind(kind.as_str())
+    .bind(rule_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let base = match (latest, previous) {
+        (Some((snapshot,)), _) => snapshot,
+        (None, Some((record, previous_version))) => {
+            let snapshot = snapshot_of(record)?;
+            insert_version(
+                &mut *conn,
+                kind,
+                rule_id,
+                previous_version,
+                &snapshot,
+                &json!({}),
+                &RuleChange {
+                    change: CHANGE_BASELINE,
+                    author: None,
+                    rollback_from: None,
+                },
+            )
+            .await?;
+            snapshot
+        }
+        (None, None) => json!({}),
+    };
+
+    let snapshot = snapshot_of(current)?;
+    let diff = diff_snapshots(&base, &snapshot);
+    insert_version(conn, kind, rule_id, version, &snapshot, &diff, change).await
+}
+
+async fn insert_version(
+    conn: &mut PgConnection,
+    kind: RuleKind,
+    rule_id: Uuid,
+    version: i32,
+    snapshot: &Value,
+    diff: &Value,
+    change: &RuleChange<'_>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO rule_versions (rule_kind, rule_id, version, snapshot, diff, change, author, rollback_from)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+    )
+    .bind(kind.as_str())
+    .bind(rule_id)
+    .bind(version)
+    .bind(snapshot)
+    .bind(diff)
+    .bind(change.change)
+    .bind(change.author)
+    .bind(change.rollback_from)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// 查询规则的所有版本，按版本号倒序
+pub async fn query_rule_versions(
+    pool: &PgPool,
+    kind: RuleKind,
+    rule_id: Uuid,
+) -> Result<Vec<RuleVersionRecord>> {
+    let records = sqlx::query_as::<_, RuleVersionRecord>(
+        "SELECT * FROM rule_versions
+         WHERE rule_kind = $1 AND rule_id = $2
+         ORDER BY version DESC",
+    )
+    .bind(kind.as_str())
+    .bind(rule_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records)
+}
+
+/// 查询规则的指定版本
+pub async fn get_rule_version(
+    pool: &PgPool,
+    kind: RuleKind,
+    rule_id: Uuid,
+    version: i32,
+) -> Result<Option<RuleVersionRecord>> {
+    let record = sqlx::query_as::<_, RuleVersionRecord>(
+        "SELECT * FROM rule_versions WHERE rule_kind = $1 AND rule_id = $2 AND version = $3",
+    )
+    .bind(kind.as_str())
+    .bind(rule_id)
+    .bind(version)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record)
+}
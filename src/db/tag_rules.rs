@@ -1,9 +1,13 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
+use tracing::warn;
 use uuid::Uuid;
 
+use super::rule_versions::{self, RuleChange, RuleKind};
+use crate::dsl::legacy::{legacy_condition, legacy_matches_null};
+
 /// 标签规则记录 - 数据库模型
 #[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct TagRuleRecord {
@@ -14,9 +18,20 @@ pub struct TagRuleRecord {
     pub condition_field: String,
     pub condition_operator: String,
     pub condition_value: String,
+    /// DSL 条件表达式（WHERE 子句），为空时由 condition_field/condition_operator/condition_value 生成等价条件
+    pub condition: Option<String>,
     pub tags: Vec<String>,
     pub description: Option<String>,
     pub enabled: bool,
+    /// 规则版本号，每次修改或回滚加一
+    pub version: i32,
+    /// 命中次数
+    #[serde(default)]
+    pub hit_count: i64,
+    /// 最近一次命中时间
+    pub last_hit_at: Option<DateTime<Utc>>,
+    /// 最近一次命中的告警ID（原始告警）
+    pub last_hit_alert_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -27,14 +42,35 @@ pub struct TagRuleInput {
     pub name: String,
     pub alert_type: String,
     pub alert_subtype: String,
+    #[serde(default)]
     pub condition_field: String,
+    #[serde(default)]
     pub condition_operator: String,
+    #[serde(default)]
     pub condition_value: String,
+    /// DSL 条件表达式，未提供时由 condition_field / condition_operator / condition_value 转换得到
+    #[serde(default)]
+    pub condition: Option<String>,
     pub tags: Vec<String>,
     pub description: Option<String>,
     pub enabled: bool,
 }
 
+impl TagRuleInput {
+    /// 规则实际生效的 DSL 条件
+    pub fn resolved_condition(&self) -> Option<String> {
+        match self.condition.as_deref().map(str::trim) {
+            Some(condition) if !condition.is_empty() => Some(condition.to_string()),
+            _ => legacy_condition(
+                &self.alert_type,
+                &self.condition_field,
+                &self.condition_operator,
+                &self.condition_value,
+            ),
+        }
+    }
+}
+
 /// 创建标签规则表
 pub async fn create_tag_rules_table(pool: &PgPool) -> Result<()> {
     sqlx::query(
@@ -46,9 +82,14 @@ pub async fn create_tag_rules_table(pool: &PgPool) -> Result<()> {
             condition_field TEXT NOT NULL,
             condition_operator TEXT NOT NULL,
             condition_value TEXT NOT NULL,
+            condition TEXT,
             tags TEXT[] NOT NULL,
             description TEXT,
             enabled BOOLEAN NOT NULL DEFAULT true,
+            version INT NOT NULL DEFAULT 1,
+            hit_count BIGINT NOT NULL DEFAULT 0,
+            last_hit_at TIMESTAMPTZ,
+            last_hit_alert_id UUID,
             created_at TIMESTAMPTZ DEFAULT now(),
             updated_at TIMESTAMPTZ DEFAULT now()
         )",
@@ -56,6 +97,23 @@ pub async fn create_tag_rules_table(pool: &PgPool) -> Result<()> {
     .execute(pool)
     .await?;
 
+    sqlx::query("ALTER TABLE tag_rules ADD COLUMN IF NOT EXISTS version INT NOT NULL DEFAULT 1")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("ALTER TABLE tag_rules ADD COLUMN IF NOT EXISTS condition TEXT")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        "ALTER TABLE tag_rules
+         ADD COLUMN IF NOT EXISTS hit_count BIGINT NOT NULL DEFAULT 0,
+         ADD COLUMN IF NOT EXISTS last_hit_at TIMESTAMPTZ,
+         ADD COLUMN IF NOT EXISTS last_hit_alert_id UUID",
+    )
+    .execute(pool)
+    .await?;
+
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_tag_rules_alert_type ON tag_rules(alert_type)")
         .execute(pool)
         .await?;
@@ -64,6 +122,43 @@ pub async fn create_tag_rules_table(pool: &PgPool) -> Result<()> {
         .execute(pool)
         .await?;
 
+    migrate_legacy_conditions(pool).await?;
+
+    Ok(())
+}
+
+/// 将旧版单条件规则转换为 DSL 条件
+async fn migrate_legacy_conditions(pool: &PgPool) -> Result<()> {
+    let rows: Vec<(Uuid, String, String, String, String)> = sqlx::query_as(
+        "SELECT id, alert_type, condition_field, condition_operator, condition_value
+         FROM tag_rules WHERE condition IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (id, alert_type, field, operator, value) in rows {
+        match legacy_condition(&alert_type, &field, &operator, &value) {
+            Some(condition) => {
+                // 旧版标签规则的 ne 对 null 字段总是匹配
+                if legacy_matches_null(&operator, &value) || operator == "ne" {
+                    warn!(
+                        "标签规则 {} 迁移为 DSL 条件后不再匹配 {} 为 null 的告警: {}",
+                        id, field, condition
+                    );
+                }
+                sqlx::query("UPDATE tag_rules SET condition = $2 WHERE id = $1")
+                    .bind(id)
+                    .bind(condition)
+                    .execute(pool)
+                    .await?;
+            }
+            None => warn!(
+                "标签规则 {} 的条件无法转换为 DSL: {} {} {}",
+                id, field, operator, value
+            ),
+        }
+    }
+
     Ok(())
 }
 
@@ -76,11 +171,28 @@ pub async fn drop_tag_rules_table(pool: &PgPool) -> Result<()> {
 }
 
 /// 创建标签规则
-pub async fn create_tag_rule(pool: &PgPool, input: &TagRuleInput) -> Result<TagRuleRecord> {
+pub async fn create_tag_rule(
+    pool: &PgPool,
+    input: &TagRuleInput,
+    change: &RuleChange<'_>,
+) -> Result<TagRuleRecord> {
+    let mut tx = pool.begin().await?;
+    let record = create_tag_rule_in_tx(&mut tx, input, change).await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// 在已有事务中创建标签规则并记录版本
+pub async fn create_tag_rule_in_tx(
+    conn: &mut PgConnection,
+    input: &TagRuleInput,
+    change: &RuleChange<'_>,
+) -> Result<TagRuleRecord> {
     let record = sqlx::query_as::<_, TagRuleRecord>(
         "INSERT INTO tag_rules (name, alert_type, alert_subtype, condition_field, 
-                                condition_operator, condition_value, tags, description, enabled)
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                                condition_operator, condition_value, condition, tags, description, enabled)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
          RETURNING *",
     )
     .bind(&input.name)
@@ -89,10 +201,22 @@ pub async fn create_tag_rule(pool: &PgPool, input: &TagRuleInput) -> Result<TagR
     .bind(&input.condition_field)
     .bind(&input.condition_operator)
     .bind(&input.condition_value)
+    .bind(input.resolved_condition())
     .bind(&input.tags)
     .bind(&input.description)
     .bind(input.enabled)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    rule_versions::append_version(
+        conn,
+        RuleKind::Tag,
+        record.id,
+        None,
+        &record,
+        record.version,
+        change,
+    )
     .await?;
 
     Ok(record)
@@ -103,12 +227,34 @@ pub async fn update_tag_rule(
     pool: &PgPool,
     id: Uuid,
     input: &TagRuleInput,
+    change: &RuleChange<'_>,
+) -> Result<TagRuleRecord> {
+    let mut tx = pool.begin().await?;
+    let record = update_tag_rule_in_tx(&mut tx, id, input, change).await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// 在已有事务中更新标签规则并记录版本
+pub async fn update_tag_rule_in_tx(
+    conn: &mut PgConnection,
+    id: Uuid,
+    input: &TagRuleInput,
+    change: &RuleChange<'_>,
 ) -> Result<TagRuleRecord> {
+    let previous =
+        sqlx::query_as::<_, TagRuleRecord>("SELECT * FROM tag_rules WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_one(&mut *conn)
+            .await?;
+
     let record = sqlx::query_as::<_, TagRuleRecord>(
         "UPDATE tag_rules
          SET name = $2, alert_type = $3, alert_subtype = $4, condition_field = $5,
-             condition_operator = $6, condition_value = $7, tags = $8, 
-             description = $9, enabled = $10, updated_at = now()
+             condition_operator = $6, condition_value = $7, condition = $8, tags = $9,
+             description = $10, enabled = $11,
+             version = version + 1, updated_at = now()
          WHERE id = $1
          RETURNING *",
     )
@@ -119,10 +265,22 @@ pub async fn update_tag_rule(
     .bind(&input.condition_field)
     .bind(&input.condition_operator)
     .bind(&input.condition_value)
+    .bind(input.resolved_condition())
     .bind(&input.tags)
     .bind(&input.description)
     .bind(input.enabled)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    rule_versions::append_version(
+        conn,
+        RuleKind::Tag,
+        id,
+        Some((&previous, previous.version)),
+        &record,
+        record.version,
+        change,
+    )
     .await?;
 
     Ok(record)
@@ -148,6 +306,25 @@ pub async fn get_tag_rule_by_id(pool: &PgPool, id: Uuid) -> Result<TagRuleRecord
     Ok(record)
 }
 
+/// 记录一条告警命中的所有标签规则
+pub async fn record_tag_rule_hits(pool: &PgPool, ids: &[Uuid], alert_id: Uuid) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query(
+        "UPDATE tag_rules
+         SET hit_count = hit_count + 1, last_hit_at = now(), last_hit_alert_id = $2
+         WHERE id = ANY($1)",
+    )
+    .bind(ids)
+    .bind(alert_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 /// 查询标签规则列表（支持分页）
 pub async fn query_tag_rules(
     pool: &PgPool,
@@ -1,9 +1,15 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::postgres::PgArguments;
+use sqlx::query::QueryAs;
+use sqlx::{PgExecutor, PgPool, Postgres};
+use tracing::{info, warn};
 use uuid::Uuid;
 
+use super::list_filter::ListQuery;
+use super::threat_event_workflow::DisposeStatus;
+
 /// 威胁事件数据库记录
 #[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct ThreatEventRecord {
@@ -51,6 +57,12 @@ pub struct ThreatEventRecord {
     pub attack_vulnerability: Option<serde_json::Value>,
     pub attack_certificate: Option<serde_json::Value>,
     pub victim_certificate: Option<serde_json::Value>,
+    /// 生成该事件的关联规则及其版本（手工创建的事件为空）
+    pub correlation_rule_id: Option<Uuid>,
+    pub correlation_rule_version: Option<i32>,
+    /// 当前处置人及最近一次处置状态流转的时间
+    pub assignee: Option<String>,
+    pub status_updated_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -102,20 +114,150 @@ pub async fn create_threat_event_table(pool: &PgPool) -> Result<()> {
             attack_vulnerability JSONB,
             attack_certificate JSONB,
             victim_certificate JSONB,
+            correlation_rule_id uuid,
+            correlation_rule_version INT,
             created_at TIMESTAMPTZ DEFAULT now()
         )",
     )
     .execute(pool)
     .await?;
 
+    sqlx::query(
+        "ALTER TABLE threat_events
+            ADD COLUMN IF NOT EXISTS correlation_rule_id uuid,
+            ADD COLUMN IF NOT EXISTS correlation_rule_version INT",
+    )
+    .execute(pool)
+    .await?;
+
+    // 本系统生成的事件没有上游编号，从独立号段分配 event_id，推送时作为 id 字段
+    sqlx::query(&format!(
+        "CREATE SEQUENCE IF NOT EXISTS threat_event_id_seq
+         START WITH {LOCAL_EVENT_ID_START} MAXVALUE {MAX_SAFE_EVENT_ID}"
+    ))
+    .execute(pool)
+    .await?;
+
+    // 旧版本的本地号段超出 JS 安全整数范围：序列上限超出时执行一次迁移
+    let (out_of_range,): (bool,) = sqlx::query_as(
+        "SELECT seqmax > $1 FROM pg_sequence WHERE seqrelid = 'threat_event_id_seq'::regclass",
+    )
+    .bind(MAX_SAFE_EVENT_ID)
+    .fetch_one(pool)
+    .await?;
+    if out_of_range {
+        renumber_local_event_ids(pool).await?;
+    }
+
+    // 上游推送的事件按 event_id 去重：唯一索引不存在时执行一次迁移
+    let (migrated,): (bool,) =
+        sqlx::query_as("SELECT to_regclass('uq_threat_events_event_id') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+    if !migrated {
+        reassign_duplicate_event_ids(pool).await?;
+    }
+
+    sqlx::query(
+        "UPDATE threat_events SET event_id = nextval('threat_event_id_seq') WHERE event_id IS NULL",
+    )
+    .execute(pool)
+    .await?;
+
     Ok(())
 }
 
-/// 插入威胁事件
-#[allow(dead_code)]
-pub async fn insert_threat_event(pool: &PgPool, event: &ThreatEventInput) -> Result<Uuid> {
-    let id = sqlx::query_scalar::<_, Uuid>(
-        "INSERT INTO threat_events (
+/// 一次性迁移：把本地号段移到 JS 安全整数范围内，并为超出范围的事件重新分配 event_id，
+/// 避免前端和推送下游按 JS 数字解析时丢失精度，逐条记录重新分配的事件
+async fn renumber_local_event_ids(pool: &PgPool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(&format!(
+        "ALTER SEQUENCE threat_event_id_seq
+         MAXVALUE {MAX_SAFE_EVENT_ID} START WITH {LOCAL_EVENT_ID_START} RESTART"
+    ))
+    .execute(&mut *tx)
+    .await?;
+
+    let renumbered: Vec<(Uuid, i64, i64)> = sqlx::query_as(
+        "UPDATE threat_events t
+         SET event_id = nextval('threat_event_id_seq')
+         FROM (SELECT id, event_id FROM threat_events WHERE event_id > $1) old
+         WHERE t.id = old.id
+         RETURNING t.id, old.event_id, t.event_id",
+    )
+    .bind(MAX_SAFE_EVENT_ID)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (id, old_event_id, new_event_id) in &renumbered {
+        warn!(
+            "威胁事件 {} 的 event_id {} 超出 JS 安全整数范围，已重新分配为 {}",
+            id, old_event_id, new_event_id
+        );
+    }
+
+    tx.commit().await?;
+    info!(
+        "威胁事件本地号段迁移完成，重新分配 {} 条事件",
+        renumbered.len()
+    );
+    Ok(())
+}
+
+/// 一次性迁移：event_id 重复的历史事件保留最早的一条，其余改为从本地号段重新分配
+/// event_id 后建立唯一索引，事件及其关联数据都不删除，逐条记录重新分配的事件
+async fn reassign_duplicate_event_ids(pool: &PgPool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let reassigned: Vec<(Uuid, i64, i64)> = sqlx::query_as(
+        "WITH duplicates AS (
+            SELECT a.id, a.event_id
+            FROM threat_events a
+            JOIN threat_events b ON a.event_id = b.event_id
+            WHERE (COALESCE(a.created_at, '-infinity'), a.id)
+                > (COALESCE(b.created_at, '-infinity'), b.id)
+            GROUP BY a.id, a.event_id
+         )
+         UPDATE threat_events t
+         SET event_id = nextval('threat_event_id_seq')
+         FROM duplicates d
+         WHERE t.id = d.id
+         RETURNING t.id, d.event_id, t.event_id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (id, old_event_id, new_event_id) in &reassigned {
+        warn!(
+            "威胁事件 {} 的 event_id {} 重复，已重新分配为 {}",
+            id, old_event_id, new_event_id
+        );
+    }
+
+    sqlx::query(
+        "CREATE UNIQUE INDEX uq_threat_events_event_id
+         ON threat_events(event_id) WHERE event_id IS NOT NULL",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    info!(
+        "威胁事件 event_id 去重迁移完成，重新分配 {} 条重复事件",
+        reassigned.len()
+    );
+    Ok(())
+}
+
+/// 本地生成事件的 event_id 起始值，远大于上游编号以避免冲突
+const LOCAL_EVENT_ID_START: i64 = 9_000_000_000_000_000;
+
+/// event_id 上限：JS 能精确表示的最大整数 2^53 - 1，前端和推送下游按数字解析 event_id
+const MAX_SAFE_EVENT_ID: i64 = 9_007_199_254_740_991;
+
+/// 威胁事件字段列，与 bind_event 的绑定顺序一致
+const EVENT_COLUMNS: &str = "
             event_id, system_code, name, description, event_type,
             attacker, victimer, start_time, end_time, found_time,
             source, mitre_technique_id, attsck_list, attack_tool, first_found_time,
@@ -126,89 +268,193 @@ pub async fn insert_threat_event(pool: &PgPool, event: &ThreatEventInput) -> Res
             attack_malware, attack_malware_sample, attack_malware_sample_family,
             attack_email_address, victim_email_address, attack_email, victim_email,
             attack_software, victim_software, attack_vulnerability,
-            attack_certificate, victim_certificate
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+            attack_certificate, victim_certificate";
+
+/// 字段列对应的占位符
+const EVENT_VALUES: &str = "
+            COALESCE($1, nextval('threat_event_id_seq')), $2, $3, $4, $5, $6, $7, $8, $9, $10,
             $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
             $21, $22, $23, $24, $25, $26, $27, $28, $29, $30,
             $31, $32, $33, $34, $35, $36, $37, $38, $39, $40,
-            $41, $42, $43
-        ) RETURNING id",
+            $41, $42, $43";
+
+/// 按 EVENT_COLUMNS 的顺序绑定威胁事件字段
+fn bind_event<'q, O>(
+    query: QueryAs<'q, Postgres, O, PgArguments>,
+    event: &'q ThreatEventInput,
+) -> QueryAs<'q, Postgres, O, PgArguments> {
+    query
+        .bind(event.event_id)
+        .bind(&event.system_code)
+        .bind(&event.name)
+        .bind(&event.description)
+        .bind(&event.event_type)
+        .bind(&event.attacker)
+        .bind(&event.victimer)
+        .bind(event.start_time)
+        .bind(event.end_time)
+        .bind(event.found_time)
+        .bind(&event.source)
+        .bind(&event.mitre_technique_id)
+        .bind(&event.attsck_list)
+        .bind(&event.attack_tool)
+        .bind(event.first_found_time)
+        .bind(&event.priority)
+        .bind(&event.severity)
+        .bind(initial_status(event).code())
+        .bind(&event.app)
+        .bind(&event.impact_assessment)
+        .bind(&event.merge_alerts)
+        .bind(&event.threat_actor)
+        .bind(&event.org)
+        .bind(&event.attack_asset_ip)
+        .bind(&event.victim_asset_ip)
+        .bind(&event.attack_asset_ip_port)
+        .bind(&event.victim_asset_ip_port)
+        .bind(&event.attack_asset_domain)
+        .bind(&event.victim_asset_domain)
+        .bind(&event.attack_url)
+        .bind(&event.victim_url)
+        .bind(&event.attack_malware)
+        .bind(&event.attack_malware_sample)
+        .bind(&event.attack_malware_sample_family)
+        .bind(&event.attack_email_address)
+        .bind(&event.victim_email_address)
+        .bind(&event.attack_email)
+        .bind(&event.victim_email)
+        .bind(&event.attack_software)
+        .bind(&event.victim_software)
+        .bind(&event.attack_vulnerability)
+        .bind(&event.attack_certificate)
+        .bind(&event.victim_certificate)
+}
+
+/// 新事件的处置状态：识别状态编码或中文名称，未提供或无法识别时为待处置
+fn initial_status(event: &ThreatEventInput) -> DisposeStatus {
+    event
+        .dispose_status
+        .as_deref()
+        .and_then(DisposeStatus::parse)
+        .unwrap_or(DisposeStatus::New)
+}
+
+/// 插入威胁事件，可传入事务
+pub async fn insert_threat_event<'e, E>(executor: E, event: &ThreatEventInput) -> Result<Uuid>
+where
+    E: PgExecutor<'e>,
+{
+    let sql =
+        format!("INSERT INTO threat_events ({EVENT_COLUMNS}) VALUES ({EVENT_VALUES}) RETURNING id");
+    let (id,): (Uuid,) = bind_event(sqlx::query_as(&sql), event)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(id)
+}
+
+/// upsert_threat_event 的写入结果
+#[derive(Debug, Clone, Copy)]
+pub struct UpsertOutcome {
+    pub id: Uuid,
+    /// 是否为新插入的事件
+    pub inserted: bool,
+}
+
+/// 按 event_id 写入上游推送的威胁事件：不存在时插入，已存在时以推送内容覆盖
+///
+/// 处置状态只在插入时取自推送内容，之后由本地处置流程维护
+pub async fn upsert_threat_event(pool: &PgPool, event: &ThreatEventInput) -> Result<UpsertOutcome> {
+    let sql = format!(
+        "INSERT INTO threat_events ({EVENT_COLUMNS}) VALUES ({EVENT_VALUES})
+         ON CONFLICT (event_id) WHERE event_id IS NOT NULL DO UPDATE SET
+            system_code = EXCLUDED.system_code,
+            name = EXCLUDED.name,
+            description = EXCLUDED.description,
+            event_type = EXCLUDED.event_type,
+            attacker = EXCLUDED.attacker,
+            victimer = EXCLUDED.victimer,
+            start_time = EXCLUDED.start_time,
+            end_time = EXCLUDED.end_time,
+            found_time = EXCLUDED.found_time,
+            source = EXCLUDED.source,
+            mitre_technique_id = EXCLUDED.mitre_technique_id,
+            attsck_list = EXCLUDED.attsck_list,
+            attack_tool = EXCLUDED.attack_tool,
+            first_found_time = EXCLUDED.first_found_time,
+            priority = EXCLUDED.priority,
+            severity = EXCLUDED.severity,
+            app = EXCLUDED.app,
+            impact_assessment = EXCLUDED.impact_assessment,
+            merge_alerts = EXCLUDED.merge_alerts,
+            threat_actor = EXCLUDED.threat_actor,
+            org = EXCLUDED.org,
+            attack_asset_ip = EXCLUDED.attack_asset_ip,
+            victim_asset_ip = EXCLUDED.victim_asset_ip,
+            attack_asset_ip_port = EXCLUDED.attack_asset_ip_port,
+            victim_asset_ip_port = EXCLUDED.victim_asset_ip_port,
+            attack_asset_domain = EXCLUDED.attack_asset_domain,
+            victim_asset_domain = EXCLUDED.victim_asset_domain,
+            attack_url = EXCLUDED.attack_url,
+            victim_url = EXCLUDED.victim_url,
+            attack_malware = EXCLUDED.attack_malware,
+            attack_malware_sample = EXCLUDED.attack_malware_sample,
+            attack_malware_sample_family = EXCLUDED.attack_malware_sample_family,
+            attack_email_address = EXCLUDED.attack_email_address,
+            victim_email_address = EXCLUDED.victim_email_address,
+            attack_email = EXCLUDED.attack_email,
+            victim_email = EXCLUDED.victim_email,
+            attack_software = EXCLUDED.attack_software,
+            victim_software = EXCLUDED.victim_software,
+            attack_vulnerability = EXCLUDED.attack_vulnerability,
+            attack_certificate = EXCLUDED.attack_certificate,
+            victim_certificate = EXCLUDED.victim_certificate
+         RETURNING id, (xmax = 0)"
+    );
+    let (id, inserted): (Uuid, bool) = bind_event(sqlx::query_as(&sql), event)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(UpsertOutcome { id, inserted })
+}
+
+/// 记录生成威胁事件的关联规则及其版本，可传入事务
+pub async fn set_threat_event_rule<'e, E>(
+    executor: E,
+    id: Uuid,
+    rule_id: Uuid,
+    rule_version: i32,
+) -> Result<()>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query(
+        "UPDATE threat_events SET correlation_rule_id = $2, correlation_rule_version = $3 WHERE id = $1",
     )
-    .bind(&event.event_id)
-    .bind(&event.system_code)
-    .bind(&event.name)
-    .bind(&event.description)
-    .bind(&event.event_type)
-    .bind(&event.attacker)
-    .bind(&event.victimer)
-    .bind(&event.start_time)
-    .bind(&event.end_time)
-    .bind(&event.found_time)
-    .bind(&event.source)
-    .bind(&event.mitre_technique_id)
-    .bind(&event.attsck_list)
-    .bind(&event.attack_tool)
-    .bind(&event.first_found_time)
-    .bind(&event.priority)
-    .bind(&event.severity)
-    .bind(&event.dispose_status)
-    .bind(&event.app)
-    .bind(&event.impact_assessment)
-    .bind(&event.merge_alerts)
-    .bind(&event.threat_actor)
-    .bind(&event.org)
-    .bind(&event.attack_asset_ip)
-    .bind(&event.victim_asset_ip)
-    .bind(&event.attack_asset_ip_port)
-    .bind(&event.victim_asset_ip_port)
-    .bind(&event.attack_asset_domain)
-    .bind(&event.victim_asset_domain)
-    .bind(&event.attack_url)
-    .bind(&event.victim_url)
-    .bind(&event.attack_malware)
-    .bind(&event.attack_malware_sample)
-    .bind(&event.attack_malware_sample_family)
-    .bind(&event.attack_email_address)
-    .bind(&event.victim_email_address)
-    .bind(&event.attack_email)
-    .bind(&event.victim_email)
-    .bind(&event.attack_software)
-    .bind(&event.victim_software)
-    .bind(&event.attack_vulnerability)
-    .bind(&event.attack_certificate)
-    .bind(&event.victim_certificate)
-    .fetch_one(pool)
+    .bind(id)
+    .bind(rule_id)
+    .bind(rule_version)
+    .execute(executor)
     .await?;
 
-    Ok(id)
+    Ok(())
 }
 
-/// 查询威胁事件（分页）
+/// 按查询条件分页查询威胁事件
 pub async fn query_threat_events(
     pool: &PgPool,
+    query: &ListQuery,
     page: u64,
     page_size: u64,
 ) -> Result<(Vec<ThreatEventRecord>, u64)> {
-    let offset = (page - 1) * page_size;
-
-    let records = sqlx::query_as::<_, ThreatEventRecord>(
-        "SELECT * FROM threat_events ORDER BY created_at DESC LIMIT $1 OFFSET $2",
-    )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(pool)
-    .await?;
-
-    let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM threat_events")
-        .fetch_one(pool)
+    let records = query
+        .fetch_page(pool, "SELECT t.* FROM threat_events t", page, page_size)
         .await?;
+    let total = query.count(pool, "threat_events t").await?;
 
-    Ok((records, total.0 as u64))
+    Ok((records, total))
 }
 
 /// 根据ID查询单个威胁事件
-#[allow(dead_code)]
 pub async fn get_threat_event_by_id(pool: &PgPool, id: Uuid) -> Result<Option<ThreatEventRecord>> {
     let record =
         sqlx::query_as::<_, ThreatEventRecord>("SELECT * FROM threat_events WHERE id = $1")
@@ -219,11 +465,17 @@ pub async fn get_threat_event_by_id(pool: &PgPool, id: Uuid) -> Result<Option<Th
     Ok(record)
 }
 
-/// 更新威胁事件
-pub async fn update_threat_event(pool: &PgPool, id: Uuid, event: &ThreatEventInput) -> Result<()> {
-    sqlx::query(
+/// 更新威胁事件内容，未提供 event_id 时保留原编号
+///
+/// 处置状态不在此修改，需通过处置流程流转；返回事件是否存在
+pub async fn update_threat_event(
+    pool: &PgPool,
+    id: Uuid,
+    event: &ThreatEventInput,
+) -> Result<bool> {
+    let result = sqlx::query(
         "UPDATE threat_events SET
-            event_id = $2,
+            event_id = COALESCE($2, threat_events.event_id),
             system_code = $3,
             name = $4,
             description = $5,
@@ -240,53 +492,51 @@ pub async fn update_threat_event(pool: &PgPool, id: Uuid, event: &ThreatEventInp
             first_found_time = $16,
             priority = $17,
             severity = $18,
-            dispose_status = $19,
-            app = $20,
-            impact_assessment = $21,
-            merge_alerts = $22,
-            threat_actor = $23,
-            org = $24,
-            attack_asset_ip = $25,
-            victim_asset_ip = $26,
-            attack_asset_ip_port = $27,
-            victim_asset_ip_port = $28,
-            attack_asset_domain = $29,
-            victim_asset_domain = $30,
-            attack_url = $31,
-            victim_url = $32,
-            attack_malware = $33,
-            attack_malware_sample = $34,
-            attack_malware_sample_family = $35,
-            attack_email_address = $36,
-            victim_email_address = $37,
-            attack_email = $38,
-            victim_email = $39,
-            attack_software = $40,
-            victim_software = $41,
-            attack_vulnerability = $42,
-            attack_certificate = $43,
-            victim_certificate = $44
+            app = $19,
+            impact_assessment = $20,
+            merge_alerts = $21,
+            threat_actor = $22,
+            org = $23,
+            attack_asset_ip = $24,
+            victim_asset_ip = $25,
+            attack_asset_ip_port = $26,
+            victim_asset_ip_port = $27,
+            attack_asset_domain = $28,
+            victim_asset_domain = $29,
+            attack_url = $30,
+            victim_url = $31,
+            attack_malware = $32,
+            attack_malware_sample = $33,
+            attack_malware_sample_family = $34,
+            attack_email_address = $35,
+            victim_email_address = $36,
+            attack_email = $37,
+            victim_email = $38,
+            attack_software = $39,
+            victim_software = $40,
+            attack_vulnerability = $41,
+            attack_certificate = $42,
+            victim_certificate = $43
         WHERE id = $1",
     )
     .bind(id)
-    .bind(&event.event_id)
+    .bind(event.event_id)
     .bind(&event.system_code)
     .bind(&event.name)
     .bind(&event.description)
     .bind(&event.event_type)
     .bind(&event.attacker)
     .bind(&event.victimer)
-    .bind(&event.start_time)
-    .bind(&event.end_time)
-    .bind(&event.found_time)
+    .bind(event.start_time)
+    .bind(event.end_time)
+    .bind(event.found_time)
     .bind(&event.source)
     .bind(&event.mitre_technique_id)
     .bind(&event.attsck_list)
     .bind(&event.attack_tool)
-    .bind(&event.first_found_time)
+    .bind(event.first_found_time)
     .bind(&event.priority)
     .bind(&event.severity)
-    .bind(&event.dispose_status)
     .bind(&event.app)
     .bind(&event.impact_assessment)
     .bind(&event.merge_alerts)
@@ -315,7 +565,7 @@ pub async fn update_threat_event(pool: &PgPool, id: Uuid, event: &ThreatEventInp
     .execute(pool)
     .await?;
 
-    Ok(())
+    Ok(result.rows_affected() > 0)
 }
 
 /// 删除威胁事件表
@@ -327,7 +577,7 @@ pub async fn drop_threat_event_table(pool: &PgPool) -> Result<()> {
 }
 
 /// 威胁事件输入结构（用于插入）
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ThreatEventInput {
     pub event_id: Option<i64>,
     pub system_code: Option<String>,
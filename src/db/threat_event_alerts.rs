@@ -0,0 +1,154 @@
+//! 威胁事件与收敛告警的关联表
+//!
+//! 记录威胁事件由哪些收敛告警组成，收敛告警再经 alert_convergence_mapping 追溯到原始告警。
+//! 关联规则生成的事件自动关联参与关联的收敛告警，分析人员也可以手工关联或解除关联。
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::alert_catalog;
+
+/// 关联来源：关联规则生成事件时自动关联
+pub const SOURCE_CORRELATION: &str = "correlation";
+/// 关联来源：手工关联
+pub const SOURCE_MANUAL: &str = "manual";
+
+/// 威胁事件关联的收敛告警
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ThreatEventAlertRecord {
+    pub threat_event_id: Uuid,
+    pub converged_alert_id: Uuid,
+    pub alert_type: i16, // 告警类型编码，与 alert_convergence_mapping 一致
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 创建威胁事件关联表
+///
+/// 首次建表时按关联规则事件的 merge_alerts 补齐已有事件的关联，需在威胁事件表和收敛告警表之后调用
+pub async fn create_threat_event_alerts_table(pool: &PgPool) -> Result<()> {
+    let (exists,): (bool,) =
+        sqlx::query_as("SELECT to_regclass('threat_event_alerts') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS threat_event_alerts (
+            threat_event_id uuid NOT NULL REFERENCES threat_events(id) ON DELETE CASCADE,
+            converged_alert_id uuid NOT NULL,
+            alert_type SMALLINT NOT NULL,
+            source VARCHAR(16) NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (threat_event_id, converged_alert_id)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_threat_event_alerts_converged
+         ON threat_event_alerts(converged_alert_id)",
+    )
+    .execute(pool)
+    .await?;
+
+    if !exists {
+        // merge_alerts 中的 alert_id 为收敛告警ID、alert_type 为告警类型名（上游事件的编号无法关联，跳过）
+        for alert_type in alert_catalog::catalog().types() {
+            sqlx::query(&format!(
+                "INSERT INTO threat_event_alerts
+                    (threat_event_id, converged_alert_id, alert_type, source, created_at)
+                 SELECT e.id, c.id, $1, $2, e.created_at
+                 FROM threat_events e
+                 CROSS JOIN LATERAL jsonb_array_elements(
+                    CASE WHEN jsonb_typeof(e.merge_alerts) = 'array'
+                         THEN e.merge_alerts ELSE '[]'::jsonb END
+                 ) m
+                 JOIN {} c ON c.id::text = m->>'alert_id'
+                 WHERE m->>'alert_type' = $3
+                 ON CONFLICT DO NOTHING",
+                alert_type.converged_table
+            ))
+            .bind(alert_type.code)
+            .bind(SOURCE_CORRELATION)
+            .bind(&alert_type.name)
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 删除威胁事件关联表
+pub async fn drop_threat_event_alerts_table(pool: &PgPool) -> Result<()> {
+    sqlx::query("DROP TABLE IF EXISTS threat_event_alerts CASCADE")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 关联一条收敛告警，已关联时返回 false；可传入事务
+pub async fn link_alert<'e, E>(
+    executor: E,
+    threat_event_id: Uuid,
+    alert_type: &str,
+    converged_alert_id: Uuid,
+    source: &str,
+) -> Result<bool>
+where
+    E: PgExecutor<'e>,
+{
+    let alert_type = alert_catalog::catalog().require(alert_type)?;
+
+    let result = sqlx::query(
+        "INSERT INTO threat_event_alerts (threat_event_id, converged_alert_id, alert_type, source)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(threat_event_id)
+    .bind(converged_alert_id)
+    .bind(alert_type.code)
+    .bind(source)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// 解除关联，未关联时返回 false
+pub async fn unlink_alert(
+    pool: &PgPool,
+    threat_event_id: Uuid,
+    converged_alert_id: Uuid,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "DELETE FROM threat_event_alerts WHERE threat_event_id = $1 AND converged_alert_id = $2",
+    )
+    .bind(threat_event_id)
+    .bind(converged_alert_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// 查询威胁事件关联的收敛告警（按关联时间先后）
+pub async fn query_event_alerts(
+    pool: &PgPool,
+    threat_event_id: Uuid,
+) -> Result<Vec<ThreatEventAlertRecord>> {
+    let records = sqlx::query_as::<_, ThreatEventAlertRecord>(
+        "SELECT * FROM threat_event_alerts
+         WHERE threat_event_id = $1
+         ORDER BY created_at, converged_alert_id",
+    )
+    .bind(threat_event_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records)
+}
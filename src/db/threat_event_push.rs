@@ -0,0 +1,240 @@
+//! 威胁事件推送日志表及相关操作
+//!
+//! 威胁事件创建、处置状态变化或手工重推时写入一条推送任务，由后台推送任务发送到 Kafka。
+//! 状态流转：pending → sending → sent；发送失败时转为 retrying 并按退避时间重试，
+//! 达到最大尝试次数后转为 failed，只能通过手工重推再次发送。
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// 触发原因：事件创建
+pub const TRIGGER_CREATED: &str = "created";
+/// 触发原因：处置状态变化
+pub const TRIGGER_STATUS_CHANGED: &str = "status_changed";
+/// 触发原因：手工重推
+pub const TRIGGER_MANUAL: &str = "manual";
+
+/// 推送状态：等待发送
+pub const STATUS_PENDING: &str = "pending";
+/// 推送状态：发送中
+pub const STATUS_SENDING: &str = "sending";
+/// 推送状态：已发送
+pub const STATUS_SENT: &str = "sent";
+/// 推送状态：发送失败，等待重试
+pub const STATUS_RETRYING: &str = "retrying";
+/// 推送状态：重试次数用尽
+pub const STATUS_FAILED: &str = "failed";
+
+/// 威胁事件推送日志记录
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct ThreatEventPushLog {
+    pub id: Uuid,
+    pub threat_event_id: Uuid,
+    pub trigger: String,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    /// 最近一次发送的消息内容
+    pub payload: Option<Value>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub pushed_at: Option<DateTime<Utc>>,
+}
+
+/// 创建威胁事件推送日志表
+pub async fn create_threat_event_push_table(pool: &PgPool) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS threat_event_push_logs (
+            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+            threat_event_id uuid NOT NULL,
+            trigger VARCHAR(32) NOT NULL,
+            status VARCHAR(16) NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            payload JSONB,
+            next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            pushed_at TIMESTAMPTZ
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_threat_event_push_logs_due
+         ON threat_event_push_logs(status, next_attempt_at)",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_threat_event_push_logs_event
+         ON threat_event_push_logs(threat_event_id)",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 删除威胁事件推送日志表
+pub async fn drop_threat_event_push_table(pool: &PgPool) -> Result<()> {
+    sqlx::query("DROP TABLE IF EXISTS threat_event_push_logs CASCADE")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 登记一条待推送任务，由后台推送任务发送
+///
+/// 可传入事务，与触发推送的修改一起提交
+pub async fn enqueue_threat_event_push<'e, E>(
+    executor: E,
+    threat_event_id: Uuid,
+    trigger: &str,
+) -> Result<Uuid>
+where
+    E: PgExecutor<'e>,
+{
+    let id: (Uuid,) = sqlx::query_as(
+        "INSERT INTO threat_event_push_logs (threat_event_id, trigger)
+         VALUES ($1, $2)
+         RETURNING id",
+    )
+    .bind(threat_event_id)
+    .bind(trigger)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(id.0)
+}
+
+/// 登记一条立即发送的推送任务（手工重推），直接处于发送中状态
+pub async fn start_threat_event_push(
+    pool: &PgPool,
+    threat_event_id: Uuid,
+    trigger: &str,
+) -> Result<ThreatEventPushLog> {
+    let log = sqlx::query_as::<_, ThreatEventPushLog>(
+        "INSERT INTO threat_event_push_logs (threat_event_id, trigger, status, attempts)
+         VALUES ($1, $2, 'sending', 1)
+         RETURNING *",
+    )
+    .bind(threat_event_id)
+    .bind(trigger)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(log)
+}
+
+/// 领取到期的推送任务并计入一次尝试
+///
+/// 发送中超过 stale_seconds 仍未结束的任务（如进程中途退出）也会被重新领取。
+pub async fn claim_due_threat_event_pushes(
+    pool: &PgPool,
+    limit: i64,
+    stale_seconds: i64,
+) -> Result<Vec<ThreatEventPushLog>> {
+    let logs = sqlx::query_as::<_, ThreatEventPushLog>(
+        "UPDATE threat_event_push_logs
+         SET status = 'sending', attempts = attempts + 1, updated_at = now()
+         WHERE id IN (
+            SELECT id FROM threat_event_push_logs
+            WHERE (status IN ('pending', 'retrying') AND next_attempt_at <= now())
+               OR (status = 'sending' AND updated_at < now() - make_interval(secs => $2))
+            ORDER BY next_attempt_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+         )
+         RETURNING *",
+    )
+    .bind(limit)
+    .bind(stale_seconds as f64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(logs)
+}
+
+/// 标记推送成功
+pub async fn mark_threat_event_push_sent(pool: &PgPool, id: Uuid, payload: &Value) -> Result<()> {
+    sqlx::query(
+        "UPDATE threat_event_push_logs
+         SET status = 'sent', payload = $2, last_error = NULL, updated_at = now(), pushed_at = now()
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(payload)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 标记推送失败：给出 retry_at 时等待重试，否则不再自动重试
+pub async fn mark_threat_event_push_failed(
+    pool: &PgPool,
+    id: Uuid,
+    error: &str,
+    retry_at: Option<DateTime<Utc>>,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE threat_event_push_logs
+         SET status = $2, last_error = $3, next_attempt_at = COALESCE($4, next_attempt_at),
+             updated_at = now()
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(if retry_at.is_some() {
+        STATUS_RETRYING
+    } else {
+        STATUS_FAILED
+    })
+    .bind(error)
+    .bind(retry_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// 分页查询推送日志，可按威胁事件和推送状态过滤
+pub async fn query_threat_event_push_logs(
+    pool: &PgPool,
+    threat_event_id: Option<Uuid>,
+    status: Option<&str>,
+    page: u64,
+    page_size: u64,
+) -> Result<(Vec<ThreatEventPushLog>, u64)> {
+    let offset = (page - 1) * page_size;
+
+    let records = sqlx::query_as::<_, ThreatEventPushLog>(
+        "SELECT * FROM threat_event_push_logs
+         WHERE ($1::uuid IS NULL OR threat_event_id = $1)
+           AND ($2::text IS NULL OR status = $2)
+         ORDER BY created_at DESC
+         LIMIT $3 OFFSET $4",
+    )
+    .bind(threat_event_id)
+    .bind(status)
+    .bind(page_size as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await?;
+
+    let total: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM threat_event_push_logs
+         WHERE ($1::uuid IS NULL OR threat_event_id = $1)
+           AND ($2::text IS NULL OR status = $2)",
+    )
+    .bind(threat_event_id)
+    .bind(status)
+    .fetch_one(pool)
+    .await?;
+
+    Ok((records, total.0 as u64))
+}
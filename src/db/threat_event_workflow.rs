@@ -0,0 +1,305 @@
+//! 威胁事件处置流程
+//!
+//! 处置状态按固定流程流转：待处置(new) → 已研判(triaged) → 处置中(in_progress) → 已处置(resolved)，
+//! 任一未结束的状态都可判定为误报(false_positive)，已处置和误报的事件可以重新打开。
+//! 每次流转连同处置人、备注和时间写入只追加的历史表 threat_event_status_history。
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::threat_event_push;
+
+/// 威胁事件处置状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisposeStatus {
+    New,
+    Triaged,
+    InProgress,
+    Resolved,
+    FalsePositive,
+}
+
+impl DisposeStatus {
+    pub const ALL: [DisposeStatus; 5] = [
+        DisposeStatus::New,
+        DisposeStatus::Triaged,
+        DisposeStatus::InProgress,
+        DisposeStatus::Resolved,
+        DisposeStatus::FalsePositive,
+    ];
+
+    /// 数据库中保存的状态编码
+    pub fn code(self) -> &'static str {
+        match self {
+            DisposeStatus::New => "new",
+            DisposeStatus::Triaged => "triaged",
+            DisposeStatus::InProgress => "in_progress",
+            DisposeStatus::Resolved => "resolved",
+            DisposeStatus::FalsePositive => "false_positive",
+        }
+    }
+
+    /// 中文名称，推送给下游时使用
+    pub fn label(self) -> &'static str {
+        match self {
+            DisposeStatus::New => "待处置",
+            DisposeStatus::Triaged => "已研判",
+            DisposeStatus::InProgress => "处置中",
+            DisposeStatus::Resolved => "已处置",
+            DisposeStatus::FalsePositive => "误报",
+        }
+    }
+
+    /// 解析状态编码或中文名称（含早期的 未审核/已审核 写法）
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        Self::ALL
+            .into_iter()
+            .find(|s| s.code() == value || s.label() == value)
+            .or(match value {
+                "未审核" => Some(DisposeStatus::New),
+                "已审核" => Some(DisposeStatus::Triaged),
+                _ => None,
+            })
+    }
+
+    /// 当前状态允许流转到的状态
+    pub fn next(self) -> &'static [DisposeStatus] {
+        use DisposeStatus::*;
+        match self {
+            New => &[Triaged, FalsePositive],
+            Triaged => &[InProgress, FalsePositive],
+            InProgress => &[Resolved, FalsePositive],
+            // 重新打开
+            Resolved => &[InProgress],
+            FalsePositive => &[Triaged],
+        }
+    }
+
+    pub fn can_transition_to(self, to: DisposeStatus) -> bool {
+        self.next().contains(&to)
+    }
+}
+
+/// 处置状态流转记录
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StatusHistoryRecord {
+    pub id: Uuid,
+    pub threat_event_id: Uuid,
+    pub from_status: String,
+    pub to_status: String,
+    pub assignee: Option<String>,
+    pub comment: Option<String>,
+    /// 执行流转的操作人
+    pub operator: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 处置状态流转请求
+#[derive(Clone, Debug, Deserialize)]
+pub struct TransitionInput {
+    pub to: DisposeStatus,
+    /// 流转后的处置人，未提供时沿用当前处置人
+    pub assignee: Option<String>,
+    pub comment: Option<String>,
+    pub operator: Option<String>,
+}
+
+/// 处置状态流转结果
+#[derive(Debug)]
+pub enum TransitionOutcome {
+    Applied(StatusHistoryRecord),
+    NotFound,
+    /// 当前状态不允许流转到目标状态
+    Rejected(DisposeStatus),
+}
+
+/// 创建处置历史表，并把处置状态规范为状态编码
+///
+/// 需在 threat_events 表创建之后调用
+pub async fn create_threat_event_workflow_tables(pool: &PgPool) -> Result<()> {
+    sqlx::query(
+        "ALTER TABLE threat_events
+            ADD COLUMN IF NOT EXISTS assignee VARCHAR(64),
+            ADD COLUMN IF NOT EXISTS status_updated_at TIMESTAMPTZ",
+    )
+    .execute(pool)
+    .await?;
+
+    // 早期数据的处置状态为自由文本，按中文名称映射，无法识别的视为待处置
+    let mut mapping = String::new();
+    for status in DisposeStatus::ALL {
+        mapping.push_str(&format!(
+            " WHEN '{}' THEN '{}' WHEN '{}' THEN '{}'",
+            status.code(),
+            status.code(),
+            status.label(),
+            status.code()
+        ));
+    }
+    sqlx::query(&format!(
+        "UPDATE threat_events SET dispose_status = CASE dispose_status{mapping}
+            WHEN '未审核' THEN 'new' WHEN '已审核' THEN 'triaged' ELSE 'new' END
+         WHERE dispose_status IS NULL OR dispose_status NOT IN ({})",
+        status_list()
+    ))
+    .execute(pool)
+    .await?;
+
+    sqlx::query(&format!(
+        "ALTER TABLE threat_events
+            ALTER COLUMN dispose_status SET DEFAULT 'new',
+            ALTER COLUMN dispose_status SET NOT NULL,
+            DROP CONSTRAINT IF EXISTS chk_threat_events_dispose_status,
+            ADD CONSTRAINT chk_threat_events_dispose_status CHECK (dispose_status IN ({}))",
+        status_list()
+    ))
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS threat_event_status_history (
+            id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+            threat_event_id uuid NOT NULL,
+            from_status VARCHAR(32) NOT NULL,
+            to_status VARCHAR(32) NOT NULL,
+            assignee VARCHAR(64),
+            comment TEXT,
+            operator VARCHAR(64),
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_threat_event_status_history_event
+         ON threat_event_status_history(threat_event_id, created_at)",
+    )
+    .execute(pool)
+    .await?;
+
+    // 历史记录只允许追加
+    sqlx::query(
+        "CREATE OR REPLACE FUNCTION forbid_threat_event_history_change() RETURNS trigger AS $$
+         BEGIN
+            RAISE EXCEPTION 'threat_event_status_history is append-only';
+         END
+         $$ LANGUAGE plpgsql",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "DROP TRIGGER IF EXISTS trg_threat_event_status_history_append_only
+         ON threat_event_status_history",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TRIGGER trg_threat_event_status_history_append_only
+         BEFORE UPDATE OR DELETE ON threat_event_status_history
+         FOR EACH ROW EXECUTE FUNCTION forbid_threat_event_history_change()",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 删除处置历史表
+pub async fn drop_threat_event_workflow_tables(pool: &PgPool) -> Result<()> {
+    sqlx::query("DROP TABLE IF EXISTS threat_event_status_history CASCADE")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+fn status_list() -> String {
+    DisposeStatus::ALL
+        .iter()
+        .map(|s| format!("'{}'", s.code()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 流转威胁事件的处置状态
+///
+/// 状态更新、历史记录和推送登记在同一事务中完成
+pub async fn transition_threat_event(
+    pool: &PgPool,
+    id: Uuid,
+    input: &TransitionInput,
+) -> Result<TransitionOutcome> {
+    let mut tx = pool.begin().await?;
+
+    let current: Option<(String, Option<String>)> = sqlx::query_as(
+        "SELECT dispose_status, assignee FROM threat_events WHERE id = $1 FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some((current, current_assignee)) = current else {
+        return Ok(TransitionOutcome::NotFound);
+    };
+    let from = DisposeStatus::parse(&current).unwrap_or(DisposeStatus::New);
+    if !from.can_transition_to(input.to) {
+        return Ok(TransitionOutcome::Rejected(from));
+    }
+
+    let assignee = input.assignee.clone().or(current_assignee);
+    sqlx::query(
+        "UPDATE threat_events
+         SET dispose_status = $2, assignee = $3, status_updated_at = now()
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(input.to.code())
+    .bind(&assignee)
+    .execute(&mut *tx)
+    .await?;
+
+    let record = sqlx::query_as::<_, StatusHistoryRecord>(
+        "INSERT INTO threat_event_status_history
+            (threat_event_id, from_status, to_status, assignee, comment, operator)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(from.code())
+    .bind(input.to.code())
+    .bind(&assignee)
+    .bind(&input.comment)
+    .bind(&input.operator)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    threat_event_push::enqueue_threat_event_push(
+        &mut *tx,
+        id,
+        threat_event_push::TRIGGER_STATUS_CHANGED,
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(TransitionOutcome::Applied(record))
+}
+
+/// 查询威胁事件的处置历史（按时间先后）
+pub async fn query_status_history(pool: &PgPool, id: Uuid) -> Result<Vec<StatusHistoryRecord>> {
+    let records = sqlx::query_as::<_, StatusHistoryRecord>(
+        "SELECT * FROM threat_event_status_history
+         WHERE threat_event_id = $1
+         ORDER BY created_at, id",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records)
+}
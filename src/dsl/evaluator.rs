@@ -0,0 +1,149 @@
+use serde_json::Value as JsonValue;
+
+use super::types::{FieldRef, PathSegment};
+
+/// 取告警字段值，缺失字段返回 None
+pub fn lookup_field<'a>(alert: &'a JsonValue, field_name: &str) -> Option<&'a JsonValue> {
+    match alert.get(field_name) {
+        Some(JsonValue::Null) | None => None,
+        Some(v) => Some(v),
+    }
+}
+
+/// 按字段路径取出全部候选值
+///
+/// `[*]` 展开数组全部元素（对象则取全部成员值），对数组取成员时作用于每个元素，
+/// 末端的数组展开为元素；null 与缺失一样不产生候选值
+pub fn resolve_path<'a>(alert: &'a JsonValue, field: &FieldRef) -> Vec<&'a JsonValue> {
+    let mut values: Vec<&JsonValue> = lookup_field(alert, &field.field_name).into_iter().collect();
+    for segment in &field.path {
+        let mut next = Vec::new();
+        for value in values {
+            match (segment, value) {
+                (PathSegment::Key(key), JsonValue::Object(map)) => next.extend(map.get(key)),
+                (PathSegment::Key(key), JsonValue::Array(items)) => {
+                    next.extend(items.iter().filter_map(|item| item.get(key)))
+                }
+                (PathSegment::Index(index), JsonValue::Array(items)) => {
+                    next.extend(items.get(*index))
+                }
+                (PathSegment::Each, JsonValue::Array(items)) => next.extend(items),
+                (PathSegment::Each, JsonValue::Object(map)) => next.extend(map.values()),
+                _ => {}
+            }
+        }
+        values = next;
+    }
+
+    values
+        .into_iter()
+        .flat_map(|value| match value {
+            JsonValue::Array(items) => items.iter().collect(),
+            value => vec![value],
+        })
+        .filter(|value| !value.is_null())
+        .collect()
+}
+
+/// 取字段引用的值：无路径时等同 [`lookup_field`]，有路径时取第一个候选值
+pub fn lookup_ref<'a>(alert: &'a JsonValue, field: &FieldRef) -> Option<&'a JsonValue> {
+    if field.path.is_empty() {
+        lookup_field(alert, &field.field_name)
+    } else {
+        resolve_path(alert, field).into_iter().next()
+    }
+}
+
+/// 将告警字段值转换为字符串形式，用于分组键和字符串比较
+pub fn json_to_string(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        JsonValue::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::types::Condition;
+    use crate::dsl::{parse_converge_rule, Predicate};
+    use serde_json::json;
+
+    fn evaluate_condition(condition: &Condition, alert: &serde_json::Value) -> bool {
+        Predicate::compile(condition).matches(alert)
+    }
+
+    fn condition_of(dsl_where: &str) -> Condition {
+        let input = format!(
+            "CONVERGE WHERE {} GROUP BY src_ip WINDOW 5m THRESHOLD 1",
+            dsl_where
+        );
+        parse_converge_rule(&input).unwrap().condition
+    }
+
+    #[test]
+    fn test_evaluate_numeric_and_string() {
+        let alert = json!({ "alarm_severity": 3, "src_ip": "10.0.0.1", "protocol": "TCP" });
+
+        assert!(evaluate_condition(
+            &condition_of("alarm_severity >= 3"),
+            &alert
+        ));
+        assert!(!evaluate_condition(
+            &condition_of("alarm_severity > 3"),
+            &alert
+        ));
+        assert!(evaluate_condition(
+            &condition_of(r#"src_ip CONTAINS "10.0" AND protocol == "TCP""#),
+            &alert
+        ));
+        assert!(evaluate_condition(
+            &condition_of(r#"protocol == "UDP" OR alarm_severity IN (1, 3)"#),
+            &alert
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_not_and_precedence() {
+        let alert = json!({ "src_ip": "10.0.0.1", "dst_ip": "8.8.8.8", "protocol": "TCP" });
+
+        let cond = condition_of(
+            r#"(src_ip IN ("1.1.1.1", "10.0.0.1") OR dst_ip IN ("9.9.9.9")) AND NOT protocol == "ICMP""#,
+        );
+        assert!(evaluate_condition(&cond, &alert));
+        assert!(!evaluate_condition(
+            &cond,
+            &json!({ "src_ip": "10.0.0.1", "protocol": "ICMP" })
+        ));
+
+        // AND 优先于 OR：true OR (false AND false)
+        assert!(evaluate_condition(
+            &condition_of(r#"protocol == "TCP" OR protocol == "UDP" AND src_ip == "x""#),
+            &alert
+        ));
+        // 括号改变结合顺序：(true OR false) AND false
+        assert!(!evaluate_condition(
+            &condition_of(r#"(protocol == "TCP" OR protocol == "UDP") AND src_ip == "x""#),
+            &alert
+        ));
+        assert!(evaluate_condition(
+            &condition_of(r#"NOT NOT protocol == "TCP""#),
+            &alert
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_missing_field() {
+        let alert = json!({ "alarm_severity": 2, "apt_group": null });
+
+        assert!(!evaluate_condition(
+            &condition_of(r#"apt_group != """#),
+            &alert
+        ));
+        assert!(!evaluate_condition(&condition_of("dst_port > 0"), &alert));
+        assert!(!evaluate_condition(
+            &condition_of(r#"alarm_name REGEX "^$""#),
+            &alert
+        ));
+    }
+}
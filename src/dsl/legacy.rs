@@ -0,0 +1,109 @@
+//! 旧版过滤规则、标签规则的单条件（字段 / 操作符 / 值）到 DSL 条件表达式的转换
+
+use regex::Regex;
+
+use super::validator::{self, FieldType};
+
+/// 将旧版单条件转换为 DSL 条件表达式
+///
+/// 数值字段且值为整数时生成数值比较，其余按字符串比较；字段名不合法或操作符未知时返回 None
+/// 与旧版一致，字段缺失时任何操作符都不匹配
+pub fn legacy_condition(
+    alert_type: &str,
+    field: &str,
+    operator: &str,
+    value: &str,
+) -> Option<String> {
+    let field = field.trim();
+    // 允许字段路径（如 data.http.host、procedure_technique_id[*]），合法性由解析器检查
+    let mut chars = field.chars();
+    let valid_field = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']' | '*'));
+    if !valid_field {
+        return None;
+    }
+
+    let numeric = matches!(
+        validator::field_type(alert_type, field),
+        Some(FieldType::Integer { .. } | FieldType::IntegerList)
+    );
+    let literal = match value.trim().parse::<i64>() {
+        Ok(n) if numeric => n.to_string(),
+        _ => quote(value),
+    };
+
+    let condition = match operator {
+        "eq" => format!("{} == {}", field, literal),
+        "ne" => format!("{} != {}", field, literal),
+        "contains" => format!("{} CONTAINS {}", field, quote(value)),
+        // 旧版规则在字段缺失时不匹配，NOT 需要显式要求字段有值
+        "not_contains" => format!(
+            "{} != \"\" AND NOT {} CONTAINS {}",
+            field,
+            field,
+            quote(value)
+        ),
+        "regex" => format!("{} REGEX {}", field, quote(value)),
+        _ => return None,
+    };
+    Some(condition)
+}
+
+/// 旧版条件是否匹配取值为 null 的字段
+///
+/// 旧版规则把 null 当作空字符串比较，转换后 null 与缺失字段一样不匹配任何条件，
+/// 迁移时据此提示行为变化
+pub fn legacy_matches_null(operator: &str, value: &str) -> bool {
+    match operator {
+        "eq" | "contains" => value.is_empty(),
+        "ne" | "not_contains" => !value.is_empty(),
+        "regex" => Regex::new(value).is_ok_and(|re| re.is_match("")),
+        _ => false,
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::{parser::parse_condition_expr, Predicate};
+    use serde_json::json;
+
+    #[test]
+    fn test_legacy_condition_round_trip() {
+        assert_eq!(
+            legacy_condition("network_attack", "alarm_severity", "eq", "1").as_deref(),
+            Some("alarm_severity == 1")
+        );
+        assert_eq!(
+            legacy_condition("network_attack", "src_ip", "gt", "1"),
+            None
+        );
+
+        // 与旧版一致：字段缺失时任何操作符都不匹配
+        for operator in ["eq", "ne", "contains", "not_contains", "regex"] {
+            let source = legacy_condition("network_attack", "apt_group", operator, "x").unwrap();
+            let predicate = Predicate::compile(&parse_condition_expr(&source).unwrap());
+            assert!(!predicate.matches(&json!({})), "{}", source);
+            assert!(
+                !predicate.matches(&json!({ "apt_group": null })),
+                "{}",
+                source
+            );
+        }
+        let source = legacy_condition("network_attack", "apt_group", "not_contains", "x").unwrap();
+        let predicate = Predicate::compile(&parse_condition_expr(&source).unwrap());
+        assert!(predicate.matches(&json!({ "apt_group": "APT28" })));
+        assert!(!predicate.matches(&json!({ "apt_group": "x-group" })));
+        assert!(legacy_matches_null("not_contains", "x"));
+        assert!(!legacy_matches_null("eq", "x"));
+
+        let source =
+            legacy_condition("host_behavior", "dst_process_cli", "regex", r#"\d+ "x""#).unwrap();
+        let predicate = Predicate::compile(&parse_condition_expr(&source).unwrap());
+        assert!(predicate.matches(&json!({ "dst_process_cli": r#"run 42 "x""# })));
+    }
+}
@@ -1,6 +1,10 @@
+pub mod evaluator;
+pub mod legacy;
 pub mod parser;
+pub mod predicate;
 pub mod types;
 pub mod validator;
 
 pub use parser::{parse_converge_rule, parse_correlate_rule};
+pub use predicate::Predicate;
 pub use validator::validate_fields;
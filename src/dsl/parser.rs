@@ -1,4 +1,4 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use pest::Parser;
 use pest_derive::Parser;
 
@@ -9,8 +9,7 @@ use super::types::*;
 pub struct DslParser;
 
 pub fn parse_converge_rule(input: &str) -> Result<ConvergeRule> {
-    let pairs =
-        DslParser::parse(Rule::converge_rule, input).map_err(|e| anyhow!("解析错误: {}", e))?;
+    let pairs = DslParser::parse(Rule::converge_rule, input).map_err(syntax_error)?;
 
     let mut condition = None;
     let mut group_by = Vec::new();
@@ -18,35 +17,33 @@ pub fn parse_converge_rule(input: &str) -> Result<ConvergeRule> {
     let mut threshold = None;
 
     for pair in pairs {
-        match pair.as_rule() {
-            Rule::converge_rule => {
-                for inner_pair in pair.into_inner() {
-                    match inner_pair.as_rule() {
-                        Rule::converge_where => {
-                            condition = Some(parse_condition(inner_pair)?);
-                        }
-                        Rule::converge_group_by => {
-                            for field_pair in inner_pair.into_inner() {
-                                if field_pair.as_rule() == Rule::identifier {
-                                    group_by.push(field_pair.as_str().to_string());
-                                }
+        if pair.as_rule() == Rule::converge_rule {
+            for inner_pair in pair.into_inner() {
+                match inner_pair.as_rule() {
+                    Rule::converge_where => {
+                        condition = Some(parse_condition(inner_pair)?);
+                    }
+                    Rule::converge_group_by => {
+                        for field_pair in inner_pair.into_inner() {
+                            if field_pair.as_rule() == Rule::identifier {
+                                group_by
+                                    .push(FieldRef::new(field_pair.as_str(), span_of(&field_pair)));
                             }
                         }
-                        Rule::converge_window => {
-                            window = Some(parse_time_window(inner_pair)?);
-                        }
-                        Rule::converge_threshold => {
-                            for num_pair in inner_pair.into_inner() {
-                                if num_pair.as_rule() == Rule::number {
-                                    threshold = Some(num_pair.as_str().parse()?);
-                                }
+                    }
+                    Rule::converge_window => {
+                        window = Some(parse_time_window(inner_pair)?);
+                    }
+                    Rule::converge_threshold => {
+                        for num_pair in inner_pair.into_inner() {
+                            if num_pair.as_rule() == Rule::number {
+                                threshold = Some(num_pair.as_str().parse()?);
                             }
                         }
-                        _ => {}
                     }
+                    _ => {}
                 }
             }
-            _ => {}
         }
     }
 
@@ -58,9 +55,22 @@ pub fn parse_converge_rule(input: &str) -> Result<ConvergeRule> {
     })
 }
 
+/// 解析独立的条件表达式（可带 WHERE 前缀），用于过滤规则和标签规则
+pub fn parse_condition_expr(input: &str) -> Result<Condition> {
+    let pair = DslParser::parse(Rule::condition_rule, input)
+        .map_err(syntax_error)?
+        .next()
+        .ok_or_else(|| anyhow!("缺少条件"))?;
+
+    let condition = pair
+        .into_inner()
+        .find(|p| p.as_rule() == Rule::condition)
+        .ok_or_else(|| anyhow!("缺少条件"))?;
+    parse_condition(condition)
+}
+
 pub fn parse_correlate_rule(input: &str) -> Result<CorrelateRule> {
-    let pairs =
-        DslParser::parse(Rule::correlate_rule, input).map_err(|e| anyhow!("解析错误: {}", e))?;
+    let pairs = DslParser::parse(Rule::correlate_rule, input).map_err(syntax_error)?;
 
     let mut events = Vec::new();
     let mut join_on = None;
@@ -68,27 +78,24 @@ pub fn parse_correlate_rule(input: &str) -> Result<CorrelateRule> {
     let mut generate = None;
 
     for pair in pairs {
-        match pair.as_rule() {
-            Rule::correlate_rule => {
-                for inner_pair in pair.into_inner() {
-                    match inner_pair.as_rule() {
-                        Rule::event_def => {
-                            events.push(parse_event_definition(inner_pair)?);
-                        }
-                        Rule::join_on => {
-                            join_on = Some(parse_join_condition(inner_pair)?);
-                        }
-                        Rule::correlate_window => {
-                            window = Some(parse_time_window(inner_pair)?);
-                        }
-                        Rule::generate_block => {
-                            generate = Some(parse_generate_block(inner_pair)?);
-                        }
-                        _ => {}
+        if pair.as_rule() == Rule::correlate_rule {
+            for inner_pair in pair.into_inner() {
+                match inner_pair.as_rule() {
+                    Rule::event_def => {
+                        events.push(parse_event_definition(inner_pair)?);
+                    }
+                    Rule::join_on => {
+                        join_on = Some(parse_join_condition(inner_pair)?);
                     }
+                    Rule::correlate_window => {
+                        window = Some(parse_time_window(inner_pair)?);
+                    }
+                    Rule::generate_block => {
+                        generate = Some(parse_generate_block(inner_pair)?);
+                    }
+                    _ => {}
                 }
             }
-            _ => {}
         }
     }
 
@@ -96,6 +103,16 @@ pub fn parse_correlate_rule(input: &str) -> Result<CorrelateRule> {
         return Err(anyhow!("关联规则至少需要2个事件定义"));
     }
 
+    // 事件条件中以事件别名开头的字段引用，首段是别名而不是字段
+    let aliases: Vec<String> = events.iter().map(|e| e.alias.clone()).collect();
+    for event in &mut events {
+        event.condition.for_each_clause_mut(&mut |clause| {
+            if aliases.contains(&clause.field.field_name) {
+                split_alias(&mut clause.field);
+            }
+        });
+    }
+
     Ok(CorrelateRule {
         events,
         join_on: join_on.ok_or_else(|| anyhow!("缺少 JOIN ON 子句"))?,
@@ -104,41 +121,81 @@ pub fn parse_correlate_rule(input: &str) -> Result<CorrelateRule> {
     })
 }
 
-fn parse_condition(pair: pest::iterators::Pair<Rule>) -> Result<Condition> {
-    let mut clauses = Vec::new();
-    let mut current_logical_op = None;
+/// 将 pest 语法错误转换为带位置的 DSL 错误
+fn syntax_error(e: pest::error::Error<Rule>) -> anyhow::Error {
+    let span = match e.location {
+        pest::error::InputLocation::Pos(pos) => Span::new(pos, pos),
+        pest::error::InputLocation::Span((start, end)) => Span::new(start, end),
+    };
+    DslError::at(
+        "syntax_error",
+        span,
+        format!("解析错误: {}", e.variant.message()),
+    )
+    .into()
+}
 
-    for inner_pair in pair.into_inner() {
-        match inner_pair.as_rule() {
-            Rule::condition => {
-                return parse_condition(inner_pair);
-            }
-            Rule::simple_condition => {
-                let clause = parse_simple_condition(inner_pair)?;
-                let mut clause = clause;
-                clause.logical_op = current_logical_op.take();
-                clauses.push(clause);
-            }
-            Rule::and_op => {
-                current_logical_op = Some(LogicalOp::And);
-            }
-            Rule::or_op => {
-                current_logical_op = Some(LogicalOp::Or);
+fn span_of(pair: &pest::iterators::Pair<Rule>) -> Span {
+    let span = pair.as_span();
+    Span::new(span.start(), span.end())
+}
+
+fn parse_condition(pair: pest::iterators::Pair<Rule>) -> Result<Condition> {
+    match pair.as_rule() {
+        Rule::or_expr => parse_logical_expr(pair, Condition::Or),
+        Rule::and_expr => parse_logical_expr(pair, Condition::And),
+        Rule::not_expr => {
+            let mut inner = pair.into_inner();
+            let first = inner.next().ok_or_else(|| anyhow!("缺少条件"))?;
+            if first.as_rule() == Rule::not_op {
+                let operand = inner.next().ok_or_else(|| anyhow!("NOT 后缺少条件"))?;
+                Ok(Condition::Not(Box::new(parse_condition(operand)?)))
+            } else {
+                parse_condition(first)
             }
-            _ => {}
+        }
+        Rule::simple_condition => Ok(Condition::Clause(parse_simple_condition(pair)?)),
+        // converge_where / condition / primary_condition 只包装一个子表达式
+        _ => {
+            let inner = pair
+                .into_inner()
+                .next()
+                .ok_or_else(|| anyhow!("缺少条件"))?;
+            parse_condition(inner)
         }
     }
+}
 
-    Ok(Condition { clauses })
+/// 解析由同一逻辑操作符连接的操作数，只有一个操作数时不产生额外层级
+fn parse_logical_expr(
+    pair: pest::iterators::Pair<Rule>,
+    combine: fn(Vec<Condition>) -> Condition,
+) -> Result<Condition> {
+    let mut operands = pair
+        .into_inner()
+        .filter(|p| !matches!(p.as_rule(), Rule::and_op | Rule::or_op))
+        .map(parse_condition)
+        .collect::<Result<Vec<_>>>()?;
+
+    if operands.len() == 1 {
+        Ok(operands.remove(0))
+    } else {
+        Ok(combine(operands))
+    }
 }
 
 fn parse_simple_condition(pair: pest::iterators::Pair<Rule>) -> Result<ConditionClause> {
+    let mut quantifier = Quantifier::Any;
     let mut field = None;
     let mut operator = None;
     let mut value = None;
+    let mut value_span = Span::default();
 
     for inner_pair in pair.into_inner() {
         match inner_pair.as_rule() {
+            Rule::quantifier if inner_pair.as_str() == "ALL" => {
+                quantifier = Quantifier::All;
+            }
             Rule::field_ref => {
                 field = Some(parse_field_ref(inner_pair)?);
             }
@@ -146,87 +203,127 @@ fn parse_simple_condition(pair: pest::iterators::Pair<Rule>) -> Result<Condition
                 operator = Some(parse_comparison_op(inner_pair)?);
             }
             Rule::value => {
+                value_span = span_of(&inner_pair);
                 value = Some(parse_value(inner_pair)?);
             }
             Rule::value_list => {
+                value_span = span_of(&inner_pair);
                 value = Some(parse_value_list(inner_pair)?);
             }
             _ => {}
         }
     }
 
+    let operator = operator.ok_or_else(|| anyhow!("缺少操作符"))?;
+    // != 本身表示没有任何值等于给定值，与 ALL 组合没有明确含义
+    if quantifier == Quantifier::All && matches!(operator, ComparisonOp::NotEqual) {
+        bail!("ALL 不能与 != 一起使用，x != y 已表示所有值都不等于 y");
+    }
+
     Ok(ConditionClause {
+        quantifier,
         field: field.ok_or_else(|| anyhow!("缺少字段"))?,
-        operator: operator.ok_or_else(|| anyhow!("缺少操作符"))?,
+        operator,
         value: value.ok_or_else(|| anyhow!("缺少值"))?,
-        logical_op: None,
+        value_span,
     })
 }
 
 fn parse_field_ref(pair: pest::iterators::Pair<Rule>) -> Result<FieldRef> {
-    let parts: Vec<_> = pair.into_inner().collect();
-
-    if parts.len() == 2 {
-        Ok(FieldRef {
-            event_alias: Some(parts[0].as_str().to_string()),
-            field_name: parts[1].as_str().to_string(),
-        })
-    } else if parts.len() == 1 {
-        Ok(FieldRef {
-            event_alias: None,
-            field_name: parts[0].as_str().to_string(),
-        })
-    } else {
-        Err(anyhow!("字段引用格式错误"))
+    let span = span_of(&pair);
+    let mut parts = pair.into_inner();
+    let name = parts.next().ok_or_else(|| anyhow!("字段引用格式错误"))?;
+
+    let mut field = FieldRef::new(name.as_str(), span);
+    for segment in parts {
+        let inner = segment
+            .into_inner()
+            .next()
+            .ok_or_else(|| anyhow!("字段路径格式错误"))?;
+        field.path.push(match inner.as_rule() {
+            Rule::identifier => PathSegment::Key(inner.as_str().to_string()),
+            _ if inner.as_str() == "*" => PathSegment::Each,
+            _ => PathSegment::Index(inner.as_str().parse()?),
+        });
+    }
+    Ok(field)
+}
+
+/// 将 `alias.field...` 的首段作为事件别名，其余部分作为字段路径
+fn split_alias(field: &mut FieldRef) {
+    if let Some(PathSegment::Key(_)) = field.path.first() {
+        let PathSegment::Key(name) = field.path.remove(0) else {
+            return;
+        };
+        field.event_alias = Some(std::mem::replace(&mut field.field_name, name));
     }
 }
 
 fn parse_comparison_op(pair: pest::iterators::Pair<Rule>) -> Result<ComparisonOp> {
-    for inner_pair in pair.into_inner() {
-        return Ok(match inner_pair.as_rule() {
-            Rule::eq_op => ComparisonOp::Equal,
-            Rule::ne_op => ComparisonOp::NotEqual,
-            Rule::gt_op => {
-                if inner_pair.as_str() == ">=" {
-                    ComparisonOp::GreaterThanOrEqual
-                } else {
-                    ComparisonOp::GreaterThan
-                }
+    let inner_pair = pair
+        .into_inner()
+        .next()
+        .ok_or_else(|| anyhow!("缺少操作符"))?;
+    Ok(match inner_pair.as_rule() {
+        Rule::eq_op => ComparisonOp::Equal,
+        Rule::ne_op => ComparisonOp::NotEqual,
+        Rule::gt_op => {
+            if inner_pair.as_str() == ">=" {
+                ComparisonOp::GreaterThanOrEqual
+            } else {
+                ComparisonOp::GreaterThan
             }
-            Rule::lt_op => {
-                if inner_pair.as_str() == "<=" {
-                    ComparisonOp::LessThanOrEqual
-                } else {
-                    ComparisonOp::LessThan
-                }
+        }
+        Rule::lt_op => {
+            if inner_pair.as_str() == "<=" {
+                ComparisonOp::LessThanOrEqual
+            } else {
+                ComparisonOp::LessThan
             }
-            Rule::contains_op => ComparisonOp::Contains,
-            Rule::regex_op => ComparisonOp::Regex,
-            Rule::in_op => ComparisonOp::In,
-            _ => return Err(anyhow!("未知的比较操作符")),
-        });
-    }
-    Err(anyhow!("缺少操作符"))
+        }
+        Rule::contains_op => ComparisonOp::Contains,
+        Rule::regex_op => ComparisonOp::Regex,
+        Rule::in_op => ComparisonOp::In,
+        _ => return Err(anyhow!("未知的比较操作符")),
+    })
 }
 
 fn parse_value(pair: pest::iterators::Pair<Rule>) -> Result<Value> {
-    for inner_pair in pair.into_inner() {
-        return Ok(match inner_pair.as_rule() {
-            Rule::number => Value::Number(inner_pair.as_str().parse()?),
-            Rule::string => {
-                let s = inner_pair
-                    .into_inner()
-                    .next()
-                    .ok_or_else(|| anyhow!("字符串解析错误"))?
-                    .as_str()
-                    .to_string();
-                Value::String(s)
+    let inner_pair = pair.into_inner().next().ok_or_else(|| anyhow!("缺少值"))?;
+    Ok(match inner_pair.as_rule() {
+        Rule::number => Value::Number(inner_pair.as_str().parse()?),
+        Rule::string => {
+            let s = inner_pair
+                .into_inner()
+                .next()
+                .ok_or_else(|| anyhow!("字符串解析错误"))?
+                .as_str();
+            Value::String(unescape_string(s))
+        }
+        Rule::identifier => Value::String(inner_pair.as_str().to_string()),
+        _ => return Err(anyhow!("未知的值类型")),
+    })
+}
+
+/// 还原字符串字面量中的 \" 和 \\，其余反斜杠序列原样保留（如正则中的 \d）
+fn unescape_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some(next @ ('"' | '\\')) => out.push(next),
+                Some(next) => {
+                    out.push(c);
+                    out.push(next);
+                }
+                None => out.push(c),
             }
-            Rule::identifier => Value::String(inner_pair.as_str().to_string()),
-            _ => return Err(anyhow!("未知的值类型")),
-        });
+        } else {
+            out.push(c);
+        }
     }
-    Err(anyhow!("缺少值"))
+    out
 }
 
 fn parse_value_list(pair: pest::iterators::Pair<Rule>) -> Result<Value> {
@@ -305,13 +402,15 @@ fn parse_join_condition(pair: pest::iterators::Pair<Rule>) -> Result<JoinConditi
             Rule::field_ref => {
                 // 找到第一个字段引用
                 if i + 2 < inner_pairs.len() {
-                    let left = parse_field_ref(inner_pairs[i].clone())?;
+                    let mut left = parse_field_ref(inner_pairs[i].clone())?;
+                    split_alias(&mut left);
 
                     // 跳过 eq_op (i+1)
                     if inner_pairs[i + 1].as_rule() == Rule::eq_op {
                         // 获取右侧字段 (i+2)
                         if inner_pairs[i + 2].as_rule() == Rule::field_ref {
-                            let right = parse_field_ref(inner_pairs[i + 2].clone())?;
+                            let mut right = parse_field_ref(inner_pairs[i + 2].clone())?;
+                            split_alias(&mut right);
 
                             clauses.push(JoinClause {
                                 left,
@@ -0,0 +1,305 @@
+use regex::Regex;
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+
+use super::evaluator::{lookup_field, resolve_path};
+use super::types::*;
+
+/// 预编译的条件表达式，规则加载时编译一次，之后对每条告警直接求值
+///
+/// 正则、数值字面量和 IN 集合都在编译阶段准备好。字段语义统一如下：
+/// - 缺失字段与 null 等价，不匹配任何比较（包括 `== ""`、`REGEX "^$"` 和 `!=`）
+/// - 数组字段（含 `[*]` 路径展开的值）默认任一元素满足即匹配，`ALL` 要求至少一个值且全部满足
+/// - `!=` 表示字段有值且没有任何值等于给定值，不能与 `ALL` 组合
+/// - 数值比较时字符串字段会尝试按数字解析，布尔值视为 0 / 1
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Test {
+        field: FieldRef,
+        quantifier: Quantifier,
+        test: Test,
+    },
+    Not(Box<Node>),
+    And(Vec<Node>),
+    Or(Vec<Node>),
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    /// 字段存在且不为 null，用于 != 排除缺失字段
+    Present,
+    /// == / IN，!= 由外层 Not 表示
+    OneOf(ValueSet),
+    Compare {
+        rhs: Option<f64>,
+        op: fn(f64, f64) -> bool,
+    },
+    Contains(Option<String>),
+    Regex(Option<Regex>),
+}
+
+/// 等值比较的候选集合，数值与字符串分开存放
+#[derive(Debug, Clone, Default)]
+struct ValueSet {
+    numbers: HashSet<i64>,
+    texts: HashSet<String>,
+}
+
+impl ValueSet {
+    fn insert(&mut self, literal: &Value) {
+        match literal {
+            Value::Number(n) => {
+                self.numbers.insert(*n);
+            }
+            Value::String(s) => {
+                self.texts.insert(s.clone());
+            }
+            Value::List(items) => items.iter().for_each(|item| self.insert(item)),
+        }
+    }
+
+    fn matches(&self, field: Option<&JsonValue>) -> bool {
+        (!self.numbers.is_empty()
+            && any_number(field, |n| {
+                n.fract() == 0.0
+                    && n >= i64::MIN as f64
+                    && n <= i64::MAX as f64
+                    && self.numbers.contains(&(n as i64))
+            }))
+            || (!self.texts.is_empty() && any_string(field, |s| self.texts.contains(s)))
+    }
+}
+
+impl Predicate {
+    /// 编译条件表达式；非法正则编译为永不匹配（入库前已由校验器拦截）
+    pub fn compile(condition: &Condition) -> Self {
+        Self {
+            root: compile_node(condition),
+        }
+    }
+
+    /// 对告警 JSON 求值
+    pub fn matches(&self, alert: &JsonValue) -> bool {
+        self.root.matches(alert)
+    }
+}
+
+impl Node {
+    fn matches(&self, alert: &JsonValue) -> bool {
+        match self {
+            Node::Test {
+                field,
+                quantifier,
+                test,
+            } => {
+                if field.path.is_empty() && *quantifier == Quantifier::Any {
+                    return test.matches(lookup_field(alert, &field.field_name));
+                }
+                let values = resolve_path(alert, field);
+                match quantifier {
+                    Quantifier::Any if values.is_empty() => test.matches(None),
+                    Quantifier::Any => values.iter().any(|v| test.matches(Some(v))),
+                    Quantifier::All => {
+                        !values.is_empty() && values.iter().all(|v| test.matches(Some(v)))
+                    }
+                }
+            }
+            Node::Not(inner) => !inner.matches(alert),
+            Node::And(items) => items.iter().all(|item| item.matches(alert)),
+            Node::Or(items) => items.iter().any(|item| item.matches(alert)),
+        }
+    }
+}
+
+impl Test {
+    fn matches(&self, field: Option<&JsonValue>) -> bool {
+        match self {
+            Test::Present => match field {
+                Some(JsonValue::Array(items)) => items.iter().any(|v| !v.is_null()),
+                Some(v) => !v.is_null(),
+                None => false,
+            },
+            Test::OneOf(set) => set.matches(field),
+            Test::Compare { rhs, op } => {
+                rhs.is_some_and(|rhs| any_number(field, |lhs| op(lhs, rhs)))
+            }
+            Test::Contains(needle) => needle
+                .as_deref()
+                .is_some_and(|needle| any_string(field, |s| s.contains(needle))),
+            Test::Regex(re) => re
+                .as_ref()
+                .is_some_and(|re| any_string(field, |s| re.is_match(s))),
+        }
+    }
+}
+
+fn compile_node(condition: &Condition) -> Node {
+    match condition {
+        Condition::Clause(clause) => compile_clause(clause),
+        Condition::Not(inner) => Node::Not(Box::new(compile_node(inner))),
+        Condition::And(items) => Node::And(items.iter().map(compile_node).collect()),
+        Condition::Or(items) => Node::Or(items.iter().map(compile_node).collect()),
+    }
+}
+
+fn compile_clause(clause: &ConditionClause) -> Node {
+    let one_of = |literal: &Value| {
+        let mut set = ValueSet::default();
+        set.insert(literal);
+        Test::OneOf(set)
+    };
+    let compare = |op: fn(f64, f64) -> bool| Test::Compare {
+        rhs: match &clause.value {
+            Value::Number(n) => Some(*n as f64),
+            Value::String(s) => s.trim().parse::<f64>().ok(),
+            Value::List(_) => None,
+        },
+        op,
+    };
+
+    let test = match &clause.operator {
+        ComparisonOp::Equal | ComparisonOp::NotEqual | ComparisonOp::In => one_of(&clause.value),
+        ComparisonOp::GreaterThan => compare(|a, b| a > b),
+        ComparisonOp::LessThan => compare(|a, b| a < b),
+        ComparisonOp::GreaterThanOrEqual => compare(|a, b| a >= b),
+        ComparisonOp::LessThanOrEqual => compare(|a, b| a <= b),
+        ComparisonOp::Contains => Test::Contains(match &clause.value {
+            Value::List(_) => None,
+            v => Some(literal_to_string(v)),
+        }),
+        ComparisonOp::Regex => Test::Regex(Regex::new(&literal_to_string(&clause.value)).ok()),
+    };
+
+    match clause.operator {
+        ComparisonOp::NotEqual => Node::And(vec![
+            Node::Test {
+                field: clause.field.clone(),
+                quantifier: Quantifier::Any,
+                test: Test::Present,
+            },
+            Node::Not(Box::new(Node::Test {
+                field: clause.field.clone(),
+                quantifier: Quantifier::Any,
+                test,
+            })),
+        ]),
+        _ => Node::Test {
+            field: clause.field.clone(),
+            quantifier: clause.quantifier,
+            test,
+        },
+    }
+}
+
+fn literal_to_string(value: &Value) -> String {
+    match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::List(items) => items
+            .iter()
+            .map(literal_to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+    }
+}
+
+/// 字段的字符串候选值中是否有满足条件的：数组取每个元素，缺失字段与 null 不匹配
+fn any_string(field: Option<&JsonValue>, mut f: impl FnMut(&str) -> bool) -> bool {
+    let mut test = |v: &JsonValue| match v {
+        JsonValue::String(s) => f(s),
+        JsonValue::Null => false,
+        other => f(&other.to_string()),
+    };
+
+    match field {
+        None => false,
+        Some(JsonValue::Array(items)) => items.iter().any(&mut test),
+        Some(v) => test(v),
+    }
+}
+
+/// 字段的数值候选值中是否有满足条件的，缺失字段不匹配
+fn any_number(field: Option<&JsonValue>, mut f: impl FnMut(f64) -> bool) -> bool {
+    let mut test = |v: &JsonValue| {
+        let n = match v {
+            JsonValue::Number(n) => n.as_f64(),
+            JsonValue::String(s) => s.trim().parse::<f64>().ok(),
+            JsonValue::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            _ => None,
+        };
+        n.is_some_and(&mut f)
+    };
+
+    match field {
+        None => false,
+        Some(JsonValue::Array(items)) => items.iter().any(&mut test),
+        Some(v) => test(v),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::parser::parse_condition_expr;
+    use serde_json::json;
+
+    fn predicate(source: &str) -> Predicate {
+        Predicate::compile(&parse_condition_expr(source).unwrap())
+    }
+
+    #[test]
+    fn test_predicate_null_and_type_semantics() {
+        let alert = json!({
+            "alarm_severity": "3",
+            "apt_group": null,
+            "dst_port": [22, 443],
+            "protocol": "TCP"
+        });
+
+        // 字符串形式的数字参与数值比较，数组任一元素命中即可
+        assert!(predicate("alarm_severity IN (2, 3)").matches(&alert));
+        assert!(predicate("alarm_severity >= 3 AND dst_port == 443").matches(&alert));
+        assert!(!predicate("dst_port > 1000").matches(&alert));
+
+        // null 与缺失字段一致：不匹配任何比较
+        for field in ["apt_group", "missing_field"] {
+            assert!(!predicate(&format!(r#"{} == """#, field)).matches(&alert));
+            assert!(!predicate(&format!(r#"{} REGEX "^$""#, field)).matches(&alert));
+            assert!(!predicate(&format!(r#"{} != "x""#, field)).matches(&alert));
+            assert!(!predicate(&format!("{} < 100", field)).matches(&alert));
+        }
+        assert!(predicate(r#"protocol != "UDP""#).matches(&alert));
+
+        assert!(
+            predicate(r#"protocol IN ("UDP", "TCP") AND NOT protocol REGEX "^U""#).matches(&alert)
+        );
+    }
+
+    #[test]
+    fn test_predicate_paths_and_quantifiers() {
+        let alert = json!({
+            "data": { "http": { "host": "evil.example" }, "dns": [{ "qname": "a.cn" }, { "qname": "b.io" }] },
+            "procedure_technique_id": ["T1059", "T1105"]
+        });
+
+        assert!(predicate(r#"data.http.host CONTAINS "evil""#).matches(&alert));
+        assert!(predicate(r#"data.dns[*].qname REGEX "\.io$""#).matches(&alert));
+        assert!(predicate(r#"data.dns[0].qname == "a.cn""#).matches(&alert));
+        assert!(!predicate(r#"ALL data.dns[*].qname REGEX "\.io$""#).matches(&alert));
+
+        assert!(predicate(r#"ALL procedure_technique_id[*] REGEX "^T1""#).matches(&alert));
+        assert!(predicate(r#"procedure_technique_id == "T1105""#).matches(&alert));
+        assert!(!predicate(r#"ALL procedure_technique_id == "T1105""#).matches(&alert));
+        // != 表示没有任何值等于给定值，不能与 ALL 组合
+        assert!(!predicate(r#"procedure_technique_id != "T1105""#).matches(&alert));
+        assert!(predicate(r#"procedure_technique_id != "T1003""#).matches(&alert));
+        assert!(parse_condition_expr(r#"ALL procedure_technique_id != "T1105""#).is_err());
+        // ALL 对不存在的路径不成立
+        assert!(!predicate(r#"ALL data.tls[*].sni == "x""#).matches(&alert));
+    }
+}
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConvergeRule {
     pub condition: Condition,
-    pub group_by: Vec<String>,
+    pub group_by: Vec<FieldRef>,
     pub window: TimeWindow,
     pub threshold: u32,
 }
@@ -22,23 +22,259 @@ pub struct EventDefinition {
     pub condition: Condition,
 }
 
+/// 条件表达式树，优先级为 NOT > AND > OR，括号可改变结合顺序
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Condition {
-    pub clauses: Vec<ConditionClause>,
+pub enum Condition {
+    Clause(ConditionClause),
+    Not(Box<Condition>),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+}
+
+impl Condition {
+    /// 按出现顺序收集表达式中的所有比较子句
+    pub fn clauses(&self) -> Vec<&ConditionClause> {
+        let mut clauses = Vec::new();
+        self.collect_clauses(&mut clauses);
+        clauses
+    }
+
+    /// 依次修改表达式中的所有比较子句
+    pub fn for_each_clause_mut(&mut self, f: &mut impl FnMut(&mut ConditionClause)) {
+        match self {
+            Condition::Clause(clause) => f(clause),
+            Condition::Not(inner) => inner.for_each_clause_mut(f),
+            Condition::And(items) | Condition::Or(items) => {
+                for item in items {
+                    item.for_each_clause_mut(f);
+                }
+            }
+        }
+    }
+
+    fn collect_clauses<'a>(&'a self, clauses: &mut Vec<&'a ConditionClause>) {
+        match self {
+            Condition::Clause(clause) => clauses.push(clause),
+            Condition::Not(inner) => inner.collect_clauses(clauses),
+            Condition::And(items) | Condition::Or(items) => {
+                for item in items {
+                    item.collect_clauses(clauses);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConditionClause {
+    #[serde(default)]
+    pub quantifier: Quantifier,
     pub field: FieldRef,
     pub operator: ComparisonOp,
     pub value: Value,
-    pub logical_op: Option<LogicalOp>,
+    #[serde(default)]
+    pub value_span: Span,
+}
+
+/// 字段路径取到多个值（数组元素）时的匹配方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quantifier {
+    /// 任一值满足即可（默认）
+    #[default]
+    Any,
+    /// 至少有一个值且全部满足
+    All,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FieldRef {
     pub event_alias: Option<String>,
     pub field_name: String,
+    /// 字段内的嵌套路径，如 `data.http.host` 中的 `http.host`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<PathSegment>,
+    #[serde(default)]
+    pub span: Span,
+}
+
+impl FieldRef {
+    pub fn new(field_name: impl Into<String>, span: Span) -> Self {
+        Self {
+            event_alias: None,
+            field_name: field_name.into(),
+            path: Vec::new(),
+            span,
+        }
+    }
+
+    /// 不含事件别名的字段路径文本，如 `procedure_technique_id[*]`
+    pub fn path_text(&self) -> String {
+        let mut text = self.field_name.clone();
+        for segment in &self.path {
+            match segment {
+                PathSegment::Key(key) => {
+                    text.push('.');
+                    text.push_str(key);
+                }
+                PathSegment::Index(index) => text.push_str(&format!("[{}]", index)),
+                PathSegment::Each => text.push_str("[*]"),
+            }
+        }
+        text
+    }
+}
+
+/// 字段路径中的一段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathSegment {
+    /// 对象成员 `.key`
+    Key(String),
+    /// 数组下标 `[n]`
+    Index(usize),
+    /// 数组全部元素 `[*]`
+    Each,
+}
+
+/// 源码中的字节区间 [start, end)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// 计算区间起点的行号和列号（从 1 开始，列按字符计）
+    pub fn line_col(&self, input: &str) -> (usize, usize) {
+        let prefix = &input[..self.start.min(input.len())];
+        let line = prefix.matches('\n').count() + 1;
+        let column = prefix
+            .rsplit('\n')
+            .next()
+            .map(|l| l.chars().count())
+            .unwrap_or(0)
+            + 1;
+        (line, column)
+    }
+}
+
+/// 带源码位置的 DSL 错误，可通过 anyhow 的 downcast_ref 取回位置
+#[derive(Debug, Clone)]
+pub struct DslError {
+    pub severity: DiagnosticSeverity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Option<Span>,
+    pub suggestion: Option<String>,
+    pub replacement: Option<String>,
+}
+
+impl DslError {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Error,
+            code,
+            message: message.into(),
+            span: None,
+            suggestion: None,
+            replacement: None,
+        }
+    }
+
+    pub fn at(code: &'static str, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span: Some(span),
+            ..Self::new(code, message)
+        }
+    }
+
+    pub fn warning(mut self) -> Self {
+        self.severity = DiagnosticSeverity::Warning;
+        self
+    }
+
+    pub fn suggest(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    /// 建议的修复：用 replacement 替换 span 对应的源码
+    pub fn replace_with(mut self, replacement: impl Into<String>) -> Self {
+        self.replacement = Some(replacement.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == DiagnosticSeverity::Error
+    }
+}
+
+impl std::fmt::Display for DslError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "（{}）", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DslError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+/// 编译诊断信息，行列号从 1 开始，便于编辑器标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: DiagnosticSeverity,
+    pub code: String,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub span: Span,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+impl Diagnostic {
+    /// 由 DSL 错误生成诊断，无位置信息的错误定位到规则开头
+    pub fn from_error(err: &DslError, input: &str) -> Self {
+        let span = err.span.unwrap_or_default();
+        let (line, column) = span.line_col(input);
+        let (end_line, end_column) = Span::new(span.end, span.end).line_col(input);
+        Self {
+            severity: err.severity,
+            code: err.code.to_string(),
+            message: err.message.clone(),
+            line,
+            column,
+            end_line,
+            end_column,
+            span,
+            suggestion: err.suggestion.clone(),
+            replacement: err.replacement.clone(),
+        }
+    }
+
+    /// 由任意编译错误生成诊断，非 DslError 的错误归为 semantic_error
+    pub fn from_anyhow(err: &anyhow::Error, input: &str) -> Self {
+        match err.downcast_ref::<DslError>() {
+            Some(dsl_err) => Self::from_error(dsl_err, input),
+            None => Self::from_error(&DslError::new("semantic_error", err.to_string()), input),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +328,18 @@ pub enum TimeUnit {
     Days,
 }
 
+impl TimeWindow {
+    /// 转换为 chrono 时长
+    pub fn to_duration(&self) -> chrono::Duration {
+        let value = self.value as i64;
+        match self.unit {
+            TimeUnit::Minutes => chrono::Duration::minutes(value),
+            TimeUnit::Hours => chrono::Duration::hours(value),
+            TimeUnit::Days => chrono::Duration::days(value),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenerateBlock {
     pub severity: u8,
@@ -111,22 +359,35 @@ pub struct CompileResponse {
     pub message: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+    /// 编译成功时返回解析得到的语法树
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ast: Option<serde_json::Value>,
 }
 
 impl CompileResponse {
-    pub fn success(message: String) -> Self {
+    pub fn success(
+        message: String,
+        ast: Option<serde_json::Value>,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Self {
         Self {
             success: true,
             message: Some(message),
             error: None,
+            diagnostics,
+            ast,
         }
     }
 
-    pub fn error(error: String) -> Self {
+    pub fn error(error: String, diagnostics: Vec<Diagnostic>) -> Self {
         Self {
             success: false,
             message: None,
             error: Some(error),
+            diagnostics,
+            ast: None,
         }
     }
 }
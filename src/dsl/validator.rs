@@ -1,185 +1,792 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use lazy_static::lazy_static;
-use std::collections::HashSet;
-use std::fs;
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
+use super::parser::parse_condition_expr;
 use super::types::*;
+use crate::alert_catalog;
 
-lazy_static! {
-    static ref VALID_FIELDS: HashSet<String> = load_valid_fields();
-}
-
-fn load_valid_fields() -> HashSet<String> {
-    let mut fields = HashSet::new();
-
-    // 尝试从 alert_fields.toml 读取字段定义
-    if let Ok(content) = fs::read_to_string("alert_fields.toml") {
-        if let Ok(toml_value) = content.parse::<toml::Value>() {
-            if let Some(table) = toml_value.as_table() {
-                for (_section_name, section_value) in table {
-                    if let Some(section_table) = section_value.as_table() {
-                        for (field_name, _) in section_table {
-                            fields.insert(field_name.clone());
-                        }
-                    }
-                }
-            }
+/// 字段的取值类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    StringList,
+    IntegerList,
+    /// JSON 扩展数据，可用嵌套路径访问，不做类型检查
+    Json,
+    /// 类型未知，不做类型检查
+    Any,
+}
+
+impl FieldType {
+    pub fn from_decl(decl: &str) -> Self {
+        match decl {
+            "String" => FieldType::String,
+            "u8" => FieldType::Integer { min: 0, max: 255 },
+            "u16" => FieldType::Integer { min: 0, max: 65535 },
+            "u32" => FieldType::Integer {
+                min: 0,
+                max: u32::MAX as i64,
+            },
+            "u64" => FieldType::Integer {
+                min: 0,
+                max: i64::MAX,
+            },
+            "i8" => FieldType::Integer {
+                min: i8::MIN as i64,
+                max: i8::MAX as i64,
+            },
+            "i16" => FieldType::Integer {
+                min: i16::MIN as i64,
+                max: i16::MAX as i64,
+            },
+            "i32" => FieldType::Integer {
+                min: i32::MIN as i64,
+                max: i32::MAX as i64,
+            },
+            "i64" => FieldType::Integer {
+                min: i64::MIN,
+                max: i64::MAX,
+            },
+            "Vec<String>" => FieldType::StringList,
+            "Vec<u8>" | "Vec<u16>" | "Vec<i64>" => FieldType::IntegerList,
+            "Json" => FieldType::Json,
+            _ => FieldType::Any,
         }
     }
 
-    // 如果文件不存在或解析失败，添加一些默认字段
-    if fields.is_empty() {
-        // 通用字段
-        fields.insert("alarm_id".to_string());
-        fields.insert("alarm_date".to_string());
-        fields.insert("alarm_severity".to_string());
-        fields.insert("alarm_name".to_string());
-        fields.insert("alarm_description".to_string());
-        fields.insert("alarm_type".to_string());
-        fields.insert("alarm_subtype".to_string());
-        fields.insert("source".to_string());
+    fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            FieldType::Integer { .. } | FieldType::IntegerList | FieldType::Json | FieldType::Any
+        )
+    }
 
-        // 网络字段
-        fields.insert("src_ip".to_string());
-        fields.insert("src_port".to_string());
-        fields.insert("dst_ip".to_string());
-        fields.insert("dst_port".to_string());
-        fields.insert("protocol".to_string());
-        fields.insert("session_id".to_string());
-        fields.insert("ip_version".to_string());
+    fn is_textual(&self) -> bool {
+        matches!(
+            self,
+            FieldType::String | FieldType::StringList | FieldType::Json | FieldType::Any
+        )
+    }
 
-        // 主机字段
-        fields.insert("host_name".to_string());
-        fields.insert("terminal_ip".to_string());
-        fields.insert("user_account".to_string());
-        fields.insert("terminal_os".to_string());
-        fields.insert("terminal_id".to_string());
-        fields.insert("dst_process_path".to_string());
-        fields.insert("dst_process_md5".to_string());
-        fields.insert("dst_process_cli".to_string());
-        fields.insert("src_process_path".to_string());
-        fields.insert("src_process_md5".to_string());
-        fields.insert("src_process_cli".to_string());
-        fields.insert("file_name".to_string());
-        fields.insert("file_md5".to_string());
-        fields.insert("file_path".to_string());
-        fields.insert("register_key_name".to_string());
-        fields.insert("register_key_value".to_string());
-        fields.insert("register_path".to_string());
+    fn name(&self) -> &'static str {
+        match self {
+            FieldType::String => "字符串",
+            FieldType::Integer { .. } => "数值",
+            FieldType::StringList => "字符串列表",
+            FieldType::IntegerList => "数值列表",
+            FieldType::Json => "JSON",
+            FieldType::Any => "任意",
+        }
+    }
+}
 
-        // 样本字段
-        fields.insert("md5".to_string());
-        fields.insert("sha1".to_string());
-        fields.insert("sha256".to_string());
-        fields.insert("sha512".to_string());
-        fields.insert("ssdeep".to_string());
-        fields.insert("sample_family".to_string());
-        fields.insert("apt_group".to_string());
-        fields.insert("file_type".to_string());
-        fields.insert("file_size".to_string());
-        fields.insert("sample_source".to_string());
-        fields.insert("sample_original_name".to_string());
-        fields.insert("sample_description".to_string());
-        fields.insert("target_platform".to_string());
-        fields.insert("language".to_string());
-        fields.insert("rule".to_string());
-        fields.insert("target_content".to_string());
-        fields.insert("compile_date".to_string());
-        fields.insert("last_analy_date".to_string());
-        fields.insert("sample_alarm_detail".to_string());
+/// 各告警类型的字段及其类型
+pub struct FieldCatalog {
+    types: HashMap<&'static str, HashMap<String, FieldType>>,
+}
 
-        // 网络攻击特有字段
-        fields.insert("signature_id".to_string());
-        fields.insert("attack_payload".to_string());
-        fields.insert("attack_stage".to_string());
-        fields.insert("attack_ip".to_string());
-        fields.insert("attacked_ip".to_string());
-        fields.insert("vul_type".to_string());
-        fields.insert("cve_id".to_string());
-        fields.insert("vul_desc".to_string());
+impl FieldCatalog {
+    /// 字段在目标告警类型中的类型；不属于任何目标类型时返回 None
+    fn field_type(&self, field: &str, alert_types: &BTreeSet<&'static str>) -> Option<FieldType> {
+        alert_types
+            .iter()
+            .filter_map(|t| self.types.get(t).and_then(|fields| fields.get(field)))
+            .copied()
+            .next()
+    }
 
+    fn is_known(&self, field: &str) -> bool {
+        self.types.values().any(|fields| fields.contains_key(field))
+    }
+
+    /// 目标告警类型中的全部字段名
+    fn field_names<'a>(
+        &'a self,
+        alert_types: &'a BTreeSet<&'static str>,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        alert_types
+            .iter()
+            .filter_map(|t| self.types.get(t))
+            .flat_map(|fields| fields.keys().map(String::as_str))
+    }
+}
+
+lazy_static! {
+    static ref FIELD_CATALOG: FieldCatalog = load_field_catalog();
+}
+
+fn load_field_catalog() -> FieldCatalog {
+    let mut types: HashMap<&'static str, HashMap<String, FieldType>> = HashMap::new();
+
+    for alert_type in alert_catalog::catalog().types() {
+        let fields: HashMap<String, FieldType> = if alert_type.fields.is_empty() {
+            // 没有字段声明的告警类型使用默认字段且不做类型检查
+            default_fields()
+                .into_iter()
+                .map(|f| (f.to_string(), FieldType::Any))
+                .collect()
+        } else {
+            alert_type
+                .fields
+                .iter()
+                .map(|field| (field.name.clone(), field.value_type()))
+                .collect()
+        };
+        types.insert(alert_type.name.as_str(), fields);
+    }
+
+    FieldCatalog { types }
+}
+
+fn default_fields() -> Vec<&'static str> {
+    vec![
+        // 通用字段
+        "alarm_id",
+        "alarm_date",
+        "alarm_severity",
+        "alarm_name",
+        "alarm_description",
+        "alarm_type",
+        "alarm_subtype",
+        "source",
+        // 网络字段
+        "src_ip",
+        "src_port",
+        "dst_ip",
+        "dst_port",
+        "protocol",
+        "session_id",
+        "ip_version",
+        // 主机字段
+        "host_name",
+        "terminal_ip",
+        "user_account",
+        "terminal_os",
+        "terminal_id",
+        "dst_process_path",
+        "dst_process_md5",
+        "dst_process_cli",
+        "src_process_path",
+        "src_process_md5",
+        "src_process_cli",
+        "file_name",
+        "file_md5",
+        "file_path",
+        "register_key_name",
+        "register_key_value",
+        "register_path",
+        // 样本字段
+        "md5",
+        "sha1",
+        "sha256",
+        "sha512",
+        "ssdeep",
+        "sample_family",
+        "apt_group",
+        "file_type",
+        "file_size",
+        "sample_source",
+        "sample_original_name",
+        "sample_description",
+        "target_platform",
+        "language",
+        "rule",
+        "target_content",
+        "compile_date",
+        "last_analy_date",
+        "sample_alarm_detail",
+        // 网络攻击特有字段
+        "signature_id",
+        "attack_payload",
+        "attack_stage",
+        "attack_ip",
+        "attacked_ip",
+        "vul_type",
+        "cve_id",
+        "vul_desc",
         // 其他
-        fields.insert("control_rule_id".to_string());
-        fields.insert("control_task_id".to_string());
-        fields.insert("procedure_technique_id".to_string());
-        fields.insert("source_file_path".to_string());
-        fields.insert("data".to_string());
+        "control_rule_id",
+        "control_task_id",
+        "procedure_technique_id",
+        "source_file_path",
+        "data",
+    ]
+}
+
+fn all_alert_types() -> BTreeSet<&'static str> {
+    alert_catalog::catalog()
+        .types()
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect()
+}
+
+/// 推断条件适用的告警类型
+///
+/// 依据 `alarm_type == N` / `alarm_type IN (...)` 子句收窄范围：AND 取交集，OR 取并集，
+/// 其余子句和 NOT 不限制告警类型
+pub fn target_alert_types(
+    condition: &Condition,
+) -> std::result::Result<BTreeSet<&'static str>, DslError> {
+    match condition {
+        Condition::Clause(clause)
+            if clause.field.field_name == "alarm_type" && clause.field.path.is_empty() =>
+        {
+            let codes = match (&clause.operator, &clause.value) {
+                (ComparisonOp::Equal, v) => vec![v],
+                (ComparisonOp::In, Value::List(items)) => items.iter().collect(),
+                (ComparisonOp::In, v) => vec![v],
+                _ => return Ok(all_alert_types()),
+            };
+
+            let mut types = BTreeSet::new();
+            for code in codes {
+                let number = match code {
+                    Value::Number(n) => Some(*n),
+                    Value::String(s) => s.trim().parse::<i64>().ok(),
+                    Value::List(_) => None,
+                };
+                let alert_type = number
+                    .and_then(|n| alert_catalog::catalog().by_code(n))
+                    .ok_or_else(|| {
+                        DslError::at(
+                            "unknown_alarm_type",
+                            clause.value_span,
+                            format!("未知的告警类型编码: {}", literal_text(code)),
+                        )
+                        .suggest(alarm_type_hint())
+                    })?;
+                types.insert(alert_type.name.as_str());
+            }
+            Ok(types)
+        }
+        Condition::Clause(_) | Condition::Not(_) => Ok(all_alert_types()),
+        Condition::And(items) => {
+            let mut types = all_alert_types();
+            for item in items {
+                let item_types = target_alert_types(item)?;
+                types = types.intersection(&item_types).copied().collect();
+            }
+            Ok(types)
+        }
+        Condition::Or(items) => {
+            let mut types = BTreeSet::new();
+            for item in items {
+                types.extend(target_alert_types(item)?);
+            }
+            Ok(types)
+        }
     }
+}
 
-    fields
+/// alarm_type 的可选取值说明，如 "alarm_type 取值为 1（网络攻击告警）、2（恶意样本告警）"
+fn alarm_type_hint() -> String {
+    let codes: Vec<String> = alert_catalog::catalog()
+        .types()
+        .iter()
+        .map(|t| format!("{}（{}）", t.code, t.display_name))
+        .collect();
+    format!("alarm_type 取值为 {}", codes.join("、"))
 }
 
 pub fn validate_fields(rule: &ConvergeRule) -> Result<()> {
+    first_error(check_converge_rule(rule))
+}
+
+pub fn validate_correlate_fields(rule: &CorrelateRule) -> Result<()> {
+    first_error(check_correlate_rule(rule))
+}
+
+/// 校验收敛规则，返回全部错误和警告
+pub fn check_converge_rule(rule: &ConvergeRule) -> Vec<DslError> {
+    let mut diagnostics = Vec::new();
+    let alert_types = condition_alert_types(&rule.condition, &mut diagnostics);
+
     // 验证条件中的字段
-    validate_condition_fields(&rule.condition)?;
+    check_condition(&rule.condition, &alert_types, &mut diagnostics);
 
     // 验证分组字段
+    let mut seen = HashSet::new();
     for field in &rule.group_by {
-        if !VALID_FIELDS.contains(field) {
-            return Err(anyhow!("未知字段: {}", field));
+        if let Err(e) = resolve_field_type(field, &alert_types) {
+            diagnostics.push(e);
+        }
+        if !seen.insert(field.field_name.as_str()) {
+            diagnostics.push(
+                DslError::at(
+                    "duplicate_group_by",
+                    field.span,
+                    format!("分组字段 {} 重复", field.field_name),
+                )
+                .warning()
+                .suggest("删除重复的分组字段"),
+            );
         }
     }
 
-    Ok(())
+    diagnostics
 }
 
-pub fn validate_correlate_fields(rule: &CorrelateRule) -> Result<()> {
-    // 收集所有事件别名
-    let mut event_aliases = HashSet::new();
+/// 校验关联规则，返回全部错误和警告
+pub fn check_correlate_rule(rule: &CorrelateRule) -> Vec<DslError> {
+    let mut diagnostics = Vec::new();
+
+    // 收集所有事件别名及各事件适用的告警类型
+    let mut event_types: HashMap<&str, BTreeSet<&'static str>> = HashMap::new();
     for event in &rule.events {
-        event_aliases.insert(event.alias.clone());
-        validate_condition_fields(&event.condition)?;
+        let alert_types = condition_alert_types(&event.condition, &mut diagnostics);
+        check_condition(&event.condition, &alert_types, &mut diagnostics);
+        event_types.insert(event.alias.as_str(), alert_types);
     }
 
-    // 验证 JOIN ON 条件中的字段引用
+    // 验证 JOIN ON 条件中的字段引用，未写别名时指向第一个事件
+    let default_alias = rule.events.first().map(|e| e.alias.as_str());
     for clause in &rule.join_on.clauses {
-        validate_field_ref(&clause.left, &event_aliases)?;
-        validate_field_ref(&clause.right, &event_aliases)?;
+        for field in [&clause.left, &clause.right] {
+            let alias = field.event_alias.as_deref().or(default_alias);
+            let Some(alert_types) = alias.and_then(|a| event_types.get(a)) else {
+                let alias = alias.unwrap_or_default();
+                let mut err = DslError::at(
+                    "unknown_event_alias",
+                    field.span,
+                    format!("未定义的事件别名: {}", alias),
+                );
+                if let Some(similar) = most_similar(alias, event_types.keys().copied()) {
+                    err = err
+                        .suggest(format!("是否应为 `{}`？", similar))
+                        .replace_with(format!("{}.{}", similar, field.path_text()));
+                }
+                diagnostics.push(err);
+                continue;
+            };
+            if let Err(e) = resolve_field_type(field, alert_types) {
+                diagnostics.push(e);
+            }
+        }
     }
 
     // 验证生成块中的严重程度
-    if rule.generate.severity > 4 {
-        return Err(anyhow!(
-            "SEVERITY 值必须在 1-4 之间，当前值: {}",
-            rule.generate.severity
+    if !(1..=4).contains(&rule.generate.severity) {
+        diagnostics.push(DslError::new(
+            "invalid_severity",
+            format!(
+                "SEVERITY 值必须在 1-4 之间，当前值: {}",
+                rule.generate.severity
+            ),
         ));
     }
 
-    Ok(())
+    diagnostics
 }
 
-fn validate_condition_fields(condition: &Condition) -> Result<()> {
-    for clause in &condition.clauses {
-        validate_field_ref(&clause.field, &HashSet::new())?;
+/// 校验过滤规则、标签规则使用的独立条件，字段按规则所属的告警类型检查
+pub fn check_condition_for(condition: &Condition, alert_type: &str) -> Vec<DslError> {
+    let mut diagnostics = Vec::new();
+    let mut alert_types = condition_alert_types(condition, &mut diagnostics);
+
+    if let Some(rule_type) = alert_catalog::catalog().get(alert_type) {
+        let rule_type = rule_type.name.as_str();
+        if alert_types.contains(rule_type) {
+            alert_types = BTreeSet::from([rule_type]);
+        } else {
+            diagnostics.push(DslError::new(
+                "contradictory_alarm_type",
+                format!("条件中的 alarm_type 与规则的告警类型 {} 不一致", alert_type),
+            ));
+        }
     }
-    Ok(())
+
+    check_condition(condition, &alert_types, &mut diagnostics);
+    diagnostics
+}
+
+/// 解析并校验独立条件，返回第一个错误
+pub fn compile_condition(source: &str, alert_type: &str) -> Result<Condition> {
+    let condition = parse_condition_expr(source)?;
+    first_error(check_condition_for(&condition, alert_type))?;
+    Ok(condition)
+}
+
+/// 字段在指定告警类型中声明的类型
+pub fn field_type(alert_type: &str, field: &str) -> Option<FieldType> {
+    FIELD_CATALOG
+        .types
+        .get(alert_type)
+        .and_then(|fields| fields.get(field))
+        .copied()
 }
 
-fn validate_field_ref(field_ref: &FieldRef, event_aliases: &HashSet<String>) -> Result<()> {
-    // 如果有事件别名，验证别名是否存在
-    if let Some(alias) = &field_ref.event_alias {
-        if !event_aliases.is_empty() && !event_aliases.contains(alias) {
-            return Err(anyhow!("未定义的事件别名: {}", alias));
+fn first_error(diagnostics: Vec<DslError>) -> Result<()> {
+    match diagnostics.into_iter().find(DslError::is_error) {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+/// 推断条件适用的告警类型，无法推断或互相矛盾时记录错误并按所有类型继续校验
+fn condition_alert_types(
+    condition: &Condition,
+    diagnostics: &mut Vec<DslError>,
+) -> BTreeSet<&'static str> {
+    match target_alert_types(condition) {
+        Ok(alert_types) if alert_types.is_empty() => {
+            let span = condition
+                .clauses()
+                .into_iter()
+                .find(|c| c.field.field_name == "alarm_type")
+                .map(|c| c.field.span);
+            diagnostics.push(DslError {
+                span,
+                ..DslError::new(
+                    "contradictory_alarm_type",
+                    "条件中的 alarm_type 约束互相矛盾，规则不会匹配任何告警",
+                )
+            });
+            all_alert_types()
         }
+        Ok(alert_types) => alert_types,
+        Err(e) => {
+            diagnostics.push(e);
+            all_alert_types()
+        }
+    }
+}
+
+fn check_condition(
+    condition: &Condition,
+    alert_types: &BTreeSet<&'static str>,
+    diagnostics: &mut Vec<DslError>,
+) {
+    for clause in condition.clauses() {
+        let result = resolve_field_type(&clause.field, alert_types)
+            .and_then(|field_type| validate_clause_types(clause, field_type));
+        if let Err(e) = result {
+            diagnostics.push(e);
+        }
+    }
+}
+
+/// 查找字段在目标告警类型中的类型
+fn resolve_field_type(
+    field_ref: &FieldRef,
+    alert_types: &BTreeSet<&'static str>,
+) -> std::result::Result<FieldType, DslError> {
+    let name = &field_ref.field_name;
+    if let Some(field_type) = FIELD_CATALOG.field_type(name, alert_types) {
+        return path_type(field_ref, field_type);
     }
 
-    // 验证字段名是否有效
-    if !VALID_FIELDS.contains(&field_ref.field_name) {
-        return Err(anyhow!("未知字段: {}", field_ref.field_name));
+    let target_types = alert_types.iter().copied().collect::<Vec<_>>().join(", ");
+    if FIELD_CATALOG.is_known(name) {
+        return Err(DslError::at(
+            "field_not_in_alert_type",
+            field_ref.span,
+            format!("字段 {} 不属于目标告警类型: {}", name, target_types),
+        )
+        .suggest("调整 alarm_type 条件或改用目标告警类型中的字段"));
+    }
+
+    let mut err = DslError::at(
+        "unknown_field",
+        field_ref.span,
+        format!("未知字段: {}", name),
+    );
+    if let Some(similar) = most_similar(name, FIELD_CATALOG.field_names(alert_types)) {
+        let path = FieldRef {
+            field_name: similar.to_string(),
+            ..field_ref.clone()
+        }
+        .path_text();
+        let replacement = match &field_ref.event_alias {
+            Some(alias) => format!("{}.{}", alias, path),
+            None => path,
+        };
+        err = err
+            .suggest(format!("是否应为 `{}`？", similar))
+            .replace_with(replacement);
     }
+    Err(err)
+}
+
+/// 字段路径取到的值的类型：JSON 字段可访问任意嵌套路径，列表字段只能取元素
+fn path_type(
+    field_ref: &FieldRef,
+    field_type: FieldType,
+) -> std::result::Result<FieldType, DslError> {
+    match (field_type, field_ref.path.as_slice()) {
+        (_, []) => Ok(field_type),
+        (FieldType::Json | FieldType::Any, _) => Ok(FieldType::Any),
+        (FieldType::StringList, [PathSegment::Each | PathSegment::Index(_)]) => {
+            Ok(FieldType::String)
+        }
+        (FieldType::IntegerList, [PathSegment::Each | PathSegment::Index(_)]) => {
+            Ok(FieldType::Integer {
+                min: i64::MIN,
+                max: i64::MAX,
+            })
+        }
+        _ => Err(DslError::at(
+            "invalid_path",
+            field_ref.span,
+            format!(
+                "字段 {} 为{}类型，不支持路径 {}",
+                field_ref.field_name,
+                field_type.name(),
+                field_ref.path_text()
+            ),
+        )
+        .suggest("只有 JSON 字段支持嵌套路径，列表字段可使用 [*] 或 [n] 取元素")),
+    }
+}
+
+/// 按字段类型检查操作符和字面量
+fn validate_clause_types(
+    clause: &ConditionClause,
+    field_type: FieldType,
+) -> std::result::Result<(), DslError> {
+    let field = &clause.field.path_text();
+    let value_error =
+        |code: &'static str, message: String| DslError::at(code, clause.value_span, message);
+
+    match &clause.operator {
+        ComparisonOp::GreaterThan
+        | ComparisonOp::LessThan
+        | ComparisonOp::GreaterThanOrEqual
+        | ComparisonOp::LessThanOrEqual => {
+            if !field_type.is_numeric() {
+                return Err(DslError::at(
+                    "unsupported_operator",
+                    clause.field.span,
+                    format!("字段 {} 为{}类型，不支持大小比较", field, field_type.name()),
+                )
+                .suggest("字符串字段请使用 ==、!=、CONTAINS 或 REGEX"));
+            }
+            match &clause.value {
+                Value::Number(_) => check_literal(clause, field_type),
+                _ => Err(value_error(
+                    "type_mismatch",
+                    format!("字段 {} 的大小比较需要数值", field),
+                )),
+            }
+        }
+        ComparisonOp::Contains | ComparisonOp::Regex => {
+            if !field_type.is_textual() {
+                return Err(DslError::at(
+                    "unsupported_operator",
+                    clause.field.span,
+                    format!(
+                        "字段 {} 为{}类型，不支持 {:?}",
+                        field,
+                        field_type.name(),
+                        clause.operator
+                    ),
+                )
+                .suggest("数值字段请使用 ==、!=、>、<、>=、<= 或 IN"));
+            }
+            let Value::String(pattern) = &clause.value else {
+                return Err(value_error(
+                    "type_mismatch",
+                    format!("{:?} 需要字符串", clause.operator),
+                ));
+            };
+            if matches!(clause.operator, ComparisonOp::Regex) {
+                if let Err(e) = Regex::new(pattern) {
+                    return Err(value_error(
+                        "invalid_regex",
+                        format!("正则表达式无效: {}", e),
+                    ));
+                }
+            }
+            Ok(())
+        }
+        ComparisonOp::Equal | ComparisonOp::NotEqual => match &clause.value {
+            Value::List(_) => Err(value_error(
+                "unsupported_operator",
+                format!("{:?} 不支持列表", clause.operator),
+            )
+            .suggest("多个候选值请使用 IN")),
+            _ => check_literal(clause, field_type),
+        },
+        ComparisonOp::In => {
+            if let Value::List(items) = &clause.value {
+                let kinds: HashSet<&str> = items.iter().map(literal_kind).collect();
+                if kinds.len() > 1 {
+                    return Err(value_error(
+                        "mixed_in_list",
+                        format!("IN 列表中的值类型不一致: {}", field),
+                    ));
+                }
+            }
+            check_literal(clause, field_type)
+        }
+    }
+}
+
+/// 字面量类型与字段类型一致，数值在字段取值范围内
+fn check_literal(
+    clause: &ConditionClause,
+    field_type: FieldType,
+) -> std::result::Result<(), DslError> {
+    let items: Vec<&Value> = match &clause.value {
+        Value::List(items) => items.iter().collect(),
+        v => vec![v],
+    };
+    let field = &clause.field.path_text();
+    let single = !matches!(clause.value, Value::List(_));
 
+    for item in items {
+        let err = match (field_type, item) {
+            (FieldType::Any, _) => None,
+            (FieldType::Integer { min, max }, Value::Number(n)) if *n < min || *n > max => {
+                Some(DslError::at(
+                    "value_out_of_range",
+                    clause.value_span,
+                    format!("值 {} 超出字段 {} 的取值范围 [{}, {}]", n, field, min, max),
+                ))
+            }
+            (FieldType::Integer { .. } | FieldType::IntegerList, Value::String(s)) => {
+                let err = DslError::at(
+                    "type_mismatch",
+                    clause.value_span,
+                    format!("字段 {} 为数值类型，不能与字符串 \"{}\" 比较", field, s),
+                );
+                match s.trim().parse::<i64>() {
+                    Ok(n) if single => Some(err.suggest("去掉引号").replace_with(n.to_string())),
+                    _ => Some(err),
+                }
+            }
+            (FieldType::String | FieldType::StringList, Value::Number(n)) => {
+                let err = DslError::at(
+                    "type_mismatch",
+                    clause.value_span,
+                    format!("字段 {} 为字符串类型，数值 {} 需要加引号", field, n),
+                );
+                Some(if single {
+                    err.replace_with(format!("\"{}\"", n))
+                } else {
+                    err
+                })
+            }
+            _ => None,
+        };
+        if let Some(err) = err {
+            return Err(err);
+        }
+    }
     Ok(())
 }
 
+/// 在候选名称中找出与 name 编辑距离最近且足够接近的一个
+fn most_similar<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).clamp(1, 3);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+fn literal_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::List(_) => "list",
+    }
+}
+
+fn literal_text(value: &Value) -> String {
+    match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("\"{}\"", s),
+        Value::List(_) => "(...)".to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dsl::parse_converge_rule;
 
     #[test]
     fn test_load_fields() {
-        let fields = load_valid_fields();
-        assert!(!fields.is_empty());
-        assert!(fields.contains("alarm_id"));
-        assert!(fields.contains("src_ip"));
+        let catalog = load_field_catalog();
+        assert!(!catalog.types.is_empty());
+        assert!(catalog.is_known("alarm_id"));
+        assert!(catalog.is_known("src_ip"));
+    }
+
+    fn validate(dsl_where: &str) -> std::result::Result<(), DslError> {
+        let input = format!(
+            "CONVERGE WHERE {} GROUP BY src_ip WINDOW 5m THRESHOLD 1",
+            dsl_where
+        );
+        let rule = parse_converge_rule(&input).unwrap();
+        validate_fields(&rule).map_err(|e| e.downcast::<DslError>().unwrap())
+    }
+
+    #[test]
+    fn test_unknown_field_suggestion() {
+        let err = validate(r#"dts_ip == "10.0.0.1""#).unwrap_err();
+        assert_eq!(err.code, "unknown_field");
+        assert_eq!(err.replacement.as_deref(), Some("dst_ip"));
+    }
+
+    #[test]
+    fn test_type_aware_validation() {
+        assert!(validate(r#"alarm_type == 3 AND terminal_ip == "10.0.0.1""#).is_ok());
+        assert!(validate("dst_port >= 1024 AND alarm_severity IN (3, 4)").is_ok());
+
+        // 主机字段不能用于网络攻击告警
+        let err = validate(r#"alarm_type == 1 AND terminal_ip == "10.0.0.1""#).unwrap_err();
+        assert!(err.message.contains("terminal_ip"));
+
+        // 字符串字段不支持大小比较，错误位置指向字段
+        let err = validate("sha256 > 5").unwrap_err();
+        assert_eq!(err.span, Some(Span::new(15, 21)));
+
+        assert!(validate(r#"dst_port REGEX "^80$""#).is_err());
+        assert!(validate(r#"alarm_name REGEX "(unclosed""#).is_err());
+        assert!(validate(r#"alarm_severity IN (1, "2")"#).is_err());
+        assert!(validate("dst_port == 70000").is_err());
+        assert_eq!(
+            FieldType::from_decl("i16"),
+            FieldType::Integer {
+                min: -32768,
+                max: 32767
+            }
+        );
+        assert!(validate("alarm_type == 1 AND alarm_type == 2").is_err());
+    }
+
+    #[test]
+    fn test_field_path_validation() {
+        assert!(validate(r#"data.http.host CONTAINS "evil" AND data.ports[*] > 1024"#).is_ok());
+        assert!(validate(r#"ALL procedure_technique_id[*] REGEX "^T1""#).is_ok());
+
+        // 列表元素保留元素类型，普通字段不支持路径
+        let err = validate("procedure_technique_id[0] > 3").unwrap_err();
+        assert_eq!(err.code, "unsupported_operator");
+        let err = validate(r#"src_ip.octet == "10""#).unwrap_err();
+        assert_eq!(err.code, "invalid_path");
     }
 }
@@ -1,49 +1,114 @@
-use crate::db::{self, alert_tag_mapping};
-use crate::models::{HostBehaviorAlert, MaliciousSampleAlert, NetworkAttackAlert};
+use crate::alert_catalog;
+use crate::db::{
+    self, alert_tag_mapping, convergence_rules::ConvergenceRuleRecord, ConvergenceGroup,
+};
+use crate::dsl::{self, evaluator, types::ConvergeRule, Predicate};
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
 use serde_json::Value;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use tracing::{info, warn};
 use uuid::Uuid;
 
+/// 由收敛规则 DSL 编译得到的可执行收敛计划
+pub struct ConvergencePlan {
+    pub rule_id: Uuid,
+    pub rule_name: String,
+    /// 编译时的规则版本，记录在新建的收敛告警上
+    pub rule_version: i32,
+    pub rule: ConvergeRule,
+    predicate: Predicate,
+}
+
+impl ConvergencePlan {
+    /// 解析并校验收敛规则，生成执行计划
+    pub fn compile(record: &ConvergenceRuleRecord) -> Result<Self> {
+        let rule = dsl::parse_converge_rule(&record.dsl_rule)?;
+        dsl::validate_fields(&rule)?;
+        Ok(Self {
+            rule_id: record.id,
+            rule_name: record.name.clone(),
+            rule_version: record.version,
+            predicate: Predicate::compile(&rule.condition),
+            rule,
+        })
+    }
+
+    /// 计算告警的分组键；告警不满足 WHERE 条件或缺少分组字段时返回 None
+    pub fn group_key(&self, alert_json: &Value) -> Option<String> {
+        if !self.predicate.matches(alert_json) {
+            return None;
+        }
+
+        let values = self
+            .rule
+            .group_by
+            .iter()
+            .map(|field| evaluator::lookup_ref(alert_json, field).map(evaluator::json_to_string))
+            .collect::<Option<Vec<String>>>()?;
+
+        serde_json::to_string(&values).ok()
+    }
+
+    pub(super) fn threshold(&self) -> i32 {
+        self.rule.threshold.max(1) as i32
+    }
+}
+
+/// 编译所有启用的收敛规则，编译失败的规则会被跳过
+pub fn compile_convergence_plans(records: &[ConvergenceRuleRecord]) -> Vec<ConvergencePlan> {
+    records
+        .iter()
+        .filter_map(|record| match ConvergencePlan::compile(record) {
+            Ok(plan) => Some(plan),
+            Err(e) => {
+                warn!(
+                    "收敛规则 '{}' ({}) 编译失败，已跳过: {}",
+                    record.name, record.id, e
+                );
+                None
+            }
+        })
+        .collect()
+}
+
 /// 根据告警类型执行相应的收敛逻辑，并为最终的收敛告警打上标签
+///
+/// 优先使用第一条命中的收敛规则；没有规则命中时退回到各告警类型的默认收敛，
+/// 默认收敛只合并 default_window 内开启的收敛告警。
+/// 返回告警归入的收敛告警ID，未知告警类型返回 None
 pub async fn process_and_tag_convergence(
     pool: &PgPool,
     alert_json: &Value,
     alert_type_str: &str,
     raw_alert_id: Uuid,
     matched_tag_ids: Vec<Uuid>,
-) -> Result<()> {
-    // 1. 根据告警类型，执行不同的收敛算法，找到或创建收敛告警ID
-    let converged_alert_id = match alert_type_str {
-        "network_attack" => {
-            let alert: NetworkAttackAlert = serde_json::from_value(alert_json.clone())?;
-            handle_network_attack_convergence(pool, &alert).await?
-        }
-        "malicious_sample" => {
-            let alert: MaliciousSampleAlert = serde_json::from_value(alert_json.clone())?;
-            handle_malicious_sample_convergence(pool, &alert).await?
-        }
-        "host_behavior" => {
-            let alert: HostBehaviorAlert = serde_json::from_value(alert_json.clone())?;
-            handle_host_behavior_convergence(pool, &alert).await?
-        }
-        _ => {
-            warn!("未知的告警类型 '{}'，无法进行收敛", alert_type_str);
-            return Ok(());
-        }
+    plans: &[ConvergencePlan],
+    default_window: Duration,
+) -> Result<Option<Uuid>> {
+    let Some(alert_type) = alert_catalog::catalog().get(alert_type_str) else {
+        warn!("未知的告警类型 '{}'，无法进行收敛", alert_type_str);
+        return Ok(None);
     };
 
-    // 2. 建立原始告警与收敛告警的映射关系
-    let alert_type_code = match alert_type_str {
-        "network_attack" => 1,
-        "malicious_sample" => 2,
-        "host_behavior" => 3,
-        _ => 0,
+    // 1. 按收敛规则或默认算法，找到或创建收敛告警ID；收敛计数、标签与映射在同一事务中写入，
+    //    避免中途失败后计数已增加而映射缺失（重复投递时按映射判断是否已收敛）
+    let mut tx = pool.begin().await?;
+    let matched_plan = plans
+        .iter()
+        .find_map(|plan| plan.group_key(alert_json).map(|key| (plan, key)));
+
+    let converged_alert_id = match matched_plan {
+        Some((plan, group_key)) => {
+            handle_rule_convergence(&mut tx, alert_json, alert_type_str, plan, &group_key).await?
+        }
+        None => {
+            let since = Utc::now() - default_window;
+            handle_default_convergence(&mut tx, alert_json, alert_type_str, since).await?
+        }
     };
-    db::insert_mapping(pool, raw_alert_id, converged_alert_id, alert_type_code).await?;
 
-    // 3. 如果有匹配的标签，将它们关联到收敛告警上
+    // 2. 如果有匹配的标签，将它们关联到收敛告警上
     if !matched_tag_ids.is_empty() {
         info!(
             "为收敛告警 {} (类型: {}) 关联 {} 个标签: {:?}",
@@ -52,8 +117,8 @@ pub async fn process_and_tag_convergence(
             matched_tag_ids.len(),
             matched_tag_ids
         );
-        alert_tag_mapping::add_alert_tags_batch(
-            pool,
+        alert_tag_mapping::add_alert_tags_batch_in_tx(
+            &mut tx,
             converged_alert_id,
             alert_type_str,
             &matched_tag_ids,
@@ -61,56 +126,66 @@ pub async fn process_and_tag_convergence(
         .await?;
     }
 
-    Ok(())
-}
+    // 3. 建立原始告警与收敛告警的映射关系；映射存在即表示收敛和打标签都已完成
+    db::insert_mapping(&mut tx, raw_alert_id, converged_alert_id, alert_type.code).await?;
+    tx.commit().await?;
 
-/// 处理网络攻击告警的收敛（基于五元组）
-async fn handle_network_attack_convergence(
-    pool: &PgPool,
-    alert: &NetworkAttackAlert,
-) -> Result<Uuid> {
-    match db::find_converged_network_attack_by_five_tuple(pool, alert).await? {
-        Some(existing_id) => {
-            db::increment_convergence_count_network_attack(pool, existing_id).await?;
-            Ok(existing_id)
-        }
-        None => {
-            let new_id = db::insert_converged_network_attack(pool, alert, 1).await?;
-            Ok(new_id)
-        }
-    }
+    Ok(Some(converged_alert_id))
 }
 
-/// 处理恶意样本告警的收敛（基于哈希）
-async fn handle_malicious_sample_convergence(
-    pool: &PgPool,
-    alert: &MaliciousSampleAlert,
+/// 按收敛规则收敛：在规则窗口内按分组键合并，计数达到阈值后才会被推送
+async fn handle_rule_convergence(
+    conn: &mut PgConnection,
+    alert_json: &Value,
+    alert_type_str: &str,
+    plan: &ConvergencePlan,
+    group_key: &str,
 ) -> Result<Uuid> {
-    match db::find_converged_malicious_sample_by_hash(pool, alert).await? {
+    let since = Utc::now() - plan.rule.window.to_duration();
+
+    match db::find_converged_by_group_key(conn, alert_type_str, plan.rule_id, group_key, since)
+        .await?
+    {
         Some(existing_id) => {
-            db::increment_convergence_count_malicious_sample(pool, existing_id).await?;
+            let count = db::increment_convergence_count_with_threshold(
+                conn,
+                alert_type_str,
+                existing_id,
+                plan.threshold(),
+            )
+            .await?;
+            if count == plan.threshold() {
+                info!(
+                    "收敛告警 {} 达到规则 '{}' 的阈值 {}",
+                    existing_id, plan.rule_name, count
+                );
+            }
             Ok(existing_id)
         }
         None => {
-            let new_id = db::insert_converged_malicious_sample(pool, alert, 1).await?;
-            Ok(new_id)
+            let group = ConvergenceGroup {
+                rule_id: plan.rule_id,
+                rule_version: plan.rule_version,
+                group_key,
+                threshold: plan.threshold(),
+            };
+            db::insert_converged_alert(conn, alert_type_str, alert_json, 1, Some(&group)).await
         }
     }
 }
 
-/// 处理主机行为告警的收敛（基于主机信息）
-async fn handle_host_behavior_convergence(
-    pool: &PgPool,
-    alert: &HostBehaviorAlert,
+/// 默认收敛：按告警类型配置的默认收敛键，合并 since 之后开启的收敛告警
+async fn handle_default_convergence(
+    conn: &mut PgConnection,
+    alert_json: &Value,
+    alert_type_str: &str,
+    since: DateTime<Utc>,
 ) -> Result<Uuid> {
-    match db::find_converged_host_behavior_by_host_info(pool, alert).await? {
+    match db::find_converged_by_default_key(conn, alert_type_str, alert_json, since).await? {
         Some(existing_id) => {
-            db::increment_convergence_count_host_behavior(pool, existing_id).await?;
+            db::increment_convergence_count(conn, alert_type_str, existing_id).await?;
             Ok(existing_id)
         }
-        None => {
-            let new_id = db::insert_converged_host_behavior(pool, alert, 1).await?;
-            Ok(new_id)
-        }
+        None => db::insert_converged_alert(conn, alert_type_str, alert_json, 1, None).await,
     }
 }
@@ -0,0 +1,562 @@
+use crate::db::{
+    correlation_rules::CorrelationRuleRecord, threat_event, threat_event_alerts, threat_event_push,
+    threat_event_workflow::DisposeStatus, ThreatEventInput,
+};
+use crate::dsl::{
+    self, evaluator,
+    types::{CorrelateRule, FieldRef, LogicalOp},
+    Predicate,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// 每个事件别名在窗口内最多缓存的告警数，防止组合搜索失控
+const MAX_PARTIALS_PER_ALIAS: usize = 256;
+
+/// 由关联规则 DSL 编译得到的可执行关联计划
+pub struct CorrelationPlan {
+    pub rule_id: Uuid,
+    pub rule_name: String,
+    /// 编译时的规则版本，记录在生成的威胁事件上
+    pub rule_version: i32,
+    pub rule: CorrelateRule,
+    /// 与 rule.events 一一对应的事件条件
+    event_predicates: Vec<Predicate>,
+}
+
+impl CorrelationPlan {
+    /// 由已解析的关联规则构建计划，预编译各事件条件
+    pub fn new(rule_id: Uuid, rule_name: String, rule_version: i32, rule: CorrelateRule) -> Self {
+        Self {
+            rule_id,
+            rule_name,
+            rule_version,
+            event_predicates: rule
+                .events
+                .iter()
+                .map(|event| Predicate::compile(&event.condition))
+                .collect(),
+            rule,
+        }
+    }
+
+    /// 解析并校验关联规则，生成执行计划
+    pub fn compile(record: &CorrelationRuleRecord) -> Result<Self> {
+        let rule = dsl::parse_correlate_rule(&record.dsl_rule)?;
+        dsl::validator::validate_correlate_fields(&rule)?;
+        Ok(Self::new(
+            record.id,
+            record.name.clone(),
+            record.version,
+            rule,
+        ))
+    }
+
+    /// JOIN ON 是否全部由 AND 连接（此时可在组合搜索中提前剪枝）
+    fn join_is_conjunctive(&self) -> bool {
+        self.rule
+            .join_on
+            .clauses
+            .iter()
+            .all(|c| !matches!(c.logical_op, Some(LogicalOp::Or)))
+    }
+
+    /// 两个计划的编译结果是否一致
+    fn same_rule(&self, other: &CorrelationPlan) -> bool {
+        serde_json::to_value(&self.rule).ok() == serde_json::to_value(&other.rule).ok()
+    }
+
+    /// 未写别名的字段引用默认指向第一个 EVENT
+    fn resolve_alias<'a>(&'a self, field: &'a FieldRef) -> &'a str {
+        field
+            .event_alias
+            .as_deref()
+            .or_else(|| self.rule.events.first().map(|e| e.alias.as_str()))
+            .unwrap_or_default()
+    }
+}
+
+/// 编译所有启用的关联规则，编译失败的规则会被跳过
+pub fn compile_correlation_plans(records: &[CorrelationRuleRecord]) -> Vec<CorrelationPlan> {
+    records
+        .iter()
+        .filter_map(|record| match CorrelationPlan::compile(record) {
+            Ok(plan) => Some(plan),
+            Err(e) => {
+                warn!(
+                    "关联规则 '{}' ({}) 编译失败，已跳过: {}",
+                    record.name, record.id, e
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// 窗口内等待关联的收敛告警
+#[derive(Clone)]
+struct PartialMatch {
+    converged_id: Uuid,
+    alert_type: String,
+    alert: Value,
+    seen_at: DateTime<Utc>,
+    /// 已参与关联、等待威胁事件写入的告警不再参与新的关联，写入成功后移除，失败时释放
+    reserved: bool,
+}
+
+/// 生成威胁事件的关联规则及其版本，以及参与关联的收敛告警 (告警类型, 收敛告警ID)
+struct EventOrigin {
+    rule_id: Uuid,
+    rule_name: String,
+    rule_version: i32,
+    alerts: Vec<(String, Uuid)>,
+    /// 被预留的部分匹配 (事件别名, 收敛告警ID)
+    reserved: Vec<(String, Uuid)>,
+}
+
+/// 流式关联引擎：按规则、按事件别名缓存窗口内的收敛告警，所有别名在 JOIN ON 上匹配时生成威胁事件
+pub struct CorrelationEngine {
+    plans: RwLock<Vec<CorrelationPlan>>,
+    partials: Mutex<HashMap<Uuid, HashMap<String, VecDeque<PartialMatch>>>>,
+}
+
+impl CorrelationEngine {
+    pub fn new(plans: Vec<CorrelationPlan>) -> Self {
+        Self {
+            plans: RwLock::new(plans),
+            partials: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn plan_count(&self) -> usize {
+        self.plans.read().unwrap().len()
+    }
+
+    /// 替换关联计划；规则内容未变化时保留其窗口内的部分匹配，其余丢弃
+    pub fn replace_plans(&self, plans: Vec<CorrelationPlan>) {
+        let mut current = self.plans.write().unwrap();
+        let mut partials = self.partials.lock().unwrap();
+
+        partials.retain(|rule_id, _| {
+            let old = current.iter().find(|p| p.rule_id == *rule_id);
+            let new = plans.iter().find(|p| p.rule_id == *rule_id);
+            matches!((old, new), (Some(o), Some(n)) if o.same_rule(n))
+        });
+        *current = plans;
+    }
+
+    /// 将一条收敛告警送入关联引擎，并把命中的威胁事件写入数据库
+    ///
+    /// 参与关联的部分匹配在威胁事件写入提交后才移除；写入失败时释放，仍可参与之后的关联
+    pub async fn correlate(
+        &self,
+        pool: &PgPool,
+        alert_json: &Value,
+        alert_type_str: &str,
+        converged_id: Uuid,
+    ) -> Result<()> {
+        let mut events = self
+            .observe(alert_json, alert_type_str, converged_id, Utc::now())
+            .into_iter();
+
+        while let Some((origin, event)) = events.next() {
+            match store_event(pool, &origin, &event).await {
+                Ok(event_id) => {
+                    self.settle(&origin, true);
+                    info!(
+                        "关联规则 '{}' (v{}) 命中，生成威胁事件 {}",
+                        origin.rule_name, origin.rule_version, event_id
+                    );
+                }
+                Err(e) => {
+                    self.settle(&origin, false);
+                    for (origin, _) in events {
+                        self.settle(&origin, false);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 威胁事件写入后处理预留的部分匹配：成功时移除，失败时释放
+    fn settle(&self, origin: &EventOrigin, stored: bool) {
+        let mut partials = self.partials.lock().unwrap();
+        let Some(buffers) = partials.get_mut(&origin.rule_id) else {
+            return;
+        };
+
+        for (alias, id) in &origin.reserved {
+            if let Some(buffer) = buffers.get_mut(alias) {
+                if stored {
+                    buffer.retain(|p| p.converged_id != *id);
+                } else {
+                    for p in buffer.iter_mut().filter(|p| p.converged_id == *id) {
+                        p.reserved = false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 更新窗口内的部分匹配，返回本次完成关联的 (来源规则, 威胁事件)
+    fn observe(
+        &self,
+        alert_json: &Value,
+        alert_type_str: &str,
+        converged_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Vec<(EventOrigin, ThreatEventInput)> {
+        let mut generated = Vec::new();
+        let plans = self.plans.read().unwrap();
+        let mut partials = self.partials.lock().unwrap();
+
+        for plan in plans.iter() {
+            let since = now - plan.rule.window.to_duration();
+            let buffers = partials.entry(plan.rule_id).or_default();
+
+            // 清理窗口外的部分匹配
+            for buffer in buffers.values_mut() {
+                buffer.retain(|p| p.seen_at >= since);
+            }
+
+            let incoming = PartialMatch {
+                converged_id,
+                alert_type: alert_type_str.to_string(),
+                alert: alert_json.clone(),
+                seen_at: now,
+                reserved: false,
+            };
+
+            let matched_aliases: Vec<&str> = plan
+                .rule
+                .events
+                .iter()
+                .zip(&plan.event_predicates)
+                .filter(|(_, predicate)| predicate.matches(alert_json))
+                .map(|(event, _)| event.alias.as_str())
+                .collect();
+
+            for alias in &matched_aliases {
+                let buffer = buffers.entry(alias.to_string()).or_default();
+                buffer.retain(|p| p.converged_id != converged_id);
+                if buffer.len() >= MAX_PARTIALS_PER_ALIAS {
+                    buffer.pop_front();
+                }
+                buffer.push_back(incoming.clone());
+            }
+
+            // 以新到达的告警为锚点，尝试补齐其余别名
+            for alias in &matched_aliases {
+                let Some(anchor) = buffers.get(*alias).and_then(|b| b.back()) else {
+                    continue;
+                };
+                let mut assignment: HashMap<&str, &PartialMatch> = HashMap::new();
+                assignment.insert(alias, anchor);
+
+                if let Some(found) = search(plan, buffers, &mut assignment) {
+                    let reserved: Vec<(String, Uuid)> = found
+                        .iter()
+                        .map(|(alias, p)| (alias.to_string(), p.converged_id))
+                        .collect();
+                    generated.push((
+                        EventOrigin {
+                            rule_id: plan.rule_id,
+                            rule_name: plan.rule_name.clone(),
+                            rule_version: plan.rule_version,
+                            alerts: found
+                                .iter()
+                                .map(|(_, p)| (p.alert_type.clone(), p.converged_id))
+                                .collect(),
+                            reserved: reserved.clone(),
+                        },
+                        build_threat_event(plan, &found),
+                    ));
+
+                    // 已参与关联的告警在威胁事件写入前预留，不再重复使用
+                    for (alias, id) in reserved {
+                        if let Some(buffer) = buffers.get_mut(&alias) {
+                            for p in buffer.iter_mut().filter(|p| p.converged_id == id) {
+                                p.reserved = true;
+                            }
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+
+        generated
+    }
+}
+
+/// 在一个事务中写入威胁事件、来源规则、关联告警和推送任务，返回威胁事件ID
+async fn store_event(
+    pool: &PgPool,
+    origin: &EventOrigin,
+    event: &ThreatEventInput,
+) -> Result<Uuid> {
+    let mut tx = pool.begin().await?;
+
+    let event_id = threat_event::insert_threat_event(&mut *tx, event).await?;
+    threat_event::set_threat_event_rule(&mut *tx, event_id, origin.rule_id, origin.rule_version)
+        .await?;
+    for (alert_type, converged_id) in &origin.alerts {
+        threat_event_alerts::link_alert(
+            &mut *tx,
+            event_id,
+            alert_type,
+            *converged_id,
+            threat_event_alerts::SOURCE_CORRELATION,
+        )
+        .await?;
+    }
+    threat_event_push::enqueue_threat_event_push(
+        &mut *tx,
+        event_id,
+        threat_event_push::TRIGGER_CREATED,
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(event_id)
+}
+
+/// 回溯搜索：为每个尚未赋值的别名从缓存中挑选告警，直到 JOIN ON 成立
+fn search<'a>(
+    plan: &'a CorrelationPlan,
+    buffers: &'a HashMap<String, VecDeque<PartialMatch>>,
+    assignment: &mut HashMap<&'a str, &'a PartialMatch>,
+) -> Option<Vec<(&'a str, &'a PartialMatch)>> {
+    let next_alias = plan
+        .rule
+        .events
+        .iter()
+        .map(|e| e.alias.as_str())
+        .find(|alias| !assignment.contains_key(alias));
+
+    let Some(alias) = next_alias else {
+        if !join_holds(plan, assignment, false) {
+            return None;
+        }
+        return Some(
+            plan.rule
+                .events
+                .iter()
+                .map(|e| (e.alias.as_str(), assignment[e.alias.as_str()]))
+                .collect(),
+        );
+    };
+
+    for candidate in buffers.get(alias).into_iter().flatten() {
+        // 已预留的告警不再参与；同一条收敛告警不能同时充当多个事件
+        if candidate.reserved
+            || assignment
+                .values()
+                .any(|p| p.converged_id == candidate.converged_id)
+        {
+            continue;
+        }
+
+        assignment.insert(alias, candidate);
+        if join_holds(plan, assignment, true) {
+            if let Some(found) = search(plan, buffers, assignment) {
+                return Some(found);
+            }
+        }
+        assignment.remove(alias);
+    }
+
+    None
+}
+
+/// 求值 JOIN ON 条件；partial 为 true 时只检查两侧别名都已赋值的子句
+fn join_holds(
+    plan: &CorrelationPlan,
+    assignment: &HashMap<&str, &PartialMatch>,
+    partial: bool,
+) -> bool {
+    if partial && !plan.join_is_conjunctive() {
+        return true;
+    }
+
+    let mut result: Option<bool> = None;
+
+    for clause in &plan.rule.join_on.clauses {
+        let left = assignment.get(plan.resolve_alias(&clause.left));
+        let right = assignment.get(plan.resolve_alias(&clause.right));
+
+        let matched = match (left, right) {
+            (Some(l), Some(r)) => join_values_equal(
+                evaluator::lookup_ref(&l.alert, &clause.left),
+                evaluator::lookup_ref(&r.alert, &clause.right),
+            ),
+            _ if partial => continue,
+            _ => false,
+        };
+
+        result = Some(match (result, &clause.logical_op) {
+            (None, _) => matched,
+            (Some(acc), Some(LogicalOp::Or)) => acc || matched,
+            (Some(acc), _) => acc && matched,
+        });
+    }
+
+    result.unwrap_or(true)
+}
+
+/// JOIN 字段按字符串形式比较，任一侧缺失时不匹配
+fn join_values_equal(left: Option<&Value>, right: Option<&Value>) -> bool {
+    match (left, right) {
+        (Some(l), Some(r)) => evaluator::json_to_string(l) == evaluator::json_to_string(r),
+        _ => false,
+    }
+}
+
+/// 根据 GENERATE 块和参与关联的告警构造威胁事件
+fn build_threat_event(
+    plan: &CorrelationPlan,
+    matched: &[(&str, &PartialMatch)],
+) -> ThreatEventInput {
+    let generate = &plan.rule.generate;
+    let (severity, priority) = match generate.severity {
+        0 | 1 => ("低危", "低"),
+        2 => ("中危", "中"),
+        3 => ("高危", "高"),
+        _ => ("严重", "高"),
+    };
+
+    let merge_alerts: Vec<Value> = matched
+        .iter()
+        .map(|(alias, p)| {
+            json!({
+                "alert_id": p.converged_id,
+                "alert_type": p.alert_type,
+                "alert_time": p.seen_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                "event_alias": alias,
+            })
+        })
+        .collect();
+
+    let collect_ips = |field: &str| -> Option<Value> {
+        let mut ips: Vec<String> = Vec::new();
+        for (_, p) in matched {
+            if let Some(ip) = evaluator::lookup_field(&p.alert, field) {
+                let ip = evaluator::json_to_string(ip);
+                if !ip.is_empty() && !ips.contains(&ip) {
+                    ips.push(ip);
+                }
+            }
+        }
+        (!ips.is_empty()).then(|| json!(ips))
+    };
+
+    let start_time = matched.iter().map(|(_, p)| p.seen_at).min();
+    let end_time = matched.iter().map(|(_, p)| p.seen_at).max();
+
+    ThreatEventInput {
+        name: Some(generate.name.clone()),
+        description: Some(generate.description.clone()),
+        start_time,
+        end_time,
+        found_time: Some(Utc::now()),
+        first_found_time: start_time,
+        source: Some(format!("关联规则: {}", plan.rule_name)),
+        priority: Some(priority.to_string()),
+        severity: Some(severity.to_string()),
+        dispose_status: Some(DisposeStatus::New.code().to_string()),
+        merge_alerts: Some(Value::Array(merge_alerts)),
+        attack_asset_ip: collect_ips("src_ip"),
+        victim_asset_ip: collect_ips("dst_ip"),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine_of(dsl_rule: &str) -> CorrelationEngine {
+        let plan = CorrelationPlan::new(
+            Uuid::new_v4(),
+            "test".to_string(),
+            1,
+            dsl::parse_correlate_rule(dsl_rule).unwrap(),
+        );
+        CorrelationEngine::new(vec![plan])
+    }
+
+    #[test]
+    fn test_correlate_join_within_window() {
+        let engine = engine_of(
+            r#"CORRELATE
+                EVENT attack WHERE alarm_type == 1
+                EVENT behavior WHERE alarm_type == 3
+                JOIN ON attack.dst_ip == behavior.terminal_ip
+                WINDOW 10m
+                GENERATE SEVERITY 3 NAME "攻击链" DESCRIPTION "网络攻击后出现主机行为""#,
+        );
+        let now = Utc::now();
+        let attack = json!({ "alarm_type": 1, "src_ip": "1.1.1.1", "dst_ip": "10.0.0.5" });
+        let other = json!({ "alarm_type": 3, "terminal_ip": "10.0.0.9" });
+        let behavior = json!({ "alarm_type": 3, "terminal_ip": "10.0.0.5" });
+
+        let attack_id = Uuid::new_v4();
+        assert!(engine
+            .observe(&attack, "network_attack", attack_id, now)
+            .is_empty());
+        assert!(engine
+            .observe(&other, "host_behavior", Uuid::new_v4(), now)
+            .is_empty());
+
+        let behavior_id = Uuid::new_v4();
+        let events = engine.observe(&behavior, "host_behavior", behavior_id, now);
+        assert_eq!(events.len(), 1);
+        let mut linked = events[0].0.alerts.clone();
+        linked.sort();
+        let mut expected = vec![
+            ("host_behavior".to_string(), behavior_id),
+            ("network_attack".to_string(), attack_id),
+        ];
+        expected.sort();
+        assert_eq!(linked, expected);
+        let event = &events[0].1;
+        assert_eq!(event.severity.as_deref(), Some("高危"));
+        assert_eq!(
+            event
+                .merge_alerts
+                .as_ref()
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // 预留的告警不参与新的关联；写入失败释放后仍可关联，写入成功后被消费
+        assert!(engine
+            .observe(&behavior, "host_behavior", Uuid::new_v4(), now)
+            .is_empty());
+        engine.settle(&events[0].0, false);
+        let retry = engine.observe(&behavior, "host_behavior", Uuid::new_v4(), now);
+        assert_eq!(retry.len(), 1);
+        engine.settle(&retry[0].0, true);
+        assert!(engine
+            .observe(&behavior, "host_behavior", Uuid::new_v4(), now)
+            .is_empty());
+
+        // 窗口外的告警不再参与
+        let late = now + chrono::Duration::minutes(11);
+        assert!(engine
+            .observe(&behavior, "host_behavior", Uuid::new_v4(), late)
+            .is_empty());
+    }
+}
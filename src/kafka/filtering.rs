@@ -1,58 +1,84 @@
+use crate::config::SubtypeSpec;
 use crate::db::filter_rules::FilterRuleRecord;
-use regex::Regex;
+use crate::dsl::{self, legacy::legacy_condition, Predicate};
+use anyhow::{anyhow, Result};
 use serde_json::Value;
 use tracing::warn;
 
-/// 检查告警是否应该根据提供的规则被过滤
-pub fn should_filter(alert_json: &Value, alert_type_str: &str, rules: &[FilterRuleRecord]) -> bool {
-    for rule in rules.iter().filter(|r| r.alert_type == alert_type_str) {
-        // 如果规则定义了子类型，则必须匹配
-        if !rule.alert_subtype.is_empty() {
-            let subtype_matches = alert_json
-                .get("alert_subtype")
-                .map(|v| v.to_string().replace('\"', "")) // 统一处理为字符串
-                .map_or(false, |sub| sub == rule.alert_subtype);
-
-            if !subtype_matches {
-                continue;
-            }
-        }
+/// 由过滤规则编译得到的可执行过滤计划
+pub struct FilterPlan {
+    pub rule: FilterRuleRecord,
+    pub subtypes: SubtypeSpec,
+    pub predicate: Predicate,
+}
 
-        // 检查核心过滤条件
-        if check_condition(alert_json, &rule.field, &rule.operator, &rule.value) {
-            // 只要有一条规则匹配，就应该过滤
-            return true;
-        }
+impl FilterPlan {
+    /// 解析子类型条件并编译 DSL 条件；尚未迁移的旧规则按单条件转换
+    pub fn compile(rule: &FilterRuleRecord) -> Result<Self> {
+        let subtypes = SubtypeSpec::parse(&rule.alert_subtype).map_err(|e| anyhow!(e))?;
+        let source = rule
+            .condition
+            .clone()
+            .or_else(|| {
+                legacy_condition(&rule.alert_type, &rule.field, &rule.operator, &rule.value)
+            })
+            .ok_or_else(|| anyhow!("规则缺少有效的过滤条件"))?;
+        let condition = dsl::validator::compile_condition(&source, &rule.alert_type)?;
+        let predicate = Predicate::compile(&condition);
+        Ok(Self {
+            rule: rule.clone(),
+            subtypes,
+            predicate,
+        })
     }
 
-    // 没有任何规则匹配，则不过滤
-    false
+    pub(super) fn matches(&self, alert_json: &Value, alert_type_str: &str) -> bool {
+        self.rule.alert_type == alert_type_str
+            && subtype_matches(alert_json, &self.subtypes)
+            && self.predicate.matches(alert_json)
+    }
 }
 
-/// 检查告警数据是否满足单个规则的条件
-fn check_condition(alert_json: &Value, field: &str, operator: &str, value: &str) -> bool {
-    let alert_field = match alert_json.get(field) {
-        Some(v) => v,
-        None => return false,
-    };
+/// 编译所有启用的过滤规则，编译失败的规则会被跳过
+pub fn compile_filter_plans(records: &[FilterRuleRecord]) -> Vec<FilterPlan> {
+    records
+        .iter()
+        .filter_map(|record| match FilterPlan::compile(record) {
+            Ok(plan) => Some(plan),
+            Err(e) => {
+                warn!(
+                    "过滤规则 '{}' ({}) 编译失败，已跳过: {}",
+                    record.name, record.id, e
+                );
+                None
+            }
+        })
+        .collect()
+}
 
-    let alert_field_str = match alert_field {
-        Value::String(s) => s.clone(),
-        Value::Number(n) => n.to_string(),
-        Value::Bool(b) => b.to_string(),
-        Value::Null => "".to_string(),
-        _ => return false, // 不处理复杂类型
-    };
+/// 查找应过滤该告警的规则，只要有一条规则匹配就过滤，返回首条匹配的规则
+pub fn find_filter_rule<'a>(
+    alert_json: &Value,
+    alert_type_str: &str,
+    plans: &'a [FilterPlan],
+) -> Option<&'a FilterPlan> {
+    plans
+        .iter()
+        .find(|plan| plan.matches(alert_json, alert_type_str))
+}
 
-    match operator {
-        "eq" => alert_field_str == value,
-        "ne" => alert_field_str != value,
-        "contains" => alert_field_str.contains(value),
-        "not_contains" => !alert_field_str.contains(value),
-        "regex" => Regex::new(value).map_or(false, |re| re.is_match(&alert_field_str)),
-        _ => {
-            warn!("未知的过滤操作符: {}", operator);
-            false
-        }
+/// 检查告警的 alarm_subtype 是否满足规则的子类型条件（编码列表或区间，空串匹配所有）
+pub(super) fn subtype_matches(alert_json: &Value, spec: &SubtypeSpec) -> bool {
+    if spec.is_any() {
+        return true;
     }
+
+    let subtype = match alert_json.get("alarm_subtype") {
+        Some(Value::Number(n)) => n.as_u64(),
+        Some(Value::String(s)) => s.trim().parse::<u64>().ok(),
+        _ => None,
+    };
+    subtype
+        .and_then(|code| u32::try_from(code).ok())
+        .is_some_and(|code| spec.matches(code))
 }
@@ -1,26 +1,71 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use rdkafka::consumer::{Consumer, StreamConsumer};
 use rdkafka::message::{Message, OwnedMessage};
 use rdkafka::ClientConfig;
 use serde_json::Value;
 use sqlx::PgPool;
-use std::collections::HashMap;
+use std::collections::{hash_map::DefaultHasher, HashMap};
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
-use tracing::{error, info, warn};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use crate::config::{KafkaConfig, TopicsConfig};
-use crate::db::{self, filter_rules::FilterRuleRecord, tag_rules};
+use crate::alert_catalog::{self, AlertShapeError};
+use crate::alert_schema;
+use crate::config::{
+    ConvergenceConfig, KafkaConfig, TopicsConfig, ValidationConfig, ValidationMode,
+};
+use crate::db::{self, dead_letters, raw_alerts::DedupState};
 
 mod convergence;
+mod correlation;
 mod filtering;
+mod rule_set;
+mod simulation;
 mod tagging;
+mod threat_events;
+
+pub use convergence::ConvergencePlan;
+pub use filtering::FilterPlan;
+pub use rule_set::{RuleRegistry, RuleSetSummary};
+pub use simulation::{SimulatedRule, Simulation, SimulationReport};
+pub use tagging::TagPlan;
+
+/// 消息处理 worker 共享的上下文
+struct WorkerContext {
+    consumer: Arc<StreamConsumer>,
+    pool: PgPool,
+    rules: Arc<RuleRegistry>,
+    convergence_cfg: ConvergenceConfig,
+    validation_cfg: ValidationConfig,
+    max_retries: u32,
+    /// 主题到告警类型的映射
+    topic_types: HashMap<String, String>,
+}
+
+impl WorkerContext {
+    /// 消息所属的告警类型；未登记的主题取最后一段作为类型名
+    fn alert_type_of<'a>(&'a self, topic: &'a str) -> &'a str {
+        match self.topic_types.get(topic) {
+            Some(alert_type) => alert_type,
+            None => topic.rsplit('.').next().unwrap_or("unknown"),
+        }
+    }
+}
 
 /// 运行 Kafka 消费者
+///
+/// 消息按 (topic, partition) 分配给固定的 worker 顺序处理，保证同一分区内的处理顺序；
+/// 处理完成后才存储 offset，由自动提交定期提交已存储的 offset，实现至少一次语义
 pub async fn run_consumer(
     kafka_cfg: KafkaConfig,
     topics_cfg: TopicsConfig,
+    convergence_cfg: ConvergenceConfig,
+    validation_cfg: ValidationConfig,
     pool: PgPool,
+    rules: Arc<RuleRegistry>,
 ) -> Result<()> {
     let consumer: StreamConsumer = ClientConfig::new()
         .set("group.id", &kafka_cfg.group_id)
@@ -28,126 +73,478 @@ pub async fn run_consumer(
         .set("enable.partition.eof", "false")
         .set("session.timeout.ms", "6000")
         .set("enable.auto.commit", "true")
+        .set("enable.auto.offset.store", "false")
         .set("auto.offset.reset", &kafka_cfg.auto_offset_reset)
         .create()?;
+    let consumer = Arc::new(consumer);
 
-    let topics = [
-        topics_cfg.network_attack.as_str(),
-        topics_cfg.malicious_sample.as_str(),
-        topics_cfg.host_behavior.as_str(),
-    ];
+    // 订阅所有已配置告警类型的主题和上游威胁事件主题
+    let mut topic_types: HashMap<String, String> = alert_catalog::catalog()
+        .types()
+        .iter()
+        .map(|t| (topics_cfg.topic_for(&t.name), t.name.clone()))
+        .collect();
+    topic_types.insert(
+        topics_cfg.raw_threat_events.clone(),
+        threat_events::THREAT_EVENT_TYPE.to_string(),
+    );
+    let topics: Vec<&str> = topic_types.keys().map(String::as_str).collect();
     consumer.subscribe(&topics)?;
     info!("Subscribed to topics: {:?}", topics);
 
-    // 加载过滤规则
-    let filter_rules = db::filter_rules::get_enabled_filter_rules(&pool).await?;
-    info!("Loaded {} enabled filter rules.", filter_rules.len());
-
-    // 加载标签规则
-    let tag_rules = db::tag_rules::get_enabled_tag_rules(&pool).await?;
-    info!("Loaded {} enabled tag rules.", tag_rules.len());
-
-    // 加载所有标签定义，用于名称到ID的映射
-    let all_tags = db::tag_management::get_all_tags(&pool).await?;
-    let tag_map: std::collections::HashMap<String, Uuid> =
-        all_tags.into_iter().map(|tag| (tag.name, tag.id)).collect();
-    info!("Loaded {} tag definitions into map.", tag_map.len());
-
-    // 将规则和映射封装以便在任务间共享
-    let processing_assets = Arc::new(ProcessingAssets {
-        filter_rules,
-        tag_rules,
-        tag_map,
+    let ctx = Arc::new(WorkerContext {
+        consumer: consumer.clone(),
+        pool,
+        rules,
+        convergence_cfg,
+        validation_cfg,
+        max_retries: kafka_cfg.max_retries,
+        topic_types,
     });
 
+    // 启动固定数量的 worker，每个 worker 拥有一个有界队列
+    let worker_count = kafka_cfg.worker_count.max(1);
+    let mut workers = Vec::with_capacity(worker_count);
+    for worker_id in 0..worker_count {
+        let (tx, rx) = mpsc::channel(kafka_cfg.worker_queue_size.max(1));
+        tokio::spawn(run_worker(worker_id, rx, ctx.clone()));
+        workers.push(tx);
+    }
+    info!(
+        "Started {} message workers (queue size {}).",
+        worker_count, kafka_cfg.worker_queue_size
+    );
+
     // 主消费循环
     loop {
         match consumer.recv().await {
             Err(e) => warn!("Kafka error: {}", e),
             Ok(m) => {
                 let owned_message = m.detach();
-                let pool_clone = pool.clone();
-                let assets_clone = processing_assets.clone();
+                let worker_id = partition_worker(
+                    owned_message.topic(),
+                    owned_message.partition(),
+                    worker_count,
+                );
 
-                tokio::spawn(async move {
-                    if let Err(e) = process_message(owned_message, pool_clone, assets_clone).await {
-                        error!("Error processing message: {}", e);
-                    }
-                });
+                // 队列已满时在此等待，暂停拉取新消息（背压）
+                if workers[worker_id].send(owned_message).await.is_err() {
+                    return Err(anyhow!("Message worker {} stopped unexpectedly", worker_id));
+                }
             }
         }
     }
 }
 
-/// 共享的处理资源
-struct ProcessingAssets {
-    filter_rules: Vec<FilterRuleRecord>,
-    tag_rules: Vec<tag_rules::TagRuleRecord>,
-    tag_map: HashMap<String, Uuid>,
+/// 同一 (topic, partition) 总是分配给同一个 worker
+fn partition_worker(topic: &str, partition: i32, worker_count: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    topic.hash(&mut hasher);
+    partition.hash(&mut hasher);
+    (hasher.finish() % worker_count as u64) as usize
+}
+
+/// worker 循环：顺序处理队列中的消息，处理完成后存储 offset
+async fn run_worker(
+    worker_id: usize,
+    mut rx: mpsc::Receiver<OwnedMessage>,
+    ctx: Arc<WorkerContext>,
+) {
+    while let Some(m) = rx.recv().await {
+        handle_message(&m, &ctx).await;
+
+        if let Err(e) = ctx
+            .consumer
+            .store_offset(m.topic(), m.partition(), m.offset())
+        {
+            warn!(
+                "Worker {} failed to store offset {}:{}@{}: {}",
+                worker_id,
+                m.topic(),
+                m.partition(),
+                m.offset(),
+                e
+            );
+        }
+    }
+}
+
+/// 处理消息并在失败时重试；重试耗尽后转入死信表，死信也无法写入时持续重试直到成功
+async fn handle_message(m: &OwnedMessage, ctx: &WorkerContext) {
+    let mut attempt: u32 = 0;
+
+    loop {
+        let e = match process_message(m, ctx).await {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+
+        if attempt < ctx.max_retries {
+            attempt += 1;
+            warn!(
+                "Error processing message {}:{}@{} (attempt {}/{}): {}",
+                m.topic(),
+                m.partition(),
+                m.offset(),
+                attempt,
+                ctx.max_retries,
+                e
+            );
+            tokio::time::sleep(retry_backoff(attempt)).await;
+            continue;
+        }
+
+        error!(
+            "Error processing message {}:{}@{} after {} retries: {}",
+            m.topic(),
+            m.partition(),
+            m.offset(),
+            ctx.max_retries,
+            e
+        );
+        let alert_type_str = ctx.alert_type_of(m.topic());
+        match store_dead_letter(
+            &ctx.pool,
+            m,
+            alert_type_str,
+            dead_letters::STAGE_PROCESS,
+            &e,
+        )
+        .await
+        {
+            Ok(()) => return,
+            Err(dle) => {
+                error!("Failed to store dead letter, will retry: {}", dle);
+                tokio::time::sleep(retry_backoff(ctx.max_retries + 1)).await;
+            }
+        }
+    }
+}
+
+/// 指数退避，最长 30 秒
+fn retry_backoff(attempt: u32) -> Duration {
+    Duration::from_millis(500u64.saturating_mul(1 << attempt.min(6)).min(30_000))
 }
 
 /// 处理单条 Kafka 消息
-async fn process_message(
-    m: OwnedMessage,
-    pool: PgPool,
-    assets: Arc<ProcessingAssets>,
-) -> Result<()> {
+async fn process_message(m: &OwnedMessage, ctx: &WorkerContext) -> Result<()> {
+    let pool = &ctx.pool;
+    let rules = &ctx.rules;
+    let topic = m.topic();
+    let alert_type_str = ctx.alert_type_of(topic);
+
     let payload = match m.payload_view::<str>() {
         Some(Ok(payload)) => payload,
         Some(Err(e)) => {
             warn!("Error viewing message payload: {}", e);
+            store_dead_letter(pool, m, alert_type_str, dead_letters::STAGE_DECODE, e).await?;
             return Ok(());
         }
         None => {
             warn!("Message with empty payload");
+            store_dead_letter(
+                pool,
+                m,
+                alert_type_str,
+                dead_letters::STAGE_DECODE,
+                "empty payload",
+            )
+            .await?;
             return Ok(());
         }
     };
 
-    let topic = m.topic();
-    let alert_type_str = topic.split('.').last().unwrap_or("unknown");
-
     // 反序列化为 JSON Value
     let payload_json: Value = match serde_json::from_str(payload) {
         Ok(json) => json,
         Err(e) => {
             error!("Failed to parse message payload into JSON: {}", e);
-            // 可以在这里将错误消息存入一个专门的“坏消息”表或队列
+            store_dead_letter(pool, m, alert_type_str, dead_letters::STAGE_PARSE, e).await?;
             return Ok(());
         }
     };
 
-    // 过滤逻辑
-    if filtering::should_filter(&payload_json, alert_type_str, &assets.filter_rules) {
+    // 上游威胁事件直接写入威胁事件表，不经过告警处理流程
+    if alert_type_str == threat_events::THREAT_EVENT_TYPE {
+        return threat_events::ingest_threat_event(pool, m, payload_json).await;
+    }
+
+    // 先按去重键判断是否重复投递：已被拒绝的告警直接跳过，已入库的告警不再重复过滤和校验
+    let dedup = db::raw_alerts::dedup_state(pool, alert_type_str, &payload_json).await?;
+    if dedup == DedupState::Rejected {
         info!(
-            "Alert of type '{}' was filtered. Storing as invalid alert.",
+            "Duplicate '{}' alert was already stored as invalid alert, skipping.",
             alert_type_str
         );
-        db::store_invalid_alert(&pool, &payload_json, alert_type_str, "filtered".to_string())
-            .await?;
         return Ok(());
     }
 
-    // 存储原始告警
-    let raw_alert_id = db::store_raw_alert(&pool, &payload_json, alert_type_str).await?;
+    // 按告警类型的 JSON Schema 校验
+    if dedup == DedupState::New && !check_schema(pool, &payload_json, alert_type_str, ctx).await? {
+        return Ok(());
+    }
+
+    // 整条消息使用同一代规则处理
+    let assets = rules.snapshot();
+
+    // 过滤逻辑
+    let filter_plan = match dedup {
+        DedupState::New => {
+            filtering::find_filter_rule(&payload_json, alert_type_str, &assets.filter_plans)
+        }
+        _ => None,
+    };
+    if let Some(plan) = filter_plan {
+        info!(
+            "Alert of type '{}' was filtered by rule '{}' ({}) of rule set v{}. Storing as invalid alert.",
+            alert_type_str, plan.rule.name, plan.rule.id, assets.version
+        );
+        let invalid_alert_id = db::store_invalid_alert(
+            pool,
+            &payload_json,
+            alert_type_str,
+            "filtered".to_string(),
+            Some(&plan.rule),
+        )
+        .await?;
+        // 只统计首次记录的命中；命中统计失败不影响告警处理
+        if let Some(invalid_alert_id) = invalid_alert_id {
+            if let Err(e) =
+                db::filter_rules::record_filter_rule_hit(pool, plan.rule.id, invalid_alert_id).await
+            {
+                warn!(
+                    "Failed to record hit of filter rule {}: {}",
+                    plan.rule.id, e
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    // 存储原始告警，不符合告警字段声明的消息转入死信表
+    let stored = match db::store_raw_alert(pool, &payload_json, alert_type_str).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            let shape_error = e
+                .downcast_ref::<AlertShapeError>()
+                .map(ToString::to_string)
+                .or_else(|| {
+                    e.downcast_ref::<serde_json::Error>()
+                        .map(ToString::to_string)
+                });
+            match shape_error {
+                Some(de) => {
+                    error!("Failed to deserialize '{}' alert: {}", alert_type_str, de);
+                    store_dead_letter(pool, m, alert_type_str, dead_letters::STAGE_DESERIALIZE, de)
+                        .await?;
+                    return Ok(());
+                }
+                None => return Err(e),
+            }
+        }
+    };
+    let raw_alert_id = stored.id;
+
+    // 重复投递的告警：按映射记录的进度只执行上次未完成的步骤，避免重复计数和重复关联
+    let resume = if stored.duplicate {
+        resume_step(db::query_mapping_state(pool, raw_alert_id).await?)
+    } else {
+        ResumeStep::Converge
+    };
+    let converged_alert_id = match resume {
+        ResumeStep::Done(converged_alert_id) => {
+            info!(
+                "Duplicate '{}' alert {} already converged into {}, skipping.",
+                alert_type_str, raw_alert_id, converged_alert_id
+            );
+            return Ok(());
+        }
+        ResumeStep::Correlate(converged_alert_id) => {
+            debug!(
+                "Duplicate '{}' alert {} converged into {} but not yet correlated, resuming.",
+                alert_type_str, raw_alert_id, converged_alert_id
+            );
+            Some(converged_alert_id)
+        }
+        ResumeStep::Converge => {
+            if stored.duplicate {
+                debug!(
+                    "Duplicate '{}' alert {} not yet converged, resuming processing.",
+                    alert_type_str, raw_alert_id
+                );
+            }
 
-    // 对原始告警应用标签规则，获取待添加的标签
-    let matched_tag_ids = tagging::get_matched_tag_ids(
-        &payload_json,
-        alert_type_str,
-        &assets.tag_rules,
-        &assets.tag_map,
+            // 对原始告警应用标签规则，获取待添加的标签
+            let tag_matches = tagging::match_tags(
+                &payload_json,
+                alert_type_str,
+                &assets.tag_plans,
+                &assets.tag_map,
+            );
+            if let Err(e) =
+                db::tag_rules::record_tag_rule_hits(pool, &tag_matches.rule_ids, raw_alert_id).await
+            {
+                warn!("Failed to record hits of tag rules: {}", e);
+            }
+
+            // 应用收敛规则，并在同一事务中为收敛告警添加匹配到的标签
+            convergence::process_and_tag_convergence(
+                pool,
+                &payload_json,
+                alert_type_str,
+                raw_alert_id,
+                tag_matches.tag_ids,
+                &assets.convergence_plans,
+                ctx.convergence_cfg.window_for(alert_type_str),
+            )
+            .await?
+        }
+    };
+
+    // 将收敛告警送入关联引擎，命中关联规则时生成威胁事件；完成后记录进度
+    if let Some(converged_alert_id) = converged_alert_id {
+        rules
+            .correlation()
+            .correlate(pool, &payload_json, alert_type_str, converged_alert_id)
+            .await?;
+        db::mark_mapping_correlated(pool, raw_alert_id).await?;
+    }
+
+    debug!(
+        "Raw alert {} ({}) processed with rule set v{}.",
+        raw_alert_id, alert_type_str, assets.version
     );
 
-    // 应用收敛规则，并将匹配到的标签ID传递过去
-    convergence::process_and_tag_convergence(
-        &pool,
-        &payload_json,
-        alert_type_str,
-        raw_alert_id,
-        matched_tag_ids,
+    Ok(())
+}
+
+/// 告警还需执行的处理步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResumeStep {
+    /// 尚未收敛：打标签、收敛并关联
+    Converge,
+    /// 已收敛（标签与收敛在同一事务中写入），只需送入关联引擎
+    Correlate(Uuid),
+    /// 已全部完成
+    Done(Uuid),
+}
+
+/// 按映射记录的进度确定重复投递的告警还需执行的步骤
+fn resume_step(state: Option<db::MappingState>) -> ResumeStep {
+    match state {
+        None => ResumeStep::Converge,
+        Some(state) if state.correlated => ResumeStep::Done(state.converged_alert_id),
+        Some(state) => ResumeStep::Correlate(state.converged_alert_id),
+    }
+}
+
+/// 按告警类型的 schema 校验告警，返回是否继续处理
+///
+/// 严格模式下不合规的告警连同逐字段错误存为无效告警；宽松模式只记录警告
+async fn check_schema(
+    pool: &PgPool,
+    payload_json: &Value,
+    alert_type_str: &str,
+    ctx: &WorkerContext,
+) -> Result<bool> {
+    let mode = ctx.validation_cfg.mode_for(alert_type_str);
+    let schema = alert_catalog::catalog()
+        .get(alert_type_str)
+        .and_then(|t| t.schema.as_ref());
+    let (Some(schema), false) = (schema, mode == ValidationMode::Off) else {
+        return Ok(true);
+    };
+
+    let violations = alert_schema::validate(schema, payload_json);
+    if violations.is_empty() {
+        return Ok(true);
+    }
+
+    let summary: Vec<String> = violations.iter().map(ToString::to_string).collect();
+    if mode == ValidationMode::Lenient {
+        warn!(
+            "Alert of type '{}' violates its schema, processing anyway: {}",
+            alert_type_str,
+            summary.join("; ")
+        );
+        return Ok(true);
+    }
+
+    if let Some(invalid_alert_id) =
+        db::store_schema_violation(pool, payload_json, alert_type_str, &violations).await?
+    {
+        info!(
+            "Alert of type '{}' violates its schema, stored as invalid alert {}: {}",
+            alert_type_str,
+            invalid_alert_id,
+            summary.join("; ")
+        );
+    }
+    Ok(false)
+}
+
+/// 将无法处理的消息连同来源位置和原始字节写入死信表
+async fn store_dead_letter(
+    pool: &PgPool,
+    m: &OwnedMessage,
+    alert_type_str: &str,
+    stage: &str,
+    error: impl std::fmt::Display,
+) -> Result<()> {
+    let id = dead_letters::insert_dead_letter(
+        pool,
+        &dead_letters::DeadLetterInput {
+            topic: m.topic(),
+            partition: m.partition(),
+            kafka_offset: m.offset(),
+            alert_type: alert_type_str,
+            payload: m.payload().unwrap_or_default(),
+            stage,
+            error: error.to_string(),
+        },
     )
     .await?;
 
+    warn!(
+        "Stored dead letter {} ({}:{}@{}, stage: {}).",
+        id,
+        m.topic(),
+        m.partition(),
+        m.offset(),
+        stage
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_after_failed_tagging() {
+        let converged_alert_id = Uuid::new_v4();
+
+        // 首次处理时打标签失败：收敛事务整体回滚，没有映射，重试时重新收敛并打标签
+        assert_eq!(resume_step(None), ResumeStep::Converge);
+
+        // 重试时收敛与标签已提交，但关联失败：再次重试只执行关联
+        let state = db::MappingState {
+            converged_alert_id,
+            correlated: false,
+        };
+        assert_eq!(
+            resume_step(Some(state)),
+            ResumeStep::Correlate(converged_alert_id)
+        );
+
+        // 关联完成后重复投递直接跳过
+        let state = db::MappingState {
+            correlated: true,
+            ..state
+        };
+        assert_eq!(
+            resume_step(Some(state)),
+            ResumeStep::Done(converged_alert_id)
+        );
+    }
+}
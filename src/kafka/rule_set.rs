@@ -0,0 +1,152 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::info;
+use uuid::Uuid;
+
+use super::convergence::{self, ConvergencePlan};
+use super::correlation::{self, CorrelationEngine};
+use super::filtering::{self, FilterPlan};
+use super::tagging::{self, TagPlan};
+use crate::db;
+
+/// 某一代规则的只读快照，消息处理期间持有同一份快照
+pub struct RuleSet {
+    pub version: u64,
+    pub loaded_at: DateTime<Utc>,
+    pub filter_plans: Vec<FilterPlan>,
+    pub tag_plans: Vec<TagPlan>,
+    pub tag_map: HashMap<String, Uuid>,
+    pub convergence_plans: Vec<ConvergencePlan>,
+}
+
+/// 规则快照概要，用于 API 展示当前生效的规则版本
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleSetSummary {
+    pub version: u64,
+    pub loaded_at: DateTime<Utc>,
+    pub filter_rules: usize,
+    pub tag_rules: usize,
+    pub tags: usize,
+    pub convergence_rules: usize,
+    pub correlation_rules: usize,
+}
+
+/// 运行时规则注册表：规则变更后重新加载并原子替换快照
+pub struct RuleRegistry {
+    current: RwLock<Arc<RuleSet>>,
+    correlation: CorrelationEngine,
+    next_version: AtomicU64,
+    reload_lock: tokio::sync::Mutex<()>,
+}
+
+impl RuleRegistry {
+    /// 从数据库加载第一代规则
+    pub async fn load(pool: &PgPool) -> Result<Arc<Self>> {
+        let (rule_set, correlation_plans) = load_rule_set(pool, 1).await?;
+        Ok(Arc::new(Self {
+            current: RwLock::new(Arc::new(rule_set)),
+            correlation: CorrelationEngine::new(correlation_plans),
+            next_version: AtomicU64::new(2),
+            reload_lock: tokio::sync::Mutex::new(()),
+        }))
+    }
+
+    /// 重新加载所有启用的规则和标签，返回新的规则版本号
+    ///
+    /// 加载失败时保留当前快照不变
+    pub async fn reload(&self, pool: &PgPool) -> Result<u64> {
+        // 串行化重载，保证版本号与快照替换顺序一致
+        let _guard = self.reload_lock.lock().await;
+
+        let version = self.next_version.load(Ordering::SeqCst);
+        let (rule_set, correlation_plans) = load_rule_set(pool, version).await?;
+        self.next_version.fetch_add(1, Ordering::SeqCst);
+
+        self.correlation.replace_plans(correlation_plans);
+        *self.current.write().unwrap() = Arc::new(rule_set);
+
+        info!("Rule set reloaded, now at version {}.", version);
+        Ok(version)
+    }
+
+    /// 获取当前规则快照
+    pub fn snapshot(&self) -> Arc<RuleSet> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn correlation(&self) -> &CorrelationEngine {
+        &self.correlation
+    }
+
+    /// 当前规则快照概要
+    pub fn summary(&self) -> RuleSetSummary {
+        let rule_set = self.snapshot();
+        RuleSetSummary {
+            version: rule_set.version,
+            loaded_at: rule_set.loaded_at,
+            filter_rules: rule_set.filter_plans.len(),
+            tag_rules: rule_set.tag_plans.len(),
+            tags: rule_set.tag_map.len(),
+            convergence_rules: rule_set.convergence_plans.len(),
+            correlation_rules: self.correlation.plan_count(),
+        }
+    }
+}
+
+/// 从数据库加载并编译一代规则
+async fn load_rule_set(
+    pool: &PgPool,
+    version: u64,
+) -> Result<(RuleSet, Vec<correlation::CorrelationPlan>)> {
+    // 加载并编译过滤规则
+    let filter_rules = db::filter_rules::get_enabled_filter_rules(pool).await?;
+    let filter_plans = filtering::compile_filter_plans(&filter_rules);
+
+    // 加载并编译标签规则
+    let tag_rules = db::tag_rules::get_enabled_tag_rules(pool).await?;
+    let tag_plans = tagging::compile_tag_plans(&tag_rules);
+
+    // 加载并编译收敛规则
+    let convergence_rules = db::convergence_rules::get_enabled_convergence_rules(pool).await?;
+    let convergence_plans = convergence::compile_convergence_plans(&convergence_rules);
+
+    // 加载并编译关联规则
+    let correlation_rules = db::correlation_rules::get_enabled_correlation_rules(pool).await?;
+    let correlation_plans = correlation::compile_correlation_plans(&correlation_rules);
+
+    // 加载所有标签定义，用于名称到ID的映射
+    let all_tags = db::tag_management::get_all_tags(pool).await?;
+    let tag_map: HashMap<String, Uuid> =
+        all_tags.into_iter().map(|tag| (tag.name, tag.id)).collect();
+
+    info!(
+        "Rule set v{}: {}/{} filter rules, {}/{} tag rules, {}/{} convergence rules, {}/{} correlation rules, {} tags.",
+        version,
+        filter_plans.len(),
+        filter_rules.len(),
+        tag_plans.len(),
+        tag_rules.len(),
+        convergence_plans.len(),
+        convergence_rules.len(),
+        correlation_plans.len(),
+        correlation_rules.len(),
+        tag_map.len()
+    );
+
+    Ok((
+        RuleSet {
+            version,
+            loaded_at: Utc::now(),
+            filter_plans,
+            tag_plans,
+            tag_map,
+            convergence_plans,
+        },
+        correlation_plans,
+    ))
+}
@@ -0,0 +1,294 @@
+//! 规则试运行：对历史原始告警执行单条规则，只统计结果，不写入任何数据
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use super::convergence::ConvergencePlan;
+use super::filtering::FilterPlan;
+use super::tagging::TagPlan;
+use crate::alert_catalog;
+use crate::db::raw_alerts::RawAlertJson;
+use crate::dsl::validator;
+
+/// 参与试运行的规则
+pub enum SimulatedRule {
+    Filter(FilterPlan),
+    Tag(TagPlan),
+    Convergence(ConvergencePlan),
+}
+
+impl SimulatedRule {
+    /// 规则可能命中的告警类型，决定需要扫描哪些原始告警表
+    pub fn alert_types(&self) -> Vec<&'static str> {
+        let alert_type = match self {
+            SimulatedRule::Filter(plan) => &plan.rule.alert_type,
+            SimulatedRule::Tag(plan) => &plan.rule.alert_type,
+            SimulatedRule::Convergence(plan) => {
+                return validator::target_alert_types(&plan.rule.condition)
+                    .map(|types| types.into_iter().collect())
+                    .unwrap_or_default();
+            }
+        };
+        alert_catalog::catalog()
+            .get(alert_type)
+            .map(|t| vec![t.name.as_str()])
+            .unwrap_or_default()
+    }
+}
+
+/// 试运行结果
+#[derive(Debug, Serialize)]
+pub struct SimulationReport {
+    /// 扫描的原始告警数
+    pub scanned: u64,
+    /// 命中规则的告警数（过滤规则为会被丢弃的告警，标签规则为会被打标的告警）
+    pub matched: u64,
+    /// 是否因达到扫描上限而未覆盖整个时间范围
+    pub truncated: bool,
+    pub by_alert_type: BTreeMap<String, AlertTypeStats>,
+    /// 命中告警样例，按入库时间排序
+    pub samples: Vec<SimulatedAlert>,
+    /// 标签规则会添加的标签
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// 收敛规则的分组结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub convergence: Option<ConvergenceSummary>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AlertTypeStats {
+    pub scanned: u64,
+    pub matched: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulatedAlert {
+    pub id: Uuid,
+    pub alert_type: String,
+    pub created_at: DateTime<Utc>,
+    pub alert: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConvergenceSummary {
+    /// 不同分组键的数量
+    pub groups: u64,
+    /// 会生成的收敛告警数（同一分组超出时间窗口后会新开一条）
+    pub converged_alerts: u64,
+    /// 计数达到阈值、会被推送的收敛告警数
+    pub reached_threshold: u64,
+    /// 告警数最多的若干分组
+    pub top_groups: Vec<SimulatedGroup>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedGroup {
+    pub alert_type: String,
+    /// GROUP BY 字段的取值，与分组键顺序一致
+    pub group_values: Value,
+    pub alerts: u64,
+    pub converged_alerts: u64,
+    pub reached_threshold: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// 单个分组在试运行中的状态
+struct GroupState {
+    group: SimulatedGroup,
+    window_start: DateTime<Utc>,
+    window_count: i32,
+}
+
+/// 按入库时间顺序逐条喂入告警的试运行器
+pub struct Simulation {
+    rule: SimulatedRule,
+    sample_size: usize,
+    report: SimulationReport,
+    groups: HashMap<(String, String), GroupState>,
+}
+
+impl Simulation {
+    pub fn new(rule: SimulatedRule, sample_size: usize) -> Self {
+        let tags = match &rule {
+            SimulatedRule::Tag(plan) => Some(plan.rule.tags.clone()),
+            _ => None,
+        };
+        Self {
+            rule,
+            sample_size,
+            report: SimulationReport {
+                scanned: 0,
+                matched: 0,
+                truncated: false,
+                by_alert_type: BTreeMap::new(),
+                samples: Vec::new(),
+                tags,
+                convergence: None,
+            },
+            groups: HashMap::new(),
+        }
+    }
+
+    pub fn alert_types(&self) -> Vec<&'static str> {
+        self.rule.alert_types()
+    }
+
+    /// 标记扫描因达到上限而提前结束
+    pub fn mark_truncated(&mut self) {
+        self.report.truncated = true;
+    }
+
+    /// 对一条原始告警执行规则
+    pub fn observe(&mut self, alert_type: &str, row: &RawAlertJson) {
+        let matched = match &self.rule {
+            SimulatedRule::Filter(plan) => plan.matches(&row.alert, alert_type),
+            SimulatedRule::Tag(plan) => plan.matches(&row.alert, alert_type),
+            SimulatedRule::Convergence(plan) => match plan.group_key(&row.alert) {
+                Some(group_key) => {
+                    let window = plan.rule.window.to_duration();
+                    let threshold = plan.threshold();
+                    self.converge(alert_type, group_key, row.created_at, window, threshold);
+                    true
+                }
+                None => false,
+            },
+        };
+
+        let stats = self
+            .report
+            .by_alert_type
+            .entry(alert_type.to_string())
+            .or_default();
+        stats.scanned += 1;
+        self.report.scanned += 1;
+        if !matched {
+            return;
+        }
+
+        stats.matched += 1;
+        self.report.matched += 1;
+        if self.report.samples.len() < self.sample_size {
+            self.report.samples.push(SimulatedAlert {
+                id: row.id,
+                alert_type: alert_type.to_string(),
+                created_at: row.created_at,
+                alert: row.alert.clone(),
+            });
+        }
+    }
+
+    /// 与线上收敛一致：分组在首条告警处开启窗口，窗口内的告警合并，超出窗口后新开一条收敛告警
+    fn converge(
+        &mut self,
+        alert_type: &str,
+        group_key: String,
+        seen_at: DateTime<Utc>,
+        window: Duration,
+        threshold: i32,
+    ) {
+        let state = self
+            .groups
+            .entry((alert_type.to_string(), group_key))
+            .or_insert_with_key(|(alert_type, group_key)| GroupState {
+                group: SimulatedGroup {
+                    alert_type: alert_type.clone(),
+                    group_values: serde_json::from_str(group_key).unwrap_or(Value::Null),
+                    alerts: 0,
+                    converged_alerts: 0,
+                    reached_threshold: 0,
+                    first_seen: seen_at,
+                    last_seen: seen_at,
+                },
+                window_start: seen_at,
+                window_count: 0,
+            });
+
+        if state.group.converged_alerts == 0 || seen_at - state.window_start >= window {
+            state.group.converged_alerts += 1;
+            state.window_start = seen_at;
+            state.window_count = 0;
+        }
+        state.window_count += 1;
+        if state.window_count == threshold {
+            state.group.reached_threshold += 1;
+        }
+        state.group.alerts += 1;
+        state.group.last_seen = seen_at;
+    }
+
+    /// 结束试运行，汇总收敛分组
+    pub fn finish(mut self, max_groups: usize) -> SimulationReport {
+        if let SimulatedRule::Convergence(_) = self.rule {
+            let mut groups: Vec<SimulatedGroup> =
+                self.groups.into_values().map(|state| state.group).collect();
+            groups.sort_by(|a, b| {
+                b.alerts
+                    .cmp(&a.alerts)
+                    .then_with(|| a.first_seen.cmp(&b.first_seen))
+            });
+
+            self.report.convergence = Some(ConvergenceSummary {
+                groups: groups.len() as u64,
+                converged_alerts: groups.iter().map(|g| g.converged_alerts).sum(),
+                reached_threshold: groups.iter().map(|g| g.reached_threshold).sum(),
+                top_groups: groups.into_iter().take(max_groups).collect(),
+            });
+        }
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::convergence_rules::ConvergenceRuleRecord;
+    use serde_json::json;
+
+    #[test]
+    fn test_simulate_convergence_windows() {
+        let now = Utc::now();
+        let record = ConvergenceRuleRecord {
+            id: Uuid::new_v4(),
+            name: "test".to_string(),
+            dsl_rule: "CONVERGE WHERE alarm_severity >= 2 GROUP BY src_ip WINDOW 5m THRESHOLD 2"
+                .to_string(),
+            description: None,
+            enabled: true,
+            version: 0,
+            created_at: now,
+            updated_at: now,
+        };
+        let plan = ConvergencePlan::compile(&record).unwrap();
+        let mut simulation = Simulation::new(SimulatedRule::Convergence(plan), 10);
+
+        let row = |minutes: i64, src_ip: &str, severity: i64| RawAlertJson {
+            id: Uuid::new_v4(),
+            created_at: now + Duration::minutes(minutes),
+            alert: json!({ "src_ip": src_ip, "alarm_severity": severity }),
+        };
+        // 1.1.1.1: 0、3 分钟在同一窗口达到阈值，10 分钟新开一条
+        for alert in [
+            row(0, "1.1.1.1", 3),
+            row(3, "1.1.1.1", 3),
+            row(4, "2.2.2.2", 3),
+            row(6, "3.3.3.3", 1),
+            row(10, "1.1.1.1", 3),
+        ] {
+            simulation.observe("network_attack", &alert);
+        }
+
+        let report = simulation.finish(10);
+        assert_eq!((report.scanned, report.matched), (5, 4));
+        let convergence = report.convergence.unwrap();
+        assert_eq!(convergence.groups, 2);
+        assert_eq!(convergence.converged_alerts, 3);
+        assert_eq!(convergence.reached_threshold, 1);
+        assert_eq!(convergence.top_groups[0].group_values, json!(["1.1.1.1"]));
+        assert_eq!(convergence.top_groups[0].alerts, 3);
+    }
+}
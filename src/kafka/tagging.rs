@@ -1,48 +1,104 @@
+use super::filtering::subtype_matches;
+use crate::config::SubtypeSpec;
 use crate::db::tag_rules::TagRuleRecord;
-use regex::Regex;
+use crate::dsl::{self, legacy::legacy_condition, Predicate};
+use anyhow::{anyhow, Result};
 use serde_json::Value;
 use std::collections::HashMap;
 use tracing::{info, warn};
 use uuid::Uuid;
 
-/// 对单个告警应用所有标签规则，并返回匹配的标签ID列表
-pub fn get_matched_tag_ids(
+/// 由标签规则编译得到的可执行打标计划
+pub struct TagPlan {
+    pub rule: TagRuleRecord,
+    pub subtypes: SubtypeSpec,
+    pub predicate: Predicate,
+}
+
+impl TagPlan {
+    /// 解析子类型条件并编译 DSL 条件；尚未迁移的旧规则按单条件转换
+    pub fn compile(rule: &TagRuleRecord) -> Result<Self> {
+        let subtypes = SubtypeSpec::parse(&rule.alert_subtype).map_err(|e| anyhow!(e))?;
+        let source = rule
+            .condition
+            .clone()
+            .or_else(|| {
+                legacy_condition(
+                    &rule.alert_type,
+                    &rule.condition_field,
+                    &rule.condition_operator,
+                    &rule.condition_value,
+                )
+            })
+            .ok_or_else(|| anyhow!("规则缺少有效的打标条件"))?;
+        let condition = dsl::validator::compile_condition(&source, &rule.alert_type)?;
+        let predicate = Predicate::compile(&condition);
+        Ok(Self {
+            rule: rule.clone(),
+            subtypes,
+            predicate,
+        })
+    }
+
+    pub(super) fn matches(&self, alert_json: &Value, alert_type_str: &str) -> bool {
+        self.rule.alert_type == alert_type_str
+            && subtype_matches(alert_json, &self.subtypes)
+            && self.predicate.matches(alert_json)
+    }
+}
+
+/// 编译所有启用的标签规则，编译失败的规则会被跳过
+pub fn compile_tag_plans(records: &[TagRuleRecord]) -> Vec<TagPlan> {
+    records
+        .iter()
+        .filter_map(|record| match TagPlan::compile(record) {
+            Ok(plan) => Some(plan),
+            Err(e) => {
+                warn!(
+                    "标签规则 '{}' ({}) 编译失败，已跳过: {}",
+                    record.name, record.id, e
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// 单个告警的打标结果
+#[derive(Debug, Default)]
+pub struct TagMatches {
+    /// 命中的标签规则ID
+    pub rule_ids: Vec<Uuid>,
+    /// 待添加的标签ID
+    pub tag_ids: Vec<Uuid>,
+}
+
+/// 对单个告警应用所有标签规则，返回命中的规则和匹配的标签ID列表
+pub fn match_tags(
     alert_json: &Value,
     alert_type_str: &str,
-    rules: &[TagRuleRecord],
+    plans: &[TagPlan],
     tag_map: &HashMap<String, Uuid>,
-) -> Vec<Uuid> {
+) -> TagMatches {
+    let mut rule_ids = Vec::new();
     let mut tags_to_add = std::collections::HashSet::new();
 
-    for rule in rules.iter().filter(|r| r.alert_type == alert_type_str) {
-        // 如果规则定义了子类型，则必须匹配
-        if !rule.alert_subtype.is_empty() {
-            let subtype_matches = alert_json
-                .get("alert_subtype")
-                .map(|v| v.to_string().replace('"', "")) // 将数字或字符串统一转为字符串并去除引号
-                .map_or(false, |sub| sub == rule.alert_subtype);
-
-            if !subtype_matches {
-                continue;
-            }
-        }
-
-        // 检查核心条件
-        if check_condition(
-            alert_json,
-            &rule.condition_field,
-            &rule.condition_operator,
-            &rule.condition_value,
-        ) {
-            // 规则匹配，将其所有标签加入待添加列表
-            for tag_name in &rule.tags {
-                tags_to_add.insert(tag_name.clone());
-            }
+    for plan in plans
+        .iter()
+        .filter(|p| p.matches(alert_json, alert_type_str))
+    {
+        // 规则匹配，将其所有标签加入待添加列表
+        rule_ids.push(plan.rule.id);
+        for tag_name in &plan.rule.tags {
+            tags_to_add.insert(tag_name.clone());
         }
     }
 
     if tags_to_add.is_empty() {
-        return Vec::new();
+        return TagMatches {
+            rule_ids,
+            tag_ids: Vec::new(),
+        };
     }
 
     // 从 tag name -> Uuid 的映射中查找ID
@@ -63,43 +119,5 @@ pub fn get_matched_tag_ids(
         );
     }
 
-    tag_ids
-}
-
-/// 检查告警数据是否满足单个规则的条件
-fn check_condition(alert_json: &Value, field: &str, operator: &str, value: &str) -> bool {
-    // 使用 .get() 安全地访问字段，如果字段不存在则条件不匹配
-    let alert_field = match alert_json.get(field) {
-        Some(v) => v,
-        None => return false,
-    };
-
-    // 将告警字段的值转换为字符串以便比较
-    let alert_field_str = match alert_field {
-        Value::String(s) => s.clone(),
-        Value::Number(n) => n.to_string(),
-        Value::Bool(b) => b.to_string(),
-        // 对空值进行特殊处理
-        Value::Null => {
-            // 如果规则要求 "ne" 且 value 是空字符串，那么 null 字段应该算 "ne" "" -> true
-            if operator == "ne" && value.is_empty() {
-                return true;
-            }
-            // 其他情况，可以视为空字符串或特定标记
-            "".to_string()
-        }
-        _ => return false, // 目前不处理数组或对象类型的字段
-    };
-
-    match operator {
-        "eq" => alert_field_str == value,
-        "ne" => alert_field_str != value,
-        "contains" => alert_field_str.contains(value),
-        "not_contains" => !alert_field_str.contains(value),
-        "regex" => Regex::new(value).map_or(false, |re| re.is_match(&alert_field_str)),
-        _ => {
-            warn!("未知的操作符: {}", operator);
-            false
-        }
-    }
+    TagMatches { rule_ids, tag_ids }
 }
@@ -0,0 +1,231 @@
+//! 上游原始威胁事件接入
+//!
+//! 消息格式见 event.md：`type` 对应 event_type，时间为 `"YYYY-MM-DD HH:MM:SS"`，
+//! 资产等列表字段为 JSON 数组。事件按 event_id（消息中的 id）写入，重复推送时以最新内容覆盖。
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rdkafka::message::OwnedMessage;
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use tracing::{error, info};
+
+use super::store_dead_letter;
+use crate::db::{dead_letters, threat_event, threat_event_push, ThreatEventInput};
+
+/// 威胁事件消息在主题映射和死信表中使用的类型名
+pub const THREAT_EVENT_TYPE: &str = "threat_event";
+
+/// 上游推送的原始威胁事件
+#[derive(Debug, Deserialize)]
+pub struct ThreatEventMessage {
+    pub id: i64,
+    pub system_code: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    pub attacker: Option<String>,
+    pub victimer: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub found_time: Option<String>,
+    pub source: Option<String>,
+    pub mitre_technique_id: Option<String>,
+    pub attsck_list: Option<String>,
+    pub attack_tool: Option<String>,
+    pub first_found_time: Option<String>,
+    pub priority: Option<String>,
+    pub severity: Option<String>,
+    pub dispose_status: Option<String>,
+    pub app: Option<String>,
+    pub impact_assessment: Option<String>,
+    pub merge_alerts: Option<Value>,
+    pub threat_actor: Option<Value>,
+    pub org: Option<Value>,
+    pub attack_asset_ip: Option<Value>,
+    pub victim_asset_ip: Option<Value>,
+    pub attack_asset_ip_port: Option<Value>,
+    pub victim_asset_ip_port: Option<Value>,
+    pub attack_asset_domain: Option<Value>,
+    pub victim_asset_domain: Option<Value>,
+    pub attack_url: Option<Value>,
+    pub victim_url: Option<Value>,
+    pub attack_malware: Option<Value>,
+    pub attack_malware_sample: Option<Value>,
+    pub attack_malware_sample_family: Option<Value>,
+    pub attack_email_address: Option<Value>,
+    pub victim_email_address: Option<Value>,
+    pub attack_email: Option<Value>,
+    pub victim_email: Option<Value>,
+    pub attack_software: Option<Value>,
+    pub victim_software: Option<Value>,
+    pub attack_vulnerability: Option<Value>,
+    pub attack_certificate: Option<Value>,
+    pub victim_certificate: Option<Value>,
+}
+
+impl ThreatEventMessage {
+    /// 转换为威胁事件，时间字段无法解析时返回错误
+    pub fn into_input(self) -> Result<ThreatEventInput, String> {
+        Ok(ThreatEventInput {
+            event_id: Some(self.id),
+            start_time: parse_event_time("start_time", self.start_time.as_deref())?,
+            end_time: parse_event_time("end_time", self.end_time.as_deref())?,
+            found_time: parse_event_time("found_time", self.found_time.as_deref())?,
+            first_found_time: parse_event_time(
+                "first_found_time",
+                self.first_found_time.as_deref(),
+            )?,
+            system_code: self.system_code,
+            name: self.name,
+            description: self.description,
+            event_type: self.event_type,
+            attacker: self.attacker,
+            victimer: self.victimer,
+            source: self.source,
+            mitre_technique_id: self.mitre_technique_id,
+            attsck_list: self.attsck_list,
+            attack_tool: self.attack_tool,
+            priority: self.priority,
+            severity: self.severity,
+            dispose_status: self.dispose_status,
+            app: self.app,
+            impact_assessment: self.impact_assessment,
+            merge_alerts: self.merge_alerts,
+            threat_actor: self.threat_actor,
+            org: self.org,
+            attack_asset_ip: self.attack_asset_ip,
+            victim_asset_ip: self.victim_asset_ip,
+            attack_asset_ip_port: self.attack_asset_ip_port,
+            victim_asset_ip_port: self.victim_asset_ip_port,
+            attack_asset_domain: self.attack_asset_domain,
+            victim_asset_domain: self.victim_asset_domain,
+            attack_url: self.attack_url,
+            victim_url: self.victim_url,
+            attack_malware: self.attack_malware,
+            attack_malware_sample: self.attack_malware_sample,
+            attack_malware_sample_family: self.attack_malware_sample_family,
+            attack_email_address: self.attack_email_address,
+            victim_email_address: self.victim_email_address,
+            attack_email: self.attack_email,
+            victim_email: self.victim_email,
+            attack_software: self.attack_software,
+            victim_software: self.victim_software,
+            attack_vulnerability: self.attack_vulnerability,
+            attack_certificate: self.attack_certificate,
+            victim_certificate: self.victim_certificate,
+        })
+    }
+}
+
+/// 解析事件时间：`YYYY-MM-DD HH:MM:SS` 按 UTC 处理（与已有威胁事件数据一致），也接受 RFC 3339
+fn parse_event_time(field: &str, value: Option<&str>) -> Result<Option<DateTime<Utc>>, String> {
+    let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .map(|dt| dt.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(value).map(|dt| dt.with_timezone(&Utc)))
+        .map(Some)
+        .map_err(|_| {
+            format!(
+                "字段 {} 的时间 '{}' 不是 YYYY-MM-DD HH:MM:SS 格式",
+                field, value
+            )
+        })
+}
+
+/// 写入一条上游威胁事件，格式不符的消息转入死信表
+pub(super) async fn ingest_threat_event(
+    pool: &PgPool,
+    m: &OwnedMessage,
+    payload_json: Value,
+) -> Result<()> {
+    let event = serde_json::from_value::<ThreatEventMessage>(payload_json)
+        .map_err(|e| e.to_string())
+        .and_then(ThreatEventMessage::into_input);
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            error!("Failed to deserialize threat event: {}", e);
+            store_dead_letter(
+                pool,
+                m,
+                THREAT_EVENT_TYPE,
+                dead_letters::STAGE_DESERIALIZE,
+                e,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let outcome = threat_event::upsert_threat_event(pool, &event).await?;
+    info!(
+        "Threat event {} (event_id {}) {}.",
+        outcome.id,
+        event.event_id.unwrap_or_default(),
+        if outcome.inserted {
+            "created"
+        } else {
+            "updated"
+        }
+    );
+
+    if outcome.inserted {
+        threat_event_push::enqueue_threat_event_push(
+            pool,
+            outcome.id,
+            threat_event_push::TRIGGER_CREATED,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_event_message_mapping() {
+        let message: ThreatEventMessage = serde_json::from_value(json!({
+            "id": 1000001,
+            "system_code": "SYS-2025-001",
+            "type": "网络攻击",
+            "start_time": "2025-09-10 08:30:00",
+            "end_time": "",
+            "found_time": "2025-09-10T09:00:00+08:00",
+            "attack_asset_ip": ["192.168.10.15", "45.67.89.101"]
+        }))
+        .unwrap();
+        let event = message.into_input().unwrap();
+
+        assert_eq!(event.event_id, Some(1000001));
+        assert_eq!(event.event_type.as_deref(), Some("网络攻击"));
+        assert_eq!(
+            event.start_time.unwrap().to_rfc3339(),
+            "2025-09-10T08:30:00+00:00"
+        );
+        assert_eq!(event.end_time, None);
+        assert_eq!(
+            event.found_time.unwrap().to_rfc3339(),
+            "2025-09-10T01:00:00+00:00"
+        );
+        assert_eq!(
+            event.attack_asset_ip,
+            Some(json!(["192.168.10.15", "45.67.89.101"]))
+        );
+    }
+
+    #[test]
+    fn test_event_message_rejects_bad_time() {
+        let message: ThreatEventMessage =
+            serde_json::from_value(json!({"id": 1, "start_time": "10/09/2025"})).unwrap();
+        let err = message.into_input().unwrap_err();
+        assert!(err.contains("start_time"));
+    }
+}
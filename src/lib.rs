@@ -1,4 +1,7 @@
-// 库文件 - 导出模块供 binary 使用
+// 库文件 - 导出模块供 binary 和基准测试使用
 
+pub mod alert_catalog;
+pub mod config;
+pub mod dsl;
 pub mod generators;
 pub mod models;
@@ -1,3 +1,5 @@
+mod alert_catalog;
+mod alert_schema;
 mod api;
 mod config;
 mod db;
@@ -5,7 +7,11 @@ mod dsl;
 mod kafka;
 mod models;
 
-use crate::config::{load_config, AlarmTypesConfig, KafkaConfig, TopicsConfig};
+use crate::config::{
+    load_config, AlarmTypesConfig, KafkaConfig, ThreatEventPushConfig, TopicsConfig,
+    ValidationConfig,
+};
+use crate::db::rule_bundle::{self, BundleFormat, ConflictMode, ImportOptions};
 use axum::{
     extract::State,
     http::{StatusCode, Uri},
@@ -16,11 +22,11 @@ use axum::{
 use clap::Parser;
 use sqlx::PgPool;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tracing::info;
-use tracing_subscriber;
 
 #[derive(Parser, Debug)]
 #[command(name = "server", about = "Axum 告警推送服务器")]
@@ -44,6 +50,22 @@ struct Args {
     /// 插入告警模拟数据（原始告警+收敛告警+映射关系）后退出
     #[arg(long, default_value_t = false)]
     insert_mock_alerts: bool,
+
+    /// 导出所有规则和标签到规则包文件后退出（按扩展名选择 .json / .toml）
+    #[arg(long, value_name = "FILE")]
+    export_rules: Option<PathBuf>,
+
+    /// 从规则包文件导入规则和标签后退出（按扩展名选择 .json / .toml）
+    #[arg(long, value_name = "FILE")]
+    import_rules: Option<PathBuf>,
+
+    /// 导入时同名规则的处理方式：skip / overwrite / rename
+    #[arg(long, default_value = "skip")]
+    conflict: ConflictMode,
+
+    /// 导入时只校验并预演，不写入数据库
+    #[arg(long, default_value_t = false)]
+    validate_only: bool,
 }
 
 // 应用状态
@@ -52,6 +74,9 @@ pub struct AppState {
     pub alarm_types: AlarmTypesConfig,
     pub kafka: KafkaConfig,
     pub topics: TopicsConfig,
+    pub validation: ValidationConfig,
+    pub threat_event_push: ThreatEventPushConfig,
+    pub rules: Arc<kafka::RuleRegistry>,
 }
 
 #[tokio::main]
@@ -64,6 +89,7 @@ async fn main() {
 
     // 读取配置
     let config = load_config("config.toml").expect("读取配置失败");
+    alert_catalog::init(&config.alarm_types);
 
     // 初始化数据库
     let pool = db::init_postgres(&config.postgres)
@@ -151,12 +177,99 @@ async fn main() {
         return;
     }
 
+    // 若指定 --export-rules，则导出规则包后退出
+    if let Some(path) = &args.export_rules {
+        let format = BundleFormat::from_path(path);
+        let result = match rule_bundle::export_bundle(&pool).await {
+            Ok(bundle) => rule_bundle::render_bundle(&bundle, format),
+            Err(e) => Err(e),
+        }
+        .and_then(|text| Ok(std::fs::write(path, text)?));
+        match result {
+            Ok(()) => println!("✅ 规则已导出到 {}", path.display()),
+            Err(e) => {
+                eprintln!("❌ 导出规则失败: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // 若指定 --import-rules，则导入规则包后退出
+    if let Some(path) = &args.import_rules {
+        let bundle = std::fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| rule_bundle::parse_bundle(&text, BundleFormat::from_path(path)));
+        let bundle = match bundle {
+            Ok(bundle) => bundle,
+            Err(e) => {
+                eprintln!("❌ 读取规则包失败: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        let options = ImportOptions {
+            conflict: args.conflict,
+            validate_only: args.validate_only,
+            author: Some("cli".to_string()),
+        };
+        let report =
+            match rule_bundle::import_bundle(&pool, bundle, &config.alarm_types, &options).await {
+                Ok(report) => report,
+                Err(e) => {
+                    eprintln!("❌ 导入规则失败: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+        if !report.errors.is_empty() {
+            eprintln!("❌ 规则包校验失败，共 {} 处错误：", report.errors.len());
+            for issue in &report.errors {
+                eprintln!("   - [{}] {}: {}", issue.section, issue.name, issue.message);
+            }
+            std::process::exit(1);
+        }
+        for item in &report.items {
+            match &item.renamed_to {
+                Some(renamed) => println!(
+                    "   - [{}] {}: {} -> {}",
+                    item.section, item.name, item.action, renamed
+                ),
+                None => println!("   - [{}] {}: {}", item.section, item.name, item.action),
+            }
+        }
+        if report.applied {
+            println!("✅ 规则包已导入，共 {} 项。", report.items.len());
+            println!("   运行中的服务需调用 POST /api/rules/reload 使规则生效。");
+        } else {
+            println!("✅ 规则包校验通过（未写入数据库）。");
+        }
+        return;
+    }
+
+    // 加载运行时规则，规则变更时由 API 触发热重载
+    let rules = kafka::RuleRegistry::load(&pool)
+        .await
+        .expect("加载规则失败");
+
     // 启动 Kafka 消费任务
     let kafka_cfg = config.kafka.clone();
     let topics_cfg = config.topics.clone();
+    let convergence_cfg = config.convergence.clone();
+    let validation_cfg = config.validation.clone();
     let pool_clone = pool.clone();
+    let rules_clone = rules.clone();
     tokio::spawn(async move {
-        if let Err(e) = kafka::run_consumer(kafka_cfg, topics_cfg, pool_clone).await {
+        if let Err(e) = kafka::run_consumer(
+            kafka_cfg,
+            topics_cfg,
+            convergence_cfg,
+            validation_cfg,
+            pool_clone,
+            rules_clone,
+        )
+        .await
+        {
             tracing::error!("Kafka consumer stopped: {}", e);
         }
     });
@@ -167,6 +280,9 @@ async fn main() {
         alarm_types: config.alarm_types,
         kafka: config.kafka.clone(),
         topics: config.topics.clone(),
+        validation: config.validation.clone(),
+        threat_event_push: config.threat_event_push.clone(),
+        rules,
     });
 
     // 配置 CORS
@@ -190,17 +306,72 @@ async fn main() {
             "/api/host-behaviors",
             get(api::alert_data::get_host_behaviors),
         )
+        // 按告警类型查询收敛告警及其原始告警，适用于所有已配置的告警类型
+        .route("/api/alerts/:alert_type", get(api::alert_data::get_alerts))
+        .route(
+            "/api/alerts/:alert_type/:alert_id/raw",
+            get(api::alert_data::get_raw_alerts_by_converged_id),
+        )
         .route(
             "/api/invalid-alerts",
             get(api::alert_data::get_invalid_alerts),
         )
+        // 死信消息（无法解析的 Kafka 消息）及重放
+        .route(
+            "/api/dead-letters",
+            get(api::dead_letters::get_dead_letters),
+        )
+        .route(
+            "/api/dead-letters/:id",
+            get(api::dead_letters::get_dead_letter_by_id),
+        )
+        .route(
+            "/api/dead-letters/:id/replay",
+            post(api::dead_letters::replay_dead_letter),
+        )
         .route(
             "/api/threat-events",
             get(api::alert_data::get_threat_events),
         )
         .route(
             "/api/threat-events/:id",
-            put(api::alert_data::update_threat_event),
+            get(api::threat_event_alerts::get_threat_event)
+                .put(api::alert_data::update_threat_event),
+        )
+        // 威胁事件关联的收敛告警
+        .route(
+            "/api/threat-events/:id/alerts",
+            post(api::threat_event_alerts::attach_alert),
+        )
+        .route(
+            "/api/threat-events/:id/alerts/:converged_alert_id",
+            delete(api::threat_event_alerts::detach_alert),
+        )
+        // 威胁事件处置流程
+        .route(
+            "/api/threat-events/workflow",
+            get(api::threat_event_workflow::get_workflow),
+        )
+        .route(
+            "/api/threat-events/:id/transitions",
+            post(api::threat_event_workflow::transition_threat_event),
+        )
+        .route(
+            "/api/threat-events/:id/history",
+            get(api::threat_event_workflow::get_status_history),
+        )
+        // 威胁事件推送：手工重推与推送日志
+        .route(
+            "/api/threat-events/:id/push",
+            post(api::threat_event_push::push_threat_event),
+        )
+        .route(
+            "/api/threat-events/:id/push-logs",
+            get(api::threat_event_push::get_event_push_logs),
+        )
+        .route(
+            "/api/auto/threat-event-push-logs",
+            get(api::threat_event_push::get_push_logs),
         )
         // 原始告警查询路由
         .route(
@@ -325,8 +496,43 @@ async fn main() {
         .route("/api/rules/tag/:id", get(api::rules::get_tag_rule_by_id))
         .route("/api/rules/tag/:id", put(api::rules::update_tag_rule))
         .route("/api/rules/tag/:id", delete(api::rules::delete_tag_rule))
+        // 规则热重载
+        .route("/api/rules/version", get(api::rules::get_rule_set_version))
+        .route("/api/rules/reload", post(api::rules::reload_rules))
+        // 规则版本历史与回滚
+        .route(
+            "/api/rules/:kind/:id/versions",
+            get(api::rules::get_rule_versions),
+        )
+        .route(
+            "/api/rules/:kind/:id/versions/diff",
+            get(api::rules::diff_rule_versions),
+        )
+        .route(
+            "/api/rules/:kind/:id/versions/:version/rollback",
+            post(api::rules::rollback_rule),
+        )
+        // 规则包导入导出
+        .route("/api/rules/export", get(api::rule_bundle::export_rules))
+        .route("/api/rules/import", post(api::rule_bundle::import_rules))
+        // 规则试运行
+        .route(
+            "/api/rules/simulate",
+            post(api::rule_simulation::simulate_rule),
+        )
         // 其他路由
         .route("/api/alarm-types", get(get_alarm_types))
+        .route("/api/alert-types", get(api::alert_data::get_alert_types))
+        // 告警 JSON Schema，供生产方自检
+        .route("/api/schemas", get(api::alert_schema::get_schemas))
+        .route(
+            "/api/schemas/:alert_type",
+            get(api::alert_schema::get_schema),
+        )
+        .route(
+            "/api/schemas/:alert_type/validate",
+            post(api::alert_schema::validate_alert),
+        )
         // 自动推送配置路由 (单例)
         .route(
             "/api/auto/push-config",
@@ -388,6 +594,12 @@ async fn main() {
         api::auto_publish::run_auto_publisher(app_state_for_auto).await;
     });
 
+    // 启动后台威胁事件推送循环
+    let app_state_for_events = app_state.clone();
+    tokio::spawn(async move {
+        api::threat_event_push::run_threat_event_publisher(app_state_for_events).await;
+    });
+
     server.await.unwrap();
 }
 